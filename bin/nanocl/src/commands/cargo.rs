@@ -19,8 +19,8 @@ use crate::{
   config::CliConfig,
   models::{
     CargoArg, CargoCommand, CargoCreateOpts, CargoExecOpts, CargoHistoryOpts,
-    CargoLogsOpts, CargoPatchOpts, CargoRestartOpts, CargoRevertOpts, CargoRow,
-    CargoRunOpts, CargoStatsOpts, GenericRemoveForceOpts, GenericRemoveOpts,
+    CargoLogsOpts, CargoPatchOpts, CargoRemoveOpts, CargoRestartOpts,
+    CargoRevertOpts, CargoRow, CargoRunOpts, CargoStatsOpts, GenericRemoveOpts,
     ProcessStatsRow,
   },
   utils,
@@ -56,9 +56,9 @@ impl GenericCommandLs for CargoArg {
   }
 }
 
-impl GenericCommandRm<GenericRemoveForceOpts, CargoDeleteQuery> for CargoArg {
+impl GenericCommandRm<CargoRemoveOpts, CargoDeleteQuery> for CargoArg {
   fn get_query(
-    opts: &GenericRemoveOpts<GenericRemoveForceOpts>,
+    opts: &GenericRemoveOpts<CargoRemoveOpts>,
     namespace: Option<String>,
   ) -> Option<CargoDeleteQuery>
   where
@@ -67,6 +67,7 @@ impl GenericCommandRm<GenericRemoveForceOpts, CargoDeleteQuery> for CargoArg {
     Some(CargoDeleteQuery {
       namespace,
       force: Some(opts.others.force),
+      propagation: opts.others.propagation.clone(),
     })
   }
 }
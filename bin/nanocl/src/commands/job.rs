@@ -2,6 +2,7 @@ use futures::StreamExt;
 use nanocl_error::io::{FromIo, IoError, IoResult};
 
 use nanocld_client::stubs::{
+  generic::GenericDeleteQuery,
   job::JobInspect,
   process::{ProcessLogQuery, ProcessWaitQuery},
 };
@@ -9,7 +10,8 @@ use nanocld_client::stubs::{
 use crate::{
   config::CliConfig,
   models::{
-    GenericDefaultOpts, JobArg, JobCommand, JobLogsOpts, JobRow, JobWaitOpts,
+    GenericRemoveOpts, GenericRemovePropagationOpts, JobArg, JobCommand,
+    JobLogsOpts, JobRow, JobWaitOpts,
   },
   utils,
 };
@@ -35,7 +37,22 @@ impl GenericCommandLs for JobArg {
   }
 }
 
-impl GenericCommandRm<GenericDefaultOpts, String> for JobArg {}
+impl GenericCommandRm<GenericRemovePropagationOpts, GenericDeleteQuery>
+  for JobArg
+{
+  fn get_query(
+    opts: &GenericRemoveOpts<GenericRemovePropagationOpts>,
+    _namespace: Option<String>,
+  ) -> Option<GenericDeleteQuery>
+  where
+    GenericDeleteQuery: serde::Serialize,
+  {
+    Some(GenericDeleteQuery {
+      force: None,
+      propagation: opts.others.propagation.clone(),
+    })
+  }
+}
 
 impl GenericCommandStart for JobArg {}
 
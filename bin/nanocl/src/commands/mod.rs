@@ -19,6 +19,7 @@ mod uninstall;
 mod version;
 mod vm;
 mod vm_image;
+mod vm_snapshot;
 
 pub use generic::*;
 
@@ -1,11 +1,14 @@
 use nanocl_error::io::IoResult;
-use nanocld_client::{stubs::namespace::NamespaceInspect, NanocldClient};
+use nanocld_client::{
+  stubs::{generic::GenericDeleteQuery, namespace::NamespaceInspect},
+  NanocldClient,
+};
 
 use crate::{
   config::CliConfig,
   models::{
-    GenericDefaultOpts, NamespaceArg, NamespaceCommand, NamespaceCreateOpts,
-    NamespaceRow,
+    GenericRemoveForceOpts, GenericRemoveOpts, NamespaceArg, NamespaceCommand,
+    NamespaceCreateOpts, NamespaceRow,
   },
 };
 use nanocld_client::stubs::namespace::NamespaceSummary;
@@ -30,7 +33,22 @@ impl GenericCommandLs for NamespaceArg {
   }
 }
 
-impl GenericCommandRm<GenericDefaultOpts, String> for NamespaceArg {}
+impl GenericCommandRm<GenericRemoveForceOpts, GenericDeleteQuery>
+  for NamespaceArg
+{
+  fn get_query(
+    opts: &GenericRemoveOpts<GenericRemoveForceOpts>,
+    _namespace: Option<String>,
+  ) -> Option<GenericDeleteQuery>
+  where
+    GenericDeleteQuery: serde::Serialize,
+  {
+    Some(GenericDeleteQuery {
+      force: Some(opts.others.force),
+      ..Default::default()
+    })
+  }
+}
 
 impl GenericCommandInspect for NamespaceArg {
   type ApiItem = NamespaceInspect;
@@ -1,11 +1,11 @@
 use nanocl_error::io::IoResult;
-use nanocld_client::stubs::resource::Resource;
+use nanocld_client::stubs::{generic::GenericDeleteQuery, resource::Resource};
 
 use crate::{
   config::CliConfig,
   models::{
-    GenericDefaultOpts, ResourceArg, ResourceCommand, ResourceHistoryOpts,
-    ResourceRevertOpts, ResourceRow,
+    GenericRemoveOpts, GenericRemovePropagationOpts, ResourceArg,
+    ResourceCommand, ResourceHistoryOpts, ResourceRevertOpts, ResourceRow,
   },
   utils,
 };
@@ -30,7 +30,22 @@ impl GenericCommandLs for ResourceArg {
   }
 }
 
-impl GenericCommandRm<GenericDefaultOpts, String> for ResourceArg {}
+impl GenericCommandRm<GenericRemovePropagationOpts, GenericDeleteQuery>
+  for ResourceArg
+{
+  fn get_query(
+    opts: &GenericRemoveOpts<GenericRemovePropagationOpts>,
+    _namespace: Option<String>,
+  ) -> Option<GenericDeleteQuery>
+  where
+    GenericDeleteQuery: serde::Serialize,
+  {
+    Some(GenericDeleteQuery {
+      force: None,
+      propagation: opts.others.propagation.clone(),
+    })
+  }
+}
 
 impl GenericCommandInspect for ResourceArg {
   type ApiItem = Resource;
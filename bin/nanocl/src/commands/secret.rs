@@ -1,16 +1,24 @@
-use nanocl_error::io::IoResult;
-use nanocld_client::stubs::secret::Secret;
+use nanocl_error::io::{IoError, IoResult};
+use nanocld_client::stubs::{
+  generic::GenericDeleteQuery,
+  secret::{
+    SecretFile, SecretInspectQuery, SecretPartial, SecretSeal, SecretTlsIssued,
+    SecretUpdateQuery,
+  },
+};
 
 use crate::{
   config::CliConfig,
   models::{
-    GenericDefaultOpts, SecretArg, SecretCommand, SecretCreateOpts, SecretRow,
+    FileCreateOpts, GenericRemoveForceOpts, GenericRemoveOpts, SecretArg,
+    SecretCommand, SecretCreateOpts, SecretHistoryOpts, SecretInspectOpts,
+    SecretKindCreateCommand, SecretRevertOpts, SecretRow, SecretSealOpts,
+    TlsCreateOpts,
   },
+  utils,
 };
 
-use super::{
-  GenericCommand, GenericCommandInspect, GenericCommandLs, GenericCommandRm,
-};
+use super::{GenericCommand, GenericCommandLs, GenericCommandRm};
 
 impl GenericCommand for SecretArg {
   fn object_name() -> &'static str {
@@ -28,10 +36,126 @@ impl GenericCommandLs for SecretArg {
   }
 }
 
-impl GenericCommandRm<GenericDefaultOpts, String> for SecretArg {}
+impl GenericCommandRm<GenericRemoveForceOpts, GenericDeleteQuery>
+  for SecretArg
+{
+  fn get_query(
+    opts: &GenericRemoveOpts<GenericRemoveForceOpts>,
+    _namespace: Option<String>,
+  ) -> Option<GenericDeleteQuery>
+  where
+    GenericDeleteQuery: serde::Serialize,
+  {
+    Some(GenericDeleteQuery {
+      force: Some(opts.others.force),
+      ..Default::default()
+    })
+  }
+}
 
-impl GenericCommandInspect for SecretArg {
-  type ApiItem = Secret;
+impl TryFrom<&FileCreateOpts> for Vec<SecretFile> {
+  type Error = IoError;
+
+  fn try_from(opts: &FileCreateOpts) -> Result<Self, Self::Error> {
+    let mode = match &opts.mode {
+      None => None,
+      Some(mode) => Some(
+        u32::from_str_radix(mode.trim_start_matches("0o"), 8).map_err(
+          |err| IoError::invalid_input("Mode", err.to_string().as_str()),
+        )?,
+      ),
+    };
+    opts
+      .from_file
+      .iter()
+      .map(|file| {
+        let (target, path) = match file.split_once('=') {
+          Some((target, path)) => (target.to_owned(), path),
+          None => {
+            let target = std::path::Path::new(file)
+              .file_name()
+              .map(|name| name.to_string_lossy().to_string())
+              .ok_or_else(|| IoError::invalid_input("File", file.as_str()))?;
+            (target, file.as_str())
+          }
+        };
+        let data = std::fs::read(path)?;
+        Ok(SecretFile {
+          target,
+          data: openssl::base64::encode_block(&data),
+          mode,
+          uid: opts.uid,
+          gid: opts.gid,
+        })
+      })
+      .collect()
+  }
+}
+
+impl TryFrom<SecretCreateOpts> for SecretPartial {
+  type Error = IoError;
+
+  fn try_from(opts: SecretCreateOpts) -> Result<Self, Self::Error> {
+    let (kind, data) = match &opts.kind {
+      SecretKindCreateCommand::Env(env) => {
+        ("nanocl.io/env", serde_json::to_value(&env.values)?)
+      }
+      SecretKindCreateCommand::Tls(tls) => {
+        let mut cert = tls.certificate.clone();
+        let mut cert_key = tls.certificate_key.clone();
+        let mut cert_client = tls.certificate_client.clone();
+        if cert.is_none() && tls.certificate_path.is_none() {
+          return Err(IoError::interrupted("Certificate", "is required"));
+        }
+        if cert_key.is_none() && tls.certificate_key_path.is_none() {
+          return Err(IoError::interrupted("Certificate key", "is required"));
+        }
+        if let Some(certificate_path) = &tls.certificate_path {
+          cert = Some(std::fs::read_to_string(certificate_path)?);
+        }
+        if let Some(certificate_key_path) = &tls.certificate_key_path {
+          cert_key = Some(std::fs::read_to_string(certificate_key_path)?);
+        }
+        if let Some(certificate_client_path) = &tls.certificate_client_path {
+          cert_client = Some(std::fs::read_to_string(certificate_client_path)?);
+        }
+        let tls = TlsCreateOpts {
+          certificate: cert,
+          certificate_key: cert_key,
+          certificate_client: cert_client,
+          certificate_path: None,
+          certificate_key_path: None,
+          certificate_client_path: None,
+          ..tls.clone()
+        };
+        ("nanocl.io/tls", serde_json::to_value(tls)?)
+      }
+      SecretKindCreateCommand::TlsIssued(tls) => {
+        let tls = SecretTlsIssued {
+          sans: tls.sans.clone(),
+          lifetime: tls.lifetime,
+          verify_client: tls.verify_client.then_some(true),
+          ..Default::default()
+        };
+        ("nanocl.io/tls-issued", serde_json::to_value(tls)?)
+      }
+      SecretKindCreateCommand::File(file) => {
+        let files: Vec<SecretFile> = file.try_into()?;
+        ("nanocl.io/file", serde_json::to_value(files)?)
+      }
+      SecretKindCreateCommand::ContainerRegistry(container_registry) => (
+        "nanocl.io/container-registry",
+        serde_json::to_value(container_registry)?,
+      ),
+    };
+    Ok(Self {
+      name: opts.name,
+      kind: kind.to_string(),
+      immutable: false,
+      data,
+      metadata: None,
+    })
+  }
 }
 
 async fn exec_secret_create(
@@ -43,6 +167,73 @@ async fn exec_secret_create(
   Ok(())
 }
 
+/// Execute the `nanocl secret inspect` command,
+/// the data is masked unless `--reveal` is set
+async fn exec_secret_inspect(
+  cli_conf: &CliConfig,
+  opts: &SecretInspectOpts,
+) -> IoResult<()> {
+  let query = SecretInspectQuery {
+    reveal: Some(opts.reveal),
+  };
+  let secret = cli_conf
+    .client
+    .inspect_secret(&opts.key, Some(&query))
+    .await?;
+  let display = opts
+    .display
+    .clone()
+    .unwrap_or(cli_conf.user_config.display_format.clone());
+  utils::print::display_format(&display, secret)?;
+  Ok(())
+}
+
+/// Execute the `nanocl secret history` command to list the history of a secret
+async fn exec_secret_history(
+  cli_conf: &CliConfig,
+  opts: &SecretHistoryOpts,
+) -> IoResult<()> {
+  let histories = cli_conf.client.list_history_secret(&opts.name).await?;
+  utils::print::print_yml(histories)?;
+  Ok(())
+}
+
+/// Execute the `nanocl secret revert` command to revert a secret to a previous version
+async fn exec_secret_revert(
+  cli_conf: &CliConfig,
+  opts: &SecretRevertOpts,
+) -> IoResult<()> {
+  let query = SecretUpdateQuery {
+    rollout: Some(!opts.no_rollout),
+  };
+  let secret = cli_conf
+    .client
+    .revert_secret(&opts.name, &opts.history_id, Some(&query))
+    .await?;
+  utils::print::print_yml(secret)?;
+  Ok(())
+}
+
+/// Execute the `nanocl secret seal` command,
+/// the sealed value is printed to be written on the node
+async fn exec_secret_seal(
+  cli_conf: &CliConfig,
+  opts: &SecretSealOpts,
+) -> IoResult<()> {
+  let data = std::fs::read_to_string(&opts.from_file)?;
+  let data =
+    serde_yaml::from_str::<serde_json::Value>(&data).map_err(|err| {
+      IoError::invalid_data(opts.from_file.as_str(), err.to_string().as_str())
+    })?;
+  let seal = SecretSeal {
+    path: opts.path.clone(),
+    data,
+  };
+  let sealed = cli_conf.client.seal_secret(&seal).await?;
+  println!("{sealed}");
+  Ok(())
+}
+
 /// Function that execute when running `nanocl secret`
 pub async fn exec_secret(
   cli_conf: &CliConfig,
@@ -55,9 +246,15 @@ pub async fn exec_secret(
     SecretCommand::Remove(opts) => {
       SecretArg::exec_rm(&cli_conf.client, opts, None).await
     }
-    SecretCommand::Inspect(opts) => {
-      SecretArg::exec_inspect(cli_conf, opts, None).await
-    }
+    SecretCommand::Inspect(opts) => exec_secret_inspect(cli_conf, opts).await,
     SecretCommand::Create(opts) => exec_secret_create(cli_conf, opts).await,
+    SecretCommand::RotateKey => {
+      let res = cli_conf.client.rotate_secret_key().await?;
+      println!("{} secrets re-encrypted", res.count);
+      Ok(())
+    }
+    SecretCommand::Seal(opts) => exec_secret_seal(cli_conf, opts).await,
+    SecretCommand::History(opts) => exec_secret_history(cli_conf, opts).await,
+    SecretCommand::Revert(opts) => exec_secret_revert(cli_conf, opts).await,
   }
 }
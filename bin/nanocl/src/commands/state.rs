@@ -34,7 +34,7 @@ use nanocld_client::{
     job::JobPartial,
     process::ProcessLogQuery,
     resource::{ResourcePartial, ResourceUpdate},
-    secret::{SecretPartial, SecretUpdate},
+    secret::{SecretInspectQuery, SecretPartial, SecretUpdate},
     statefile::Statefile,
     system::NativeEventAction,
     vm_spec::{VmSpecPartial, VmSpecUpdate},
@@ -45,9 +45,9 @@ use nanocld_client::{
 use crate::{
   config::CliConfig,
   models::{
-    CargoArg, Context, DisplayFormat, GenericDefaultOpts,
-    GenericRemoveForceOpts, GenericRemoveOpts, JobArg, ResourceArg, SecretArg,
-    StateApplyOpts, StateArg, StateCommand, StateLogsOpts, StateRef,
+    CargoArg, CargoRemoveOpts, Context, DisplayFormat, GenericRemoveForceOpts,
+    GenericRemoveOpts, GenericRemovePropagationOpts, JobArg, ResourceArg,
+    SecretArg, StateApplyOpts, StateArg, StateCommand, StateLogsOpts, StateRef,
     StateRemoveOpts, StateRoot, VmArg,
   },
   utils,
@@ -608,7 +608,8 @@ async fn state_apply(
       let pg = utils::progress::create_progress("(submitting)", &pg_style);
       let metadata = insert_nanocl_group(&secret.metadata, &nanocl_group);
       secret.metadata = Some(metadata);
-      match client.inspect_secret(&secret.name).await {
+      let query = SecretInspectQuery { reveal: Some(true) };
+      match client.inspect_secret(&secret.name, Some(&query)).await {
         Err(_) => {
           client.create_secret(&secret).await?;
           pg.set_message("(created)");
@@ -617,7 +618,7 @@ async fn state_apply(
           let cmp: SecretPartial = inspect.into();
           if cmp != secret {
             let update: SecretUpdate = secret.clone().into();
-            client.patch_secret(&secret.name, &update).await?;
+            client.patch_secret(&secret.name, &update, None).await?;
             pg.set_message("(updated)");
           } else {
             pg.finish_with_message("(unchanged)");
@@ -645,7 +646,7 @@ async fn state_apply(
           client,
         )
         .await?;
-        client.delete_job(&job.name).await?;
+        client.delete_job(&job.name, None).await?;
         waiter.await??;
         pg.set_message("(cleared)");
       }
@@ -985,20 +986,23 @@ async fn state_remove(
     None => "global",
     Some(namespace) => namespace,
   };
-  let mut gen_rm_opts = GenericRemoveOpts::<GenericDefaultOpts> {
+  let mut gen_rm_opts = GenericRemoveOpts::<GenericRemovePropagationOpts> {
     keys: Vec::default(),
     skip_confirm: true,
-    others: GenericDefaultOpts,
+    others: GenericRemovePropagationOpts { propagation: None },
   };
   if let Some(jobs) = &state_file.data.jobs {
     gen_rm_opts.keys = jobs.iter().map(|job| job.name.clone()).collect();
     let _ = JobArg::exec_rm(client, &gen_rm_opts, None).await;
   }
   if let Some(cargoes) = &state_file.data.cargoes {
-    let opts = GenericRemoveOpts::<GenericRemoveForceOpts> {
+    let opts = GenericRemoveOpts::<CargoRemoveOpts> {
       keys: cargoes.iter().map(|cargo| cargo.name.clone()).collect(),
       skip_confirm: true,
-      others: GenericRemoveForceOpts { force: true },
+      others: CargoRemoveOpts {
+        force: true,
+        propagation: None,
+      },
     };
     let _ = CargoArg::exec_rm(client, &opts, Some(namespace.to_owned())).await;
   }
@@ -1015,9 +1019,13 @@ async fn state_remove(
     let _ = ResourceArg::exec_rm(client, &gen_rm_opts, None).await;
   }
   if let Some(secrets) = &state_file.data.secrets {
-    gen_rm_opts.keys =
-      secrets.iter().map(|secret| secret.name.clone()).collect();
-    let _ = SecretArg::exec_rm(client, &gen_rm_opts, None).await;
+    // The objects of the statefile using the secrets are being destroyed
+    let opts = GenericRemoveOpts::<GenericRemoveForceOpts> {
+      keys: secrets.iter().map(|secret| secret.name.clone()).collect(),
+      skip_confirm: true,
+      others: GenericRemoveForceOpts { force: true },
+    };
+    let _ = SecretArg::exec_rm(client, &opts, None).await;
   }
   Ok(())
 }
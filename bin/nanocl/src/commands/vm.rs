@@ -12,15 +12,19 @@ use futures::{
 };
 use ntex::{rt, time, util::Bytes, ws};
 #[cfg(not(target_os = "windows"))]
-use termios::{tcsetattr, Termios, ECHO, ICANON, TCSANOW};
+use termios::{tcsetattr, Termios, ECHO, ICANON, ISIG, IXON, TCSANOW};
 
 use nanocl_error::io::{FromIo, IoResult};
 use nanocld_client::{
   stubs::{
+    generic::{GenericClause, GenericFilter},
     process::{OutputKind, OutputLog},
     system::{EventActorKind, NativeEventAction},
-    vm::VmInspect,
-    vm_spec::VmSpecPartial,
+    vm::{
+      VmAttachQuery, VmConsoleSize, VmDeleteQuery, VmInspect, VmLogQuery,
+      VmStats,
+    },
+    vm_spec::{VmCloudInit, VmSpecPartial},
   },
   NanocldClient,
 };
@@ -28,13 +32,15 @@ use nanocld_client::{
 use crate::{
   config::CliConfig,
   models::{
-    GenericDefaultOpts, VmArg, VmCommand, VmCreateOpts, VmPatchOpts, VmRow,
-    VmRunOpts,
+    GenericRemoveOpts, GenericRemovePropagationOpts, VmArg, VmAttachOpts,
+    VmCommand, VmCreateOpts, VmLogsOpts, VmPatchOpts, VmRow, VmRunOpts,
+    VmStatsOpts, VmStatsRow,
   },
   utils,
 };
 
 use super::vm_image::exec_vm_image;
+use super::vm_snapshot::exec_vm_snapshot;
 use super::{
   GenericCommand, GenericCommandInspect, GenericCommandLs, GenericCommandRm,
   GenericCommandStart, GenericCommandStop,
@@ -56,7 +62,20 @@ impl GenericCommandLs for VmArg {
   }
 }
 
-impl GenericCommandRm<GenericDefaultOpts, String> for VmArg {}
+impl GenericCommandRm<GenericRemovePropagationOpts, VmDeleteQuery> for VmArg {
+  fn get_query(
+    opts: &GenericRemoveOpts<GenericRemovePropagationOpts>,
+    namespace: Option<String>,
+  ) -> Option<VmDeleteQuery>
+  where
+    VmDeleteQuery: serde::Serialize,
+  {
+    Some(VmDeleteQuery {
+      namespace,
+      propagation: opts.others.propagation.clone(),
+    })
+  }
+}
 
 impl GenericCommandStart for VmArg {}
 
@@ -82,6 +101,22 @@ async fn wait_vm_state(
   Ok(waiter)
 }
 
+/// Set the cloud-init user-data of a virtual machine from a cloud-config file
+fn set_user_data(
+  vm: &mut VmSpecPartial,
+  path: &Option<String>,
+) -> IoResult<()> {
+  if let Some(path) = path {
+    let user_data = std::fs::read_to_string(path)
+      .map_err(|err| err.map_err_context(|| path))?;
+    vm.cloud_init = Some(VmCloudInit {
+      user_data: Some(user_data),
+      ..Default::default()
+    });
+  }
+  Ok(())
+}
+
 /// Function executed when running `nanocl vm create`
 /// It will create a new virtual machine but not start it
 pub async fn exec_vm_create(
@@ -90,7 +125,8 @@ pub async fn exec_vm_create(
   options: &VmCreateOpts,
 ) -> IoResult<()> {
   let client = &cli_conf.client;
-  let vm = options.clone().into();
+  let mut vm: VmSpecPartial = options.clone().into();
+  set_user_data(&mut vm, &options.user_data)?;
   let vm = client.create_vm(&vm, args.namespace.as_deref()).await?;
   println!("{}", &vm.spec.vm_key);
   Ok(())
@@ -105,7 +141,8 @@ pub async fn exec_vm_run(
   options: &VmRunOpts,
 ) -> IoResult<()> {
   let client = &cli_conf.client;
-  let vm: VmSpecPartial = options.clone().into();
+  let mut vm: VmSpecPartial = options.clone().into();
+  set_user_data(&mut vm, &options.user_data)?;
   let waiter =
     wait_vm_state(&vm.name, args, NativeEventAction::Start, client).await?;
   let vm = client.create_vm(&vm, args.namespace.as_deref()).await?;
@@ -116,7 +153,13 @@ pub async fn exec_vm_run(
   if options.attach {
     #[cfg(not(target_os = "windows"))]
     {
-      exec_vm_attach(cli_conf, args, &options.name).await?;
+      let opts = VmAttachOpts {
+        name: options.name.clone(),
+        detach_keys: options.detach_keys.clone(),
+        // The guest is booting so there is no shell to type stty in
+        resize_guest: false,
+      };
+      exec_vm_attach(cli_conf, args, &opts).await?;
     }
     #[cfg(target_os = "windows")]
     {
@@ -145,19 +188,69 @@ pub async fn exec_vm_patch(
   Ok(())
 }
 
+/// Get the size of the terminal attached to stdout
+#[cfg(not(target_os = "windows"))]
+fn get_console_size() -> Option<VmConsoleSize> {
+  let mut size = nix::libc::winsize {
+    ws_row: 0,
+    ws_col: 0,
+    ws_xpixel: 0,
+    ws_ypixel: 0,
+  };
+  let fd = std::io::stdout().as_raw_fd();
+  // SAFETY: TIOCGWINSZ only write into the given winsize struct
+  let res = unsafe { nix::libc::ioctl(fd, nix::libc::TIOCGWINSZ, &mut size) };
+  if res != 0 || size.ws_col == 0 || size.ws_row == 0 {
+    return None;
+  }
+  Some(VmConsoleSize {
+    width: size.ws_col,
+    height: size.ws_row,
+  })
+}
+
 /// Function executed when running `nanocl vm attach`
 /// It will attach to a virtual machine console
 #[cfg(not(target_os = "windows"))]
 pub async fn exec_vm_attach(
   cli_conf: &CliConfig,
   args: &VmArg,
-  name: &str,
+  opts: &VmAttachOpts,
 ) -> IoResult<()> {
   let client = &cli_conf.client;
   /// How often heartbeat pings are sent
   const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
-  let conn = client.attach_vm(name, args.namespace.as_deref()).await?;
+  /// How often the terminal size is checked
+  const RESIZE_INTERVAL: Duration = Duration::from_millis(500);
+  let query = VmAttachQuery {
+    namespace: args.namespace.clone(),
+    detach_keys: opts.detach_keys.clone(),
+    resize_guest: Some(opts.resize_guest),
+  };
+  let conn = client.attach_vm(&opts.name, Some(&query)).await?;
   let (mut tx, mut rx) = mpsc::unbounded();
+  // keep the console size in sync with the terminal
+  let mut resize_tx = tx.clone();
+  rt::spawn(async move {
+    let mut current = None;
+    loop {
+      let size = get_console_size();
+      if size.is_some() && size != current {
+        current = size;
+        let Ok(msg) = serde_json::to_vec(&size) else {
+          return;
+        };
+        if resize_tx
+          .send(ws::Message::Binary(Bytes::from(msg)))
+          .await
+          .is_err()
+        {
+          return;
+        }
+      }
+      time::sleep(RESIZE_INTERVAL).await;
+    }
+  });
   // start heartbeat task
   let sink = conn.sink();
   rt::spawn(async move {
@@ -172,8 +265,10 @@ pub async fn exec_vm_attach(
   let mut termios = Termios::from_fd(std::io::stdin().as_raw_fd())?;
   // Save a copy of the original terminal settings
   let original_termios = termios;
-  // Disable canonical mode and echo
-  termios.c_lflag &= !(ICANON | ECHO);
+  // Disable canonical mode, echo and signals
+  // so control keys are sent to the vm and can be used to detach
+  termios.c_lflag &= !(ICANON | ECHO | ISIG);
+  termios.c_iflag &= !IXON;
   // Redirect the output of the console to the TTY device
   let mut stderr = std::io::stderr();
   let mut stdout = std::io::stdout();
@@ -237,7 +332,7 @@ pub async fn exec_vm_attach(
           .await
           .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
       }
-      Err(_) => break,
+      Ok(ws::Frame::Close(_)) | Err(_) => break,
       _ => (),
     }
   }
@@ -246,6 +341,61 @@ pub async fn exec_vm_attach(
   Ok(())
 }
 
+/// Function executed when running `nanocl vm logs`
+/// It will print the serial console logs of a virtual machine
+pub async fn exec_vm_logs(
+  cli_conf: &CliConfig,
+  args: &VmArg,
+  opts: &VmLogsOpts,
+) -> IoResult<()> {
+  let query = VmLogQuery {
+    namespace: args.namespace.clone(),
+    tail: opts.tail.clone(),
+    follow: Some(opts.follow),
+  };
+  let mut stream = cli_conf.client.logs_vm(&opts.name, Some(&query)).await?;
+  let mut stdout = std::io::stdout();
+  while let Some(output) = stream.next().await {
+    let output = output?;
+    stdout.write_all(output.data.as_bytes())?;
+    stdout.flush()?;
+  }
+  Ok(())
+}
+
+/// Function executed when running `nanocl vm stats`
+/// It will print the last statistics sampled by the daemon for the vms
+pub async fn exec_vm_stats(
+  cli_conf: &CliConfig,
+  opts: &VmStatsOpts,
+  namespace: &str,
+) -> IoResult<()> {
+  let mut rows = Vec::new();
+  for name in &opts.names {
+    let filter = GenericFilter::new()
+      .r#where("kind", GenericClause::Eq("nanocl.io/vm".to_owned()))
+      .r#where(
+        "data",
+        GenericClause::Contains(serde_json::json!({
+          "Name": name,
+          "Namespace": namespace,
+        })),
+      )
+      .limit(2);
+    let metrics = cli_conf.client.list_metric(Some(&filter)).await?;
+    let mut samples = metrics.into_iter().filter_map(|metric| {
+      let stats = serde_json::from_value::<VmStats>(metric.data).ok()?;
+      Some((metric.created_at, stats))
+    });
+    match samples.next() {
+      Some(last) => rows.push(VmStatsRow::new(last, samples.next())),
+      None => eprintln!("No stats found for vm {name}"),
+    }
+  }
+  utils::print::print_table(rows);
+  Ok(())
+}
+
 /// Function executed when running `nanocl vm`
 /// It will execute the subcommand passed as argument
 pub async fn exec_vm(cli_conf: &CliConfig, args: &VmArg) -> IoResult<()> {
@@ -253,6 +403,9 @@ pub async fn exec_vm(cli_conf: &CliConfig, args: &VmArg) -> IoResult<()> {
   let namespace = args.namespace.clone().unwrap_or("global".to_owned());
   match &args.command {
     VmCommand::Image(args) => exec_vm_image(client, args).await,
+    VmCommand::Snapshot(args) => {
+      exec_vm_snapshot(client, args, &namespace).await
+    }
     VmCommand::Create(options) => exec_vm_create(cli_conf, args, options).await,
     VmCommand::List(opts) => VmArg::exec_ls(client, args, opts).await,
     VmCommand::Remove(opts) => {
@@ -267,12 +420,22 @@ pub async fn exec_vm(cli_conf: &CliConfig, args: &VmArg) -> IoResult<()> {
     VmCommand::Stop(opts) => {
       VmArg::exec_stop(client, opts, Some(namespace.clone())).await
     }
+    VmCommand::Reboot { name } => {
+      client.reboot_process("vm", name, Some(&namespace)).await?;
+      Ok(())
+    }
+    VmCommand::Reset { name } => {
+      client.reset_process("vm", name, Some(&namespace)).await?;
+      Ok(())
+    }
     VmCommand::Run(options) => exec_vm_run(cli_conf, args, options).await,
     VmCommand::Patch(options) => exec_vm_patch(cli_conf, args, options).await,
-    VmCommand::Attach { name } => {
+    VmCommand::Logs(opts) => exec_vm_logs(cli_conf, args, opts).await,
+    VmCommand::Stats(opts) => exec_vm_stats(cli_conf, opts, &namespace).await,
+    VmCommand::Attach(opts) => {
       #[cfg(not(target_os = "windows"))]
       {
-        exec_vm_attach(cli_conf, args, name).await
+        exec_vm_attach(cli_conf, args, opts).await
       }
       #[cfg(target_os = "windows")]
       {
@@ -1,19 +1,27 @@
 use std::path::Path;
 
-use futures::StreamExt;
+use futures::{future, StreamExt};
 use indicatif::{ProgressBar, ProgressStyle};
+use tokio::io::AsyncWriteExt;
 use tokio_util::codec;
 
 use nanocl_error::io::{FromIo, IoResult};
 use nanocld_client::{
-  stubs::vm_image::{VmImage, VmImageCloneStream},
+  stubs::{
+    system::{EventActorKind, EventCondition, EventKind, NativeEventAction},
+    vm_image::{
+      VmImage, VmImageCloneStream, VmImageExportQuery, VmImagePullPayload,
+      VmImagePullProgress,
+    },
+  },
   NanocldClient,
 };
 
 use crate::{
   models::{
-    GenericDefaultOpts, VmImageArg, VmImageCommand, VmImageCreateOpts,
-    VmImageResizeOpts, VmImageRow,
+    convert_size, GenericDefaultOpts, VmImageArg, VmImageCommand,
+    VmImageCreateOpts, VmImageExportOpts, VmImagePullOpts, VmImageResizeOpts,
+    VmImageRow, VmImageTemplateOpts,
   },
   utils,
 };
@@ -105,6 +113,140 @@ async fn exec_vm_image_clone(
   Ok(())
 }
 
+/// Generate the name of a pulled image from the file name in the url
+fn gen_pull_name(url: &str) -> String {
+  let file_name = url
+    .split(['?', '#'])
+    .next()
+    .unwrap_or_default()
+    .trim_end_matches('/')
+    .rsplit('/')
+    .next()
+    .unwrap_or_default();
+  let mut name = file_name;
+  for ext in [".gz", ".xz", ".img", ".qcow2", ".raw", ".vmdk", ".vhdx"] {
+    name = name.strip_suffix(ext).unwrap_or(name);
+  }
+  name
+    .chars()
+    .map(|c| {
+      if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+        c
+      } else {
+        '-'
+      }
+    })
+    .collect()
+}
+
+/// Function that execute when running `nanocl vm image pull`
+async fn exec_vm_image_pull(
+  client: &NanocldClient,
+  options: &VmImagePullOpts,
+) -> IoResult<()> {
+  let name = options
+    .name
+    .clone()
+    .unwrap_or_else(|| gen_pull_name(&options.url));
+  let payload = VmImagePullPayload {
+    url: options.url.clone(),
+    checksum: options.checksum.clone(),
+  };
+  // Watch the progress before pulling to not miss the first events
+  let mut events = client
+    .watch_events(Some(vec![EventCondition {
+      actor_key: Some(name.clone()),
+      actor_kind: Some(EventActorKind::VmImage),
+      kind: vec![EventKind::Normal],
+      action: vec![NativeEventAction::Downloading],
+      ..Default::default()
+    }]))
+    .await?;
+  let pg = ProgressBar::new(100);
+  let style = ProgressStyle::with_template(
+    "[{elapsed_precise}] [{bar:20.cyan/blue}] {pos:>7}% {msg}",
+  )
+  .unwrap()
+  .progress_chars("=> ");
+  pg.set_style(style);
+  let progress = std::pin::pin!(async {
+    while let Some(Ok(event)) = events.next().await {
+      let Some(progress) = event
+        .metadata
+        .and_then(|metadata| metadata.get("state").cloned())
+        .and_then(|state| {
+          serde_json::from_value::<VmImagePullProgress>(state).ok()
+        })
+      else {
+        continue;
+      };
+      match progress {
+        VmImagePullProgress::Download(progress) => {
+          pg.set_message("Downloading");
+          if let Some(total) = progress.total {
+            pg.set_position(utils::math::calculate_percentage(
+              progress.current,
+              total,
+            ));
+          }
+        }
+        VmImagePullProgress::Verify => {
+          pg.set_message("Verifying");
+        }
+        VmImagePullProgress::Convert(progress) => {
+          pg.set_message("Converting");
+          pg.set_position(progress as u64);
+        }
+      }
+    }
+  });
+  let pull = std::pin::pin!(client.pull_vm_image(&name, &payload));
+  let res = match future::select(pull, progress).await {
+    future::Either::Left((res, _)) => res,
+    future::Either::Right((_, pull)) => pull.await,
+  };
+  pg.finish_and_clear();
+  println!("{}", res?.name);
+  Ok(())
+}
+
+/// Function that execute when running `nanocl vm image export`
+async fn exec_vm_image_export(
+  client: &NanocldClient,
+  options: &VmImageExportOpts,
+) -> IoResult<()> {
+  let output = options
+    .output
+    .clone()
+    .unwrap_or_else(|| format!("{}.qcow2", options.name));
+  let query = VmImageExportQuery {
+    flatten: Some(options.flatten),
+    compress: Some(options.compress),
+  };
+  let mut stream = client.export_vm_image(&options.name, Some(&query)).await?;
+  let mut file = tokio::fs::File::create(&output)
+    .await
+    .map_err(|err| err.map_err_context(|| &output))?;
+  let pg = ProgressBar::new_spinner();
+  let mut received: u64 = 0;
+  while let Some(bytes) = stream.next().await {
+    let bytes = bytes?;
+    file
+      .write_all(&bytes)
+      .await
+      .map_err(|err| err.map_err_context(|| &output))?;
+    received += bytes.len() as u64;
+    pg.set_message(convert_size(received as i64));
+    pg.tick();
+  }
+  file
+    .flush()
+    .await
+    .map_err(|err| err.map_err_context(|| &output))?;
+  pg.finish_and_clear();
+  Ok(())
+}
+
 /// Function that execute when running `nanocl vm resize`
 async fn exec_vm_resize(
   client: &NanocldClient,
@@ -115,6 +257,19 @@ async fn exec_vm_resize(
   Ok(())
 }
 
+/// Function that execute when running `nanocl vm image template`
+async fn exec_vm_image_template(
+  client: &NanocldClient,
+  options: &VmImageTemplateOpts,
+) -> IoResult<()> {
+  if options.unset {
+    client.untemplate_vm_image(&options.name).await?;
+  } else {
+    client.template_vm_image(&options.name).await?;
+  }
+  Ok(())
+}
+
 /// Function that execute when running `nanocl vm image`
 pub async fn exec_vm_image(
   client: &NanocldClient,
@@ -132,5 +287,14 @@ pub async fn exec_vm_image(
       exec_vm_image_clone(client, name, clone_name).await
     }
     VmImageCommand::Resize(opts) => exec_vm_resize(client, opts).await,
+    VmImageCommand::Pull(opts) => exec_vm_image_pull(client, opts).await,
+    VmImageCommand::Export(opts) => exec_vm_image_export(client, opts).await,
+    VmImageCommand::Template(opts) => {
+      exec_vm_image_template(client, opts).await
+    }
+    VmImageCommand::Flatten { name } => {
+      client.flatten_vm_image(name).await?;
+      Ok(())
+    }
   }
 }
@@ -0,0 +1,93 @@
+use nanocl_error::io::{FromIo, IoResult};
+use nanocld_client::NanocldClient;
+
+use crate::{
+  models::{
+    VmSnapshotArg, VmSnapshotCommand, VmSnapshotCreateOpts,
+    VmSnapshotRemoveOpts, VmSnapshotRow,
+  },
+  utils,
+};
+
+/// Function that execute when running `nanocl vm snapshot ls`
+async fn exec_vm_snapshot_ls(
+  client: &NanocldClient,
+  name: &str,
+  quiet: bool,
+  namespace: &str,
+) -> IoResult<()> {
+  let snapshots = client.list_vm_snapshot(name, Some(namespace)).await?;
+  if quiet {
+    for snapshot in snapshots {
+      println!("{}", snapshot.key);
+    }
+    return Ok(());
+  }
+  let rows = snapshots
+    .into_iter()
+    .map(VmSnapshotRow::from)
+    .collect::<Vec<VmSnapshotRow>>();
+  utils::print::print_table(rows);
+  Ok(())
+}
+
+/// Function that execute when running `nanocl vm snapshot create`
+async fn exec_vm_snapshot_create(
+  client: &NanocldClient,
+  opts: &VmSnapshotCreateOpts,
+  namespace: &str,
+) -> IoResult<()> {
+  let payload = opts.clone().into();
+  let snapshot = client
+    .create_vm_snapshot(&opts.name, &payload, Some(namespace))
+    .await?;
+  println!("{}", snapshot.key);
+  Ok(())
+}
+
+/// Function that execute when running `nanocl vm snapshot rm`
+async fn exec_vm_snapshot_rm(
+  client: &NanocldClient,
+  opts: &VmSnapshotRemoveOpts,
+  namespace: &str,
+) -> IoResult<()> {
+  if !opts.skip_confirm {
+    utils::dialog::confirm(&format!(
+      "Delete snapshots {} of vm {} ?",
+      opts.keys.join(","),
+      opts.name
+    ))
+    .map_err(|err| err.map_err_context(|| "Delete"))?;
+  }
+  for key in &opts.keys {
+    client
+      .delete_vm_snapshot(&opts.name, key, Some(namespace))
+      .await?;
+  }
+  Ok(())
+}
+
+/// Function that execute when running `nanocl vm snapshot`
+pub async fn exec_vm_snapshot(
+  client: &NanocldClient,
+  args: &VmSnapshotArg,
+  namespace: &str,
+) -> IoResult<()> {
+  match &args.command {
+    VmSnapshotCommand::Create(opts) => {
+      exec_vm_snapshot_create(client, opts, namespace).await
+    }
+    VmSnapshotCommand::List { name, quiet } => {
+      exec_vm_snapshot_ls(client, name, *quiet, namespace).await
+    }
+    VmSnapshotCommand::Remove(opts) => {
+      exec_vm_snapshot_rm(client, opts, namespace).await
+    }
+    VmSnapshotCommand::Restore { name, key } => {
+      client
+        .restore_vm_snapshot(name, key, Some(namespace))
+        .await?;
+      Ok(())
+    }
+  }
+}
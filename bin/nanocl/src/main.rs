@@ -778,7 +778,40 @@ mod tests {
       "../../tests/ca.key"
     );
     assert_cli_ok!("secret", "inspect", "test-cli");
+    assert_cli_ok!("secret", "inspect", "--reveal", "test-cli");
+    assert_cli_ok!("secret", "history", "test-cli");
     assert_cli_ok!("secret", "rm", "-y", "test-cli");
+    assert_cli_ok!(
+      "secret",
+      "create",
+      "test-cli-file",
+      "file",
+      "--from-file",
+      "server.key=../../tests/server.key",
+      "--mode",
+      "0600"
+    );
+    assert_cli_ok!("secret", "inspect", "test-cli-file");
+    assert_cli_ok!("secret", "rm", "-y", "test-cli-file");
+    assert_cli_ok!(
+      "secret",
+      "seal",
+      "--from-file",
+      "../../tests/Statefile.yml",
+      "test-cli/statefile"
+    );
+    assert_cli_ok!(
+      "secret",
+      "create",
+      "test-cli-issued",
+      "tls-issued",
+      "--san",
+      "test-cli.global.c",
+      "--lifetime",
+      "7"
+    );
+    assert_cli_ok!("secret", "inspect", "test-cli-issued");
+    assert_cli_ok!("secret", "rm", "-y", "test-cli-issued");
   }
 
   #[ntex::test]
@@ -810,6 +843,7 @@ mod tests {
     assert_cli_ok!("vm", "ls");
     assert_cli_ok!("vm", "inspect", "test-cli-vm");
     assert_cli_ok!("vm", "start", "test-cli-vm");
+    assert_cli_ok!("vm", "stats", "test-cli-vm");
     assert_cli_ok!("vm", "stop", "test-cli-vm");
     assert_cli_ok!("vm", "rm", "-y", "test-cli-vm");
     assert_cli_ok!("vm", "run", "test-cli-vm", "test-cli-image");
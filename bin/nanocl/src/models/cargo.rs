@@ -7,13 +7,25 @@ use bollard_next::exec::CreateExecOptions;
 use nanocld_client::stubs::{
   cargo::CargoSummary,
   cargo_spec::{CargoSpecPartial, CargoSpecUpdate, Config, HostConfig},
+  generic::DeletePropagation,
 };
 
 use super::{
-  GenericInspectOpts, GenericListOpts, GenericRemoveForceOpts,
-  GenericRemoveOpts, GenericStartOpts, GenericStopOpts,
+  GenericInspectOpts, GenericListOpts, GenericRemoveOpts, GenericStartOpts,
+  GenericStopOpts,
 };
 
+/// `nanocl cargo rm` available options
+#[derive(Clone, Parser)]
+pub struct CargoRemoveOpts {
+  /// Remove the cargoes even if they are running
+  #[clap(short = 'f', long)]
+  pub force: bool,
+  /// What happens to the owned resources: foreground, background or orphan
+  #[clap(long)]
+  pub propagation: Option<DeletePropagation>,
+}
+
 /// `nanocl cargo create` available options
 #[derive(Clone, Parser)]
 pub struct CargoCreateOpts {
@@ -252,7 +264,7 @@ pub enum CargoCommand {
   Restart(CargoRestartOpts),
   /// Remove cargo by its name
   #[clap(alias("rm"))]
-  Remove(GenericRemoveOpts<GenericRemoveForceOpts>),
+  Remove(GenericRemoveOpts<CargoRemoveOpts>),
   /// Inspect a cargo by its name
   Inspect(GenericInspectOpts),
   /// Update a cargo by its name
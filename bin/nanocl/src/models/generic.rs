@@ -1,7 +1,10 @@
 use clap::{Args, Parser};
 use serde::Deserialize;
 
-use nanocld_client::stubs::{generic::GenericFilter, system::ObjPsStatus};
+use nanocld_client::stubs::{
+  generic::{DeletePropagation, GenericFilter},
+  system::ObjPsStatus,
+};
 
 use super::DisplayFormat;
 
@@ -81,6 +84,15 @@ pub struct GenericRemoveForceOpts {
   pub force: bool,
 }
 
+/// Generic propagation options for the remove command
+/// of objects owning resources
+#[derive(Clone, Parser)]
+pub struct GenericRemovePropagationOpts {
+  /// What happens to the owned resources: foreground, background or orphan
+  #[clap(long)]
+  pub propagation: Option<DeletePropagation>,
+}
+
 /// Generic start options for the start command
 #[derive(Clone, Parser)]
 pub struct GenericStartOpts {
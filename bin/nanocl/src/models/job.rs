@@ -5,7 +5,8 @@ use tabled::Tabled;
 use nanocld_client::stubs::{job::JobSummary, process::WaitCondition};
 
 use super::{
-  GenericInspectOpts, GenericListOpts, GenericRemoveOpts, GenericStartOpts,
+  GenericInspectOpts, GenericListOpts, GenericRemoveOpts,
+  GenericRemovePropagationOpts, GenericStartOpts,
 };
 
 /// `nanocl job wait` available options
@@ -48,7 +49,7 @@ pub enum JobCommand {
   List(GenericListOpts),
   /// Remove job by its name
   #[clap(alias("rm"))]
-  Remove(GenericRemoveOpts),
+  Remove(GenericRemoveOpts<GenericRemovePropagationOpts>),
   /// Inspect a job by its name
   Inspect(GenericInspectOpts),
   /// Show logs of a job
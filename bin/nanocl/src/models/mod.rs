@@ -20,6 +20,7 @@ mod uninstall;
 mod version;
 mod vm;
 mod vm_image;
+mod vm_snapshot;
 
 pub use backup::*;
 pub use cargo::*;
@@ -38,6 +39,7 @@ pub use state::*;
 pub use uninstall::*;
 pub use vm::*;
 pub use vm_image::*;
+pub use vm_snapshot::*;
 
 /// Cli available options and commands
 #[derive(Parser)]
@@ -4,7 +4,10 @@ use tabled::Tabled;
 
 use nanocld_client::stubs::namespace::NamespaceSummary;
 
-use super::{GenericInspectOpts, GenericListOpts, GenericRemoveOpts};
+use super::{
+  GenericInspectOpts, GenericListOpts, GenericRemoveForceOpts,
+  GenericRemoveOpts,
+};
 
 /// `nanocl namespace` available commands
 #[derive(Clone, Subcommand)]
@@ -15,7 +18,7 @@ pub enum NamespaceCommand {
   Inspect(GenericInspectOpts),
   /// Remove a namespace
   #[clap(alias("rm"))]
-  Remove(GenericRemoveOpts),
+  Remove(GenericRemoveOpts<GenericRemoveForceOpts>),
   /// List existing namespaces
   #[clap(alias("ls"))]
   List(GenericListOpts),
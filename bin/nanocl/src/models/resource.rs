@@ -4,14 +4,17 @@ use tabled::Tabled;
 
 use nanocld_client::stubs::resource::Resource;
 
-use super::{GenericInspectOpts, GenericListOpts, GenericRemoveOpts};
+use super::{
+  GenericInspectOpts, GenericListOpts, GenericRemoveOpts,
+  GenericRemovePropagationOpts,
+};
 
 /// `nanocl resource` available commands
 #[derive(Clone, Subcommand)]
 pub enum ResourceCommand {
   /// Remove existing resource
   #[clap(alias("rm"))]
-  Remove(GenericRemoveOpts),
+  Remove(GenericRemoveOpts<GenericRemovePropagationOpts>),
   /// List existing namespaces
   #[clap(alias("ls"))]
   List(GenericListOpts),
@@ -38,6 +41,8 @@ pub struct ResourceRow {
   pub name: String,
   /// Kind of resource
   pub kind: String,
+  /// Phase of the resource in its controller
+  pub status: String,
   /// When the resource was created
   #[tabled(rename = "CREATED AT")]
   pub created_at: String,
@@ -60,9 +65,17 @@ impl From<Resource> for ResourceRow {
       .timestamp_opt(resource.spec.created_at.and_utc().timestamp(), 0)
       .unwrap()
       .format("%Y-%m-%d %H:%M:%S");
+    let status = match resource.status {
+      None => "-".to_owned(),
+      Some(status) if status.retries > 0 => {
+        format!("{} (retry {})", status.phase, status.retries)
+      }
+      Some(status) => status.phase.to_string(),
+    };
     Self {
       name: resource.spec.resource_key,
       kind: format!("{}/{}", resource.kind, resource.spec.version),
+      status,
       created_at: format!("{created_at}"),
       updated_at: format!("{updated_at}"),
     }
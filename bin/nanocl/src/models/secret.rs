@@ -3,24 +3,75 @@ use clap::{Parser, Subcommand};
 use serde::Serialize;
 use tabled::Tabled;
 
-use nanocl_error::io::IoError;
-use nanocld_client::stubs::secret::{Secret, SecretPartial};
+use nanocld_client::stubs::secret::Secret;
 
-use super::{GenericInspectOpts, GenericListOpts, GenericRemoveOpts};
+use super::{
+  DisplayFormat, GenericListOpts, GenericRemoveForceOpts, GenericRemoveOpts,
+};
 
 /// `nanocl resource` available commands
 #[derive(Clone, Subcommand)]
 pub enum SecretCommand {
   /// Remove existing secret
   #[clap(alias("rm"))]
-  Remove(GenericRemoveOpts),
+  Remove(GenericRemoveOpts<GenericRemoveForceOpts>),
   /// List existing secret
   #[clap(alias("ls"))]
   List(GenericListOpts),
   /// Inspect a secret
-  Inspect(GenericInspectOpts),
+  Inspect(SecretInspectOpts),
   /// Create a new secret
   Create(SecretCreateOpts),
+  /// Rotate the master key and re-encrypt every secret
+  RotateKey,
+  /// Seal a value for the file backend of the external secrets
+  Seal(SecretSealOpts),
+  /// List secret history
+  History(SecretHistoryOpts),
+  /// Revert secret to a specific history
+  Revert(SecretRevertOpts),
+}
+
+/// `nanocl secret inspect` available options
+#[derive(Clone, Parser)]
+pub struct SecretInspectOpts {
+  /// Display format
+  #[clap(long)]
+  pub display: Option<DisplayFormat>,
+  /// Show the data in clear instead of masked values
+  #[clap(long)]
+  pub reveal: bool,
+  /// Name of the secret to inspect
+  pub key: String,
+}
+
+/// `nanocl secret seal` available options
+#[derive(Clone, Parser)]
+pub struct SecretSealOpts {
+  /// Json or yaml file of the value to seal
+  #[clap(long = "from-file")]
+  pub from_file: String,
+  /// Path of the sealed file relative to `{state_dir}/secrets/external`
+  pub path: String,
+}
+
+/// `nanocl secret history` available options
+#[derive(Clone, Parser)]
+pub struct SecretHistoryOpts {
+  /// Name of secret to browse history
+  pub name: String,
+}
+
+/// `nanocl secret revert` available options
+#[derive(Clone, Parser)]
+pub struct SecretRevertOpts {
+  /// Name of secret to revert
+  pub name: String,
+  /// Revert to a specific historic
+  pub history_id: String,
+  /// Keep the cargoes using the secret running with the previous data
+  #[clap(long)]
+  pub no_rollout: bool,
 }
 
 /// `nanocl secret` available arguments
@@ -76,6 +127,37 @@ pub struct TlsCreateOpts {
   pub verify_client: bool,
 }
 
+/// Create a new nanocl.io/tls-issued secret signed by the internal CA
+#[derive(Clone, Parser)]
+pub struct TlsIssuedCreateOpts {
+  /// Subject alternative names, dns names or ip addresses
+  #[clap(long = "san", required = true)]
+  pub sans: Vec<String>,
+  /// Lifetime of the certificate in days default to 90
+  #[clap(long)]
+  pub lifetime: Option<u32>,
+  /// Verify client
+  #[clap(long)]
+  pub verify_client: bool,
+}
+
+/// Create a new nanocl.io/file secret
+#[derive(Clone, Parser)]
+pub struct FileCreateOpts {
+  /// Files to store in the form of `path` or `target=path`
+  #[clap(long = "from-file", required = true)]
+  pub from_file: Vec<String>,
+  /// Unix permissions of the files in octal at most 0777 default to 0400
+  #[clap(long)]
+  pub mode: Option<String>,
+  /// Owner uid of the files default to root
+  #[clap(long)]
+  pub uid: Option<u32>,
+  /// Owner gid of the files default to root
+  #[clap(long)]
+  pub gid: Option<u32>,
+}
+
 /// Create a new nanocl.io/container-registry secret
 #[derive(Clone, Parser, Serialize)]
 #[serde(rename_all = "PascalCase")]
@@ -89,63 +171,12 @@ pub struct ContainerRegistryCreateOpts {
   pub registrytoken: Option<String>,
 }
 
-impl TryFrom<SecretCreateOpts> for SecretPartial {
-  type Error = IoError;
-
-  fn try_from(opts: SecretCreateOpts) -> Result<Self, Self::Error> {
-    let (kind, data) = match &opts.kind {
-      SecretKindCreateCommand::Env(env) => {
-        ("nanocl.io/env", serde_json::to_value(&env.values)?)
-      }
-      SecretKindCreateCommand::Tls(tls) => {
-        let mut cert = tls.certificate.clone();
-        let mut cert_key = tls.certificate_key.clone();
-        let mut cert_client = tls.certificate_client.clone();
-        if cert.is_none() && tls.certificate_path.is_none() {
-          return Err(IoError::interrupted("Certificate", "is required"));
-        }
-        if cert_key.is_none() && tls.certificate_key_path.is_none() {
-          return Err(IoError::interrupted("Certificate key", "is required"));
-        }
-        if let Some(certificate_path) = &tls.certificate_path {
-          cert = Some(std::fs::read_to_string(certificate_path)?);
-        }
-        if let Some(certificate_key_path) = &tls.certificate_key_path {
-          cert_key = Some(std::fs::read_to_string(certificate_key_path)?);
-        }
-        if let Some(certificate_client_path) = &tls.certificate_client_path {
-          cert_client = Some(std::fs::read_to_string(certificate_client_path)?);
-        }
-        let tls = TlsCreateOpts {
-          certificate: cert,
-          certificate_key: cert_key,
-          certificate_client: cert_client,
-          certificate_path: None,
-          certificate_key_path: None,
-          certificate_client_path: None,
-          ..tls.clone()
-        };
-        ("nanocl.io/tls", serde_json::to_value(tls)?)
-      }
-      SecretKindCreateCommand::ContainerRegistry(container_registry) => (
-        "nanocl.io/container-registry",
-        serde_json::to_value(container_registry)?,
-      ),
-    };
-    Ok(Self {
-      name: opts.name,
-      kind: kind.to_string(),
-      immutable: false,
-      data,
-      metadata: None,
-    })
-  }
-}
-
 #[derive(Clone, Subcommand)]
 pub enum SecretKindCreateCommand {
   Env(EnvCreateOpts),
   Tls(TlsCreateOpts),
+  TlsIssued(TlsIssuedCreateOpts),
+  File(FileCreateOpts),
   ContainerRegistry(ContainerRegistryCreateOpts),
 }
 
@@ -2,14 +2,15 @@ use chrono::TimeZone;
 use clap::{Parser, Subcommand};
 use tabled::Tabled;
 
-use nanocld_client::stubs::vm::VmSummary;
+use nanocld_client::stubs::vm::{VmStats, VmSummary};
 use nanocld_client::stubs::vm_spec::{
   VmDisk, VmHostConfig, VmSpecPartial, VmSpecUpdate,
 };
 
 use super::{
-  GenericInspectOpts, GenericListOpts, GenericRemoveOpts, GenericStartOpts,
-  GenericStopOpts, VmImageArg,
+  GenericInspectOpts, GenericListOpts, GenericRemoveOpts,
+  GenericRemovePropagationOpts, GenericStartOpts, GenericStopOpts, VmImageArg,
+  VmSnapshotArg,
 };
 
 /// `nanocl vm` available commands
@@ -19,6 +20,8 @@ pub enum VmCommand {
   Run(VmRunOpts),
   /// Manage vm images
   Image(VmImageArg),
+  /// Manage vm snapshots
+  Snapshot(VmSnapshotArg),
   /// Create a vm
   Create(VmCreateOpts),
   /// List vms
@@ -26,22 +29,68 @@ pub enum VmCommand {
   List(GenericListOpts),
   /// Remove vms
   #[clap(alias = "rm")]
-  Remove(GenericRemoveOpts),
+  Remove(GenericRemoveOpts<GenericRemovePropagationOpts>),
   /// Inspect a vm
   Inspect(GenericInspectOpts),
   /// Start a vm
   Start(GenericStartOpts),
   /// Stop a vm
   Stop(GenericStopOpts),
-  /// Attach to a vm
-  Attach {
+  /// Reboot a vm gracefully
+  Reboot {
+    /// Name of the vm
+    name: String,
+  },
+  /// Reset a vm like pressing its reset button
+  Reset {
     /// Name of the vm
     name: String,
   },
+  /// Attach to a vm
+  Attach(VmAttachOpts),
+  /// Show the serial console logs of a vm
+  Logs(VmLogsOpts),
+  /// Show the last resource usage sampled for vms
+  Stats(VmStatsOpts),
   /// Patch a vm
   Patch(VmPatchOpts),
 }
 
+/// `nanocl vm attach` available options
+#[derive(Clone, Parser)]
+pub struct VmAttachOpts {
+  /// Name of the vm
+  pub name: String,
+  /// Key sequence to detach from the vm (eg: ctrl-p,ctrl-q) default to ctrl-c
+  #[clap(long)]
+  pub detach_keys: Option<String>,
+  /// Type stty rows and cols on the console when the terminal is resized,
+  /// use it only while the console is at a shell prompt
+  #[clap(long)]
+  pub resize_guest: bool,
+}
+
+/// `nanocl vm logs` available options
+#[derive(Clone, Parser)]
+pub struct VmLogsOpts {
+  /// Name of the vm
+  pub name: String,
+  /// If integer only return last n lines, if "all" returns all lines
+  #[clap(short = 't')]
+  pub tail: Option<String>,
+  /// Bool, if set open the log as stream
+  #[clap(short = 'f')]
+  pub follow: bool,
+}
+
+/// `nanocl vm stats` available options
+#[derive(Clone, Parser)]
+pub struct VmStatsOpts {
+  /// Names of the vms
+  #[clap(required = true)]
+  pub names: Vec<String>,
+}
+
 /// `nanocl vm patch` available options
 #[derive(Clone, Parser)]
 pub struct VmPatchOpts {
@@ -68,6 +117,9 @@ pub struct VmPatchOpts {
   /// Enable KVM
   #[clap(long)]
   pub kvm: bool,
+  /// Seconds to wait for the vm to power off before forcing the stop
+  #[clap(long)]
+  pub shutdown_timeout: Option<u64>,
   /// network interface of the vm
   #[clap(long)]
   pub net_iface: Option<String>,
@@ -84,6 +136,7 @@ impl From<VmPatchOpts> for VmSpecUpdate {
       hostname: val.hostname,
       host_config: Some(VmHostConfig {
         kvm: Some(val.kvm),
+        shutdown_timeout: val.shutdown_timeout,
         cpu: val.cpu.unwrap_or(1),
         memory: val.memory.unwrap_or(512),
         net_iface: val.net_iface,
@@ -124,9 +177,18 @@ pub struct VmRunOpts {
   /// Enable KVM
   #[clap(long)]
   pub kvm: bool,
+  /// Seconds to wait for the vm to power off before forcing the stop
+  #[clap(long)]
+  pub shutdown_timeout: Option<u64>,
+  /// Path to a cloud-config file used as user-data
+  #[clap(long)]
+  pub user_data: Option<String>,
   /// Attach to the vm
   #[clap(short, long)]
   pub attach: bool,
+  /// Key sequence to detach from the vm (eg: ctrl-p,ctrl-q) default to ctrl-c
+  #[clap(long)]
+  pub detach_keys: Option<String>,
   /// Name of the vm
   pub name: String,
   /// Name of the vm image
@@ -151,6 +213,7 @@ impl From<VmRunOpts> for VmSpecPartial {
         memory: val.memory.unwrap_or(512),
         net_iface: val.net_iface,
         kvm: Some(val.kvm),
+        shutdown_timeout: val.shutdown_timeout,
         ..Default::default()
       }),
       ..Default::default()
@@ -185,6 +248,12 @@ pub struct VmCreateOpts {
   /// Enable KVM
   #[clap(long)]
   pub kvm: bool,
+  /// Seconds to wait for the vm to power off before forcing the stop
+  #[clap(long)]
+  pub shutdown_timeout: Option<u64>,
+  /// Path to a cloud-config file used as user-data
+  #[clap(long)]
+  pub user_data: Option<String>,
   /// Name of the vm
   pub name: String,
   /// Name of the vm image
@@ -205,6 +274,7 @@ impl From<VmCreateOpts> for VmSpecPartial {
         memory: val.memory.unwrap_or(512),
         net_iface: val.net_iface,
         kvm: Some(val.kvm),
+        shutdown_timeout: val.shutdown_timeout,
         ..Default::default()
       }),
       disk: VmDisk {
@@ -275,3 +345,98 @@ pub struct VmArg {
   #[clap(subcommand)]
   pub command: VmCommand,
 }
+
+/// A row of the vm stats table
+#[derive(Tabled)]
+#[tabled(rename_all = "UPPERCASE")]
+pub struct VmStatsRow {
+  /// Name of the vm
+  pub(crate) name: String,
+  /// Number of virtual cpus
+  pub(crate) vcpus: String,
+  /// Cpu usage since the previous sample
+  #[tabled(rename = "CPU %")]
+  pub(crate) cpu_usage: String,
+  /// Memory used by the guest
+  #[tabled(rename = "MEM USAGE / LIMIT")]
+  pub(crate) mem_usage_limit: String,
+  /// Bytes received and transmitted
+  #[tabled(rename = "NET I/O")]
+  pub(crate) net_io: String,
+  /// Bytes read and written
+  #[tabled(rename = "BLOCK I/O")]
+  pub(crate) block_io: String,
+  /// When the stats were sampled
+  #[tabled(rename = "SAMPLED AT")]
+  pub(crate) sampled_at: String,
+}
+
+impl VmStatsRow {
+  /// Create a row from the last sample of a vm
+  /// and the previous one used to compute the cpu usage
+  pub fn new(
+    last: (chrono::NaiveDateTime, VmStats),
+    prev: Option<(chrono::NaiveDateTime, VmStats)>,
+  ) -> Self {
+    let (sampled_at, stats) = last;
+    let cpu_time = |stats: &VmStats| {
+      stats.cpus.iter().filter_map(|cpu| cpu.time).sum::<u64>()
+    };
+    let cpu_usage = match prev {
+      Some((prev_at, prev)) if !stats.cpus.is_empty() => {
+        let elapsed =
+          (sampled_at - prev_at).num_nanoseconds().unwrap_or_default() as f64;
+        let used = cpu_time(&stats).saturating_sub(cpu_time(&prev)) as f64;
+        if elapsed > 0.0 {
+          format!("{:.2}%", used / elapsed / stats.cpus.len() as f64 * 100.0)
+        } else {
+          "<none>".to_owned()
+        }
+      }
+      _ => "<none>".to_owned(),
+    };
+    let mem_usage_limit = match &stats.memory {
+      Some(memory) => {
+        let used = match (memory.total, memory.available.or(memory.free)) {
+          (Some(total), Some(free)) => total.saturating_sub(free),
+          _ => memory.actual,
+        };
+        format!(
+          "{:.1}MiB / {:.1}MiB",
+          used as f64 / 1024.00 / 1024.00,
+          memory.actual as f64 / 1024.00 / 1024.00
+        )
+      }
+      None => "<none>".to_owned(),
+    };
+    let (rx, tx) = stats.net.iter().fold((0, 0), |(rx, tx), net| {
+      (rx + net.rx_bytes, tx + net.tx_bytes)
+    });
+    let (read, write) = stats.block.iter().fold((0, 0), |(rd, wr), block| {
+      (rd + block.rd_bytes, wr + block.wr_bytes)
+    });
+    let binding = chrono::Local::now();
+    let tz = binding.offset();
+    let sampled_at = tz
+      .timestamp_opt(sampled_at.and_utc().timestamp(), 0)
+      .unwrap()
+      .format("%Y-%m-%d %H:%M:%S");
+    Self {
+      name: stats.name,
+      vcpus: stats.cpus.len().to_string(),
+      cpu_usage,
+      mem_usage_limit,
+      net_io: format!(
+        "{:.1}MB / {:.1}MB",
+        rx as f64 / 1000.00 / 1000.00,
+        tx as f64 / 1000.00 / 1000.00
+      ),
+      block_io: format!(
+        "{:.1}MB / {:.1}MB",
+        read as f64 / 1000.00 / 1000.00,
+        write as f64 / 1000.00 / 1000.00
+      ),
+      sampled_at: format!("{sampled_at}"),
+    }
+  }
+}
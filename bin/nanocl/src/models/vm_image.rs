@@ -20,6 +20,17 @@ pub enum VmImageCommand {
   },
   /// Resize a VM image
   Resize(VmImageResizeOpts),
+  /// Pull a VM image from an http(s) url
+  Pull(VmImagePullOpts),
+  /// Export a VM image as a qcow2 file
+  Export(VmImageExportOpts),
+  /// Mark a base VM image as a read-only template
+  Template(VmImageTemplateOpts),
+  /// Detach a linked clone from the image it's based on
+  Flatten {
+    /// Name of the VM image
+    name: String,
+  },
   /// List VM images
   #[clap(alias("ls"))]
   List(GenericListOpts),
@@ -37,6 +48,45 @@ pub struct VmImageCreateOpts {
   pub file_path: String,
 }
 
+/// `nanocl vm image template` available options
+#[derive(Clone, Parser)]
+pub struct VmImageTemplateOpts {
+  /// Name of the VM image
+  pub name: String,
+  /// Turn the template back into a base image
+  #[clap(long)]
+  pub unset: bool,
+}
+
+/// `nanocl vm image pull` available options
+#[derive(Clone, Parser)]
+pub struct VmImagePullOpts {
+  /// Name of the VM image default to the name of the file in the url
+  #[clap(long)]
+  pub name: Option<String>,
+  /// Checksum to verify the image eg: sha256:<hex> or sha512:<hex>
+  #[clap(long)]
+  pub checksum: Option<String>,
+  /// Url of the VM image (raw, qcow2, vmdk or vhdx)
+  pub url: String,
+}
+
+/// `nanocl vm image export` available options
+#[derive(Clone, Parser)]
+pub struct VmImageExportOpts {
+  /// File to write the image to default to `<name>.qcow2`
+  #[clap(short, long)]
+  pub output: Option<String>,
+  /// Merge the backing chain into the exported image
+  #[clap(long)]
+  pub flatten: bool,
+  /// Compress the exported image
+  #[clap(long)]
+  pub compress: bool,
+  /// Name of the VM image
+  pub name: String,
+}
+
 /// `nanocl vm image resize` available options
 #[derive(Clone, Parser)]
 pub struct VmImageResizeOpts {
@@ -79,13 +129,15 @@ pub struct VmImageRow {
   pub format: String,
   /// Size of the VM image
   pub size: String,
+  /// Image the VM image is based on
+  pub parent: String,
   /// When the VM image was created
   #[tabled(rename = "CREATED AT")]
   pub created_at: String,
 }
 
 /// Convert size to human readable format
-fn convert_size(size: i64) -> String {
+pub(crate) fn convert_size(size: i64) -> String {
   if size >= 1_000_000_000 {
     format!("{} GB", size / 1024 / 1024 / 1024)
   } else {
@@ -112,6 +164,7 @@ impl From<VmImage> for VmImageRow {
       kind: item.kind,
       format: item.format,
       size,
+      parent: item.parent.unwrap_or_else(|| "<none>".to_owned()),
       created_at: format!("{created_at}"),
     }
   }
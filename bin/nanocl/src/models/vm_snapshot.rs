@@ -0,0 +1,113 @@
+use chrono::TimeZone;
+use clap::{Parser, Subcommand};
+use tabled::Tabled;
+
+use nanocld_client::stubs::vm_snapshot::{VmSnapshot, VmSnapshotPartial};
+
+use super::convert_size;
+
+/// `nanocl vm snapshot` available commands
+#[derive(Clone, Subcommand)]
+pub enum VmSnapshotCommand {
+  /// Create a snapshot of a VM
+  Create(VmSnapshotCreateOpts),
+  /// List snapshots of a VM
+  #[clap(alias("ls"))]
+  List {
+    /// Name of the VM
+    name: String,
+    /// Only show snapshot keys
+    #[clap(short, long)]
+    quiet: bool,
+  },
+  /// Remove snapshots of a VM
+  #[clap(alias("rm"))]
+  Remove(VmSnapshotRemoveOpts),
+  /// Restore a VM to a snapshot
+  Restore {
+    /// Name of the VM
+    name: String,
+    /// Key of the snapshot
+    key: String,
+  },
+}
+
+/// `nanocl vm snapshot create` available options
+#[derive(Clone, Parser)]
+pub struct VmSnapshotCreateOpts {
+  /// Name of the VM
+  pub name: String,
+  /// Name of the snapshot
+  pub snapshot_name: String,
+}
+
+/// Convert VmSnapshotCreateOpts to VmSnapshotPartial
+impl From<VmSnapshotCreateOpts> for VmSnapshotPartial {
+  fn from(opts: VmSnapshotCreateOpts) -> Self {
+    Self {
+      name: opts.snapshot_name,
+    }
+  }
+}
+
+/// `nanocl vm snapshot rm` available options
+#[derive(Clone, Parser)]
+pub struct VmSnapshotRemoveOpts {
+  /// Skip confirmation
+  #[clap(short = 'y', long)]
+  pub skip_confirm: bool,
+  /// Name of the VM
+  pub name: String,
+  /// Keys of the snapshots to remove
+  pub keys: Vec<String>,
+}
+
+/// `nanocl vm snapshot` available arguments
+#[derive(Clone, Parser)]
+pub struct VmSnapshotArg {
+  /// Command to run
+  #[clap(subcommand)]
+  pub command: VmSnapshotCommand,
+}
+
+/// A row for the vm snapshot table
+#[derive(Tabled)]
+#[tabled(rename_all = "UPPERCASE")]
+pub struct VmSnapshotRow {
+  /// Key of the snapshot
+  pub key: String,
+  /// Name of the snapshot
+  pub name: String,
+  /// Key of the parent snapshot
+  pub parent: String,
+  /// Size of the snapshot
+  pub size: String,
+  /// When the snapshot was created
+  #[tabled(rename = "CREATED AT")]
+  pub created_at: String,
+}
+
+/// Convert VmSnapshot to VmSnapshotRow
+impl From<VmSnapshot> for VmSnapshotRow {
+  fn from(item: VmSnapshot) -> Self {
+    // Convert the created_at to the current timezone
+    let binding = chrono::Local::now();
+    let tz = binding.offset();
+    let created_at = tz
+      .timestamp_opt(item.created_at.and_utc().timestamp(), 0)
+      .unwrap()
+      .format("%Y-%m-%d %H:%M:%S");
+    let size_virtual = convert_size(item.size_virtual);
+    let size_actual = convert_size(item.size_actual);
+    Self {
+      key: item.key.to_string(),
+      name: item.name,
+      parent: item
+        .parent
+        .map(|parent| parent.to_string())
+        .unwrap_or("<none>".to_owned()),
+      size: format!("{size_actual} / {size_virtual}"),
+      created_at: format!("{created_at}"),
+    }
+  }
+}
@@ -0,0 +1,17 @@
+-- Your SQL goes here
+CREATE TABLE IF NOT EXISTS "vm_snapshots" (
+  "key" UUID NOT NULL PRIMARY KEY,
+  "name" VARCHAR NOT NULL,
+  "vm_key" VARCHAR NOT NULL REFERENCES vms("key"),
+  "created_at" TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  "path" VARCHAR NOT NULL,
+  "parent" UUID REFERENCES vm_snapshots("key"),
+  "size_actual" BIGINT NOT NULL,
+  "size_virtual" BIGINT NOT NULL
+);
+
+CREATE INDEX "vm_snapshots_key_idx" ON "vm_snapshots" ("key");
+CREATE INDEX "vm_snapshots_name_idx" ON "vm_snapshots" ("name");
+CREATE INDEX "vm_snapshots_vm_key_idx" ON "vm_snapshots" ("vm_key");
+CREATE INDEX "vm_snapshots_created_at_idx" ON "vm_snapshots" ("created_at");
+CREATE INDEX "vm_snapshots_parent_idx" ON "vm_snapshots" ("parent");
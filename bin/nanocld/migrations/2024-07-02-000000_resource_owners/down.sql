@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS "resources_owners_idx";
+ALTER TABLE "resources" DROP COLUMN IF EXISTS "finalizers";
+ALTER TABLE "resources" DROP COLUMN IF EXISTS "owners";
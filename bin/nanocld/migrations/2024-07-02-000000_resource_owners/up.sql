@@ -0,0 +1,5 @@
+-- Your SQL goes here
+ALTER TABLE "resources" ADD COLUMN IF NOT EXISTS "owners" JSONB NOT NULL DEFAULT '[]';
+ALTER TABLE "resources" ADD COLUMN IF NOT EXISTS "finalizers" JSONB NOT NULL DEFAULT '[]';
+
+CREATE INDEX "resources_owners_idx" ON "resources" USING GIN ("owners");
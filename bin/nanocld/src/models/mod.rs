@@ -26,6 +26,9 @@ pub use vm::*;
 pub mod vm_image;
 pub use vm_image::*;
 
+mod vm_snapshot;
+pub use vm_snapshot::*;
+
 mod resource;
 pub use resource::*;
 
@@ -27,6 +27,12 @@ pub struct ResourceDb {
   pub kind: String,
   /// The spec key reference
   pub spec_key: uuid::Uuid,
+  /// The status reported by the controller
+  pub status: Option<serde_json::Value>,
+  /// The objects owning the resource
+  pub owners: serde_json::Value,
+  /// The finalizers blocking the deletion of the resource
+  pub finalizers: serde_json::Value,
 }
 
 /// This structure represent the update of a resource in the database.
@@ -37,6 +43,12 @@ pub struct ResourceUpdateDb {
   pub key: Option<String>,
   /// The spec key reference
   pub spec_key: Option<uuid::Uuid>,
+  /// The status reported by the controller
+  pub status: Option<serde_json::Value>,
+  /// The objects owning the resource
+  pub owners: Option<serde_json::Value>,
+  /// The finalizers blocking the deletion of the resource
+  pub finalizers: Option<serde_json::Value>,
 }
 
 /// Helper to convert a `SpecDb` to a `ResourceSpec`
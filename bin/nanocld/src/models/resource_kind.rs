@@ -71,12 +71,17 @@ impl TryFrom<&ResourceKindPartial> for SpecDb {
     let data = serde_json::to_value(&p.data)
       .map_err(|err| err.map_err_context(|| "ResourceKind"))?;
     utils::key::ensure_kind(&p.name)?;
-    if p.data.url.is_none() && p.data.schema.is_none() {
+    if p.data.url.is_none() && p.data.schema.is_none() && p.data.wasm.is_none()
+    {
       return Err(IoError::invalid_input(
         "ResourceKind",
-        "Invalid data nor url or schema defined",
+        "Invalid data nor url, schema or wasm defined",
       ));
     }
+    utils::resource_kind::validate_conversion(&p.data)?;
+    utils::resource_kind::validate_controller(&p.data)?;
+    utils::resource_kind::validate_wasm(&p.data)?;
+    utils::resource_kind::validate_immutable(&p.data)?;
     Ok(SpecDb {
       key: uuid::Uuid::new_v4(),
       created_at: chrono::Utc::now().naive_utc(),
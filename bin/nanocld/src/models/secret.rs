@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
 
@@ -10,6 +12,7 @@ use crate::schema::secrets;
 /// This structure represent the secret in the database.
 /// A secret is a key/value pair that can be used by the user to store
 /// sensitive data. It is stored as a json object in the database.
+/// The data is encrypted at rest as a [SecretEnvelope](SecretEnvelope).
 #[derive(
   Clone, Serialize, Deserialize, Queryable, Identifiable, Insertable,
 )]
@@ -82,3 +85,29 @@ impl From<&SecretUpdate> for SecretUpdateDb {
     }
   }
 }
+
+/// Encrypted data of a secret stored in place of its plain data.
+/// The data is encrypted with a random data key using AES-256-GCM,
+/// the data key is itself encrypted with a master key of the node.
+/// Both ciphers are base64 encoded as `nonce || ciphertext || tag`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "PascalCase")]
+pub struct SecretEnvelope {
+  /// Id of the master key used to encrypt the data key
+  pub key_id: String,
+  /// The data key encrypted with the master key
+  pub data_key: String,
+  /// The data encrypted with the data key
+  pub data: String,
+}
+
+/// Master keys used to encrypt the data keys of the secrets.
+/// The keys are stored in the `conf_dir` of the daemon,
+/// a new key is added during a rotation until every secret use it.
+#[derive(Clone, Default)]
+pub struct SecretKeyring {
+  /// Id of the key used to encrypt new data keys
+  pub current: String,
+  /// Master keys indexed by their id
+  pub keys: HashMap<String, Vec<u8>>,
+}
@@ -23,3 +23,12 @@ pub struct SpecDb {
   /// Metadata (user defined) of the resource kind version
   pub metadata: Option<serde_json::Value>,
 }
+
+/// This structure is used to update a specification in the database.
+/// Only the secret histories are updated to encrypt them with a new key.
+#[derive(Debug, Default, AsChangeset)]
+#[diesel(table_name = specs)]
+pub struct SpecUpdateDb {
+  /// Config of the specification
+  pub data: Option<serde_json::Value>,
+}
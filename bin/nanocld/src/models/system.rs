@@ -1,11 +1,21 @@
-use std::sync::Arc;
+use std::{
+  collections::HashMap,
+  sync::{Arc, RwLock},
+  time::Instant,
+};
 
 use futures::channel::mpsc;
 use ntex::rt;
 
-use nanocl_stubs::{config::DaemonConfig, system::Event};
+use nanocl_stubs::{
+  config::DaemonConfig, resource_kind::ResourceKindWasm, system::Event,
+};
 
-use super::{Pool, RawEventEmitter, TaskManager};
+use super::{Pool, RawEventEmitter, SecretKeyring, TaskManager};
+
+/// WebAssembly modules compiled by kind and version
+pub type WasmModules =
+  HashMap<(String, String), (ResourceKindWasm, wasmi::Engine, wasmi::Module)>;
 
 /// This structure represent the state of the system.
 /// Used to share the state between the different handlers.
@@ -19,6 +29,14 @@ pub struct SystemStateInner {
   pub config: DaemonConfig,
   /// Manager of the tasks
   pub task_manager: TaskManager,
+  /// Master keys used to encrypt the secrets
+  pub(crate) secret_keys: RwLock<SecretKeyring>,
+  /// Values of the external secrets with the time they have been resolved
+  pub(crate) external_secrets:
+    RwLock<HashMap<String, (Instant, serde_json::Value)>>,
+  /// Compiled WebAssembly controllers by kind and version
+  /// with the module definition they have been compiled from
+  pub(crate) wasm_modules: RwLock<WasmModules>,
   /// Event emitter
   pub(crate) event_emitter: mpsc::UnboundedSender<Event>,
   /// Http event client
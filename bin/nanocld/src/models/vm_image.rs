@@ -8,11 +8,13 @@ use crate::schema::vm_images;
 /// This structure represent a virtual machine image in the database.
 /// A virtual machine image is a file that represent a virtual machine disk.
 ///
-/// Two kind of virtual machine image are supported:
+/// The following kind of virtual machine image are supported:
 /// - Base: A base image is a virtual machine image that is not based on another image.
+/// - Template: A read-only base image that can be shared by many virtual machines.
 /// - Snapshot: A snapshot image is a virtual machine image that is based on a base image.
+/// - Data: An empty image used as a data disk of a virtual machine.
 ///
-/// A `Snapshot` of a `Base` image will alway be use to create a virtual machine.
+/// A `Snapshot` of a `Base` or `Template` image will alway be use to create a virtual machine.
 #[derive(
   Clone, Debug, Queryable, Identifiable, Insertable, Serialize, Deserialize,
 )]
@@ -26,7 +28,7 @@ pub struct VmImageDb {
   pub node_name: String,
   /// The created at date
   pub created_at: chrono::NaiveDateTime,
-  /// The kind of the virtual machine image (Base, Snapshot)
+  /// The kind of the virtual machine image (Base, Template, Snapshot, Data)
   pub kind: String,
   /// The path of the virtual machine image
   pub path: String,
@@ -41,13 +43,29 @@ pub struct VmImageDb {
 }
 
 /// This structure is used to update a virtual machine image in the database.
-#[derive(Clone, Debug, AsChangeset)]
+#[derive(Clone, Debug, Default, AsChangeset)]
 #[diesel(table_name = vm_images)]
 pub struct VmImageUpdateDb {
+  /// The kind of the virtual machine image
+  pub kind: Option<String>,
   /// The actual size of the virtual machine image
-  pub size_actual: i64,
+  pub size_actual: Option<i64>,
   /// The virtual size of the virtual machine image
-  pub size_virtual: i64,
+  pub size_virtual: Option<i64>,
+  /// The parent of the virtual machine image
+  pub parent: Option<Option<String>>,
+}
+
+/// Helper to refresh the sizes of a virtual machine image
+impl From<QemuImgInfo> for VmImageUpdateDb {
+  fn from(info: QemuImgInfo) -> Self {
+    Self {
+      kind: None,
+      size_actual: Some(info.actual_size),
+      size_virtual: Some(info.virtual_size),
+      parent: None,
+    }
+  }
 }
 
 /// This structure is used to parse the output of the qemu-img info command.
@@ -60,6 +78,8 @@ pub struct QemuImgInfo {
   pub virtual_size: i64,
   /// The actual size of the virtual machine image
   pub actual_size: i64,
+  /// The backing file of the virtual machine image if any
+  pub backing_filename: Option<String>,
 }
 
 /// Helper to convert a `VmImageDb` to a `VmImage`
@@ -73,6 +93,7 @@ impl From<VmImageDb> for VmImage {
       format: db.format,
       size_actual: db.size_actual,
       size_virtual: db.size_virtual,
+      parent: db.parent,
     }
   }
 }
@@ -0,0 +1,64 @@
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use nanocl_stubs::vm_snapshot::VmSnapshot;
+
+use crate::schema::vm_snapshots;
+
+/// This structure represent a virtual machine snapshot in the database.
+/// A snapshot is a qcow2 file frozen at a point in time.
+/// The disk of the virtual machine is an overlay of its last snapshot,
+/// the snapshots of a virtual machine form a chain through their `parent`.
+#[derive(
+  Clone, Debug, Queryable, Identifiable, Insertable, Serialize, Deserialize,
+)]
+#[diesel(primary_key(key))]
+#[diesel(table_name = vm_snapshots)]
+#[serde(rename_all = "PascalCase")]
+pub struct VmSnapshotDb {
+  /// The key of the snapshot
+  pub key: uuid::Uuid,
+  /// The name of the snapshot
+  pub name: String,
+  /// The key of the virtual machine
+  pub vm_key: String,
+  /// The created at date
+  pub created_at: chrono::NaiveDateTime,
+  /// The path of the snapshot file
+  pub path: String,
+  /// The snapshot this one is based on
+  pub parent: Option<uuid::Uuid>,
+  /// The actual size of the snapshot
+  pub size_actual: i64,
+  /// The virtual size of the snapshot
+  pub size_virtual: i64,
+}
+
+/// This structure is used to update a virtual machine snapshot in the database.
+#[derive(Clone, Debug, AsChangeset)]
+#[diesel(table_name = vm_snapshots)]
+#[diesel(treat_none_as_null = true)]
+pub struct VmSnapshotUpdateDb {
+  /// The snapshot this one is based on
+  pub parent: Option<uuid::Uuid>,
+  /// The actual size of the snapshot
+  pub size_actual: i64,
+  /// The virtual size of the snapshot
+  pub size_virtual: i64,
+}
+
+/// Helper to convert a `VmSnapshotDb` to a `VmSnapshot`
+impl From<VmSnapshotDb> for VmSnapshot {
+  fn from(db: VmSnapshotDb) -> Self {
+    Self {
+      key: db.key,
+      name: db.name,
+      vm_key: db.vm_key,
+      created_at: db.created_at,
+      path: db.path,
+      parent: db.parent,
+      size_actual: db.size_actual,
+      size_virtual: db.size_virtual,
+    }
+  }
+}
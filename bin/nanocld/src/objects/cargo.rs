@@ -8,7 +8,9 @@ use nanocl_error::http::{HttpError, HttpResult};
 use nanocl_stubs::{
   cargo::{Cargo, CargoDeleteQuery, CargoInspect},
   cargo_spec::CargoSpecPartial,
-  system::{NativeEventAction, ObjPsStatusKind, ObjPsStatusPartial},
+  system::{
+    EventActorKind, NativeEventAction, ObjPsStatusKind, ObjPsStatusPartial,
+  },
 };
 
 use crate::{
@@ -102,15 +104,24 @@ impl ObjDelByPk for CargoDb {
         "Unable to delete cargo with running instances without force option",
       ));
     }
-    let status = ObjPsStatusDb::read_by_pk(pk, &state.inner.pool).await?;
-    let new_status = ObjPsStatusUpdate {
-      wanted: Some(ObjPsStatusKind::Destroy.to_string()),
-      prev_wanted: Some(status.wanted),
-      actual: Some(ObjPsStatusKind::Destroying.to_string()),
-      prev_actual: Some(status.actual),
-    };
-    ObjPsStatusDb::update_pk(pk, new_status, &state.inner.pool).await?;
-    Ok(cargo)
+    utils::dependent::delete_with_owned(
+      EventActorKind::Cargo,
+      pk,
+      opts.propagation.as_ref(),
+      state,
+      async {
+        let status = ObjPsStatusDb::read_by_pk(pk, &state.inner.pool).await?;
+        let new_status = ObjPsStatusUpdate {
+          wanted: Some(ObjPsStatusKind::Destroy.to_string()),
+          prev_wanted: Some(status.wanted),
+          actual: Some(ObjPsStatusKind::Destroying.to_string()),
+          prev_actual: Some(status.actual),
+        };
+        ObjPsStatusDb::update_pk(pk, new_status, &state.inner.pool).await?;
+        Ok(cargo)
+      },
+    )
+    .await
   }
 }
 
@@ -1,7 +1,10 @@
 use nanocl_error::http::HttpResult;
 use nanocl_stubs::{
+  generic::GenericDeleteQuery,
   job::{Job, JobInspect, JobPartial},
-  system::{NativeEventAction, ObjPsStatusKind, ObjPsStatusPartial},
+  system::{
+    EventActorKind, NativeEventAction, ObjPsStatusKind, ObjPsStatusPartial,
+  },
 };
 
 use crate::{
@@ -40,7 +43,7 @@ impl ObjCreate for JobDb {
 }
 
 impl ObjDelByPk for JobDb {
-  type ObjDelOpts = ();
+  type ObjDelOpts = GenericDeleteQuery;
   type ObjDelOut = Job;
 
   fn get_del_event() -> NativeEventAction {
@@ -49,19 +52,28 @@ impl ObjDelByPk for JobDb {
 
   async fn fn_del_obj_by_pk(
     pk: &str,
-    _opts: &Self::ObjDelOpts,
+    opts: &Self::ObjDelOpts,
     state: &crate::models::SystemState,
   ) -> HttpResult<Self::ObjDelOut> {
     let job = JobDb::transform_read_by_pk(pk, &state.inner.pool).await?;
-    let status = ObjPsStatusDb::read_by_pk(pk, &state.inner.pool).await?;
-    let new_status = ObjPsStatusUpdate {
-      wanted: Some(ObjPsStatusKind::Destroy.to_string()),
-      prev_wanted: Some(status.wanted),
-      actual: Some(ObjPsStatusKind::Destroying.to_string()),
-      prev_actual: Some(status.actual),
-    };
-    ObjPsStatusDb::update_pk(pk, new_status, &state.inner.pool).await?;
-    Ok(job)
+    utils::dependent::delete_with_owned(
+      EventActorKind::Job,
+      pk,
+      opts.propagation.as_ref(),
+      state,
+      async {
+        let status = ObjPsStatusDb::read_by_pk(pk, &state.inner.pool).await?;
+        let new_status = ObjPsStatusUpdate {
+          wanted: Some(ObjPsStatusKind::Destroy.to_string()),
+          prev_wanted: Some(status.wanted),
+          actual: Some(ObjPsStatusKind::Destroying.to_string()),
+          prev_actual: Some(status.actual),
+        };
+        ObjPsStatusDb::update_pk(pk, new_status, &state.inner.pool).await?;
+        Ok(job)
+      },
+    )
+    .await
   }
 }
 
@@ -1,9 +1,15 @@
+use ntex::rt;
+
 use nanocl_error::http::{HttpError, HttpResult};
-use nanocl_stubs::namespace::{Namespace, NamespaceInspect, NamespacePartial};
+use nanocl_stubs::{
+  generic::GenericDeleteQuery,
+  namespace::{Namespace, NamespaceInspect, NamespacePartial},
+};
 
 use crate::{
   models::{CargoDb, NamespaceDb, SystemState},
   repositories::generic::*,
+  utils,
 };
 
 use super::generic::*;
@@ -56,21 +62,45 @@ impl ObjInspectByPk for NamespaceDb {
   }
 }
 
+/// Remove the namespace from the database and its network
+async fn remove(pk: &str, state: &SystemState) -> HttpResult<()> {
+  NamespaceDb::del_by_pk(pk, &state.inner.pool).await?;
+  if let Err(err) = state.inner.docker_api.remove_network(pk).await {
+    log::error!("Unable to remove network {} got error: {}", pk, err);
+  }
+  Ok(())
+}
+
 impl ObjDelByPk for NamespaceDb {
-  type ObjDelOpts = ();
+  type ObjDelOpts = GenericDeleteQuery;
   type ObjDelOut = Namespace;
 
   async fn fn_del_obj_by_pk(
     pk: &str,
-    _opts: &Self::ObjDelOpts,
+    opts: &Self::ObjDelOpts,
     state: &SystemState,
   ) -> HttpResult<Self::ObjDelOut> {
     let item = NamespaceDb::read_by_pk(pk, &state.inner.pool).await?;
-    CargoDb::delete_by_namespace(pk, state).await?;
-    NamespaceDb::del_by_pk(pk, &state.inner.pool).await?;
-    if let Err(err) = state.inner.docker_api.remove_network(pk).await {
-      log::error!("Unable to remove network {} got error: {}", pk, err);
+    let dependents = utils::dependent::list_namespace(pk, state).await?;
+    if dependents.is_empty() {
+      remove(pk, state).await?;
+      return Ok(item.into());
     }
+    let force = opts.force.unwrap_or(false);
+    utils::dependent::delete_namespace(&dependents, force, state).await?;
+    // The cargoes and vms are destroyed in background,
+    // the namespace is removed once they are gone
+    let pk = pk.to_owned();
+    let state = state.clone();
+    rt::spawn(async move {
+      let res = match utils::dependent::wait_namespace(&pk, &state).await {
+        Ok(_) => remove(&pk, &state).await,
+        Err(err) => Err(err),
+      };
+      if let Err(err) = res {
+        log::error!("namespace::delete: {pk}: {err}");
+      }
+    });
     Ok(item.into())
   }
 }
@@ -1,12 +1,17 @@
 use nanocl_error::http::{HttpError, HttpResult};
 use nanocl_stubs::{
-  resource::{Resource, ResourcePartial},
-  system::NativeEventAction,
+  generic::GenericDeleteQuery,
+  resource::{
+    Resource, ResourcePartial, ResourcePhase, ResourceStatus,
+    ResourceStatusPartial,
+  },
+  system::{EventActorKind, NativeEventAction},
 };
 
 use crate::{
-  models::{ResourceDb, SpecDb, SystemState},
+  models::{ResourceDb, SystemState},
   repositories::generic::*,
+  utils,
 };
 
 use super::generic::*;
@@ -28,33 +33,59 @@ impl ObjCreate for ResourceDb {
         &obj.name
       )));
     }
-    let obj = ResourceDb::hook_create(obj, &state.inner.pool).await?;
+    if let Some(owners) = &obj.owners {
+      utils::dependent::ensure_owners(&obj.name, owners, state).await?;
+    }
+    let (obj, status) = ResourceDb::hook_create(obj, None, state).await?;
     let resource =
-      ResourceDb::create_from_spec(&obj, &state.inner.pool).await?;
+      ResourceDb::create_from_spec(&obj, status, &state.inner.pool).await?;
     Ok(resource)
   }
 }
 
 impl ObjDelByPk for ResourceDb {
   type ObjDelOut = Resource;
-  type ObjDelOpts = ();
+  type ObjDelOpts = GenericDeleteQuery;
 
   async fn fn_del_obj_by_pk(
     key: &str,
-    _opts: &Self::ObjDelOpts,
+    opts: &Self::ObjDelOpts,
     state: &SystemState,
   ) -> HttpResult<Self::ObjDelOut> {
-    let resource =
+    let mut resource =
       ResourceDb::transform_read_by_pk(key, &state.inner.pool).await?;
-    if let Err(err) =
-      ResourceDb::hook_delete(&resource, &state.inner.pool).await
-    {
-      log::warn!("{err}");
+    // Persist the phase before deleting the owned resources
+    // so a resource owning it back doesn't delete it again
+    let status = resource.status.clone().unwrap_or_default();
+    if status.phase != ResourcePhase::Deleting {
+      let status = ResourceStatus {
+        phase: ResourcePhase::Deleting,
+        updated_at: chrono::Utc::now().naive_utc(),
+        ..status
+      };
+      resource =
+        ResourceDb::update_status(key, &status, &state.inner.pool).await?;
     }
-    ResourceDb::del_by_pk(&resource.spec.resource_key, &state.inner.pool)
-      .await?;
-    SpecDb::del_by_kind_key(&resource.spec.resource_key, &state.inner.pool)
-      .await?;
+    utils::dependent::delete_with_owned(
+      EventActorKind::Resource,
+      key,
+      opts.propagation.as_ref(),
+      state,
+      async { Ok(utils::resource::delete(&resource, state).await?) },
+    )
+    .await
+  }
+
+  async fn del_obj_by_pk(
+    key: &str,
+    opts: &Self::ObjDelOpts,
+    state: &SystemState,
+  ) -> HttpResult<Self::ObjDelOut> {
+    let resource = Self::fn_del_obj_by_pk(key, opts, state).await?;
+    let action = utils::resource::get_del_event(key, state).await;
+    state
+      .emit_normal_native_action_sync(&resource, action)
+      .await;
     Ok(resource)
   }
 }
@@ -72,10 +103,40 @@ impl ObjPutByPk for ResourceDb {
     obj: &Self::ObjPutIn,
     state: &SystemState,
   ) -> HttpResult<Self::ObjPutOut> {
-    ResourceDb::read_by_pk(pk, &state.inner.pool).await?;
-    let resource = ResourceDb::hook_create(obj, &state.inner.pool).await?;
+    let current =
+      ResourceDb::transform_read_by_pk(pk, &state.inner.pool).await?;
+    let previous = current.status.as_ref();
+    if matches!(previous, Some(status) if status.phase == ResourcePhase::Deleting)
+    {
+      return Err(HttpError::conflict(format!(
+        "Resource {pk} is being deleted"
+      )));
+    }
+    if let Some(owners) = &obj.owners {
+      utils::dependent::ensure_owners(pk, owners, state).await?;
+    }
+    let (resource, status) =
+      ResourceDb::hook_create(obj, Some(&current), state).await?;
     let resource =
-      ResourceDb::update_from_spec(&resource, &state.inner.pool).await?;
+      ResourceDb::update_from_spec(&resource, status, &state.inner.pool)
+        .await?;
     Ok(resource)
   }
 }
+
+impl ObjPatchByPk for ResourceDb {
+  type ObjPatchIn = ResourceStatusPartial;
+  type ObjPatchOut = Resource;
+
+  fn get_patch_event() -> NativeEventAction {
+    NativeEventAction::Update
+  }
+
+  async fn fn_patch_obj_by_pk(
+    pk: &str,
+    obj: &Self::ObjPatchIn,
+    state: &SystemState,
+  ) -> HttpResult<Self::ObjPatchOut> {
+    utils::resource::patch_status(pk, obj, state).await
+  }
+}
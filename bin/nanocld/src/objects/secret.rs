@@ -1,12 +1,14 @@
 use nanocl_error::http::HttpResult;
 use nanocl_stubs::{
+  generic::GenericDeleteQuery,
   secret::{Secret, SecretPartial, SecretUpdate},
   system::NativeEventAction,
 };
 
 use crate::{
-  models::{SecretDb, SystemState},
+  models::{SecretDb, SpecDb, SystemState},
   repositories::generic::*,
+  utils,
 };
 
 use super::generic::*;
@@ -19,7 +21,14 @@ impl ObjCreate for SecretDb {
     obj: &Self::ObjCreateIn,
     state: &SystemState,
   ) -> HttpResult<Self::ObjCreateOut> {
-    let secret = SecretDb::create_from(obj, &state.inner.pool).await?;
+    let mut obj = obj.clone();
+    utils::secret::validate_data(&obj.kind, &obj.data)?;
+    if obj.kind == "nanocl.io/tls-issued" {
+      obj.data = utils::secret::issue_tls(&obj.data, state).await?;
+    }
+    let keyring = utils::secret::get_keyring(state)?;
+    let secret =
+      SecretDb::create_sealed(&obj, &keyring, &state.inner.pool).await?;
     let secret: Secret = secret.try_into()?;
     Ok(secret)
   }
@@ -27,15 +36,20 @@ impl ObjCreate for SecretDb {
 
 impl ObjDelByPk for SecretDb {
   type ObjDelOut = Secret;
-  type ObjDelOpts = ();
+  type ObjDelOpts = GenericDeleteQuery;
 
   async fn fn_del_obj_by_pk(
     pk: &str,
-    _opts: &Self::ObjDelOpts,
+    opts: &Self::ObjDelOpts,
     state: &SystemState,
   ) -> HttpResult<Self::ObjDelOut> {
     let secret = SecretDb::transform_read_by_pk(pk, &state.inner.pool).await?;
+    if !opts.force.unwrap_or(false) {
+      let dependents = utils::dependent::list_secret(pk, state).await?;
+      utils::dependent::ensure_none("Secret", pk, &dependents)?;
+    }
     SecretDb::del_by_pk(pk, &state.inner.pool).await?;
+    SpecDb::del_by_kind("Secret", pk, &state.inner.pool).await?;
     Ok(secret)
   }
 }
@@ -53,7 +67,14 @@ impl ObjPatchByPk for SecretDb {
     obj: &Self::ObjPatchIn,
     state: &SystemState,
   ) -> HttpResult<Self::ObjPatchOut> {
-    let secret = SecretDb::update_pk(pk, obj, &state.inner.pool)
+    let secret = SecretDb::read_by_pk(pk, &state.inner.pool).await?;
+    let mut obj = obj.clone();
+    utils::secret::validate_data(&secret.kind, &obj.data)?;
+    if secret.kind == "nanocl.io/tls-issued" {
+      obj.data = utils::secret::issue_tls(&obj.data, state).await?;
+    }
+    let keyring = utils::secret::get_keyring(state)?;
+    let secret = SecretDb::update_sealed(pk, &obj, &keyring, &state.inner.pool)
       .await?
       .try_into()?;
     Ok(secret)
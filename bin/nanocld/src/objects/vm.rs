@@ -2,9 +2,10 @@ use nanocl_error::http::{HttpError, HttpResult};
 
 use nanocl_stubs::{
   system::{
-    NativeEventAction, ObjPsStatus, ObjPsStatusKind, ObjPsStatusPartial,
+    EventActorKind, NativeEventAction, ObjPsStatus, ObjPsStatusKind,
+    ObjPsStatusPartial,
   },
-  vm::{Vm, VmInspect},
+  vm::{Vm, VmDeleteQuery, VmInspect},
   vm_spec::VmSpecPartial,
 };
 
@@ -45,9 +46,13 @@ impl ObjCreate for VmDb {
     }
     let image =
       VmImageDb::read_by_pk(&vm.disk.image, &state.inner.pool).await?;
-    if image.kind.as_str() != "Base" {
+    if !matches!(image.kind.as_str(), "Base" | "Template") {
       return Err(HttpError::bad_request(format!("Image {} is not a base image please convert the snapshot into a base image first", &vm.disk.image)));
     }
+    if let Some(disks) = &vm.disks {
+      vm.disks =
+        Some(utils::vm_image::prepare_data_disks(&vm_key, disks, state).await?);
+    }
     let snap_name = format!("{}.{vm_key}", &image.name);
     let size = vm.disk.size.unwrap_or(20);
     log::debug!("Creating snapshot {snap_name} with size {size}");
@@ -87,7 +92,7 @@ impl ObjCreate for VmDb {
 }
 
 impl ObjDelByPk for VmDb {
-  type ObjDelOpts = ();
+  type ObjDelOpts = VmDeleteQuery;
   type ObjDelOut = Vm;
 
   fn get_del_event() -> NativeEventAction {
@@ -96,19 +101,28 @@ impl ObjDelByPk for VmDb {
 
   async fn fn_del_obj_by_pk(
     pk: &str,
-    _opts: &Self::ObjDelOpts,
+    opts: &Self::ObjDelOpts,
     state: &SystemState,
   ) -> HttpResult<Self::ObjDelOut> {
     let vm = VmDb::transform_read_by_pk(pk, &state.inner.pool).await?;
-    let status = ObjPsStatusDb::read_by_pk(pk, &state.inner.pool).await?;
-    let new_status = ObjPsStatusUpdate {
-      wanted: Some(ObjPsStatusKind::Destroy.to_string()),
-      prev_wanted: Some(status.wanted),
-      actual: Some(ObjPsStatusKind::Destroying.to_string()),
-      prev_actual: Some(status.actual),
-    };
-    ObjPsStatusDb::update_pk(pk, new_status, &state.inner.pool).await?;
-    Ok(vm)
+    utils::dependent::delete_with_owned(
+      EventActorKind::Vm,
+      pk,
+      opts.propagation.as_ref(),
+      state,
+      async {
+        let status = ObjPsStatusDb::read_by_pk(pk, &state.inner.pool).await?;
+        let new_status = ObjPsStatusUpdate {
+          wanted: Some(ObjPsStatusKind::Destroy.to_string()),
+          prev_wanted: Some(status.wanted),
+          actual: Some(ObjPsStatusKind::Destroying.to_string()),
+          prev_actual: Some(status.actual),
+        };
+        ObjPsStatusDb::update_pk(pk, new_status, &state.inner.pool).await?;
+        Ok(vm)
+      },
+    )
+    .await
   }
 }
 
@@ -129,15 +143,29 @@ impl ObjPutByPk for VmDb {
       actual: Some(ObjPsStatusKind::Updating.to_string()),
       prev_actual: Some(status.actual),
     };
+    let mut spec = obj.spec.clone();
+    if let Some(disks) = &spec.disks {
+      spec.disks = Some(
+        utils::vm_image::prepare_data_disks(&vm.spec.vm_key, disks, state)
+          .await?,
+      );
+    }
     ObjPsStatusDb::update_pk(pk, new_status, &state.inner.pool).await?;
-    let vm = VmDb::update_from_spec(
+    let updated = VmDb::update_from_spec(
       &vm.spec.vm_key,
-      &obj.spec,
+      &spec,
       &obj.version,
       &state.inner.pool,
     )
     .await?;
-    Ok(vm)
+    // Only report the disks detached once the new spec is stored
+    utils::vm_image::record_orphaned_disks(
+      &vm,
+      spec.disks.as_deref().unwrap_or_default(),
+      NativeEventAction::Update,
+      state,
+    );
+    Ok(updated)
   }
 }
 
@@ -159,6 +187,11 @@ impl ObjPatchByPk for VmDb {
     let vm_partial = VmSpecPartial {
       name: spec.name.to_owned().unwrap_or(vm.spec.name.clone()),
       disk: old_spec.disk,
+      disks: if spec.disks.is_some() {
+        spec.disks.clone()
+      } else {
+        old_spec.disks
+      },
       host_config: Some(
         spec.host_config.to_owned().unwrap_or(old_spec.host_config),
       ),
@@ -182,6 +215,11 @@ impl ObjPatchByPk for VmDb {
       } else {
         old_spec.ssh_key
       },
+      cloud_init: if spec.cloud_init.is_some() {
+        spec.cloud_init.clone()
+      } else {
+        old_spec.cloud_init
+      },
       mac_address: old_spec.mac_address,
       labels: if spec.labels.is_some() {
         spec.labels.clone()
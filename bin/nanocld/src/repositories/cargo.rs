@@ -1,14 +1,13 @@
 use std::collections::HashMap;
 
 use diesel::prelude::*;
-use futures_util::{stream::FuturesUnordered, StreamExt};
 use nanocl_error::{
   http::HttpResult,
   io::{IoError, IoResult},
 };
 
 use nanocl_stubs::{
-  cargo::{Cargo, CargoDeleteQuery, CargoSummary},
+  cargo::{Cargo, CargoSummary},
   cargo_spec::{CargoSpec, CargoSpecPartial},
   generic::{GenericClause, GenericFilter, GenericFilterNsp},
   system::ObjPsStatus,
@@ -20,7 +19,6 @@ use crate::{
     CargoDb, CargoUpdateDb, ColumnType, NamespaceDb, ObjPsStatusDb, Pool,
     ProcessDb, SpecDb, SystemState,
   },
-  objects::generic::*,
   schema::cargoes,
   utils,
 };
@@ -191,34 +189,6 @@ impl CargoDb {
     Ok(count)
   }
 
-  /// This remove all cargo in the given namespace and all their instances (containers)
-  /// from the system (database and docker).
-  pub async fn delete_by_namespace(
-    namespace: &str,
-    state: &SystemState,
-  ) -> HttpResult<()> {
-    let namespace =
-      NamespaceDb::read_by_pk(namespace, &state.inner.pool).await?;
-    let cargoes =
-      CargoDb::read_by_namespace(&namespace.name, &state.inner.pool).await?;
-    cargoes
-      .into_iter()
-      .map(|cargo| async move {
-        CargoDb::del_obj_by_pk(
-          &cargo.spec.cargo_key,
-          &CargoDeleteQuery::default(),
-          state,
-        )
-        .await
-      })
-      .collect::<FuturesUnordered<_>>()
-      .collect::<Vec<HttpResult<_>>>()
-      .await
-      .into_iter()
-      .collect::<HttpResult<Vec<_>>>()?;
-    Ok(())
-  }
-
   /// List the cargoes for the given query
   pub async fn list(
     query: &GenericFilterNsp,
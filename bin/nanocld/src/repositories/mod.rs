@@ -12,5 +12,6 @@ mod secret;
 mod spec;
 mod vm;
 mod vm_image;
+mod vm_snapshot;
 
 pub mod generic;
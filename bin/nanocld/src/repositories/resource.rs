@@ -2,15 +2,15 @@ use std::collections::HashMap;
 
 use diesel::prelude::*;
 
-use jsonschema::{Draft, Validator};
 use nanocl_error::{
-  http::{HttpError, HttpResult},
-  io::IoResult,
+  http::HttpResult, http_client::HttpClientError, io::IoResult,
 };
 
 use nanocl_stubs::{
   generic::GenericFilter,
-  resource::{Resource, ResourcePartial},
+  resource::{
+    Resource, ResourceOwner, ResourcePartial, ResourcePhase, ResourceStatus,
+  },
   resource_kind::ResourceKind,
 };
 
@@ -18,8 +18,9 @@ use crate::{
   gen_sql_multiple, gen_sql_order_by, gen_sql_query,
   models::{
     ColumnType, Pool, ResourceDb, ResourceKindDb, ResourceUpdateDb, SpecDb,
+    SystemState,
   },
-  schema::resources,
+  schema::{resources, specs},
   utils,
 };
 
@@ -37,6 +38,8 @@ impl RepositoryBase for ResourceDb {
       ("spec_key", (ColumnType::Text, "resources.spec_key")),
       ("data", (ColumnType::Json, "specs.data")),
       ("metadata", (ColumnType::Json, "specs.metadata")),
+      ("status", (ColumnType::Json, "resources.status")),
+      ("owners", (ColumnType::Json, "resources.owners")),
     ])
   }
 }
@@ -112,6 +115,11 @@ impl WithSpec for ResourceDb {
       created_at: self.created_at,
       kind: self.kind,
       spec: r.clone().into(),
+      status: self
+        .status
+        .and_then(|status| serde_json::from_value(status).ok()),
+      owners: serde_json::from_value(self.owners).unwrap_or_default(),
+      finalizers: serde_json::from_value(self.finalizers).unwrap_or_default(),
     }
   }
 }
@@ -133,9 +141,24 @@ impl ResourceDb {
     }
   }
 
+  /// Serialize a status to be stored,
+  /// a ready status observe the spec it is stored with.
+  fn gen_status(
+    status: Option<ResourceStatus>,
+    spec_key: &uuid::Uuid,
+  ) -> Option<serde_json::Value> {
+    status.map(|mut status| {
+      if status.phase == ResourcePhase::Ready {
+        status.observed_version = Some(*spec_key);
+      }
+      serde_json::to_value(status).unwrap_or_default()
+    })
+  }
+
   /// Create a new resource from a spec.
   pub async fn create_from_spec(
     item: &ResourcePartial,
+    status: Option<ResourceStatus>,
     pool: &Pool,
   ) -> IoResult<Resource> {
     let (kind, version) = ResourceDb::parse_kind(&item.kind, pool).await?;
@@ -154,6 +177,13 @@ impl ResourceDb {
       created_at: chrono::Utc::now().naive_utc(),
       kind,
       spec_key: spec.key.to_owned(),
+      status: Self::gen_status(status, &spec.key),
+      owners: serde_json::to_value(item.owners.clone().unwrap_or_default())
+        .unwrap_or_default(),
+      finalizers: serde_json::to_value(
+        item.finalizers.clone().unwrap_or_default(),
+      )
+      .unwrap_or_default(),
     };
     let resource_db = ResourceDb::create_from(new_item, pool).await?;
     let item = resource_db.with_spec(&spec);
@@ -163,6 +193,7 @@ impl ResourceDb {
   /// Update a resource from a spec.
   pub async fn update_from_spec(
     item: &ResourcePartial,
+    status: Option<ResourceStatus>,
     pool: &Pool,
   ) -> IoResult<Resource> {
     let key = item.name.clone();
@@ -181,6 +212,15 @@ impl ResourceDb {
     let resource_update = ResourceUpdateDb {
       key: None,
       spec_key: Some(spec.key.to_owned()),
+      status: Self::gen_status(status, &spec.key),
+      owners: item
+        .owners
+        .as_ref()
+        .map(|owners| serde_json::to_value(owners).unwrap_or_default()),
+      finalizers: item
+        .finalizers
+        .as_ref()
+        .map(|finalizers| serde_json::to_value(finalizers).unwrap_or_default()),
     };
     let resource_db =
       ResourceDb::update_pk(&key, resource_update, pool).await?;
@@ -188,57 +228,250 @@ impl ResourceDb {
     Ok(item)
   }
 
+  /// Update resources from their specs in a single transaction,
+  /// none of them are updated if one fails.
+  pub async fn update_from_specs(
+    items: &[(ResourcePartial, Option<ResourceStatus>)],
+    pool: &Pool,
+  ) -> IoResult<Vec<Resource>> {
+    let mut updates = Vec::new();
+    for (item, status) in items {
+      let (_, version) = ResourceDb::parse_kind(&item.kind, pool).await?;
+      let spec = SpecDb {
+        key: uuid::Uuid::new_v4(),
+        created_at: chrono::Utc::now().naive_utc(),
+        kind_name: "Resource".to_owned(),
+        kind_key: item.name.clone(),
+        version,
+        data: item.data.clone(),
+        metadata: item.metadata.clone(),
+      };
+      let resource_update = ResourceUpdateDb {
+        key: None,
+        spec_key: Some(spec.key.to_owned()),
+        status: Self::gen_status(status.clone(), &spec.key),
+        owners: item
+          .owners
+          .as_ref()
+          .map(|owners| serde_json::to_value(owners).unwrap_or_default()),
+        finalizers: item.finalizers.as_ref().map(|finalizers| {
+          serde_json::to_value(finalizers).unwrap_or_default()
+        }),
+      };
+      updates.push((item.name.clone(), spec, resource_update));
+    }
+    let pool = pool.clone();
+    ntex::rt::spawn_blocking(move || {
+      let mut conn = utils::store::get_pool_conn(&pool)?;
+      conn
+        .transaction::<_, diesel::result::Error, _>(|conn| {
+          updates
+            .into_iter()
+            .map(|(key, spec, resource_update)| {
+              let spec = diesel::insert_into(specs::table)
+                .values(spec)
+                .get_result::<SpecDb>(conn)?;
+              let resource_db = diesel::update(resources::table.find(key))
+                .set(resource_update)
+                .get_result::<ResourceDb>(conn)?;
+              Ok(resource_db.with_spec(&spec))
+            })
+            .collect::<Result<Vec<_>, _>>()
+        })
+        .map_err(|err| *Self::map_err(err))
+    })
+    .await?
+  }
+
+  /// Update the status of a resource without changing its spec.
+  pub async fn update_status(
+    key: &str,
+    status: &ResourceStatus,
+    pool: &Pool,
+  ) -> IoResult<Resource> {
+    let resource_update = ResourceUpdateDb {
+      key: None,
+      spec_key: None,
+      status: Some(serde_json::to_value(status).unwrap_or_default()),
+      owners: None,
+      finalizers: None,
+    };
+    ResourceDb::update_pk(key, resource_update, pool).await?;
+    ResourceDb::transform_read_by_pk(key, pool).await
+  }
+
+  /// Update the owners of a resource without changing its spec.
+  pub async fn update_owners(
+    key: &str,
+    owners: &[ResourceOwner],
+    pool: &Pool,
+  ) -> IoResult<Resource> {
+    let resource_update = ResourceUpdateDb {
+      key: None,
+      spec_key: None,
+      status: None,
+      owners: Some(serde_json::to_value(owners).unwrap_or_default()),
+      finalizers: None,
+    };
+    ResourceDb::update_pk(key, resource_update, pool).await?;
+    ResourceDb::transform_read_by_pk(key, pool).await
+  }
+
+  /// Update the finalizers of a resource without changing its spec.
+  pub async fn update_finalizers(
+    key: &str,
+    finalizers: &[String],
+    pool: &Pool,
+  ) -> IoResult<Resource> {
+    let resource_update = ResourceUpdateDb {
+      key: None,
+      spec_key: None,
+      status: None,
+      owners: None,
+      finalizers: Some(serde_json::to_value(finalizers).unwrap_or_default()),
+    };
+    ResourceDb::update_pk(key, resource_update, pool).await?;
+    ResourceDb::transform_read_by_pk(key, pool).await
+  }
+
   /// This hook is called when a resource is created.
   /// It call a custom controller at a specific url or just validate a schema.
   /// If the resource is a Kind Kind, it will create a resource Kind with an associated version.
   /// To call a custom controller, the resource Kind must have a Url field in his config.
   /// Unless it must have a Schema field in his config that is a Validator to validate the resource.
+  /// The defaults of the schema are set on the data and the immutable fields
+  /// of the kind cannot change when updating the current resource.
+  /// A kind with a WebAssembly module mutates, validates and applies
+  /// the resource in process instead of calling a controller.
+  /// The returned status is pending with a retry date when the controller is unreachable,
+  /// it's `None` when the kind have no controller.
   pub async fn hook_create(
     resource: &ResourcePartial,
-    pool: &Pool,
-  ) -> HttpResult<ResourcePartial> {
+    current: Option<&Resource>,
+    state: &SystemState,
+  ) -> HttpResult<(ResourcePartial, Option<ResourceStatus>)> {
+    let pool = &state.inner.pool;
+    let previous = current.and_then(|current| current.status.as_ref());
     let mut resource = resource.clone();
     let (kind, version) = ResourceDb::parse_kind(&resource.kind, pool).await?;
     log::trace!("hook_create_resource kind: {kind} {version}");
     let kind: ResourceKind = SpecDb::get_version(&kind, &version, pool)
       .await?
       .try_into()?;
-    if let Some(schema) = &kind.data.schema {
-      let schema: Validator = Validator::options()
-        .with_draft(Draft::Draft7)
-        .build(schema)
-        .map_err(|err| {
-          HttpError::bad_request(format!("Invalid schema {}", err))
-        })?;
-      schema.validate(&resource.data).map_err(|err| {
-        HttpError::bad_request(format!("Invalid schema {err}"))
-      })?;
+    resource.data =
+      utils::resource_kind::validate_data(&kind.data, &resource.data)?;
+    let wasm_ctrl = utils::wasm_ctrl::WasmCtrl::from_kind(
+      &kind.name,
+      &kind.version,
+      &kind.data,
+      state,
+    )
+    .await?;
+    if let Some(wasm_ctrl) = &wasm_ctrl {
+      let data = wasm_ctrl
+        .mutate(&version, &resource.name, &resource.data)
+        .await?;
+      // The mutated data must still match the schema of the kind
+      resource.data = utils::resource_kind::validate_data(&kind.data, &data)?;
     }
-    if let Some(url) = &kind.data.url {
-      let ctrl_client = utils::ctrl_client::CtrlClient::new(&kind.name, url);
-      let config = ctrl_client
-        .apply_rule(&version, &resource.name, &resource.data)
+    if let Some(current) =
+      current.filter(|current| current.spec.version == version)
+    {
+      utils::resource_kind::ensure_immutable(
+        &kind.data,
+        &current.spec.data,
+        &resource.data,
+      )?;
+    }
+    if let Some(wasm_ctrl) = &wasm_ctrl {
+      wasm_ctrl
+        .validate(&version, &resource.name, &resource.data)
+        .await?;
+      resource.data = wasm_ctrl
+        .apply(&version, &resource.name, &resource.data)
         .await?;
-      resource.data = config;
+      return Ok((resource, Some(utils::resource::gen_ready_status(previous))));
     }
-    Ok(resource)
+    let Some(ctrl_client) =
+      utils::ctrl_client::CtrlClient::from_kind(&kind.name, &kind.data, state)
+        .await?
+    else {
+      return Ok((resource, None));
+    };
+    let status = match ctrl_client
+      .apply_rule(&version, &resource.name, &resource.data)
+      .await
+    {
+      Ok(config) => {
+        resource.data = config;
+        utils::resource::gen_ready_status(previous)
+      }
+      Err(HttpClientError::IoError(err)) => {
+        log::warn!("hook_create_resource {}: {err}", resource.name);
+        utils::resource::gen_retry_status(
+          previous,
+          ResourcePhase::Pending,
+          &err.to_string(),
+        )
+      }
+      Err(err) => return Err(err.into()),
+    };
+    Ok((resource, Some(status)))
   }
 
   /// This hook is called when a resource is deleted.
   /// It call a custom controller at a specific url.
   /// If the resource is a Kind Kind, it will delete the resource Kind with an associated version.
-  pub async fn hook_delete(resource: &Resource, pool: &Pool) -> HttpResult<()> {
+  /// The returned status is deleting with a retry date when the controller is unreachable.
+  pub async fn hook_delete(
+    resource: &Resource,
+    state: &SystemState,
+  ) -> HttpResult<Option<ResourceStatus>> {
+    let pool = &state.inner.pool;
     let (kind, version) = ResourceDb::parse_kind(&resource.kind, pool).await?;
     let kind: ResourceKind = SpecDb::get_version(&kind, &version, pool)
       .await?
       .try_into()?;
     log::debug!("hook_delete_resource kind: {kind:?}");
-    if let Some(url) = &kind.data.url {
-      let ctrl_client = utils::ctrl_client::CtrlClient::new(&kind.name, url);
-      ctrl_client
-        .delete_rule(&resource.spec.version, &resource.spec.resource_key)
+    if let Some(wasm_ctrl) = utils::wasm_ctrl::WasmCtrl::from_kind(
+      &kind.name,
+      &kind.version,
+      &kind.data,
+      state,
+    )
+    .await?
+    {
+      wasm_ctrl
+        .delete(
+          &resource.spec.version,
+          &resource.spec.resource_key,
+          &resource.spec.data,
+        )
         .await?;
     }
-    Ok(())
+    if let Some(ctrl_client) =
+      utils::ctrl_client::CtrlClient::from_kind(&kind.name, &kind.data, state)
+        .await?
+    {
+      match ctrl_client
+        .delete_rule(&resource.spec.version, &resource.spec.resource_key)
+        .await
+      {
+        Ok(_) => {}
+        Err(HttpClientError::IoError(err)) => {
+          log::warn!(
+            "hook_delete_resource {}: {err}",
+            resource.spec.resource_key
+          );
+          return Ok(Some(utils::resource::gen_retry_status(
+            resource.status.as_ref(),
+            ResourcePhase::Deleting,
+            &err.to_string(),
+          )));
+        }
+        Err(err) => return Err(err.into()),
+      }
+    }
+    Ok(None)
   }
 }
@@ -1,14 +1,24 @@
 use std::collections::HashMap;
 
 use diesel::prelude::*;
+use openssl::{
+  base64,
+  rand::rand_bytes,
+  symm::{decrypt_aead, encrypt_aead, Cipher},
+};
+
+use nanocl_error::io::{IoError, IoResult};
 
 use nanocl_stubs::generic::GenericFilter;
 
-use nanocl_stubs::secret::Secret;
+use nanocl_stubs::secret::{Secret, SecretPartial, SecretUpdate};
 
 use crate::{
   gen_sql_multiple, gen_sql_order_by, gen_sql_query,
-  models::{ColumnType, SecretDb, SecretUpdateDb},
+  models::{
+    ColumnType, Pool, SecretDb, SecretEnvelope, SecretKeyring, SecretUpdateDb,
+    SpecDb,
+  },
   schema::secrets,
 };
 
@@ -91,3 +101,186 @@ impl RepositoryReadByTransform for SecretDb {
     input.try_into()
   }
 }
+
+/// Size of the nonce used by AES-256-GCM
+const NONCE_LEN: usize = 12;
+/// Size of the authentication tag of AES-256-GCM
+const TAG_LEN: usize = 16;
+
+/// Encrypt data using AES-256-GCM and encode it as `nonce || cipher || tag`.
+/// The additional data bind the cipher to the secret it belongs to.
+fn encrypt(key: &[u8], aad: &[u8], data: &[u8]) -> IoResult<String> {
+  let mut nonce = [0; NONCE_LEN];
+  let mut tag = [0; TAG_LEN];
+  rand_bytes(&mut nonce)
+    .map_err(|err| IoError::other("Secret", err.to_string().as_str()))?;
+  let cipher = encrypt_aead(
+    Cipher::aes_256_gcm(),
+    key,
+    Some(&nonce),
+    aad,
+    data,
+    &mut tag,
+  )
+  .map_err(|err| IoError::other("Secret", err.to_string().as_str()))?;
+  Ok(base64::encode_block(&[&nonce[..], &cipher, &tag].concat()))
+}
+
+/// Decrypt data encoded by [encrypt](encrypt)
+fn decrypt(key: &[u8], aad: &[u8], data: &str) -> IoResult<Vec<u8>> {
+  let data = base64::decode_block(data)
+    .map_err(|err| IoError::invalid_data("Secret", err.to_string().as_str()))?;
+  if data.len() < NONCE_LEN + TAG_LEN {
+    return Err(IoError::invalid_data("Secret", "Cipher is too short"));
+  }
+  let (nonce, data) = data.split_at(NONCE_LEN);
+  let (data, tag) = data.split_at(data.len() - TAG_LEN);
+  decrypt_aead(Cipher::aes_256_gcm(), key, Some(nonce), aad, data, tag)
+    .map_err(|_| IoError::invalid_data("Secret", "Unable to decrypt data"))
+}
+
+impl SecretDb {
+  /// Encrypt the data of a secret with a new data key
+  /// wrapped by the current master key of the keyring
+  pub fn seal(
+    key: &str,
+    data: &serde_json::Value,
+    keyring: &SecretKeyring,
+  ) -> IoResult<serde_json::Value> {
+    let master_key = keyring.keys.get(&keyring.current).ok_or_else(|| {
+      IoError::not_found("Secret master key", &keyring.current)
+    })?;
+    let mut data_key = [0; 32];
+    rand_bytes(&mut data_key)
+      .map_err(|err| IoError::other("Secret", err.to_string().as_str()))?;
+    let envelope = SecretEnvelope {
+      key_id: keyring.current.clone(),
+      data_key: encrypt(master_key, key.as_bytes(), &data_key)?,
+      data: encrypt(&data_key, key.as_bytes(), &serde_json::to_vec(data)?)?,
+    };
+    Ok(serde_json::to_value(envelope)?)
+  }
+
+  /// Decrypt data sealed for the secret `key`.
+  /// Data stored before the encryption at rest is returned as it is.
+  pub fn unseal_data(
+    key: &str,
+    data: &serde_json::Value,
+    keyring: &SecretKeyring,
+  ) -> IoResult<serde_json::Value> {
+    let Ok(envelope) = serde_json::from_value::<SecretEnvelope>(data.clone())
+    else {
+      return Ok(data.clone());
+    };
+    let master_key = keyring.keys.get(&envelope.key_id).ok_or_else(|| {
+      IoError::not_found("Secret master key", &envelope.key_id)
+    })?;
+    let data_key = decrypt(master_key, key.as_bytes(), &envelope.data_key)?;
+    let data = decrypt(&data_key, key.as_bytes(), &envelope.data)?;
+    Ok(serde_json::from_slice(&data)?)
+  }
+
+  /// Decrypt the data of the secret.
+  pub fn unseal(&self, keyring: &SecretKeyring) -> IoResult<serde_json::Value> {
+    SecretDb::unseal_data(&self.key, &self.data, keyring)
+  }
+
+  /// Keep the encrypted data of the secret as a new version in its history.
+  /// Versions are numbered from 1 in the order they are created.
+  async fn create_history(&self, pool: &Pool) -> IoResult<SpecDb> {
+    let version = SpecDb::read_by_kind("Secret", &self.key, pool)
+      .await?
+      .first()
+      .and_then(|spec| spec.version.parse::<usize>().ok())
+      .unwrap_or_default()
+      + 1;
+    let history = SpecDb::from_secret(
+      &self.key,
+      &version.to_string(),
+      &self.data,
+      &self.metadata,
+    );
+    SpecDb::create_from(history, pool).await
+  }
+
+  /// Create a new secret with its data encrypted
+  pub async fn create_sealed(
+    item: &SecretPartial,
+    keyring: &SecretKeyring,
+    pool: &Pool,
+  ) -> IoResult<SecretDb> {
+    let mut secret = SecretDb::from(item);
+    secret.data = SecretDb::seal(&secret.key, &secret.data, keyring)?;
+    let secret = SecretDb::create_from(secret, pool).await?;
+    secret.create_history(pool).await?;
+    Ok(secret)
+  }
+
+  /// Update a secret with its new data encrypted
+  pub async fn update_sealed(
+    pk: &str,
+    item: &SecretUpdate,
+    keyring: &SecretKeyring,
+    pool: &Pool,
+  ) -> IoResult<SecretDb> {
+    let mut update = SecretUpdateDb::from(item);
+    update.data = Some(SecretDb::seal(pk, &item.data, keyring)?);
+    let secret = SecretDb::update_pk(pk, update, pool).await?;
+    secret.create_history(pool).await?;
+    Ok(secret)
+  }
+
+  /// Restore the data and metadata of a secret from a version of its history.
+  /// The restored data is recorded as a new version.
+  pub async fn revert(
+    pk: &str,
+    history: &SpecDb,
+    pool: &Pool,
+  ) -> IoResult<SecretDb> {
+    let update = SecretUpdateDb {
+      data: Some(history.data.clone()),
+      metadata: history.metadata.clone(),
+    };
+    let secret = SecretDb::update_pk(pk, update, pool).await?;
+    secret.create_history(pool).await?;
+    Ok(secret)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn seal() {
+    let keyring = SecretKeyring {
+      current: "test".to_owned(),
+      keys: HashMap::from([("test".to_owned(), vec![7; 32])]),
+    };
+    let data = serde_json::json!(["PASSWORD=secret"]);
+    let mut secret = SecretDb::from(&SecretPartial {
+      name: "env".to_owned(),
+      kind: "nanocl.io/env".to_owned(),
+      immutable: false,
+      data: data.clone(),
+      metadata: None,
+    });
+    // Plain data stored before the encryption is returned as it is
+    assert_eq!(secret.unseal(&keyring).unwrap(), data);
+    secret.data = SecretDb::seal(&secret.key, &data, &keyring).unwrap();
+    assert!(!secret.data.to_string().contains("PASSWORD"));
+    let envelope =
+      serde_json::from_value::<SecretEnvelope>(secret.data.clone()).unwrap();
+    assert_eq!(envelope.key_id, "test");
+    assert_eq!(secret.unseal(&keyring).unwrap(), data);
+    // The cipher is bound to the secret key
+    secret.key = "other".to_owned();
+    assert!(secret.unseal(&keyring).is_err());
+    secret.key = "env".to_owned();
+    let keyring = SecretKeyring {
+      current: "other".to_owned(),
+      keys: HashMap::from([("other".to_owned(), vec![8; 32])]),
+    };
+    assert!(secret.unseal(&keyring).is_err());
+  }
+}
@@ -7,12 +7,13 @@ use nanocl_error::io::IoResult;
 use nanocl_stubs::{
   cargo_spec::{CargoSpec, CargoSpecPartial},
   generic::{GenericClause, GenericFilter},
+  secret::SecretHistory,
   vm_spec::{VmSpec, VmSpecPartial},
 };
 
 use crate::{
   gen_sql_multiple, gen_sql_order_by, gen_sql_query,
-  models::{ColumnType, Pool, SpecDb},
+  models::{ColumnType, Pool, SpecDb, SpecUpdateDb},
   schema::specs,
 };
 
@@ -34,6 +35,10 @@ impl RepositoryBase for SpecDb {
 
 impl RepositoryCreate for SpecDb {}
 
+impl RepositoryUpdate for SpecDb {
+  type UpdateItem = SpecUpdateDb;
+}
+
 impl RepositoryDelBy for SpecDb {
   fn gen_del_query(
     filter: &GenericFilter,
@@ -88,6 +93,32 @@ impl SpecDb {
     SpecDb::del_by(&filter, pool).await
   }
 
+  /// Filter the specs of an object by its kind and key,
+  /// keys of different kinds can be the same
+  fn gen_kind_filter(kind_name: &str, key: &str) -> GenericFilter {
+    GenericFilter::new()
+      .r#where("kind_name", GenericClause::Eq(kind_name.to_owned()))
+      .r#where("kind_key", GenericClause::Eq(key.to_owned()))
+  }
+
+  /// Delete the specs of an object of the given kind
+  pub async fn del_by_kind(
+    kind_name: &str,
+    key: &str,
+    pool: &Pool,
+  ) -> IoResult<()> {
+    SpecDb::del_by(&SpecDb::gen_kind_filter(kind_name, key), pool).await
+  }
+
+  /// Read the specs of an object of the given kind, the newest first
+  pub async fn read_by_kind(
+    kind_name: &str,
+    key: &str,
+    pool: &Pool,
+  ) -> IoResult<Vec<SpecDb>> {
+    SpecDb::read_by(&SpecDb::gen_kind_filter(kind_name, key), pool).await
+  }
+
   pub async fn get_version(
     name: &str,
     version: &str,
@@ -140,6 +171,35 @@ impl SpecDb {
     })
   }
 
+  /// Create a version of a secret.
+  /// The data must already be encrypted with the secret key.
+  pub fn from_secret(
+    key: &str,
+    version: &str,
+    data: &serde_json::Value,
+    metadata: &Option<serde_json::Value>,
+  ) -> Self {
+    Self {
+      key: uuid::Uuid::new_v4(),
+      created_at: chrono::Utc::now().naive_utc(),
+      kind_name: "Secret".to_owned(),
+      kind_key: key.to_owned(),
+      version: version.to_owned(),
+      data: data.clone(),
+      metadata: metadata.clone(),
+    }
+  }
+
+  pub fn to_secret_history(&self) -> SecretHistory {
+    SecretHistory {
+      key: self.key,
+      secret_key: self.kind_key.clone(),
+      created_at: self.created_at,
+      version: self.version.clone(),
+      metadata: self.metadata.clone(),
+    }
+  }
+
   pub fn try_to_cargo_spec(&self) -> IoResult<CargoSpec> {
     let p = serde_json::from_value::<CargoSpecPartial>(self.data.clone())?;
     let spec = CargoSpec {
@@ -171,8 +231,10 @@ impl SpecDb {
       hostname: p.hostname,
       password: p.password,
       disk: p.disk,
+      disks: p.disks,
       host_config: p.host_config.unwrap_or_default(),
       ssh_key: p.ssh_key,
+      cloud_init: p.cloud_init,
       user: p.user,
       mac_address: p.mac_address,
       labels: p.labels,
@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+
+use diesel::prelude::*;
+
+use nanocl_error::io::IoResult;
+
+use nanocl_stubs::generic::{GenericClause, GenericFilter};
+
+use crate::{
+  gen_sql_multiple, gen_sql_order_by, gen_sql_query,
+  models::{ColumnType, Pool, VmSnapshotDb, VmSnapshotUpdateDb},
+  schema::vm_snapshots,
+};
+
+use super::generic::*;
+
+impl RepositoryBase for VmSnapshotDb {
+  fn get_columns<'a>() -> HashMap<&'a str, (ColumnType, &'a str)> {
+    HashMap::from([
+      ("key", (ColumnType::Uuid, "vm_snapshots.key")),
+      ("name", (ColumnType::Text, "vm_snapshots.name")),
+      ("vm_key", (ColumnType::Text, "vm_snapshots.vm_key")),
+      ("parent", (ColumnType::Uuid, "vm_snapshots.parent")),
+      ("path", (ColumnType::Text, "vm_snapshots.path")),
+      (
+        "created_at",
+        (ColumnType::Timestamptz, "vm_snapshots.created_at"),
+      ),
+    ])
+  }
+}
+
+impl RepositoryCreate for VmSnapshotDb {}
+
+impl RepositoryUpdate for VmSnapshotDb {
+  type UpdateItem = VmSnapshotUpdateDb;
+}
+
+impl RepositoryDelByPk for VmSnapshotDb {}
+
+impl RepositoryDelBy for VmSnapshotDb {
+  fn gen_del_query(
+    filter: &GenericFilter,
+  ) -> diesel::query_builder::BoxedDeleteStatement<
+    'static,
+    diesel::pg::Pg,
+    <Self as diesel::associations::HasTable>::Table,
+  >
+  where
+    Self: diesel::associations::HasTable,
+  {
+    let mut query = diesel::delete(vm_snapshots::table).into_boxed();
+    let columns = Self::get_columns();
+    gen_sql_query!(query, filter, columns)
+  }
+}
+
+impl RepositoryReadBy for VmSnapshotDb {
+  type Output = VmSnapshotDb;
+
+  fn get_pk() -> &'static str {
+    "key"
+  }
+
+  fn gen_read_query(
+    filter: &GenericFilter,
+    is_multiple: bool,
+  ) -> impl diesel::query_dsl::methods::LoadQuery<
+    'static,
+    diesel::pg::PgConnection,
+    Self::Output,
+  > {
+    let mut query = vm_snapshots::table.into_boxed();
+    let columns = Self::get_columns();
+    query = gen_sql_query!(query, filter, columns);
+    if let Some(orders) = &filter.order_by {
+      query = gen_sql_order_by!(query, orders, columns);
+    } else {
+      query = query.order(vm_snapshots::created_at.desc());
+    }
+    if is_multiple {
+      gen_sql_multiple!(query, filter);
+    }
+    query
+  }
+}
+
+impl VmSnapshotDb {
+  /// Read the snapshots of a virtual machine
+  pub async fn read_by_vm(
+    vm_key: &str,
+    pool: &Pool,
+  ) -> IoResult<Vec<VmSnapshotDb>> {
+    let filter = GenericFilter::new()
+      .r#where("vm_key", GenericClause::Eq(vm_key.to_owned()));
+    VmSnapshotDb::read_by(&filter, pool).await
+  }
+
+  /// Read the snapshots directly based on the given snapshot
+  pub async fn read_by_parent(
+    key: &uuid::Uuid,
+    pool: &Pool,
+  ) -> IoResult<Vec<VmSnapshotDb>> {
+    let filter = GenericFilter::new()
+      .r#where("parent", GenericClause::Eq(key.to_string()));
+    VmSnapshotDb::read_by(&filter, pool).await
+  }
+}
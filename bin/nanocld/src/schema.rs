@@ -123,6 +123,9 @@ diesel::table! {
         created_at -> Timestamptz,
         kind -> Varchar,
         spec_key -> Uuid,
+        status -> Nullable<Jsonb>,
+        owners -> Jsonb,
+        finalizers -> Jsonb,
     }
 }
 
@@ -164,6 +167,19 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    vm_snapshots (key) {
+        key -> Uuid,
+        name -> Varchar,
+        vm_key -> Varchar,
+        created_at -> Timestamptz,
+        path -> Varchar,
+        parent -> Nullable<Uuid>,
+        size_actual -> Int8,
+        size_virtual -> Int8,
+    }
+}
+
 diesel::table! {
     vms (key) {
         key -> Varchar,
@@ -185,6 +201,7 @@ diesel::joinable!(processes -> nodes (node_name));
 diesel::joinable!(resource_kinds -> specs (spec_key));
 diesel::joinable!(resources -> specs (spec_key));
 diesel::joinable!(vm_images -> nodes (node_name));
+diesel::joinable!(vm_snapshots -> vms (vm_key));
 diesel::joinable!(vms -> namespaces (namespace_name));
 diesel::joinable!(vms -> object_process_statuses (status_key));
 diesel::joinable!(vms -> specs (spec_key));
@@ -205,5 +222,6 @@ diesel::allow_tables_to_appear_in_same_query!(
   secrets,
   specs,
   vm_images,
+  vm_snapshots,
   vms,
 );
@@ -18,6 +18,7 @@ use crate::{
     ("name" = String, Path, description = "Name of the cargo"),
     ("force" = bool, Query, description = "If true forces the delete operation even if the cargo is started"),
     ("namespace" = Option<String>, Query, description = "Namespace where the cargoes belongs default to 'global'"),
+    ("propagation" = Option<nanocl_stubs::generic::DeletePropagation>, Query, description = "What happens to the resources it owns: Foreground, Background or Orphan default to Background"),
   ),
   responses(
     (status = 202, description = "Cargo deleted"),
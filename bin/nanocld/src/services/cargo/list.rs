@@ -1,7 +1,11 @@
 use ntex::web;
 
 use nanocl_error::http::HttpResult;
-use nanocl_stubs::generic::GenericListQueryNsp;
+use nanocl_stubs::{
+  cargo::CargoSummary,
+  generic::{GenericFilterNsp, GenericListQueryNsp},
+  system::EventActorKind,
+};
 
 use crate::{
   models::{CargoDb, SystemState},
@@ -16,6 +20,8 @@ use crate::{
   params(
     ("filter" = Option<String>, Query, description = "Generic filter", example = "{ \"filter\": { \"where\": { \"name\": { \"eq\": \"test\" } } } }"),
     ("namespace" = Option<String>, Query, description = "Namespace where the cargoes belongs default to 'global'"),
+    ("watch" = Option<bool>, Query, description = "Stream the items followed by their changes"),
+    ("cursor" = Option<String>, Query, description = "Cursor of a previous watch to resume from"),
   ),
   responses(
     (status = 200, description = "List of cargoes", body = [nanocl_stubs::cargo::CargoSummary]),
@@ -27,6 +33,24 @@ pub async fn list_cargo(
   qs: web::types::Query<GenericListQueryNsp>,
 ) -> HttpResult<web::HttpResponse> {
   let query = utils::query_string::parse_qs_nsp_filter(&qs)?;
+  if qs.watch.unwrap_or_default() {
+    return utils::watch::stream(
+      EventActorKind::Cargo,
+      qs.cursor.clone(),
+      query.filter.clone().unwrap_or_default(),
+      "key",
+      |cargo: &CargoSummary| cargo.spec.cargo_key.clone(),
+      move |state, filter| {
+        let query = GenericFilterNsp {
+          filter: Some(filter),
+          namespace: query.namespace.clone(),
+        };
+        async move { CargoDb::list(&query, &state).await }
+      },
+      &state,
+    )
+    .await;
+  }
   let cargoes = CargoDb::list(&query, &state).await?;
   Ok(web::HttpResponse::Ok().json(&cargoes))
 }
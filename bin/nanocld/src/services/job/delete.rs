@@ -1,6 +1,7 @@
 use ntex::web;
 
 use nanocl_error::http::HttpResult;
+use nanocl_stubs::generic::GenericDeleteQuery;
 
 use crate::{
   models::{JobDb, SystemState},
@@ -14,6 +15,7 @@ use crate::{
   path = "/jobs/{name}",
   params(
     ("name" = String, Path, description = "Name of the job"),
+    ("propagation" = Option<nanocl_stubs::generic::DeletePropagation>, Query, description = "What happens to the resources it owns: Foreground, Background or Orphan default to Background"),
   ),
   responses(
     (status = 202, description = "Job deleted"),
@@ -24,7 +26,8 @@ use crate::{
 pub async fn delete_job(
   state: web::types::State<SystemState>,
   path: web::types::Path<(String, String)>,
+  qs: web::types::Query<GenericDeleteQuery>,
 ) -> HttpResult<web::HttpResponse> {
-  JobDb::del_obj_by_pk(&path.1, &(), &state).await?;
+  JobDb::del_obj_by_pk(&path.1, &qs, &state).await?;
   Ok(web::HttpResponse::Accepted().finish())
 }
@@ -1,7 +1,9 @@
 use ntex::web;
 
 use nanocl_error::http::HttpResult;
-use nanocl_stubs::generic::GenericListQuery;
+use nanocl_stubs::{
+  generic::GenericListQuery, job::JobSummary, system::EventActorKind,
+};
 
 use crate::{
   models::{JobDb, SystemState},
@@ -15,6 +17,8 @@ use crate::{
   path = "/jobs",
   params(
     ("filter" = Option<String>, Query, description = "Generic filter", example = "{ \"filter\": { \"where\": { \"name\": { \"eq\": \"job-example\" } } } }"),
+    ("watch" = Option<bool>, Query, description = "Stream the items followed by their changes"),
+    ("cursor" = Option<String>, Query, description = "Cursor of a previous watch to resume from"),
   ),
   responses(
     (status = 200, description = "List of jobs", body = [nanocl_stubs::job::JobSummary]),
@@ -27,6 +31,18 @@ pub async fn list_job(
   qs: web::types::Query<GenericListQuery>,
 ) -> HttpResult<web::HttpResponse> {
   let filter = utils::query_string::parse_qs_filter(&qs)?;
+  if qs.watch.unwrap_or_default() {
+    return utils::watch::stream(
+      EventActorKind::Job,
+      qs.cursor.clone(),
+      filter,
+      "key",
+      |job: &JobSummary| job.spec.name.clone(),
+      |state, filter| async move { JobDb::list(&filter, &state).await },
+      &state,
+    )
+    .await;
+  }
   let jobs = JobDb::list(&filter, &state).await?;
   Ok(web::HttpResponse::Ok().json(&jobs))
 }
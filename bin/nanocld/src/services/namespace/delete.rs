@@ -1,6 +1,7 @@
 use ntex::web;
 
 use nanocl_error::http::HttpResult;
+use nanocl_stubs::generic::GenericDeleteQuery;
 
 use crate::{
   models::{NamespaceDb, SystemState},
@@ -13,10 +14,11 @@ use crate::{
   tag = "Namespaces",
   path = "/namespaces/{name}",
   params(
-    ("name" = String, Path, description = "Name of the namespace to delete")
+    ("name" = String, Path, description = "Name of the namespace to delete"),
+    ("force" = Option<bool>, Query, description = "Also delete the cargoes with running instances"),
   ),
   responses(
-    (status = 202, description = "Namespace have been deleted"),
+    (status = 202, description = "Namespace is being deleted with its cargoes and virtual machines"),
     (status = 404, description = "Namespace is not existing", body = crate::services::openapi::ApiError),
   ),
 ))]
@@ -24,7 +26,8 @@ use crate::{
 pub async fn delete_namespace(
   state: web::types::State<SystemState>,
   path: web::types::Path<(String, String)>,
+  qs: web::types::Query<GenericDeleteQuery>,
 ) -> HttpResult<web::HttpResponse> {
-  NamespaceDb::del_obj_by_pk(&path.1, &(), &state).await?;
+  NamespaceDb::del_obj_by_pk(&path.1, &qs, &state).await?;
   Ok(web::HttpResponse::Accepted().into())
 }
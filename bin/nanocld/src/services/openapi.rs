@@ -19,6 +19,9 @@ use super::{
 #[derive(ToSchema)]
 pub struct ApiError {
   msg: String,
+  /// Every invalid field when the data of a resource is invalid
+  details:
+    Option<Vec<nanocl_stubs::resource_kind::ResourceKindValidationError>>,
 }
 
 /// Helper to generate the versioned OpenAPI documentation
@@ -67,6 +70,10 @@ impl Modify for VersionModifier {
     secret::delete_secret,
     secret::patch_secret,
     secret::count_secret,
+    secret::rotate_secret_key,
+    secret::seal_secret,
+    secret::list_secret_history,
+    secret::revert_secret,
     // Job
     job::list_job,
     job::delete_job,
@@ -90,10 +97,15 @@ impl Modify for VersionModifier {
     // VM Image
     vm_image::list_vm_images,
     vm_image::import_vm_image,
+    vm_image::pull_vm_image,
+    vm_image::export_vm_image,
     vm_image::delete_vm_image,
     vm_image::resize_vm_image,
     vm_image::clone_vm_image,
     vm_image::snapshot_vm_image,
+    vm_image::template_vm_image,
+    vm_image::untemplate_vm_image,
+    vm_image::flatten_vm_image,
     // Vm
     vm::list_vm,
     vm::inspect_vm,
@@ -102,6 +114,12 @@ impl Modify for VersionModifier {
     vm::list_vm_history,
     vm::patch_vm,
     vm::vm_attach,
+    vm::logs_vm,
+    vm::list_vm_snapshot,
+    vm::create_vm_snapshot,
+    vm::inspect_vm_snapshot,
+    vm::delete_vm_snapshot,
+    vm::restore_vm_snapshot,
     // Resource Kind
     resource_kind::list_resource_kind,
     resource_kind::create_resource_kind,
@@ -109,6 +127,8 @@ impl Modify for VersionModifier {
     resource_kind::inspect_resource_kind,
     resource_kind::count_resource_kind,
     resource_kind::inspect_resource_kind_version,
+    resource_kind::list_resource_kind_resources,
+    resource_kind::migrate_resource_kind,
     // Resource
     resource::list_resource,
     resource::inspect_resource,
@@ -118,6 +138,8 @@ impl Modify for VersionModifier {
     resource::list_resource_history,
     resource::revert_resource,
     resource::count_resource,
+    resource::patch_resource_status,
+    resource::patch_resource_finalizers,
     // Metric
     metric::list_metric,
     metric::create_metric,
@@ -130,6 +152,8 @@ impl Modify for VersionModifier {
     process::stop_processes,
     process::list_processes,
     process::restart_processes,
+    process::reboot_processes,
+    process::reset_processes,
     process::kill_processes,
     process::wait_processes,
     process::stats_processes,
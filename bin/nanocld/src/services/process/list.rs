@@ -1,7 +1,9 @@
 use ntex::web;
 
 use nanocl_error::http::HttpResult;
-use nanocl_stubs::generic::GenericListQuery;
+use nanocl_stubs::{
+  generic::GenericListQuery, process::Process, system::EventActorKind,
+};
 
 use crate::{
   models::{ProcessDb, SystemState},
@@ -16,6 +18,8 @@ use crate::{
   path = "/processes",
   params(
     ("filter" = Option<String>, Query, description = "Generic filter", example = "{ \"where\": { \"name\": { \"eq\": \"test\" } } }"),
+    ("watch" = Option<bool>, Query, description = "Stream the items followed by their changes"),
+    ("cursor" = Option<String>, Query, description = "Cursor of a previous watch to resume from"),
   ),
   responses(
     (status = 200, description = "List of instances", body = [nanocl_stubs::process::Process]),
@@ -27,6 +31,22 @@ pub async fn list_processes(
   qs: web::types::Query<GenericListQuery>,
 ) -> HttpResult<web::HttpResponse> {
   let filter = utils::query_string::parse_qs_filter(&qs)?;
+  if qs.watch.unwrap_or_default() {
+    return utils::watch::stream(
+      EventActorKind::Process,
+      qs.cursor.clone(),
+      filter,
+      "name",
+      |process: &Process| process.name.clone(),
+      |state, filter| async move {
+        let processes =
+          ProcessDb::transform_read_by(&filter, &state.inner.pool).await?;
+        Ok(processes)
+      },
+      &state,
+    )
+    .await;
+  }
   let processes =
     ProcessDb::transform_read_by(&filter, &state.inner.pool).await?;
   Ok(web::HttpResponse::Ok().json(&processes))
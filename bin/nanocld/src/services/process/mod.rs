@@ -5,6 +5,8 @@ pub mod inspect;
 pub mod kill;
 pub mod list;
 pub mod log;
+pub mod reboot;
+pub mod reset;
 pub mod restart;
 pub mod start;
 pub mod stats;
@@ -16,6 +18,8 @@ pub use inspect::*;
 pub use kill::*;
 pub use list::*;
 pub use log::*;
+pub use reboot::*;
+pub use reset::*;
 pub use restart::*;
 pub use start::*;
 pub use stats::*;
@@ -28,6 +32,8 @@ pub fn ntex_config(config: &mut web::ServiceConfig) {
   config.service(list_processes);
   config.service(logs_processes);
   config.service(restart_processes);
+  config.service(reboot_processes);
+  config.service(reset_processes);
   config.service(start_processes);
   config.service(stop_processes);
   config.service(kill_processes);
@@ -124,4 +130,24 @@ mod tests {
       "basic process inspect"
     );
   }
+
+  #[ntex::test]
+  async fn reset_not_found() {
+    let system = gen_default_test_system().await;
+    let client = system.client;
+    for action in ["reset", "reboot", "restart"] {
+      let res = client
+        .send_post(
+          &format!("/processes/vm/not-found/{action}"),
+          None::<String>,
+          None::<String>,
+        )
+        .await;
+      test_status_code!(
+        res.status(),
+        http::StatusCode::NOT_FOUND,
+        format!("{action} unknown vm")
+      );
+    }
+  }
 }
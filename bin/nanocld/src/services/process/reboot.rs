@@ -0,0 +1,40 @@
+use ntex::web;
+
+use nanocl_error::http::{HttpError, HttpResult};
+use nanocl_stubs::{generic::GenericNspQuery, process::ProcessKind};
+
+use crate::{models::SystemState, utils};
+
+/// Reboot a virtual machine gracefully by powering it off and resetting it
+#[cfg_attr(feature = "dev", utoipa::path(
+  post,
+  tag = "Processes",
+  path = "/processes/{kind}/{name}/reboot",
+  params(
+    ("kind" = String, Path, description = "Kind of the process only vm is supported", example = "vm"),
+    ("name" = String, Path, description = "Name of the process", example = "ubuntu"),
+    ("namespace" = Option<String>, Query, description = "Namespace where the process belongs if needed"),
+  ),
+  responses(
+    (status = 202, description = "Virtual machine rebooting"),
+    (status = 400, description = "The process kind doesn't support reboot", body = crate::services::openapi::ApiError),
+    (status = 404, description = "The virtual machine does not exist", body = crate::services::openapi::ApiError),
+  ),
+))]
+#[web::post("/processes/{kind}/{name}/reboot")]
+pub async fn reboot_processes(
+  state: web::types::State<SystemState>,
+  path: web::types::Path<(String, String, String)>,
+  qs: web::types::Query<GenericNspQuery>,
+) -> HttpResult<web::HttpResponse> {
+  let (_, kind, name) = path.into_inner();
+  let kind: ProcessKind = kind.parse().map_err(HttpError::bad_request)?;
+  if kind != ProcessKind::Vm {
+    return Err(HttpError::bad_request(format!(
+      "Reboot is not supported for process kind {kind}"
+    )));
+  }
+  let kind_key = utils::key::gen_kind_key(&kind, &name, &qs.namespace);
+  utils::container::vm::spawn_reboot(&kind_key, &state).await?;
+  Ok(web::HttpResponse::Accepted().finish())
+}
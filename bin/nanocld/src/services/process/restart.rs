@@ -1,11 +1,12 @@
 use ntex::web;
 
 use nanocl_error::http::{HttpError, HttpResult};
-use nanocl_stubs::generic::GenericNspQuery;
+use nanocl_stubs::{generic::GenericNspQuery, process::ProcessKind};
 
 use crate::{models::SystemState, utils};
 
-/// Restart all processes of given kind and name (cargo, job, vm)
+/// Restart all processes of given kind and name (cargo, job, vm).
+/// Virtual machines are rebooted gracefully like with `/reboot`.
 #[cfg_attr(feature = "dev", utoipa::path(
   post,
   tag = "Processes",
@@ -17,6 +18,7 @@ use crate::{models::SystemState, utils};
   ),
   responses(
     (status = 202, description = "Process instances restarted"),
+    (status = 404, description = "The virtual machine does not exist", body = crate::services::openapi::ApiError),
   ),
 ))]
 #[web::post("/processes/{kind}/{name}/restart")]
@@ -28,6 +30,10 @@ pub async fn restart_processes(
   let (_, kind, name) = path.into_inner();
   let kind = kind.parse().map_err(HttpError::bad_request)?;
   let kind_pk = utils::key::gen_kind_key(&kind, &name, &qs.namespace);
+  if kind == ProcessKind::Vm {
+    utils::container::vm::spawn_reboot(&kind_pk, &state).await?;
+    return Ok(web::HttpResponse::Accepted().finish());
+  }
   utils::container::process::restart_instances(&kind_pk, &kind, &state).await?;
   Ok(web::HttpResponse::Accepted().finish())
 }
@@ -1,6 +1,7 @@
 use ntex::web;
 
 use nanocl_error::http::HttpResult;
+use nanocl_stubs::generic::GenericDeleteQuery;
 
 use crate::{
   models::{ResourceDb, SystemState},
@@ -13,18 +14,21 @@ use crate::{
   tag = "Resources",
   path = "/resources/{name}",
   params(
-    ("name" = String, Path, description = "The resource name to delete")
+    ("name" = String, Path, description = "The resource name to delete"),
+    ("propagation" = Option<nanocl_stubs::generic::DeletePropagation>, Query, description = "What happens to the resources it owns: Foreground, Background or Orphan default to Background"),
   ),
   responses(
     (status = 202, description = "The resource and his history has been deleted"),
     (status = 404, description = "Resource doesn't exist", body = crate::services::openapi::ApiError),
+    (status = 409, description = "Owned resources are still being deleted", body = crate::services::openapi::ApiError),
   ),
 ))]
 #[web::delete("/resources/{name}")]
 pub async fn delete_resource(
   state: web::types::State<SystemState>,
   path: web::types::Path<(String, String)>,
+  qs: web::types::Query<GenericDeleteQuery>,
 ) -> HttpResult<web::HttpResponse> {
-  ResourceDb::del_obj_by_pk(&path.1, &(), &state).await?;
+  ResourceDb::del_obj_by_pk(&path.1, &qs, &state).await?;
   Ok(web::HttpResponse::Accepted().finish())
 }
@@ -1,7 +1,9 @@
 use ntex::web;
 
 use nanocl_error::http::HttpResult;
-use nanocl_stubs::generic::GenericListQuery;
+use nanocl_stubs::{
+  generic::GenericListQuery, resource::Resource, system::EventActorKind,
+};
 
 use crate::{
   models::{ResourceDb, SystemState},
@@ -16,6 +18,8 @@ use crate::{
   path = "/resources",
   params(
     ("filter" = Option<String>, Query, description = "Generic filter", example = "{ \"filter\": { \"where\": { \"kind\": { \"eq\": \"ncproxy.io/rule\" } } } }"),
+    ("watch" = Option<bool>, Query, description = "Stream the items followed by their changes"),
+    ("cursor" = Option<String>, Query, description = "Cursor of a previous watch to resume from"),
   ),
   responses(
     (status = 200, description = "List of resources", body = [nanocl_stubs::resource::Resource]),
@@ -27,6 +31,22 @@ pub async fn list_resource(
   qs: web::types::Query<GenericListQuery>,
 ) -> HttpResult<web::HttpResponse> {
   let filter = utils::query_string::parse_qs_filter(&qs)?;
+  if qs.watch.unwrap_or_default() {
+    return utils::watch::stream(
+      EventActorKind::Resource,
+      qs.cursor.clone(),
+      filter,
+      "key",
+      |resource: &Resource| resource.spec.resource_key.clone(),
+      |state, filter| async move {
+        let items =
+          ResourceDb::transform_read_by(&filter, &state.inner.pool).await?;
+        Ok(items)
+      },
+      &state,
+    )
+    .await;
+  }
   let items = ResourceDb::transform_read_by(&filter, &state.inner.pool).await?;
   Ok(web::HttpResponse::Ok().json(&items))
 }
@@ -6,6 +6,8 @@ pub mod delete;
 pub mod inspect;
 pub mod list;
 pub mod list_history;
+pub mod patch_finalizers;
+pub mod patch_status;
 pub mod put;
 pub mod revert;
 
@@ -15,6 +17,8 @@ pub use delete::*;
 pub use inspect::*;
 pub use list::*;
 pub use list_history::*;
+pub use patch_finalizers::*;
+pub use patch_status::*;
 pub use put::*;
 pub use revert::*;
 
@@ -27,14 +31,25 @@ pub fn ntex_config(config: &mut web::ServiceConfig) {
   config.service(count_resource);
   config.service(list_resource_history);
   config.service(revert_resource);
+  config.service(patch_resource_status);
+  config.service(patch_resource_finalizers);
 }
 
 #[cfg(test)]
 mod tests {
   use nanocl_stubs::{
-    generic::{GenericClause, GenericFilter, GenericListQuery},
-    resource::{Resource, ResourcePartial, ResourceUpdate},
-    resource_kind::{ResourceKindPartial, ResourceKindSpec},
+    generic::{
+      DeletePropagation, GenericClause, GenericDeleteQuery, GenericFilter,
+      GenericListQuery,
+    },
+    resource::{
+      Resource, ResourceCondition, ResourceFinalizersPatch, ResourceOwner,
+      ResourcePartial, ResourcePhase, ResourceStatusPartial, ResourceUpdate,
+    },
+    resource_kind::{
+      ResourceKindPartial, ResourceKindSpec, ResourceKindValidationError,
+    },
+    system::EventActorKind,
   };
   use ntex::http;
 
@@ -76,6 +91,10 @@ mod tests {
       data: ResourceKindSpec {
         schema: Some(spec),
         url: None,
+        conversion: None,
+        controller: None,
+        immutable: None,
+        wasm: None,
       },
     };
     let res = client
@@ -96,6 +115,8 @@ mod tests {
       metadata: Some(serde_json::json!({
         "Test": "gg",
       })),
+      owners: None,
+      finalizers: None,
     };
     let mut res = client
       .send_post(ENDPOINT, Some(&resource), None::<String>)
@@ -207,6 +228,8 @@ mod tests {
     let new_resource = ResourceUpdate {
       data: data.clone(),
       metadata: None,
+      owners: None,
+      finalizers: None,
     };
     let mut res = client
       .send_put(
@@ -240,4 +263,504 @@ mod tests {
       "delete resource kind"
     );
   }
+
+  #[ntex::test]
+  async fn status() {
+    const TEST_RESOURCE: &str = "test_resource_status";
+    const TEST_RESOURCE_KIND: &str = "test.io/test-resource-status";
+    let system = gen_default_test_system().await;
+    let client = system.client;
+    // The controller of the kind is unreachable
+    let payload = ResourceKindPartial {
+      name: TEST_RESOURCE_KIND.to_owned(),
+      version: "v1".to_owned(),
+      metadata: None,
+      data: ResourceKindSpec {
+        schema: None,
+        url: Some("http://127.0.0.1:1".to_owned()),
+        conversion: None,
+        controller: None,
+        immutable: None,
+        wasm: None,
+      },
+    };
+    let res = client
+      .send_post("/resource/kinds", Some(&payload), None::<String>)
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::CREATED,
+      "create resource kind"
+    );
+    let resource = ResourcePartial {
+      name: TEST_RESOURCE.to_owned(),
+      kind: TEST_RESOURCE_KIND.to_owned(),
+      data: serde_json::json!({ "Username": "test" }),
+      metadata: None,
+      owners: None,
+      finalizers: None,
+    };
+    let mut res = client
+      .send_post(ENDPOINT, Some(&resource), None::<String>)
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::CREATED,
+      "create resource with unreachable controller"
+    );
+    let resource = res.json::<Resource>().await.unwrap();
+    let status = resource.status.clone().unwrap();
+    assert_eq!(status.phase, ResourcePhase::Pending);
+    assert_eq!(status.retries, 1);
+    assert!(status.next_retry_at.is_some());
+    // The controller report the resource as converged
+    let partial = ResourceStatusPartial {
+      phase: Some(ResourcePhase::Ready),
+      conditions: Some(vec![ResourceCondition {
+        r#type: "Reachable".to_owned(),
+        status: true,
+        reason: None,
+        message: None,
+      }]),
+      observed_version: Some(resource.spec.key),
+      message: None,
+    };
+    let mut res = client
+      .send_patch(
+        &format!("{ENDPOINT}/{TEST_RESOURCE}/status"),
+        Some(&partial),
+        None::<String>,
+      )
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::OK,
+      "patch resource status"
+    );
+    let resource = res.json::<Resource>().await.unwrap();
+    let status = resource.status.unwrap();
+    assert_eq!(status.phase, ResourcePhase::Ready);
+    assert_eq!(status.retries, 0);
+    assert_eq!(status.observed_version, Some(resource.spec.key));
+    assert_eq!(status.conditions.len(), 1);
+    let partial = ResourceStatusPartial {
+      observed_version: Some(uuid::Uuid::new_v4()),
+      ..Default::default()
+    };
+    let res = client
+      .send_patch(
+        &format!("{ENDPOINT}/{TEST_RESOURCE}/status"),
+        Some(&partial),
+        None::<String>,
+      )
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::BAD_REQUEST,
+      "patch resource status with unknown version"
+    );
+    // The resource is kept until the controller delete it
+    let res = client
+      .send_delete(&format!("{ENDPOINT}/{TEST_RESOURCE}"), None::<String>)
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::ACCEPTED,
+      "delete resource with unreachable controller"
+    );
+    let mut res = client
+      .send_get(
+        &format!("{ENDPOINT}/{TEST_RESOURCE}/inspect"),
+        None::<String>,
+      )
+      .await;
+    test_status_code!(res.status(), http::StatusCode::OK, "inspect resource");
+    let resource = res.json::<Resource>().await.unwrap();
+    assert_eq!(resource.status.unwrap().phase, ResourcePhase::Deleting);
+    let res = client
+      .send_put(
+        &format!("{ENDPOINT}/{TEST_RESOURCE}"),
+        Some(&ResourceUpdate {
+          data: serde_json::json!({}),
+          metadata: None,
+          owners: None,
+          finalizers: None,
+        }),
+        None::<String>,
+      )
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::CONFLICT,
+      "put resource being deleted"
+    );
+    let res = client
+      .send_delete(
+        &format!("/resource/kinds/{TEST_RESOURCE_KIND}"),
+        Some(&GenericDeleteQuery {
+          force: Some(true),
+          ..Default::default()
+        }),
+      )
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::ACCEPTED,
+      "delete resource kind"
+    );
+  }
+
+  #[ntex::test]
+  async fn owners() {
+    const TEST_OWNER: &str = "test_resource_owner";
+    const TEST_OWNED: &str = "test_resource_owned";
+    const TEST_FINALIZED: &str = "test_resource_finalized";
+    const TEST_RESOURCE_KIND: &str = "test.io/test-resource-owners";
+    const TEST_FINALIZER: &str = "test.io/cleanup";
+    let system = gen_default_test_system().await;
+    let client = system.client;
+    let payload = ResourceKindPartial {
+      name: TEST_RESOURCE_KIND.to_owned(),
+      version: "v1".to_owned(),
+      metadata: None,
+      data: ResourceKindSpec {
+        schema: None,
+        url: None,
+        conversion: None,
+        controller: None,
+        immutable: None,
+        wasm: None,
+      },
+    };
+    let res = client
+      .send_post("/resource/kinds", Some(&payload), None::<String>)
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::CREATED,
+      "create resource kind"
+    );
+    let gen_resource = |name: &str, owners, finalizers| ResourcePartial {
+      name: name.to_owned(),
+      kind: TEST_RESOURCE_KIND.to_owned(),
+      data: serde_json::json!({}),
+      metadata: None,
+      owners,
+      finalizers,
+    };
+    let owner = ResourceOwner {
+      kind: EventActorKind::Resource,
+      key: TEST_OWNER.to_owned(),
+    };
+    let res = client
+      .send_post(
+        ENDPOINT,
+        Some(&gen_resource(TEST_OWNED, Some(vec![owner.clone()]), None)),
+        None::<String>,
+      )
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::BAD_REQUEST,
+      "create resource with missing owner"
+    );
+    for resource in [
+      gen_resource(TEST_OWNER, None, None),
+      gen_resource(TEST_OWNED, Some(vec![owner.clone()]), None),
+      gen_resource(
+        TEST_FINALIZED,
+        Some(vec![owner.clone()]),
+        Some(vec![TEST_FINALIZER.to_owned()]),
+      ),
+    ] {
+      let res = client
+        .send_post(ENDPOINT, Some(&resource), None::<String>)
+        .await;
+      test_status_code!(
+        res.status(),
+        http::StatusCode::CREATED,
+        format!("create resource {}", resource.name)
+      );
+    }
+    // The finalized resource is kept until its finalizer is removed
+    let res = client
+      .send_delete(&format!("{ENDPOINT}/{TEST_FINALIZED}"), None::<String>)
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::ACCEPTED,
+      "delete resource with finalizer"
+    );
+    let mut res = client
+      .send_get(
+        &format!("{ENDPOINT}/{TEST_FINALIZED}/inspect"),
+        None::<String>,
+      )
+      .await;
+    test_status_code!(res.status(), http::StatusCode::OK, "inspect resource");
+    let resource = res.json::<Resource>().await.unwrap();
+    assert_eq!(resource.status.unwrap().phase, ResourcePhase::Deleting);
+    let res = client
+      .send_patch(
+        &format!("{ENDPOINT}/{TEST_FINALIZED}/finalizers"),
+        Some(&ResourceFinalizersPatch {
+          add: vec!["test.io/other".to_owned()],
+          ..Default::default()
+        }),
+        None::<String>,
+      )
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::CONFLICT,
+      "add finalizer to resource being deleted"
+    );
+    let res = client
+      .send_patch(
+        &format!("{ENDPOINT}/{TEST_FINALIZED}/finalizers"),
+        Some(&ResourceFinalizersPatch {
+          remove: vec![TEST_FINALIZER.to_owned()],
+          ..Default::default()
+        }),
+        None::<String>,
+      )
+      .await;
+    test_status_code!(res.status(), http::StatusCode::OK, "remove finalizer");
+    let res = client
+      .send_get(
+        &format!("{ENDPOINT}/{TEST_FINALIZED}/inspect"),
+        None::<String>,
+      )
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::NOT_FOUND,
+      "inspect finalized resource"
+    );
+    // The owned resource is kept without reference to the deleted owner
+    let res = client
+      .send_delete(
+        &format!("{ENDPOINT}/{TEST_OWNER}"),
+        Some(&GenericDeleteQuery {
+          propagation: Some(DeletePropagation::Orphan),
+          ..Default::default()
+        }),
+      )
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::ACCEPTED,
+      "delete owner with orphan propagation"
+    );
+    let mut res = client
+      .send_get(&format!("{ENDPOINT}/{TEST_OWNED}/inspect"), None::<String>)
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::OK,
+      "inspect orphaned resource"
+    );
+    let resource = res.json::<Resource>().await.unwrap();
+    assert!(resource.owners.is_empty());
+    // The owned resource is deleted before its owner
+    let res = client
+      .send_post(
+        ENDPOINT,
+        Some(&gen_resource(TEST_OWNER, None, None)),
+        None::<String>,
+      )
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::CREATED,
+      "create resource owner"
+    );
+    let res = client
+      .send_put(
+        &format!("{ENDPOINT}/{TEST_OWNED}"),
+        Some(&ResourceUpdate {
+          data: serde_json::json!({}),
+          metadata: None,
+          owners: Some(vec![owner]),
+          finalizers: None,
+        }),
+        None::<String>,
+      )
+      .await;
+    test_status_code!(res.status(), http::StatusCode::OK, "put resource owner");
+    // The owner cannot be owned back by the resource it owns
+    let res = client
+      .send_put(
+        &format!("{ENDPOINT}/{TEST_OWNER}"),
+        Some(&ResourceUpdate {
+          data: serde_json::json!({}),
+          metadata: None,
+          owners: Some(vec![ResourceOwner {
+            kind: EventActorKind::Resource,
+            key: TEST_OWNED.to_owned(),
+          }]),
+          finalizers: None,
+        }),
+        None::<String>,
+      )
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::BAD_REQUEST,
+      "put owner owned by its resource"
+    );
+    let res = client
+      .send_delete(
+        &format!("{ENDPOINT}/{TEST_OWNER}"),
+        Some(&GenericDeleteQuery {
+          propagation: Some(DeletePropagation::Foreground),
+          ..Default::default()
+        }),
+      )
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::ACCEPTED,
+      "delete owner with foreground propagation"
+    );
+    let res = client
+      .send_get(&format!("{ENDPOINT}/{TEST_OWNED}/inspect"), None::<String>)
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::NOT_FOUND,
+      "inspect owned resource"
+    );
+    let res = client
+      .send_delete(
+        &format!("/resource/kinds/{TEST_RESOURCE_KIND}"),
+        Some(&GenericDeleteQuery {
+          force: Some(true),
+          ..Default::default()
+        }),
+      )
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::ACCEPTED,
+      "delete resource kind"
+    );
+  }
+
+  #[ntex::test]
+  async fn validation() {
+    const TEST_RESOURCE: &str = "test_resource_validation";
+    const TEST_RESOURCE_KIND: &str = "test.io/test-resource-validation";
+    let system = gen_default_test_system().await;
+    let client = system.client;
+    let payload = ResourceKindPartial {
+      name: TEST_RESOURCE_KIND.to_owned(),
+      version: "v1".to_owned(),
+      metadata: None,
+      data: ResourceKindSpec {
+        schema: Some(serde_json::json!({
+          "type": "object",
+          "required": ["Username"],
+          "properties": {
+            "Username": { "type": "string" },
+            "Port": { "type": "integer", "default": 80 },
+          },
+        })),
+        url: None,
+        conversion: None,
+        controller: None,
+        immutable: Some(vec!["/Username".to_owned()]),
+        wasm: None,
+      },
+    };
+    let res = client
+      .send_post("/resource/kinds", Some(&payload), None::<String>)
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::CREATED,
+      "create resource kind"
+    );
+    let resource = ResourcePartial {
+      name: TEST_RESOURCE.to_owned(),
+      kind: TEST_RESOURCE_KIND.to_owned(),
+      data: serde_json::json!({ "Port": "80" }),
+      metadata: None,
+      owners: None,
+      finalizers: None,
+    };
+    let mut res = client
+      .send_post(ENDPOINT, Some(&resource), None::<String>)
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::BAD_REQUEST,
+      "create invalid resource"
+    );
+    let body = res.json::<serde_json::Value>().await.unwrap();
+    let errors = serde_json::from_value::<Vec<ResourceKindValidationError>>(
+      body["details"].clone(),
+    )
+    .unwrap();
+    assert_eq!(errors.len(), 2);
+    // The defaults of the schema are stored
+    let resource = ResourcePartial {
+      data: serde_json::json!({ "Username": "nanocl" }),
+      ..resource
+    };
+    let mut res = client
+      .send_post(ENDPOINT, Some(&resource), None::<String>)
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::CREATED,
+      "create resource"
+    );
+    let created = res.json::<Resource>().await.unwrap();
+    assert_eq!(created.spec.data["Port"], 80);
+    let res = client
+      .send_put(
+        &format!("{ENDPOINT}/{TEST_RESOURCE}"),
+        Some(&ResourceUpdate {
+          data: serde_json::json!({ "Username": "admin" }),
+          metadata: None,
+          owners: None,
+          finalizers: None,
+        }),
+        None::<String>,
+      )
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::BAD_REQUEST,
+      "put resource immutable field"
+    );
+    let res = client
+      .send_put(
+        &format!("{ENDPOINT}/{TEST_RESOURCE}"),
+        Some(&ResourceUpdate {
+          data: serde_json::json!({ "Username": "nanocl", "Port": 8080 }),
+          metadata: None,
+          owners: None,
+          finalizers: None,
+        }),
+        None::<String>,
+      )
+      .await;
+    test_status_code!(res.status(), http::StatusCode::OK, "put resource");
+    let res = client
+      .send_delete(
+        &format!("/resource/kinds/{TEST_RESOURCE_KIND}"),
+        Some(&GenericDeleteQuery {
+          force: Some(true),
+          ..Default::default()
+        }),
+      )
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::ACCEPTED,
+      "delete resource kind"
+    );
+  }
 }
@@ -0,0 +1,33 @@
+use ntex::web;
+
+use nanocl_error::http::HttpResult;
+use nanocl_stubs::resource::ResourceFinalizersPatch;
+
+use crate::{models::SystemState, utils};
+
+/// Add or remove finalizers of a resource.
+/// A resource being deleted is removed with its last finalizer.
+#[cfg_attr(feature = "dev", utoipa::path(
+  patch,
+  request_body = ResourceFinalizersPatch,
+  tag = "Resources",
+  path = "/resources/{name}/finalizers",
+  params(
+    ("name" = String, Path, description = "Name of the resource")
+  ),
+  responses(
+    (status = 200, description = "Resource finalizers updated", body = nanocl_stubs::resource::Resource),
+    (status = 404, description = "Resource does not exit", body = crate::services::openapi::ApiError),
+    (status = 409, description = "Finalizers cannot be added to a resource being deleted", body = crate::services::openapi::ApiError),
+  ),
+))]
+#[web::patch("/resources/{name}/finalizers")]
+pub async fn patch_resource_finalizers(
+  state: web::types::State<SystemState>,
+  path: web::types::Path<(String, String)>,
+  payload: web::types::Json<ResourceFinalizersPatch>,
+) -> HttpResult<web::HttpResponse> {
+  let resource =
+    utils::resource::patch_finalizers(&path.1, &payload, &state).await?;
+  Ok(web::HttpResponse::Ok().json(&resource))
+}
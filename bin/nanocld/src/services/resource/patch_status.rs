@@ -0,0 +1,34 @@
+use ntex::web;
+
+use nanocl_error::http::HttpResult;
+use nanocl_stubs::resource::ResourceStatusPartial;
+
+use crate::{
+  models::{ResourceDb, SystemState},
+  objects::generic::*,
+};
+
+/// Update the status of a resource, called by the controller of its kind
+#[cfg_attr(feature = "dev", utoipa::path(
+  patch,
+  request_body = ResourceStatusPartial,
+  tag = "Resources",
+  path = "/resources/{name}/status",
+  params(
+    ("name" = String, Path, description = "Name of the resource")
+  ),
+  responses(
+    (status = 200, description = "Resource status updated", body = nanocl_stubs::resource::Resource),
+    (status = 400, description = "Observed version is not a spec of the resource", body = crate::services::openapi::ApiError),
+    (status = 404, description = "Resource does not exit", body = crate::services::openapi::ApiError),
+  ),
+))]
+#[web::patch("/resources/{name}/status")]
+pub async fn patch_resource_status(
+  state: web::types::State<SystemState>,
+  path: web::types::Path<(String, String)>,
+  payload: web::types::Json<ResourceStatusPartial>,
+) -> HttpResult<web::HttpResponse> {
+  let resource = ResourceDb::patch_obj_by_pk(&path.1, &payload, &state).await?;
+  Ok(web::HttpResponse::Ok().json(&resource))
+}
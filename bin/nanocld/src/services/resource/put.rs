@@ -36,6 +36,8 @@ pub async fn put_resource(
     kind: resource.kind,
     data: payload.data.clone(),
     metadata: payload.metadata.clone(),
+    owners: payload.owners.clone(),
+    finalizers: payload.finalizers.clone(),
   };
   let resource =
     ResourceDb::put_obj_by_pk(&path.1, &new_resource, &state).await?;
@@ -36,6 +36,8 @@ pub async fn revert_resource(
     kind: resource.kind,
     data: history.data,
     metadata: history.metadata,
+    owners: None,
+    finalizers: None,
   };
   let resource =
     ResourceDb::put_obj_by_pk(&path.1, &new_resource, &state).await?;
@@ -3,7 +3,10 @@ use ntex::web;
 use nanocl_error::http::HttpResult;
 use nanocl_stubs::resource_kind::ResourceKindPartial;
 
-use crate::models::{ResourceKindDb, SystemState};
+use crate::{
+  models::{ResourceKindDb, SystemState},
+  utils,
+};
 
 /// Create a resource kind
 #[cfg_attr(feature = "dev", utoipa::path(
@@ -13,7 +16,9 @@ use crate::models::{ResourceKindDb, SystemState};
   request_body = ResourceKindPartial,
   responses(
     (status = 201, description = "Job created", body = nanocl_stubs::resource_kind::ResourceKind),
+    (status = 400, description = "Invalid resource kind", body = crate::services::openapi::ApiError),
     (status = 409, description = "Resource kind already exist", body = crate::services::openapi::ApiError),
+    (status = 502, description = "Controller is not healthy", body = crate::services::openapi::ApiError),
   ),
 ))]
 #[web::post("/resource/kinds")]
@@ -22,6 +27,7 @@ pub async fn create_resource_kind(
   _version: web::types::Path<String>,
   payload: web::types::Json<ResourceKindPartial>,
 ) -> HttpResult<web::HttpResponse> {
+  utils::resource_kind::check_controller(&payload, &state).await?;
   let item =
     ResourceKindDb::create_from_spec(&payload, &state.inner.pool).await?;
   Ok(web::HttpResponse::Created().json(&item))
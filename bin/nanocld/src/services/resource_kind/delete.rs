@@ -1,10 +1,12 @@
 use ntex::web;
 
 use nanocl_error::http::HttpResult;
+use nanocl_stubs::generic::GenericDeleteQuery;
 
 use crate::{
   models::{ResourceKindDb, SpecDb, SystemState},
   repositories::generic::*,
+  utils,
 };
 
 /// Delete a resource kind
@@ -15,19 +17,28 @@ use crate::{
   params(
     ("domain" = String, Path, description = "Domain of the resource kind"),
     ("name" = String, Path, description = "Name of the resource kind"),
+    ("force" = Option<bool>, Query, description = "Delete the resources of the kind with it"),
   ),
   responses(
     (status = 202, description = "Resource kind deleted"),
     (status = 404, description = "Resource kind doesn't exist", body = crate::services::openapi::ApiError),
+    (status = 409, description = "Resource kind still have resources", body = crate::services::openapi::ApiError),
   ),
 ))]
 #[web::delete("/resource/kinds/{domain}/{name}")]
 pub async fn delete_resource_kind(
   state: web::types::State<SystemState>,
   path: web::types::Path<(String, String, String)>,
+  qs: web::types::Query<GenericDeleteQuery>,
 ) -> HttpResult<web::HttpResponse> {
   let key = format!("{}/{}", path.1, path.2);
   ResourceKindDb::read_by_pk(&key, &state.inner.pool).await?;
+  if qs.force.unwrap_or(false) {
+    utils::dependent::delete_resource_kind(&key, &state).await?;
+  } else {
+    let dependents = utils::dependent::list_resource_kind(&key, &state).await?;
+    utils::dependent::ensure_none("Resource kind", &key, &dependents)?;
+  }
   ResourceKindDb::del_by_pk(&key, &state.inner.pool).await?;
   SpecDb::del_by_kind_key(&key, &state.inner.pool).await?;
   Ok(web::HttpResponse::Accepted().into())
@@ -0,0 +1,57 @@
+use ntex::web;
+
+use nanocl_error::http::HttpResult;
+use nanocl_stubs::resource_kind::ResourceKindMigrate;
+
+use crate::{models::SystemState, utils};
+
+/// List the resources of a kind grouped by the version they are stored with
+#[cfg_attr(feature = "dev", utoipa::path(
+  get,
+  tag = "ResourceKinds",
+  path = "/resource/kinds/{domain}/{name}/resources",
+  params(
+    ("domain" = String, Path, description = "Domain of the resource kind"),
+    ("name" = String, Path, description = "Name of the resource kind"),
+  ),
+  responses(
+    (status = 200, description = "Resources per version", body = [nanocl_stubs::resource_kind::ResourceKindVersionResources]),
+  ),
+))]
+#[web::get("/resource/kinds/{domain}/{name}/resources")]
+pub async fn list_resource_kind_resources(
+  state: web::types::State<SystemState>,
+  path: web::types::Path<(String, String, String)>,
+) -> HttpResult<web::HttpResponse> {
+  let key = format!("{}/{}", path.1, path.2);
+  let versions = utils::resource_kind::list_versions(&key, &state).await?;
+  Ok(web::HttpResponse::Ok().json(&versions))
+}
+
+/// Convert the resources of a kind to a version and update them.
+/// Nothing is updated when a resource cannot be converted or validated.
+#[cfg_attr(feature = "dev", utoipa::path(
+  post,
+  tag = "ResourceKinds",
+  path = "/resource/kinds/{domain}/{name}/migrate",
+  request_body = ResourceKindMigrate,
+  params(
+    ("domain" = String, Path, description = "Domain of the resource kind"),
+    ("name" = String, Path, description = "Name of the resource kind"),
+  ),
+  responses(
+    (status = 200, description = "Resources migrated", body = nanocl_stubs::resource_kind::ResourceKindMigration),
+    (status = 400, description = "A resource cannot be converted to the version", body = crate::services::openapi::ApiError),
+    (status = 404, description = "Version of the resource kind doesn't exist", body = crate::services::openapi::ApiError),
+  ),
+))]
+#[web::post("/resource/kinds/{domain}/{name}/migrate")]
+pub async fn migrate_resource_kind(
+  state: web::types::State<SystemState>,
+  path: web::types::Path<(String, String, String)>,
+  payload: web::types::Json<ResourceKindMigrate>,
+) -> HttpResult<web::HttpResponse> {
+  let key = format!("{}/{}", path.1, path.2);
+  let migration = utils::resource_kind::migrate(&key, &payload, &state).await?;
+  Ok(web::HttpResponse::Ok().json(&migration))
+}
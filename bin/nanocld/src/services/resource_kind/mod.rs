@@ -5,12 +5,14 @@ pub mod create;
 pub mod delete;
 pub mod inspect;
 pub mod list;
+pub mod migrate;
 
 pub use count::*;
 pub use create::*;
 pub use delete::*;
 pub use inspect::*;
 pub use list::*;
+pub use migrate::*;
 
 pub fn ntex_config(config: &mut web::ServiceConfig) {
   config
@@ -19,22 +21,47 @@ pub fn ntex_config(config: &mut web::ServiceConfig) {
     .service(delete_resource_kind)
     .service(inspect_resource_kind)
     .service(count_resource_kind)
-    .service(inspect_resource_kind_version);
+    .service(inspect_resource_kind_version)
+    .service(list_resource_kind_resources)
+    .service(migrate_resource_kind);
 }
 
 #[cfg(test)]
 mod tests {
+  use std::collections::HashMap;
+
   use ntex::http;
 
   const ENDPOINT: &str = "/resource/kinds";
 
   use crate::utils::tests::*;
 
-  use nanocl_stubs::resource_kind::{
-    ResourceKind, ResourceKindInspect, ResourceKindPartial, ResourceKindSpec,
-    ResourceKindVersion,
+  use nanocl_stubs::{
+    generic::GenericDeleteQuery,
+    resource::{Resource, ResourcePartial},
+    resource_kind::{
+      ResourceKind, ResourceKindController, ResourceKindConversion,
+      ResourceKindInspect, ResourceKindMapping, ResourceKindMigrate,
+      ResourceKindMigration, ResourceKindPartial, ResourceKindSpec,
+      ResourceKindVersion, ResourceKindVersionResources, ResourceKindWasm,
+    },
   };
 
+  /// Module with an allocator returning a constant output from a hook
+  fn gen_wasm_blob(hook: &str, output: &str) -> String {
+    let wat = format!(
+      r#"(module
+        (memory (export "memory") 1)
+        (data (i32.const 0) "{}")
+        (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+        (func (export "{hook}") (param i32 i32) (result i64)
+          (i64.const {})))"#,
+      output.replace('"', "\\\""),
+      output.len(),
+    );
+    openssl::base64::encode_block(&wat::parse_str(wat).unwrap())
+  }
+
   #[ntex::test]
   async fn test_inspect_version_not_found() {
     let system = gen_default_test_system().await;
@@ -63,6 +90,10 @@ mod tests {
       data: ResourceKindSpec {
         schema: None,
         url: Some("unix:///run/nanocl/proxy.sock".to_owned()),
+        conversion: None,
+        controller: None,
+        immutable: None,
+        wasm: None,
       },
     };
     let res = client
@@ -86,6 +117,10 @@ mod tests {
       data: ResourceKindSpec {
         schema: None,
         url: None,
+        conversion: None,
+        controller: None,
+        immutable: None,
+        wasm: None,
       },
     };
     let res = client
@@ -97,6 +132,58 @@ mod tests {
       "resource kind create"
     );
   }
+
+  #[ntex::test]
+  async fn test_unhealthy_controller() {
+    let system = gen_default_test_system().await;
+    let client = system.client;
+    let payload = ResourceKindPartial {
+      name: "test.io/api-test-unhealthy".to_owned(),
+      version: "v1".to_owned(),
+      metadata: None,
+      data: ResourceKindSpec {
+        schema: None,
+        url: Some("unix:///run/nanocl/unknown.sock".to_owned()),
+        conversion: None,
+        controller: Some(ResourceKindController {
+          timeout: Some(1),
+          health_path: Some("/health".to_owned()),
+          ..Default::default()
+        }),
+        immutable: None,
+        wasm: None,
+      },
+    };
+    let res = client
+      .send_post(ENDPOINT, Some(&payload), None::<String>)
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::BAD_GATEWAY,
+      "resource kind create with unhealthy controller"
+    );
+    let payload = ResourceKindPartial {
+      data: ResourceKindSpec {
+        controller: Some(ResourceKindController {
+          tls: Some("test-controller-tls".to_owned()),
+          ..Default::default()
+        }),
+        immutable: None,
+        wasm: None,
+        ..payload.data
+      },
+      ..payload
+    };
+    let res = client
+      .send_post(ENDPOINT, Some(&payload), None::<String>)
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::BAD_REQUEST,
+      "resource kind create with tls over unix socket"
+    );
+  }
+
   #[ntex::test]
   async fn basic_list() {
     let system = gen_default_test_system().await;
@@ -109,6 +196,10 @@ mod tests {
       data: ResourceKindSpec {
         schema: None,
         url: Some("unix:///run/nanocl/proxy.sock".to_owned()),
+        conversion: None,
+        controller: None,
+        immutable: None,
+        wasm: None,
       },
     };
     let mut res = client
@@ -167,4 +258,374 @@ mod tests {
       "resource kind delete"
     );
   }
+
+  #[ntex::test]
+  async fn dependents() {
+    let system = gen_default_test_system().await;
+    let client = system.client;
+    let payload = ResourceKindPartial {
+      name: "test.io/dependent-test".to_owned(),
+      version: "v1".to_owned(),
+      metadata: None,
+      data: ResourceKindSpec {
+        schema: Some(serde_json::json!({ "type": "object" })),
+        url: None,
+        conversion: None,
+        controller: None,
+        immutable: None,
+        wasm: None,
+      },
+    };
+    let res = client
+      .send_post(ENDPOINT, Some(&payload), None::<String>)
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::CREATED,
+      "resource kind create"
+    );
+    let resource = ResourcePartial {
+      name: "dependent-test".to_owned(),
+      kind: payload.name.clone(),
+      data: serde_json::json!({}),
+      metadata: None,
+      owners: None,
+      finalizers: None,
+    };
+    let res = client
+      .send_post("/resources", Some(&resource), None::<String>)
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::CREATED,
+      "resource create"
+    );
+    let res = client
+      .send_delete(&format!("{ENDPOINT}/{}", payload.name), None::<String>)
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::CONFLICT,
+      "resource kind delete with resources"
+    );
+    let res = client
+      .send_delete(
+        &format!("{ENDPOINT}/{}", payload.name),
+        Some(&GenericDeleteQuery {
+          force: Some(true),
+          ..Default::default()
+        }),
+      )
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::ACCEPTED,
+      "resource kind delete with force"
+    );
+    let res = client
+      .send_get(
+        &format!("/resources/{}/inspect", resource.name),
+        None::<String>,
+      )
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::NOT_FOUND,
+      "resource deleted with its kind"
+    );
+  }
+
+  #[ntex::test]
+  async fn migrate() {
+    const KIND: &str = "test.io/migrate-test";
+    const RESOURCE: &str = "migrate-test";
+    let system = gen_default_test_system().await;
+    let client = system.client;
+    let v1 = ResourceKindPartial {
+      name: KIND.to_owned(),
+      version: "v1".to_owned(),
+      metadata: None,
+      data: ResourceKindSpec {
+        schema: Some(serde_json::json!({
+          "type": "object",
+          "required": ["Username"],
+        })),
+        url: None,
+        conversion: None,
+        controller: None,
+        immutable: None,
+        wasm: None,
+      },
+    };
+    let res = client.send_post(ENDPOINT, Some(&v1), None::<String>).await;
+    test_status_code!(res.status(), http::StatusCode::CREATED, "create v1");
+    let resource = ResourcePartial {
+      name: RESOURCE.to_owned(),
+      kind: KIND.to_owned(),
+      data: serde_json::json!({ "Username": "nanocl" }),
+      metadata: None,
+      owners: None,
+      finalizers: None,
+    };
+    let res = client
+      .send_post("/resources", Some(&resource), None::<String>)
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::CREATED,
+      "create resource"
+    );
+    let v2 = ResourceKindPartial {
+      version: "v2".to_owned(),
+      data: ResourceKindSpec {
+        schema: Some(serde_json::json!({
+          "type": "object",
+          "required": ["User"],
+        })),
+        url: None,
+        conversion: Some(ResourceKindConversion::Mappings(HashMap::from([(
+          "v1".to_owned(),
+          vec![ResourceKindMapping {
+            from: Some("/Username".to_owned()),
+            to: Some("/User/Name".to_owned()),
+            default: None,
+          }],
+        )]))),
+        controller: None,
+        immutable: None,
+        wasm: None,
+      },
+      ..v1.clone()
+    };
+    let res = client.send_post(ENDPOINT, Some(&v2), None::<String>).await;
+    test_status_code!(res.status(), http::StatusCode::CREATED, "create v2");
+    let v3 = ResourceKindPartial {
+      version: "v3".to_owned(),
+      ..v1.clone()
+    };
+    let res = client.send_post(ENDPOINT, Some(&v3), None::<String>).await;
+    test_status_code!(res.status(), http::StatusCode::CREATED, "create v3");
+    let mut res = client
+      .send_get(&format!("{ENDPOINT}/{KIND}/resources"), None::<String>)
+      .await;
+    test_status_code!(res.status(), http::StatusCode::OK, "list resources");
+    let versions = res
+      .json::<Vec<ResourceKindVersionResources>>()
+      .await
+      .unwrap();
+    assert_eq!(versions.len(), 1);
+    assert_eq!(versions[0].version, "v1");
+    assert_eq!(versions[0].resources, vec![RESOURCE.to_owned()]);
+    // v3 doesn't declare a conversion
+    let payload = ResourceKindMigrate {
+      version: "v3".to_owned(),
+      ..Default::default()
+    };
+    let res = client
+      .send_post(
+        &format!("{ENDPOINT}/{KIND}/migrate"),
+        Some(&payload),
+        None::<String>,
+      )
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::BAD_REQUEST,
+      "migrate without conversion"
+    );
+    let payload = ResourceKindMigrate {
+      version: "v2".to_owned(),
+      from: Some("v1".to_owned()),
+      dry_run: Some(true),
+    };
+    let mut res = client
+      .send_post(
+        &format!("{ENDPOINT}/{KIND}/migrate"),
+        Some(&payload),
+        None::<String>,
+      )
+      .await;
+    test_status_code!(res.status(), http::StatusCode::OK, "migrate dry run");
+    let migration = res.json::<ResourceKindMigration>().await.unwrap();
+    assert!(migration.dry_run);
+    assert_eq!(
+      migration.resources[0].data,
+      serde_json::json!({ "User": { "Name": "nanocl" } })
+    );
+    let payload = ResourceKindMigrate {
+      dry_run: None,
+      ..payload
+    };
+    let res = client
+      .send_post(
+        &format!("{ENDPOINT}/{KIND}/migrate"),
+        Some(&payload),
+        None::<String>,
+      )
+      .await;
+    test_status_code!(res.status(), http::StatusCode::OK, "migrate");
+    let mut res = client
+      .send_get(&format!("/resources/{RESOURCE}/inspect"), None::<String>)
+      .await;
+    let resource = res.json::<Resource>().await.unwrap();
+    assert_eq!(resource.spec.version, "v2");
+    assert_eq!(resource.spec.data["User"]["Name"], "nanocl");
+    let res = client
+      .send_delete(
+        &format!("{ENDPOINT}/{KIND}"),
+        Some(&GenericDeleteQuery {
+          force: Some(true),
+          ..Default::default()
+        }),
+      )
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::ACCEPTED,
+      "delete resource kind"
+    );
+  }
+
+  #[ntex::test]
+  async fn wasm() {
+    const KIND: &str = "test.io/wasm-test";
+    let system = gen_default_test_system().await;
+    let client = system.client;
+    let payload = ResourceKindPartial {
+      name: KIND.to_owned(),
+      version: "v1".to_owned(),
+      metadata: None,
+      data: ResourceKindSpec {
+        schema: None,
+        url: None,
+        conversion: None,
+        controller: None,
+        immutable: None,
+        wasm: Some(ResourceKindWasm {
+          blob: Some("not a module".to_owned()),
+          ..Default::default()
+        }),
+      },
+    };
+    let res = client
+      .send_post(ENDPOINT, Some(&payload), None::<String>)
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::BAD_REQUEST,
+      "resource kind create with an invalid module"
+    );
+    let payload = ResourceKindPartial {
+      data: ResourceKindSpec {
+        wasm: Some(ResourceKindWasm {
+          blob: Some(gen_wasm_blob("apply", r#"{"Data":{"Applied":true}}"#)),
+          fuel: Some(1_000_000),
+          memory: Some(1),
+          ..Default::default()
+        }),
+        ..payload.data
+      },
+      ..payload
+    };
+    let res = client
+      .send_post(ENDPOINT, Some(&payload), None::<String>)
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::CREATED,
+      "resource kind create with a module"
+    );
+    let resource = ResourcePartial {
+      name: "wasm-test".to_owned(),
+      kind: KIND.to_owned(),
+      data: serde_json::json!({ "Port": 80 }),
+      metadata: None,
+      owners: None,
+      finalizers: None,
+    };
+    let mut res = client
+      .send_post("/resources", Some(&resource), None::<String>)
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::CREATED,
+      "create resource applied by the module"
+    );
+    let created = res.json::<Resource>().await.unwrap();
+    assert_eq!(created.spec.data, serde_json::json!({ "Applied": true }));
+    let v2 = ResourceKindPartial {
+      version: "v2".to_owned(),
+      data: ResourceKindSpec {
+        wasm: Some(ResourceKindWasm {
+          blob: Some(gen_wasm_blob("validate", r#"{"Error":"refused"}"#)),
+          ..Default::default()
+        }),
+        ..payload.data.clone()
+      },
+      ..payload.clone()
+    };
+    let res = client.send_post(ENDPOINT, Some(&v2), None::<String>).await;
+    test_status_code!(res.status(), http::StatusCode::CREATED, "create v2");
+    let res = client
+      .send_post(
+        "/resources",
+        Some(&ResourcePartial {
+          name: "wasm-test-refused".to_owned(),
+          ..resource.clone()
+        }),
+        None::<String>,
+      )
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::BAD_REQUEST,
+      "create resource refused by the module"
+    );
+    let v3 = ResourceKindPartial {
+      version: "v3".to_owned(),
+      data: ResourceKindSpec {
+        schema: Some(serde_json::json!({
+          "type": "object",
+          "required": ["Port"],
+        })),
+        wasm: Some(ResourceKindWasm {
+          blob: Some(gen_wasm_blob("mutate", r#"{"Data":{"Mutated":true}}"#)),
+          ..Default::default()
+        }),
+        ..payload.data.clone()
+      },
+      ..payload.clone()
+    };
+    let res = client.send_post(ENDPOINT, Some(&v3), None::<String>).await;
+    test_status_code!(res.status(), http::StatusCode::CREATED, "create v3");
+    let res = client
+      .send_post(
+        "/resources",
+        Some(&ResourcePartial {
+          name: "wasm-test-mutated".to_owned(),
+          ..resource.clone()
+        }),
+        None::<String>,
+      )
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::BAD_REQUEST,
+      "create resource mutated out of its schema"
+    );
+    let res = client
+      .send_delete(
+        &format!("{ENDPOINT}/{KIND}"),
+        Some(&GenericDeleteQuery {
+          force: Some(true),
+          ..Default::default()
+        }),
+      )
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::ACCEPTED,
+      "delete resource kind"
+    );
+  }
 }
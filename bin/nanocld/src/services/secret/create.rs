@@ -43,5 +43,5 @@ pub async fn create_secret(
     _ => {}
   }
   let secret = SecretDb::create_obj(&payload, &state).await?;
-  Ok(web::HttpResponse::Created().json(&secret))
+  Ok(web::HttpResponse::Created().json(&utils::secret::mask_secret(secret)))
 }
@@ -1,6 +1,7 @@
 use ntex::web;
 
 use nanocl_error::http::HttpResult;
+use nanocl_stubs::generic::GenericDeleteQuery;
 
 use crate::{
   models::{SecretDb, SystemState},
@@ -13,18 +14,21 @@ use crate::{
   tag = "Secrets",
   path = "/secrets/{key}",
   params(
-    ("key" = String, Path, description = "Key of the secret")
+    ("key" = String, Path, description = "Key of the secret"),
+    ("force" = Option<bool>, Query, description = "Delete the secret even if cargoes, jobs or proxy rules use it"),
   ),
   responses(
     (status = 202, description = "Secret have been deleted"),
     (status = 404, description = "Secret doesn't exists", body = crate::services::openapi::ApiError),
+    (status = 409, description = "Secret is used by other objects", body = crate::services::openapi::ApiError),
   ),
 ))]
 #[web::delete("/secrets/{key}")]
 pub async fn delete_secret(
   state: web::types::State<SystemState>,
   path: web::types::Path<(String, String)>,
+  qs: web::types::Query<GenericDeleteQuery>,
 ) -> HttpResult<web::HttpResponse> {
-  SecretDb::del_obj_by_pk(&path.1, &(), &state).await?;
+  SecretDb::del_obj_by_pk(&path.1, &qs, &state).await?;
   Ok(web::HttpResponse::Accepted().into())
 }
@@ -1,11 +1,9 @@
 use ntex::web;
 
 use nanocl_error::http::HttpResult;
+use nanocl_stubs::secret::SecretInspectQuery;
 
-use crate::{
-  models::{SecretDb, SystemState},
-  repositories::generic::*,
-};
+use crate::{models::SystemState, utils};
 
 /// Get detailed information about a secret
 #[cfg_attr(feature = "dev", utoipa::path(
@@ -13,10 +11,11 @@ use crate::{
   tag = "Secrets",
   path = "/secrets/{key}/inspect",
   params(
-    ("key" = String, Path, description = "Key of the secret")
+    ("key" = String, Path, description = "Key of the secret"),
+    ("reveal" = Option<bool>, Query, description = "Return the data in clear instead of masked values"),
   ),
   responses(
-    (status = 200, description = "Detailed information about a secret", body = nanocl_stubs::secret::Secret),
+    (status = 200, description = "Detailed information about a secret", body = nanocl_stubs::secret::SecretInspect),
     (status = 404, description = "Secret doesn't", body = crate::services::openapi::ApiError),
   ),
 ))]
@@ -24,8 +23,9 @@ use crate::{
 pub async fn inspect_secret(
   state: web::types::State<SystemState>,
   path: web::types::Path<(String, String)>,
+  qs: web::types::Query<SecretInspectQuery>,
 ) -> HttpResult<web::HttpResponse> {
-  let secret =
-    SecretDb::transform_read_by_pk(&path.1, &state.inner.pool).await?;
+  let reveal = qs.reveal.unwrap_or_default();
+  let secret = utils::secret::inspect(&path.1, reveal, &state).await?;
   Ok(web::HttpResponse::Ok().json(&secret))
 }
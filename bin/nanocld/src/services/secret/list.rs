@@ -1,7 +1,11 @@
 use ntex::web;
 
 use nanocl_error::http::HttpResult;
-use nanocl_stubs::generic::GenericListQuery;
+use nanocl_stubs::{
+  generic::{GenericFilter, GenericListQuery},
+  secret::Secret,
+  system::EventActorKind,
+};
 
 use crate::{
   models::{SecretDb, SystemState},
@@ -9,13 +13,31 @@ use crate::{
   utils,
 };
 
-/// List secret with optional filter
+/// List the secrets matching a filter without their data
+async fn list(
+  filter: &GenericFilter,
+  state: &SystemState,
+) -> HttpResult<Vec<Secret>> {
+  let items = SecretDb::transform_read_by(filter, &state.inner.pool)
+    .await?
+    .into_iter()
+    .map(|secret| Secret {
+      data: serde_json::Value::Null,
+      ..secret
+    })
+    .collect::<Vec<_>>();
+  Ok(items)
+}
+
+/// List secret with optional filter, the data of the secrets is omitted
 #[cfg_attr(feature = "dev", utoipa::path(
   get,
   tag = "Secrets",
   path = "/secrets",
   params(
     ("filter" = Option<String>, Query, description = "Generic filter", example = "{ \"filter\": { \"where\": { \"kind\": { \"eq\": \"Env\" } } } }"),
+    ("watch" = Option<bool>, Query, description = "Stream the items followed by their changes"),
+    ("cursor" = Option<String>, Query, description = "Cursor of a previous watch to resume from"),
   ),
   responses(
     (status = 200, description = "List of secret", body = [nanocl_stubs::secret::Secret]),
@@ -27,6 +49,18 @@ pub async fn list_secret(
   qs: web::types::Query<GenericListQuery>,
 ) -> HttpResult<web::HttpResponse> {
   let filter = utils::query_string::parse_qs_filter(&qs)?;
-  let items = SecretDb::transform_read_by(&filter, &state.inner.pool).await?;
+  if qs.watch.unwrap_or_default() {
+    return utils::watch::stream(
+      EventActorKind::Secret,
+      qs.cursor.clone(),
+      filter,
+      "key",
+      |secret: &Secret| secret.name.clone(),
+      |state, filter| async move { list(&filter, &state).await },
+      &state,
+    )
+    .await;
+  }
+  let items = list(&filter, &state).await?;
   Ok(web::HttpResponse::Ok().json(&items))
 }
@@ -0,0 +1,35 @@
+use ntex::web;
+
+use nanocl_error::http::HttpResult;
+
+use crate::{
+  models::{SecretDb, SpecDb, SystemState},
+  repositories::generic::*,
+};
+
+/// List secret histories
+#[cfg_attr(feature = "dev", utoipa::path(
+  get,
+  tag = "Secrets",
+  path = "/secrets/{key}/histories",
+  params(
+    ("key" = String, Path, description = "Key of the secret"),
+  ),
+  responses(
+    (status = 200, description = "List of secret histories", body = Vec<nanocl_stubs::secret::SecretHistory>),
+    (status = 404, description = "Secret does not exist", body = crate::services::openapi::ApiError),
+  ),
+))]
+#[web::get("/secrets/{key}/histories")]
+pub async fn list_secret_history(
+  state: web::types::State<SystemState>,
+  path: web::types::Path<(String, String)>,
+) -> HttpResult<web::HttpResponse> {
+  SecretDb::read_by_pk(&path.1, &state.inner.pool).await?;
+  let histories = SpecDb::read_by_kind("Secret", &path.1, &state.inner.pool)
+    .await?
+    .iter()
+    .map(SpecDb::to_secret_history)
+    .collect::<Vec<_>>();
+  Ok(web::HttpResponse::Ok().json(&histories))
+}
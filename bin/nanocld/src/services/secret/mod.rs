@@ -5,14 +5,22 @@ pub mod create;
 pub mod delete;
 pub mod inspect;
 pub mod list;
+pub mod list_history;
 pub mod patch;
+pub mod revert;
+pub mod rotate_key;
+pub mod seal;
 
 pub use count::*;
 pub use create::*;
 pub use delete::*;
 pub use inspect::*;
 pub use list::*;
+pub use list_history::*;
 pub use patch::*;
+pub use revert::*;
+pub use rotate_key::*;
+pub use seal::*;
 
 pub fn ntex_config(config: &mut web::ServiceConfig) {
   config.service(list_secret);
@@ -21,23 +29,41 @@ pub fn ntex_config(config: &mut web::ServiceConfig) {
   config.service(delete_secret);
   config.service(count_secret);
   config.service(patch_secret);
+  config.service(rotate_secret_key);
+  config.service(seal_secret);
+  config.service(list_secret_history);
+  config.service(revert_secret);
 }
 
 #[cfg(test)]
 mod test_secret {
-  use ntex::http;
+  use futures::{Stream, StreamExt, TryStreamExt};
+  use ntex::{http, util::Bytes};
 
   use serde_json::json;
 
-  use nanocl_stubs::secret::{Secret, SecretPartial};
+  use nanocl_stubs::{
+    cargo::CargoDeleteQuery,
+    cargo_spec::{CargoSpec, CargoSpecPartial},
+    generic::{
+      GenericClause, GenericCount, GenericDeleteQuery, GenericFilter,
+      GenericListQuery, WatchEvent, WatchEventType,
+    },
+    secret::{
+      Secret, SecretHistory, SecretInspect, SecretInspectQuery, SecretPartial,
+      SecretSeal, SecretTlsIssued, SecretUpdate, SecretUpdateQuery,
+    },
+  };
 
-  use crate::utils::tests::*;
+  use crate::{utils, utils::tests::*};
 
   const ENDPOINT: &str = "/secrets";
 
   async fn test_list(client: &TestClient) {
-    let res = client.send_get(ENDPOINT, None::<String>).await;
+    let mut res = client.send_get(ENDPOINT, None::<String>).await;
     test_status_code!(res.status(), http::StatusCode::OK, "list secrets");
+    let secrets = res.json::<Vec<Secret>>().await.unwrap();
+    assert!(secrets.iter().all(|secret| secret.data.is_null()));
   }
 
   async fn test_create(client: &TestClient) {
@@ -83,10 +109,24 @@ mod test_secret {
   }
 
   async fn test_inspect_by_id(client: &TestClient) {
-    let res = client
+    let mut res = client
       .send_get(&format!("{ENDPOINT}/test-secret/inspect"), None::<String>)
       .await;
     test_status_code!(res.status(), http::StatusCode::OK, "inspect secret");
+    let secret = res.json::<SecretInspect>().await.unwrap();
+    assert_eq!(
+      secret.data,
+      json!({ "Tls": { "cert": "********", "key": "********" } })
+    );
+    let mut res = client
+      .send_get(
+        &format!("{ENDPOINT}/test-secret/inspect"),
+        Some(&SecretInspectQuery { reveal: Some(true) }),
+      )
+      .await;
+    test_status_code!(res.status(), http::StatusCode::OK, "reveal secret");
+    let secret = res.json::<SecretInspect>().await.unwrap();
+    assert_eq!(secret.data["Tls"]["key"], "MY KEY");
   }
 
   async fn test_delete(client: &TestClient) {
@@ -110,4 +150,534 @@ mod test_secret {
     test_list(&client).await;
     test_delete(&client).await;
   }
+
+  #[ntex::test]
+  async fn encryption() {
+    let system = gen_default_test_system().await;
+    let client = system.client;
+    let name = "test-secret-encryption";
+    let reveal = SecretInspectQuery { reveal: Some(true) };
+    let data = json!(["PASSWORD=my-password"]);
+    let res = client
+      .send_post(
+        ENDPOINT,
+        Some(SecretPartial {
+          name: name.to_owned(),
+          kind: "nanocl.io/env".to_owned(),
+          immutable: false,
+          data: data.clone(),
+          metadata: None,
+        }),
+        None::<String>,
+      )
+      .await;
+    test_status_code!(res.status(), http::StatusCode::CREATED, "create secret");
+    let mut res = client.send_get(ENDPOINT, None::<String>).await;
+    let secrets = res.json::<Vec<Secret>>().await.unwrap();
+    let secret = secrets.iter().find(|secret| secret.name == name).unwrap();
+    assert!(!secret.data.to_string().contains("my-password"));
+    let mut res = client
+      .send_post(
+        &format!("{ENDPOINT}/rotate-key"),
+        None::<String>,
+        None::<String>,
+      )
+      .await;
+    test_status_code!(res.status(), http::StatusCode::OK, "rotate key");
+    let count = res.json::<GenericCount>().await.unwrap();
+    assert!(count.count >= 1);
+    let mut res = client
+      .send_get(&format!("{ENDPOINT}/{name}/inspect"), Some(&reveal))
+      .await;
+    test_status_code!(res.status(), http::StatusCode::OK, "inspect secret");
+    let secret = res.json::<Secret>().await.unwrap();
+    assert_eq!(secret.data, data);
+    let res = client
+      .send_delete(&format!("{ENDPOINT}/{name}"), None::<String>)
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::ACCEPTED,
+      "delete secret"
+    );
+  }
+
+  #[ntex::test]
+  async fn history() {
+    let system = gen_default_test_system().await;
+    let client = system.client;
+    let name = "test-secret-history";
+    let reveal = SecretInspectQuery { reveal: Some(true) };
+    let res = client
+      .send_post(
+        ENDPOINT,
+        Some(SecretPartial {
+          name: name.to_owned(),
+          kind: "nanocl.io/env".to_owned(),
+          immutable: false,
+          data: json!(["PASSWORD=first"]),
+          metadata: None,
+        }),
+        None::<String>,
+      )
+      .await;
+    test_status_code!(res.status(), http::StatusCode::CREATED, "create secret");
+    let res = client
+      .send_patch(
+        &format!("{ENDPOINT}/{name}"),
+        Some(SecretUpdate {
+          data: json!(["PASSWORD=second"]),
+          metadata: None,
+        }),
+        Some(SecretUpdateQuery {
+          rollout: Some(true),
+        }),
+      )
+      .await;
+    test_status_code!(res.status(), http::StatusCode::OK, "patch secret");
+    let mut res = client
+      .send_get(&format!("{ENDPOINT}/{name}/histories"), None::<String>)
+      .await;
+    test_status_code!(res.status(), http::StatusCode::OK, "list histories");
+    let histories = res.json::<Vec<SecretHistory>>().await.unwrap();
+    assert_eq!(histories.len(), 2);
+    assert_eq!(histories[0].version, "2");
+    let first = histories.iter().find(|h| h.version == "1").unwrap();
+    let res = client
+      .send_patch(
+        &format!("{ENDPOINT}/{name}/histories/{}/revert", first.key),
+        None::<String>,
+        None::<String>,
+      )
+      .await;
+    test_status_code!(res.status(), http::StatusCode::OK, "revert secret");
+    let mut res = client
+      .send_get(&format!("{ENDPOINT}/{name}/inspect"), Some(&reveal))
+      .await;
+    let secret = res.json::<SecretInspect>().await.unwrap();
+    assert_eq!(secret.data, json!(["PASSWORD=first"]));
+    assert!(secret.consumers.is_empty());
+    let res = client
+      .send_patch(
+        &format!(
+          "{ENDPOINT}/{name}/histories/{}/revert",
+          uuid::Uuid::new_v4()
+        ),
+        None::<String>,
+        None::<String>,
+      )
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::NOT_FOUND,
+      "revert unknown history"
+    );
+    let res = client
+      .send_delete(&format!("{ENDPOINT}/{name}"), None::<String>)
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::ACCEPTED,
+      "delete secret"
+    );
+  }
+
+  #[ntex::test]
+  async fn tls_issued() {
+    let system = gen_default_test_system().await;
+    let client = system.client;
+    let name = "test-secret-tls-issued";
+    let reveal = SecretInspectQuery { reveal: Some(true) };
+    let res = client
+      .send_post(
+        ENDPOINT,
+        Some(SecretPartial {
+          name: name.to_owned(),
+          kind: "nanocl.io/tls-issued".to_owned(),
+          immutable: false,
+          data: json!({ "Sans": [] }),
+          metadata: None,
+        }),
+        None::<String>,
+      )
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::BAD_REQUEST,
+      "create issued secret without san"
+    );
+    let res = client
+      .send_post(
+        ENDPOINT,
+        Some(SecretPartial {
+          name: name.to_owned(),
+          kind: "nanocl.io/tls-issued".to_owned(),
+          immutable: false,
+          data: json!({ "Sans": ["api.global.c", "127.0.0.1"], "Lifetime": 7 }),
+          metadata: None,
+        }),
+        None::<String>,
+      )
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::CREATED,
+      "create issued secret"
+    );
+    let mut res = client
+      .send_get(&format!("{ENDPOINT}/{name}/inspect"), Some(&reveal))
+      .await;
+    let secret = res.json::<SecretInspect>().await.unwrap();
+    let tls = serde_json::from_value::<SecretTlsIssued>(secret.data).unwrap();
+    let ssl_config = tls.to_ssl_config().unwrap();
+    assert!(ssl_config.certificate.starts_with("-----BEGIN CERTIFICATE"));
+    assert!(ssl_config.certificate_client.is_some());
+    assert!(tls.not_after.is_some());
+    let res = client
+      .send_delete(&format!("{ENDPOINT}/{name}"), None::<String>)
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::ACCEPTED,
+      "delete secret"
+    );
+  }
+
+  #[ntex::test]
+  async fn external() {
+    let system = gen_default_test_system().await;
+    let client = system.client;
+    let name = "test-secret-external";
+    let res = client
+      .send_post(
+        ENDPOINT,
+        Some(SecretPartial {
+          name: name.to_owned(),
+          kind: "nanocl.io/external".to_owned(),
+          immutable: false,
+          data: json!({
+            "Kind": "nanocl.io/env",
+            "Backend": "File",
+            "Path": "../secret.key",
+          }),
+          metadata: None,
+        }),
+        None::<String>,
+      )
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::BAD_REQUEST,
+      "create external secret outside of the file backend"
+    );
+    let seal = SecretSeal {
+      path: format!("test/{name}"),
+      data: json!({ "PASSWORD": "nanocl" }),
+    };
+    let mut res = client
+      .send_post(&format!("{ENDPOINT}/seal"), Some(&seal), None::<String>)
+      .await;
+    test_status_code!(res.status(), http::StatusCode::OK, "seal value");
+    let sealed = res.json::<serde_json::Value>().await.unwrap();
+    let dir = format!(
+      "{}/secrets/external/test",
+      system.state.inner.config.state_dir
+    );
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(format!("{dir}/{name}"), sealed.to_string()).unwrap();
+    let res = client
+      .send_post(
+        ENDPOINT,
+        Some(SecretPartial {
+          name: name.to_owned(),
+          kind: "nanocl.io/external".to_owned(),
+          immutable: false,
+          data: json!({
+            "Kind": "nanocl.io/env",
+            "Backend": "File",
+            "Path": seal.path,
+          }),
+          metadata: None,
+        }),
+        None::<String>,
+      )
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::CREATED,
+      "create external secret"
+    );
+    let secrets = Some(vec![name.to_owned()]);
+    let envs = utils::secret::load_env_secrets(&secrets, &system.state)
+      .await
+      .unwrap();
+    assert_eq!(envs, vec!["PASSWORD=nanocl".to_owned()]);
+    std::fs::remove_file(format!("{dir}/{name}")).unwrap();
+    let err = utils::secret::load_env_secrets(&secrets, &system.state)
+      .await
+      .unwrap_err();
+    assert!(err.to_string().contains(name));
+    let res = client
+      .send_delete(&format!("{ENDPOINT}/{name}"), None::<String>)
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::ACCEPTED,
+      "delete secret"
+    );
+  }
+
+  #[ntex::test]
+  async fn dependents() {
+    let system = gen_default_test_system().await;
+    let client = system.client;
+    let name = "test-secret-dependent";
+    let res = client
+      .send_post(
+        ENDPOINT,
+        Some(SecretPartial {
+          name: name.to_owned(),
+          kind: "nanocl.io/env".to_owned(),
+          immutable: false,
+          data: json!(["PASSWORD=nanocl"]),
+          metadata: None,
+        }),
+        None::<String>,
+      )
+      .await;
+    test_status_code!(res.status(), http::StatusCode::CREATED, "create secret");
+    let res = client
+      .send_post(
+        "/cargoes",
+        Some(&CargoSpecPartial {
+          name: name.to_owned(),
+          container: bollard_next::container::Config {
+            image: Some(
+              "ghcr.io/next-hat/nanocl-get-started:latest".to_owned(),
+            ),
+            ..Default::default()
+          },
+          secrets: Some(vec![name.to_owned()]),
+          ..Default::default()
+        }),
+        None::<String>,
+      )
+      .await;
+    test_status_code!(res.status(), http::StatusCode::CREATED, "create cargo");
+    let mut res = client
+      .send_delete(&format!("{ENDPOINT}/{name}"), None::<String>)
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::CONFLICT,
+      "delete secret used by a cargo"
+    );
+    let err = res.json::<serde_json::Value>().await.unwrap();
+    assert!(err["msg"].as_str().unwrap().contains("Cargo/"));
+    let res = client
+      .send_delete(
+        &format!("/cargoes/{name}"),
+        Some(CargoDeleteQuery {
+          force: Some(true),
+          ..Default::default()
+        }),
+      )
+      .await;
+    test_status_code!(res.status(), http::StatusCode::ACCEPTED, "delete cargo");
+    // The cargo is destroyed in background and may still reference the secret
+    let res = client
+      .send_delete(
+        &format!("{ENDPOINT}/{name}"),
+        Some(GenericDeleteQuery {
+          force: Some(true),
+          ..Default::default()
+        }),
+      )
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::ACCEPTED,
+      "delete secret with force"
+    );
+  }
+
+  #[ntex::test]
+  async fn shared_key() {
+    let system = gen_default_test_system().await;
+    let client = system.client;
+    let cargo_name = "test-secret-shared";
+    // The key of the cargo in the global namespace
+    let name = "test-secret-shared.global";
+    let res = client
+      .send_post(
+        "/cargoes",
+        Some(&CargoSpecPartial {
+          name: cargo_name.to_owned(),
+          container: bollard_next::container::Config {
+            image: Some(
+              "ghcr.io/next-hat/nanocl-get-started:latest".to_owned(),
+            ),
+            ..Default::default()
+          },
+          ..Default::default()
+        }),
+        None::<String>,
+      )
+      .await;
+    test_status_code!(res.status(), http::StatusCode::CREATED, "create cargo");
+    let res = client
+      .send_post(
+        ENDPOINT,
+        Some(SecretPartial {
+          name: name.to_owned(),
+          kind: "nanocl.io/env".to_owned(),
+          immutable: false,
+          data: json!(["PASSWORD=first"]),
+          metadata: None,
+        }),
+        None::<String>,
+      )
+      .await;
+    test_status_code!(res.status(), http::StatusCode::CREATED, "create secret");
+    let res = client
+      .send_patch(
+        &format!("{ENDPOINT}/{name}"),
+        Some(SecretUpdate {
+          data: json!(["PASSWORD=second"]),
+          metadata: None,
+        }),
+        None::<String>,
+      )
+      .await;
+    test_status_code!(res.status(), http::StatusCode::OK, "patch secret");
+    let mut res = client
+      .send_get(&format!("{ENDPOINT}/{name}/histories"), None::<String>)
+      .await;
+    test_status_code!(res.status(), http::StatusCode::OK, "list histories");
+    let histories = res.json::<Vec<SecretHistory>>().await.unwrap();
+    let versions = histories
+      .iter()
+      .map(|history| history.version.as_str())
+      .collect::<Vec<_>>();
+    assert_eq!(versions, ["2", "1"]);
+    let res = client
+      .send_delete(&format!("{ENDPOINT}/{name}"), None::<String>)
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::ACCEPTED,
+      "delete secret"
+    );
+    let mut res = client
+      .send_get(&format!("/cargoes/{cargo_name}/histories"), None::<String>)
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::OK,
+      "list cargo histories"
+    );
+    let histories = res.json::<Vec<CargoSpec>>().await.unwrap();
+    assert_eq!(histories.len(), 1);
+    let res = client
+      .send_delete(
+        &format!("/cargoes/{cargo_name}"),
+        Some(CargoDeleteQuery {
+          force: Some(true),
+          ..Default::default()
+        }),
+      )
+      .await;
+    test_status_code!(res.status(), http::StatusCode::ACCEPTED, "delete cargo");
+  }
+
+  /// Read the next change of a watch stream
+  async fn next_change<S, E>(stream: &mut S) -> WatchEvent<Secret>
+  where
+    S: Stream<Item = Result<Bytes, E>> + Unpin,
+    E: std::fmt::Debug,
+  {
+    let mut payload = Vec::new();
+    while let Some(bytes) = stream.next().await {
+      payload.extend(bytes.unwrap().to_vec());
+      if payload.last() == Some(&b'\n') {
+        break;
+      }
+    }
+    serde_json::from_slice(&payload).unwrap()
+  }
+
+  #[ntex::test]
+  async fn watch() {
+    let system = gen_default_test_system().await;
+    let client = system.client;
+    let name = "test-secret-watch";
+    let res = client
+      .send_post(
+        ENDPOINT,
+        Some(SecretPartial {
+          name: name.to_owned(),
+          kind: "nanocl.io/env".to_owned(),
+          immutable: false,
+          data: json!(["PASSWORD=nanocl"]),
+          metadata: None,
+        }),
+        None::<String>,
+      )
+      .await;
+    test_status_code!(res.status(), http::StatusCode::CREATED, "create secret");
+    let filter =
+      GenericFilter::new().r#where("name", GenericClause::Eq(name.to_owned()));
+    let query = GenericListQuery {
+      watch: Some(true),
+      ..GenericListQuery::try_from(filter).unwrap()
+    };
+    let res = client.send_get(ENDPOINT, Some(&query)).await;
+    test_status_code!(res.status(), http::StatusCode::OK, "watch secrets");
+    let mut stream = res.into_stream();
+    let change = next_change(&mut stream).await;
+    assert_eq!(change.r#type, WatchEventType::Added);
+    assert_eq!(change.key, name);
+    assert!(change.object.unwrap().data.is_null());
+    let cursor = change.cursor;
+    let res = client
+      .send_delete(&format!("{ENDPOINT}/{name}"), None::<String>)
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::ACCEPTED,
+      "delete secret"
+    );
+    let change = next_change(&mut stream).await;
+    assert_eq!(change.r#type, WatchEventType::Deleted);
+    assert_eq!(change.key, name);
+    // Resuming from the cursor replays the deletion
+    let res = client
+      .send_get(
+        ENDPOINT,
+        Some(&GenericListQuery {
+          cursor,
+          ..query.clone()
+        }),
+      )
+      .await;
+    test_status_code!(res.status(), http::StatusCode::OK, "resume watch");
+    let mut stream = res.into_stream();
+    let change = next_change(&mut stream).await;
+    assert_eq!(change.r#type, WatchEventType::Deleted);
+    assert!(change.object.is_none());
+    let res = client
+      .send_get(
+        ENDPOINT,
+        Some(&GenericListQuery {
+          cursor: Some("expired".to_owned()),
+          ..query
+        }),
+      )
+      .await;
+    test_status_code!(
+      res.status(),
+      http::StatusCode::BAD_REQUEST,
+      "watch with an invalid cursor"
+    );
+  }
 }
@@ -1,11 +1,12 @@
 use ntex::web;
 
 use nanocl_error::http::HttpResult;
-use nanocl_stubs::secret::SecretUpdate;
+use nanocl_stubs::secret::{SecretUpdate, SecretUpdateQuery};
 
 use crate::{
   models::{SecretDb, SystemState},
   objects::generic::*,
+  utils,
 };
 
 /// Update a secret
@@ -16,6 +17,7 @@ use crate::{
   path = "/secrets/{key}",
   params(
     ("key" = String, Path, description = "Key of the secret"),
+    ("rollout" = Option<bool>, Query, description = "Rolling update the cargoes using the secret (default to true)"),
   ),
   responses(
     (status = 200, description = "Secret patched", body = nanocl_stubs::secret::Secret),
@@ -27,7 +29,11 @@ pub async fn patch_secret(
   state: web::types::State<SystemState>,
   path: web::types::Path<(String, String)>,
   payload: web::types::Json<SecretUpdate>,
+  qs: web::types::Query<SecretUpdateQuery>,
 ) -> HttpResult<web::HttpResponse> {
   let item = SecretDb::patch_obj_by_pk(&path.1, &payload, &state).await?;
-  Ok(web::HttpResponse::Ok().json(&item))
+  if qs.rollout.unwrap_or(true) {
+    utils::secret::rollout(&path.1, &state).await?;
+  }
+  Ok(web::HttpResponse::Ok().json(&utils::secret::mask_secret(item)))
 }
@@ -0,0 +1,42 @@
+use ntex::web;
+
+use nanocl_error::http::HttpResult;
+use nanocl_stubs::{secret::SecretUpdateQuery, system::NativeEventAction};
+
+use crate::{
+  models::{SpecDb, SystemState},
+  repositories::generic::*,
+  utils,
+};
+
+/// Revert a secret to a specific history record
+#[cfg_attr(feature = "dev", utoipa::path(
+  patch,
+  tag = "Secrets",
+  path = "/secrets/{key}/histories/{id}/revert",
+  params(
+    ("key" = String, Path, description = "Key of the secret"),
+    ("id" = String, Path, description = "Key of the secret history"),
+    ("rollout" = Option<bool>, Query, description = "Rolling update the cargoes using the secret (default to true)"),
+  ),
+  responses(
+    (status = 200, description = "Secret reverted", body = nanocl_stubs::secret::Secret),
+    (status = 404, description = "Secret or history does not exist", body = crate::services::openapi::ApiError),
+  ),
+))]
+#[web::patch("/secrets/{key}/histories/{id}/revert")]
+pub async fn revert_secret(
+  state: web::types::State<SystemState>,
+  path: web::types::Path<(String, String, uuid::Uuid)>,
+  qs: web::types::Query<SecretUpdateQuery>,
+) -> HttpResult<web::HttpResponse> {
+  let history = SpecDb::read_by_pk(&path.2, &state.inner.pool).await?;
+  let secret = utils::secret::revert(&path.1, &history, &state).await?;
+  state
+    .emit_normal_native_action_sync(&secret, NativeEventAction::Update)
+    .await;
+  if qs.rollout.unwrap_or(true) {
+    utils::secret::rollout(&path.1, &state).await?;
+  }
+  Ok(web::HttpResponse::Ok().json(&utils::secret::mask_secret(secret)))
+}
@@ -0,0 +1,26 @@
+use ntex::web;
+
+use nanocl_error::http::HttpResult;
+use nanocl_stubs::generic::GenericCount;
+
+use crate::{models::SystemState, utils};
+
+/// Rotate the master key and re-encrypt every secret
+#[cfg_attr(feature = "dev", utoipa::path(
+  post,
+  tag = "Secrets",
+  path = "/secrets/rotate-key",
+  responses(
+    (status = 200, description = "Number of secrets re-encrypted", body = GenericCount),
+    (status = 409, description = "A rotation is already in progress", body = crate::services::openapi::ApiError),
+  ),
+))]
+#[web::post("/secrets/rotate-key")]
+pub async fn rotate_secret_key(
+  state: web::types::State<SystemState>,
+) -> HttpResult<web::HttpResponse> {
+  let count = utils::secret::rotate_key(&state).await?;
+  Ok(web::HttpResponse::Ok().json(&GenericCount {
+    count: count as i64,
+  }))
+}
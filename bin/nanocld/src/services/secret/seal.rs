@@ -0,0 +1,29 @@
+use ntex::web;
+
+use nanocl_error::http::{HttpError, HttpResult};
+use nanocl_stubs::secret::SecretSeal;
+
+use crate::{models::SystemState, utils};
+
+/// Seal a value with the master key to be stored by the file backend
+/// of the `nanocl.io/external` secrets
+#[cfg_attr(feature = "dev", utoipa::path(
+  post,
+  request_body = SecretSeal,
+  tag = "Secrets",
+  path = "/secrets/seal",
+  responses(
+    (status = 200, description = "The sealed value to write in the file", body = nanocl_stubs::generic::Any),
+    (status = 400, description = "Invalid path", body = crate::services::openapi::ApiError),
+  ),
+))]
+#[web::post("/secrets/seal")]
+pub async fn seal_secret(
+  state: web::types::State<SystemState>,
+  payload: web::types::Json<SecretSeal>,
+) -> HttpResult<web::HttpResponse> {
+  let keyring = utils::secret::get_keyring(&state)?;
+  let sealed = utils::secret_backend::seal_file(&payload, &keyring)
+    .map_err(HttpError::bad_request)?;
+  Ok(web::HttpResponse::Ok().json(&sealed))
+}
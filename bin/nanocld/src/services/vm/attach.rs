@@ -11,17 +11,55 @@ use ntex::{
 };
 use tokio::io::AsyncWriteExt;
 
-use bollard_next::container::AttachContainerOptions;
+use bollard_next::container::{
+  AttachContainerOptions, ResizeContainerTtyOptions,
+};
 use nanocl_error::http::HttpError;
-use nanocl_stubs::{generic::GenericNspQuery, process::OutputLog};
+use nanocl_stubs::{
+  process::OutputLog,
+  vm::{VmAttachQuery, VmConsoleSize},
+};
 
 use crate::{
   models::{SystemState, WsConState},
-  utils,
+  utils, vars,
 };
 
+/// Command typed on the console to tell the guest its new size,
+/// the leading carriage return ensure it's not appended to a pending input
+fn gen_stty(size: &VmConsoleSize) -> Bytes {
+  Bytes::from(format!("\rstty rows {} cols {}\r", size.height, size.width))
+}
+
+/// Resize the tty of the qemu process to the size of the client terminal.
+/// The guest behind the serial console isn't aware of it.
+async fn resize_console(
+  name: &str,
+  size: VmConsoleSize,
+  state: &SystemState,
+) -> io::Result<()> {
+  state
+    .inner
+    .docker_api
+    .resize_container_tty(
+      name,
+      ResizeContainerTtyOptions {
+        width: size.width,
+        height: size.height,
+      },
+    )
+    .await
+    .map_err(io::Error::other)
+}
+
 async fn ws_attach_service(
-  (key, sink, state): (String, ws::WsSink, web::types::State<SystemState>),
+  (key, detach_keys, resize_guest, sink, state): (
+    String,
+    String,
+    bool,
+    ws::WsSink,
+    web::types::State<SystemState>,
+  ),
 ) -> Result<
   impl Service<ws::Frame, Response = Option<ws::Message>, Error = io::Error>,
   web::Error,
@@ -31,18 +69,19 @@ async fn ws_attach_service(
   let (tx, rx) = oneshot::channel();
   rt::spawn(utils::ws::heartbeat(con_state.clone(), sink.clone(), rx));
   let (s_cmd, mut r_cmd) = mpsc::channel::<Result<Bytes, web::Error>>();
+  let name = format!("{key}.v");
   let stream = state
     .inner
     .docker_api
     .attach_container(
-      &format!("{key}.v"),
+      &name,
       Some(AttachContainerOptions::<String> {
         stdin: Some(true),
         stdout: Some(true),
         stderr: Some(true),
         stream: Some(true),
         logs: Some(false),
-        detach_keys: Some("ctrl-c".to_owned()),
+        detach_keys: Some(detach_keys),
       }),
     )
     .await
@@ -69,9 +108,11 @@ async fn ws_attach_service(
       output.push(b'\n');
       let msg = ws::Message::Binary(Bytes::from(output));
       if sink.send(msg).await.is_err() {
-        break;
+        return;
       }
     }
+    // The console has been detached or the VM stopped
+    let _ = sink.send(ws::Message::Close(None)).await;
   });
   rt::spawn(async move {
     let mut stdin = stream.input;
@@ -104,7 +145,24 @@ async fn ws_attach_service(
         let _ = s_cmd.send(Ok(text));
         None
       }
-      ws::Frame::Binary(_) => None,
+      ws::Frame::Binary(bin) => {
+        match serde_json::from_slice::<VmConsoleSize>(&bin) {
+          Ok(size) => {
+            if resize_guest {
+              let _ = s_cmd.send(Ok(gen_stty(&size)));
+            }
+            let name = name.clone();
+            let state = state.clone();
+            rt::spawn(async move {
+              if let Err(err) = resize_console(&name, size, &state).await {
+                log::warn!("vm_attach: unable to resize {name}: {err}");
+              }
+            });
+          }
+          Err(err) => log::warn!("vm_attach: invalid message: {err}"),
+        }
+        None
+      }
       ws::Frame::Close(reason) => Some(ws::Message::Close(reason)),
       _ => Some(ws::Message::Close(None)),
     };
@@ -126,6 +184,8 @@ async fn ws_attach_service(
   params(
     ("name" = String, Path, description = "Name of the virtual machine"),
     ("namespace" = Option<String>, Query, description = "Namespace where the virtual machine belongs default to 'global'"),
+    ("detach_keys" = Option<String>, Query, description = "Key sequence used to detach from the console default to 'ctrl-c'"),
+    ("resize_guest" = Option<bool>, Query, description = "Type stty rows and cols on the console when it's resized so the guest know its size"),
   ),
   responses(
     (status = 101, description = "Websocket connection"),
@@ -135,15 +195,26 @@ pub async fn vm_attach(
   state: web::types::State<SystemState>,
   path: web::types::Path<(String, String)>,
   req: web::HttpRequest,
-  qs: web::types::Query<GenericNspQuery>,
+  qs: web::types::Query<VmAttachQuery>,
 ) -> Result<web::HttpResponse, web::Error> {
   let namespace = utils::key::resolve_nsp(&qs.namespace);
   let key = utils::key::gen_key(&namespace, &path.1);
+  let detach_keys = qs
+    .detach_keys
+    .clone()
+    .unwrap_or_else(|| vars::VM_DETACH_KEYS.to_owned());
+  let resize_guest = qs.resize_guest.unwrap_or_default();
   web::ws::start(
     req,
     // inject state to ws_attach_service factory
     map_config(fn_factory_with_config(ws_attach_service), move |cfg| {
-      (key.clone(), cfg, state.clone())
+      (
+        key.clone(),
+        detach_keys.clone(),
+        resize_guest,
+        cfg,
+        state.clone(),
+      )
     }),
   )
   .await
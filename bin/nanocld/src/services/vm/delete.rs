@@ -1,7 +1,7 @@
 use ntex::web;
 
 use nanocl_error::http::HttpResult;
-use nanocl_stubs::generic::GenericNspQuery;
+use nanocl_stubs::vm::VmDeleteQuery;
 
 use crate::{
   models::{SystemState, VmDb},
@@ -17,6 +17,7 @@ use crate::{
   params(
     ("name" = String, Path, description = "The name of the virtual machine"),
     ("namespace" = Option<String>, Query, description = "Namespace where the virtual machine belongs default to 'global'"),
+    ("propagation" = Option<nanocl_stubs::generic::DeletePropagation>, Query, description = "What happens to the resources it owns: Foreground, Background or Orphan default to Background"),
   ),
   responses(
     (status = 200, description = "The virtual machine has been deleted"),
@@ -27,11 +28,11 @@ use crate::{
 pub async fn delete_vm(
   state: web::types::State<SystemState>,
   path: web::types::Path<(String, String)>,
-  qs: web::types::Query<GenericNspQuery>,
+  qs: web::types::Query<VmDeleteQuery>,
 ) -> HttpResult<web::HttpResponse> {
   let name = path.1.to_owned();
   let namespace = utils::key::resolve_nsp(&qs.namespace);
   let key = utils::key::gen_key(&namespace, &name);
-  VmDb::del_obj_by_pk(&key, &(), &state).await?;
+  VmDb::del_obj_by_pk(&key, &qs, &state).await?;
   Ok(web::HttpResponse::Ok().finish())
 }
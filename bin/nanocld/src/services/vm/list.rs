@@ -1,7 +1,11 @@
 use ntex::web;
 
 use nanocl_error::http::HttpResult;
-use nanocl_stubs::generic::GenericListQueryNsp;
+use nanocl_stubs::{
+  generic::{GenericFilterNsp, GenericListQueryNsp},
+  system::EventActorKind,
+  vm::VmSummary,
+};
 
 use crate::{
   models::{SystemState, VmDb},
@@ -16,6 +20,8 @@ use crate::{
   params(
     ("filter" = Option<String>, Query, description = "Generic filter", example = "{ \"filter\": { \"where\": { \"name\": { \"eq\": \"my-vm\" } } } }"),
     ("namespace" = Option<String>, Query, description = "Namespace where the virtual machine belongs default to 'global'"),
+    ("watch" = Option<bool>, Query, description = "Stream the items followed by their changes"),
+    ("cursor" = Option<String>, Query, description = "Cursor of a previous watch to resume from"),
   ),
   responses(
     (status = 200, description = "List of virtual machine", body = [nanocl_stubs::vm::VmSummary]),
@@ -27,6 +33,24 @@ pub async fn list_vm(
   qs: web::types::Query<GenericListQueryNsp>,
 ) -> HttpResult<web::HttpResponse> {
   let query = utils::query_string::parse_qs_nsp_filter(&qs)?;
+  if qs.watch.unwrap_or_default() {
+    return utils::watch::stream(
+      EventActorKind::Vm,
+      qs.cursor.clone(),
+      query.filter.clone().unwrap_or_default(),
+      "key",
+      |vm: &VmSummary| vm.spec.vm_key.clone(),
+      move |state, filter| {
+        let query = GenericFilterNsp {
+          filter: Some(filter),
+          namespace: query.namespace.clone(),
+        };
+        async move { VmDb::list(&query, &state.inner.pool).await }
+      },
+      &state,
+    )
+    .await;
+  }
   let vms = VmDb::list(&query, &state.inner.pool).await?;
   Ok(web::HttpResponse::Ok().json(&vms))
 }
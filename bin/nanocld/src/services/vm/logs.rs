@@ -0,0 +1,46 @@
+use ntex::web;
+
+use nanocl_error::http::HttpResult;
+use nanocl_stubs::{process::OutputLog, vm::VmLogQuery};
+
+use crate::{
+  models::{SystemState, VmDb},
+  repositories::generic::*,
+  utils,
+};
+
+/// Get the serial console logs of a virtual machine
+#[cfg_attr(feature = "dev", utoipa::path(
+  get,
+  tag = "Vms",
+  path = "/vms/{name}/logs",
+  params(
+    ("name" = String, Path, description = "Name of the virtual machine"),
+    ("namespace" = Option<String>, Query, description = "Namespace where the virtual machine belongs default to 'global'"),
+    ("tail" = Option<String>, Query, description = "Only return the n last (integer) or all ('all') lines"),
+    ("follow" = Option<bool>, Query, description = "Boolean to keep the stream open and send new lines"),
+  ),
+  responses(
+    (status = 200, description = "Console logs of the virtual machine", content_type = "application/vdn.nanocl.raw-stream"),
+    (status = 404, description = "The virtual machine does not exist", body = crate::services::openapi::ApiError),
+  ),
+))]
+#[web::get("/vms/{name}/logs")]
+pub async fn logs_vm(
+  state: web::types::State<SystemState>,
+  path: web::types::Path<(String, String)>,
+  qs: web::types::Query<VmLogQuery>,
+) -> HttpResult<web::HttpResponse> {
+  let namespace = utils::key::resolve_nsp(&qs.namespace);
+  let key = utils::key::gen_key(&namespace, &path.1);
+  VmDb::read_by_pk(&key, &state.inner.pool).await?;
+  let vm_dir = utils::container::vm::get_state_dir(&key, &state);
+  let log_path = utils::vm_console::get_log_path(&vm_dir);
+  let stream = utils::vm_console::stream_logs(&log_path, &qs)?;
+  let stream = utils::stream::transform_stream::<OutputLog, OutputLog>(stream);
+  Ok(
+    web::HttpResponse::Ok()
+      .content_type("application/vdn.nanocl.raw-stream")
+      .streaming(stream),
+  )
+}
@@ -7,7 +7,9 @@ pub mod delete;
 pub mod inspect;
 pub mod list;
 pub mod list_history;
+pub mod logs;
 pub mod patch;
+pub mod snapshot;
 
 pub use attach::*;
 pub use count::*;
@@ -16,7 +18,9 @@ pub use delete::*;
 pub use inspect::*;
 pub use list::*;
 pub use list_history::*;
+pub use logs::*;
 pub use patch::*;
+pub use snapshot::*;
 
 pub fn ntex_config(config: &mut web::ServiceConfig) {
   config.service(list_vm);
@@ -26,6 +30,12 @@ pub fn ntex_config(config: &mut web::ServiceConfig) {
   config.service(count_vm);
   config.service(list_vm_history);
   config.service(patch_vm);
+  config.service(logs_vm);
+  config.service(list_vm_snapshot);
+  config.service(create_vm_snapshot);
+  config.service(inspect_vm_snapshot);
+  config.service(delete_vm_snapshot);
+  config.service(restore_vm_snapshot);
   config.service(
     web::resource("/vms/{name}/attach").route(web::get().to(vm_attach)),
   );
@@ -33,8 +43,9 @@ pub fn ntex_config(config: &mut web::ServiceConfig) {
 
 #[cfg(test)]
 mod tests {
-  use nanocl_stubs::vm::{VmInspect, VmSummary};
-  use nanocl_stubs::vm_spec::{VmDisk, VmSpecPartial};
+  use nanocl_stubs::vm::{VmInspect, VmLogQuery, VmSummary};
+  use nanocl_stubs::vm_snapshot::{VmSnapshot, VmSnapshotPartial};
+  use nanocl_stubs::vm_spec::{VmDataDisk, VmDisk, VmSpecPartial};
   use ntex::http;
 
   use crate::services::vm_image::tests::ensure_test_image;
@@ -77,9 +88,203 @@ mod tests {
     test_status_code!(res.status(), http::StatusCode::OK, "list vm");
     let vms = res.json::<Vec<VmSummary>>().await.unwrap();
     assert!(vms.iter().any(|i| i.spec.name == name));
+    let res = client
+      .send_get(
+        &format!("/vms/{name}/logs"),
+        Some(VmLogQuery {
+          tail: Some("10".to_owned()),
+          ..Default::default()
+        }),
+      )
+      .await;
+    test_status_code!(res.status(), http::StatusCode::OK, "logs vm");
+    let res = client.delete(&format!("/vms/{name}")).send().await.unwrap();
+    test_status_code!(res.status(), http::StatusCode::OK, "delete vm");
+    ntex::time::sleep(std::time::Duration::from_secs(1)).await;
+    system.state.wait_event_loop().await;
+  }
+
+  #[ntex::test]
+  async fn snapshot() {
+    ensure_test_image().await;
+    let system = gen_default_test_system().await;
+    let client = system.client;
+    let name = "api-test-vm-snapshot";
+    let res = client
+      .post("/vms")
+      .send_json(&VmSpecPartial {
+        name: name.to_owned(),
+        disk: VmDisk {
+          image: "ubuntu-22-test".to_owned(),
+          ..Default::default()
+        },
+        ..Default::default()
+      })
+      .await
+      .unwrap();
+    test_status_code!(res.status(), http::StatusCode::OK, "create vm");
+    let mut res = client
+      .post(&format!("/vms/{name}/snapshots"))
+      .send_json(&VmSnapshotPartial {
+        name: "first".to_owned(),
+      })
+      .await
+      .unwrap();
+    test_status_code!(res.status(), http::StatusCode::OK, "create snapshot");
+    let first = res.json::<VmSnapshot>().await.unwrap();
+    assert_eq!(first.parent, None);
+    let res = client
+      .post(&format!("/vms/{name}/snapshots"))
+      .send_json(&VmSnapshotPartial {
+        name: "first".to_owned(),
+      })
+      .await
+      .unwrap();
+    test_status_code!(
+      res.status(),
+      http::StatusCode::CONFLICT,
+      "create duplicated snapshot"
+    );
+    let mut res = client
+      .post(&format!("/vms/{name}/snapshots"))
+      .send_json(&VmSnapshotPartial {
+        name: "second".to_owned(),
+      })
+      .await
+      .unwrap();
+    test_status_code!(res.status(), http::StatusCode::OK, "create snapshot");
+    let second = res.json::<VmSnapshot>().await.unwrap();
+    assert_eq!(second.parent, Some(first.key));
+    let mut res = client
+      .get(&format!("/vms/{name}/snapshots/{}", second.key))
+      .send()
+      .await
+      .unwrap();
+    test_status_code!(res.status(), http::StatusCode::OK, "inspect snapshot");
+    assert_eq!(res.json::<VmSnapshot>().await.unwrap(), second);
+    let res = client
+      .get(&format!("/vms/{name}/snapshots/not-a-key"))
+      .send()
+      .await
+      .unwrap();
+    test_status_code!(
+      res.status(),
+      http::StatusCode::BAD_REQUEST,
+      "inspect invalid snapshot"
+    );
+    let unknown = uuid::Uuid::new_v4();
+    let res = client
+      .post(&format!("/vms/{name}/snapshots/{unknown}/restore"))
+      .send()
+      .await
+      .unwrap();
+    test_status_code!(
+      res.status(),
+      http::StatusCode::NOT_FOUND,
+      "restore unknown snapshot"
+    );
+    let res = client
+      .delete(&format!("/vms/{name}/snapshots/{unknown}"))
+      .send()
+      .await
+      .unwrap();
+    test_status_code!(
+      res.status(),
+      http::StatusCode::NOT_FOUND,
+      "delete unknown snapshot"
+    );
+    let res = client
+      .post(&format!("/vms/{name}/snapshots/{}/restore", first.key))
+      .send()
+      .await
+      .unwrap();
+    test_status_code!(res.status(), http::StatusCode::OK, "restore snapshot");
+    let res = client
+      .delete(&format!("/vms/{name}/snapshots/{}", first.key))
+      .send()
+      .await
+      .unwrap();
+    test_status_code!(res.status(), http::StatusCode::OK, "delete snapshot");
+    let mut res = client
+      .get(&format!("/vms/{name}/snapshots"))
+      .send()
+      .await
+      .unwrap();
+    test_status_code!(res.status(), http::StatusCode::OK, "list snapshots");
+    let snapshots = res.json::<Vec<VmSnapshot>>().await.unwrap();
+    assert_eq!(snapshots.len(), 1);
+    assert_eq!(snapshots[0].key, second.key);
+    assert_eq!(snapshots[0].parent, None);
     let res = client.delete(&format!("/vms/{name}")).send().await.unwrap();
     test_status_code!(res.status(), http::StatusCode::OK, "delete vm");
     ntex::time::sleep(std::time::Duration::from_secs(1)).await;
     system.state.wait_event_loop().await;
   }
+
+  #[ntex::test]
+  async fn data_disks() {
+    ensure_test_image().await;
+    let system = gen_default_test_system().await;
+    let client = system.client;
+    let name = "api-test-vm-disks";
+    let res = client
+      .post("/vms")
+      .send_json(&VmSpecPartial {
+        name: name.to_owned(),
+        disk: VmDisk {
+          image: "ubuntu-22-test".to_owned(),
+          ..Default::default()
+        },
+        disks: Some(vec![VmDataDisk {
+          name: "data".to_owned(),
+          size: Some(1),
+          ..Default::default()
+        }]),
+        ..Default::default()
+      })
+      .await
+      .unwrap();
+    test_status_code!(res.status(), http::StatusCode::OK, "create vm");
+    let mut res = client
+      .get(&format!("/vms/{name}/inspect"))
+      .send()
+      .await
+      .unwrap();
+    test_status_code!(res.status(), http::StatusCode::OK, "inspect vm");
+    let vm = res.json::<VmInspect>().await.unwrap();
+    let image = format!("data.{name}.global");
+    let disks = vm.spec.disks.unwrap_or_default();
+    assert_eq!(disks[0].image, Some(image.clone()));
+    let res = client
+      .delete(&format!("/vms/images/{image}"))
+      .send()
+      .await
+      .unwrap();
+    test_status_code!(
+      res.status(),
+      http::StatusCode::CONFLICT,
+      "delete attached image"
+    );
+    let res = client.delete(&format!("/vms/{name}")).send().await.unwrap();
+    test_status_code!(res.status(), http::StatusCode::OK, "delete vm");
+    ntex::time::sleep(std::time::Duration::from_secs(1)).await;
+    system.state.wait_event_loop().await;
+    // The data disk is kept detached after the vm is deleted
+    let res = client
+      .get(&format!("/vms/images/{image}/inspect"))
+      .send()
+      .await
+      .unwrap();
+    test_status_code!(res.status(), http::StatusCode::OK, "inspect data disk");
+    let res = client
+      .delete(&format!("/vms/images/{image}"))
+      .send()
+      .await
+      .unwrap();
+    test_status_code!(
+      res.status(),
+      http::StatusCode::OK,
+      "delete detached image"
+    );
+  }
 }
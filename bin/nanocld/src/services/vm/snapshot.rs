@@ -0,0 +1,145 @@
+use ntex::web;
+
+use nanocl_error::http::HttpResult;
+use nanocl_stubs::{generic::GenericNspQuery, vm_snapshot::VmSnapshotPartial};
+
+use crate::{models::SystemState, utils};
+
+/// List snapshots of a virtual machine
+#[cfg_attr(feature = "dev", utoipa::path(
+  get,
+  tag = "Vms",
+  path = "/vms/{name}/snapshots",
+  params(
+    ("name" = String, Path, description = "The name of the virtual machine"),
+    ("namespace" = Option<String>, Query, description = "Namespace where the virtual machine belongs default to 'global'"),
+  ),
+  responses(
+    (status = 200, description = "List of snapshots", body = [nanocl_stubs::vm_snapshot::VmSnapshot]),
+    (status = 404, description = "The virtual machine does not exist", body = crate::services::openapi::ApiError),
+  ),
+))]
+#[web::get("/vms/{name}/snapshots")]
+pub async fn list_vm_snapshot(
+  state: web::types::State<SystemState>,
+  path: web::types::Path<(String, String)>,
+  qs: web::types::Query<GenericNspQuery>,
+) -> HttpResult<web::HttpResponse> {
+  let namespace = utils::key::resolve_nsp(&qs.namespace);
+  let key = utils::key::gen_key(&namespace, &path.1);
+  let snapshots = utils::vm_snapshot::list(&key, &state).await?;
+  Ok(web::HttpResponse::Ok().json(&snapshots))
+}
+
+/// Create a snapshot of a virtual machine
+#[cfg_attr(feature = "dev", utoipa::path(
+  post,
+  tag = "Vms",
+  path = "/vms/{name}/snapshots",
+  request_body = VmSnapshotPartial,
+  params(
+    ("name" = String, Path, description = "The name of the virtual machine"),
+    ("namespace" = Option<String>, Query, description = "Namespace where the virtual machine belongs default to 'global'"),
+  ),
+  responses(
+    (status = 200, description = "The snapshot has been created", body = nanocl_stubs::vm_snapshot::VmSnapshot),
+    (status = 404, description = "The virtual machine does not exist", body = crate::services::openapi::ApiError),
+    (status = 409, description = "The snapshot already exists", body = crate::services::openapi::ApiError),
+  ),
+))]
+#[web::post("/vms/{name}/snapshots")]
+pub async fn create_vm_snapshot(
+  state: web::types::State<SystemState>,
+  path: web::types::Path<(String, String)>,
+  payload: web::types::Json<VmSnapshotPartial>,
+  qs: web::types::Query<GenericNspQuery>,
+) -> HttpResult<web::HttpResponse> {
+  let namespace = utils::key::resolve_nsp(&qs.namespace);
+  let key = utils::key::gen_key(&namespace, &path.1);
+  let snapshot = utils::vm_snapshot::create(&key, &payload, &state).await?;
+  Ok(web::HttpResponse::Ok().json(&snapshot))
+}
+
+/// Inspect a snapshot of a virtual machine
+#[cfg_attr(feature = "dev", utoipa::path(
+  get,
+  tag = "Vms",
+  path = "/vms/{name}/snapshots/{id}",
+  params(
+    ("name" = String, Path, description = "The name of the virtual machine"),
+    ("id" = String, Path, description = "The key of the snapshot"),
+    ("namespace" = Option<String>, Query, description = "Namespace where the virtual machine belongs default to 'global'"),
+  ),
+  responses(
+    (status = 200, description = "Detailed information about the snapshot", body = nanocl_stubs::vm_snapshot::VmSnapshot),
+    (status = 400, description = "The key of the snapshot is invalid", body = crate::services::openapi::ApiError),
+    (status = 404, description = "The snapshot does not exist", body = crate::services::openapi::ApiError),
+  ),
+))]
+#[web::get("/vms/{name}/snapshots/{id}")]
+pub async fn inspect_vm_snapshot(
+  state: web::types::State<SystemState>,
+  path: web::types::Path<(String, String, String)>,
+  qs: web::types::Query<GenericNspQuery>,
+) -> HttpResult<web::HttpResponse> {
+  let namespace = utils::key::resolve_nsp(&qs.namespace);
+  let key = utils::key::gen_key(&namespace, &path.1);
+  let snapshot = utils::vm_snapshot::inspect(&key, &path.2, &state).await?;
+  Ok(web::HttpResponse::Ok().json(&snapshot))
+}
+
+/// Delete a snapshot of a virtual machine
+#[cfg_attr(feature = "dev", utoipa::path(
+  delete,
+  tag = "Vms",
+  path = "/vms/{name}/snapshots/{id}",
+  params(
+    ("name" = String, Path, description = "The name of the virtual machine"),
+    ("id" = String, Path, description = "The key of the snapshot"),
+    ("namespace" = Option<String>, Query, description = "Namespace where the virtual machine belongs default to 'global'"),
+  ),
+  responses(
+    (status = 200, description = "The snapshot has been deleted"),
+    (status = 400, description = "The key of the snapshot is invalid", body = crate::services::openapi::ApiError),
+    (status = 404, description = "The snapshot does not exist", body = crate::services::openapi::ApiError),
+  ),
+))]
+#[web::delete("/vms/{name}/snapshots/{id}")]
+pub async fn delete_vm_snapshot(
+  state: web::types::State<SystemState>,
+  path: web::types::Path<(String, String, String)>,
+  qs: web::types::Query<GenericNspQuery>,
+) -> HttpResult<web::HttpResponse> {
+  let namespace = utils::key::resolve_nsp(&qs.namespace);
+  let key = utils::key::gen_key(&namespace, &path.1);
+  utils::vm_snapshot::delete(&key, &path.2, &state).await?;
+  Ok(web::HttpResponse::Ok().finish())
+}
+
+/// Restore a virtual machine to one of its snapshots
+#[cfg_attr(feature = "dev", utoipa::path(
+  post,
+  tag = "Vms",
+  path = "/vms/{name}/snapshots/{id}/restore",
+  params(
+    ("name" = String, Path, description = "The name of the virtual machine"),
+    ("id" = String, Path, description = "The key of the snapshot"),
+    ("namespace" = Option<String>, Query, description = "Namespace where the virtual machine belongs default to 'global'"),
+  ),
+  responses(
+    (status = 200, description = "The virtual machine has been restored", body = nanocl_stubs::vm_snapshot::VmSnapshot),
+    (status = 400, description = "The key of the snapshot is invalid", body = crate::services::openapi::ApiError),
+    (status = 404, description = "The snapshot does not exist", body = crate::services::openapi::ApiError),
+  ),
+))]
+#[web::post("/vms/{name}/snapshots/{id}/restore")]
+pub async fn restore_vm_snapshot(
+  state: web::types::State<SystemState>,
+  path: web::types::Path<(String, String, String)>,
+  qs: web::types::Query<GenericNspQuery>,
+) -> HttpResult<web::HttpResponse> {
+  let namespace = utils::key::resolve_nsp(&qs.namespace);
+  let key = utils::key::gen_key(&namespace, &path.1);
+  let snapshot = utils::vm_snapshot::restore(&key, &path.2, &state).await?;
+  Ok(web::HttpResponse::Ok().json(&snapshot))
+}
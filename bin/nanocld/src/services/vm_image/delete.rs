@@ -15,6 +15,7 @@ use crate::{models::SystemState, utils};
   responses(
     (status = 200, description = "Image have been deleted"),
     (status = 404, description = "The vm image does not exist", body = crate::services::openapi::ApiError),
+    (status = 409, description = "The vm image is attached to a vm", body = crate::services::openapi::ApiError),
   ),
 ))]
 #[web::delete("/vms/images/{name}")]
@@ -23,6 +24,7 @@ pub async fn delete_vm_image(
   path: web::types::Path<(String, String)>,
 ) -> HttpResult<web::HttpResponse> {
   let pk = path.1.to_owned();
+  utils::vm_image::ensure_detached(&pk, &state).await?;
   utils::vm_image::delete_by_pk(&pk, &state).await?;
   Ok(web::HttpResponse::Ok().into())
 }
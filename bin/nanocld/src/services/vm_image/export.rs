@@ -0,0 +1,44 @@
+use ntex::{http, web};
+
+use nanocl_error::http::HttpResult;
+use nanocl_stubs::vm_image::VmImageExportQuery;
+
+use crate::{
+  models::{SystemState, VmImageDb},
+  repositories::generic::*,
+  utils,
+};
+
+/// Export a virtual machine image as a qcow2 file
+#[cfg_attr(feature = "dev", utoipa::path(
+  get,
+  tag = "VmImages",
+  path = "/vms/images/{name}/export",
+  params(
+    ("name" = String, Path, description = "The name of the vm image"),
+    ("flatten" = Option<bool>, Query, description = "Merge the backing chain into the exported image, otherwise an image with a parent reference it by its file name"),
+    ("compress" = Option<bool>, Query, description = "Compress the exported image"),
+  ),
+  responses(
+    (status = 200, description = "The qcow2 image", content_type = "application/octet-stream"),
+    (status = 404, description = "The vm image does not exist", body = crate::services::openapi::ApiError),
+  ),
+))]
+#[web::get("/vms/images/{name}/export")]
+pub async fn export_vm_image(
+  state: web::types::State<SystemState>,
+  path: web::types::Path<(String, String)>,
+  qs: web::types::Query<VmImageExportQuery>,
+) -> HttpResult<web::HttpResponse> {
+  let image = VmImageDb::read_by_pk(&path.1, &state.inner.pool).await?;
+  let rx = utils::vm_image::export(&image, &qs, &state).await?;
+  Ok(
+    web::HttpResponse::Ok()
+      .content_type("application/octet-stream")
+      .header(
+        http::header::CONTENT_DISPOSITION,
+        format!("attachment; filename=\"{}.qcow2\"", image.name),
+      )
+      .streaming(rx),
+  )
+}
@@ -0,0 +1,29 @@
+use ntex::web;
+
+use nanocl_error::http::HttpResult;
+
+use crate::{models::SystemState, utils};
+
+/// Detach a linked clone from the image it's based on
+#[cfg_attr(feature = "dev", utoipa::path(
+  post,
+  tag = "VmImages",
+  path = "/vms/images/{name}/flatten",
+  params(
+    ("name" = String, Path, description = "The name of the vm image"),
+  ),
+  responses(
+    (status = 200, description = "The image have been flattened", body = nanocl_stubs::vm_image::VmImage),
+    (status = 400, description = "The image is not a linked clone", body = crate::services::openapi::ApiError),
+    (status = 404, description = "The vm image does not exist", body = crate::services::openapi::ApiError),
+    (status = 409, description = "The image is used by a running vm", body = crate::services::openapi::ApiError),
+  ),
+))]
+#[web::post("/vms/images/{name}/flatten")]
+pub async fn flatten_vm_image(
+  state: web::types::State<SystemState>,
+  path: web::types::Path<(String, String)>,
+) -> HttpResult<web::HttpResponse> {
+  let image = utils::vm_image::flatten(&path.1, &state).await?;
+  Ok(web::HttpResponse::Ok().json(&image))
+}
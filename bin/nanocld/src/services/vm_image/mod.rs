@@ -4,22 +4,32 @@ pub mod clone;
 pub mod count;
 pub mod create_snapshot;
 pub mod delete;
+pub mod export;
+pub mod flatten;
 pub mod import;
 pub mod inspect;
 pub mod list;
+pub mod pull;
 pub mod resize;
+pub mod template;
 
 pub use clone::*;
 pub use count::*;
 pub use create_snapshot::*;
 pub use delete::*;
+pub use export::*;
+pub use flatten::*;
 pub use import::*;
 pub use inspect::*;
 pub use list::*;
+pub use pull::*;
 pub use resize::*;
+pub use template::*;
 
 pub fn ntex_config(config: &mut web::ServiceConfig) {
   config.service(import_vm_image);
+  config.service(pull_vm_image);
+  config.service(export_vm_image);
   config.service(list_vm_images);
   config.service(delete_vm_image);
   config.service(snapshot_vm_image);
@@ -27,6 +37,9 @@ pub fn ntex_config(config: &mut web::ServiceConfig) {
   config.service(count_vm_image);
   config.service(resize_vm_image);
   config.service(inspect_vm_image);
+  config.service(template_vm_image);
+  config.service(untemplate_vm_image);
+  config.service(flatten_vm_image);
 }
 
 #[cfg(test)]
@@ -36,7 +49,9 @@ pub mod tests {
   use tokio_util::codec;
 
   use nanocl_error::io::{FromIo, IoError, IoResult};
-  use nanocl_stubs::vm_image::VmImage;
+  use nanocl_stubs::vm_image::{
+    VmImage, VmImageExportQuery, VmImagePullPayload, VmImageResizePayload,
+  };
 
   use crate::utils::tests::*;
 
@@ -117,4 +132,176 @@ pub mod tests {
       .unwrap();
     test_status_code!(res.status(), StatusCode::OK, "Unable to delete image");
   }
+
+  #[ntex::test]
+  async fn pull() {
+    let system = gen_default_test_system().await;
+    let client = system.client;
+    let name = "raw-test-pull";
+    // A raw disk of 1MiB served by a local http server
+    let data = vec![0u8; 1024 * 1024];
+    let checksum = openssl::sha::sha256(&data)
+      .iter()
+      .fold(String::new(), |acc, byte| format!("{acc}{byte:02x}"));
+    let data = ntex::util::Bytes::from(data);
+    let srv = ntex::web::test::server(move || {
+      let data = data.clone();
+      ntex::web::App::new().service(ntex::web::resource("/disk.raw").to(
+        move || {
+          let data = data.clone();
+          async move { data }
+        },
+      ))
+    });
+    let res = client
+      .post(&format!("/vms/images/{name}/pull"))
+      .send_json(&VmImagePullPayload {
+        url: srv.url("/disk.raw"),
+        checksum: Some(format!("sha256:{}", "0".repeat(64))),
+      })
+      .await
+      .unwrap();
+    test_status_code!(
+      res.status(),
+      StatusCode::BAD_REQUEST,
+      "pull bad checksum"
+    );
+    assert!(inspect_image(name).await.is_err());
+    let mut res = client
+      .post(&format!("/vms/images/{name}/pull"))
+      .send_json(&VmImagePullPayload {
+        url: srv.url("/disk.raw"),
+        checksum: Some(format!("sha256:{checksum}")),
+      })
+      .await
+      .unwrap();
+    test_status_code!(res.status(), StatusCode::OK, "pull image");
+    let image = res.json::<VmImage>().await.unwrap();
+    assert_eq!(image.name, name);
+    assert_eq!(image.format, "qcow2");
+    for query in [
+      VmImageExportQuery::default(),
+      VmImageExportQuery {
+        flatten: Some(true),
+        compress: Some(true),
+      },
+    ] {
+      let mut res = client
+        .send_get(&format!("/vms/images/{name}/export"), Some(query))
+        .await;
+      test_status_code!(res.status(), StatusCode::OK, "export image");
+      let body = res.body().limit(10 * 1024 * 1024).await.unwrap();
+      assert!(body.starts_with(b"QFI\xfb"), "exported image is not qcow2");
+    }
+    let res = client
+      .delete(&format!("/vms/images/{name}"))
+      .send()
+      .await
+      .unwrap();
+    test_status_code!(res.status(), StatusCode::OK, "Unable to delete image");
+  }
+
+  #[ntex::test]
+  async fn template() {
+    let system = gen_default_test_system().await;
+    let client = system.client;
+    let name = "raw-test-template";
+    let clone = "raw-test-template-clone";
+    let data = ntex::util::Bytes::from(vec![0u8; 1024 * 1024]);
+    let srv = ntex::web::test::server(move || {
+      let data = data.clone();
+      ntex::web::App::new().service(ntex::web::resource("/disk.raw").to(
+        move || {
+          let data = data.clone();
+          async move { data }
+        },
+      ))
+    });
+    let res = client
+      .post(&format!("/vms/images/{name}/pull"))
+      .send_json(&VmImagePullPayload {
+        url: srv.url("/disk.raw"),
+        checksum: None,
+      })
+      .await
+      .unwrap();
+    test_status_code!(res.status(), StatusCode::OK, "pull image");
+    let mut res = client
+      .post(&format!("/vms/images/{name}/template"))
+      .send()
+      .await
+      .unwrap();
+    test_status_code!(res.status(), StatusCode::OK, "template image");
+    let image = res.json::<VmImage>().await.unwrap();
+    assert_eq!(image.kind, "Template");
+    let res = client
+      .post(&format!("/vms/images/{name}/resize"))
+      .send_json(&VmImageResizePayload {
+        size: 2,
+        shrink: false,
+      })
+      .await
+      .unwrap();
+    test_status_code!(res.status(), StatusCode::BAD_REQUEST, "resize template");
+    let res = client
+      .post(&format!("/vms/images/{name}/snapshot/{clone}"))
+      .send()
+      .await
+      .unwrap();
+    test_status_code!(res.status(), StatusCode::OK, "clone template");
+    let image = inspect_image(clone).await.unwrap();
+    assert_eq!(image.parent.as_deref(), Some(name));
+    let parent = inspect_image(name).await.unwrap();
+    for compress in [None, Some(true)] {
+      let query = VmImageExportQuery {
+        flatten: None,
+        compress,
+      };
+      let mut res = client
+        .send_get(&format!("/vms/images/{clone}/export"), Some(query))
+        .await;
+      test_status_code!(res.status(), StatusCode::OK, "export clone");
+      let body = res.body().limit(10 * 1024 * 1024).await.unwrap();
+      assert!(body.starts_with(b"QFI\xfb"), "exported clone is not qcow2");
+      let contains = |pattern: &[u8]| {
+        body.windows(pattern.len()).any(|window| window == pattern)
+      };
+      assert!(contains(format!("{name}.img").as_bytes()));
+      assert!(!contains(parent.path.as_bytes()));
+    }
+    let res = client
+      .delete(&format!("/vms/images/{name}"))
+      .send()
+      .await
+      .unwrap();
+    test_status_code!(res.status(), StatusCode::CONFLICT, "delete template");
+    let res = client
+      .delete(&format!("/vms/images/{name}/template"))
+      .send()
+      .await
+      .unwrap();
+    test_status_code!(res.status(), StatusCode::CONFLICT, "untemplate image");
+    let mut res = client
+      .post(&format!("/vms/images/{clone}/flatten"))
+      .send()
+      .await
+      .unwrap();
+    test_status_code!(res.status(), StatusCode::OK, "flatten clone");
+    let image = res.json::<VmImage>().await.unwrap();
+    assert_eq!(image.parent, None);
+    let res = client
+      .delete(&format!("/vms/images/{name}/template"))
+      .send()
+      .await
+      .unwrap();
+    test_status_code!(res.status(), StatusCode::OK, "untemplate image");
+    for image in [clone, name] {
+      let res = client
+        .delete(&format!("/vms/images/{image}"))
+        .send()
+        .await
+        .unwrap();
+      test_status_code!(res.status(), StatusCode::OK, "delete image");
+    }
+  }
 }
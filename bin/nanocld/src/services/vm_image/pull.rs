@@ -0,0 +1,32 @@
+use ntex::web;
+
+use nanocl_error::http::HttpResult;
+use nanocl_stubs::vm_image::{VmImage, VmImagePullPayload};
+
+use crate::{models::SystemState, utils};
+
+/// Pull a virtual machine image from an http(s) url.
+/// The progress is emitted as `Downloading` events of the image.
+#[cfg_attr(feature = "dev", utoipa::path(
+  post,
+  tag = "VmImages",
+  request_body = VmImagePullPayload,
+  path = "/vms/images/{name}/pull",
+  params(
+    ("name" = String, Path, description = "The name of the vm image"),
+  ),
+  responses(
+    (status = 200, description = "The pulled vm image", body = VmImage),
+    (status = 400, description = "Invalid url or checksum", body = crate::services::openapi::ApiError),
+    (status = 409, description = "The vm image already exists", body = crate::services::openapi::ApiError),
+  ),
+))]
+#[web::post("/vms/images/{name}/pull")]
+pub async fn pull_vm_image(
+  state: web::types::State<SystemState>,
+  path: web::types::Path<(String, String)>,
+  payload: web::types::Json<VmImagePullPayload>,
+) -> HttpResult<web::HttpResponse> {
+  let image = utils::vm_image_pull::pull(&path.1, &payload, &state).await?;
+  Ok(web::HttpResponse::Ok().json(&VmImage::from(image)))
+}
@@ -0,0 +1,53 @@
+use ntex::web;
+
+use nanocl_error::http::HttpResult;
+
+use crate::{models::SystemState, utils};
+
+/// Mark a virtual machine image as a read-only template
+#[cfg_attr(feature = "dev", utoipa::path(
+  post,
+  tag = "VmImages",
+  path = "/vms/images/{name}/template",
+  params(
+    ("name" = String, Path, description = "The name of the vm image"),
+  ),
+  responses(
+    (status = 200, description = "The image is now a template", body = nanocl_stubs::vm_image::VmImage),
+    (status = 400, description = "The image is not a base image", body = crate::services::openapi::ApiError),
+    (status = 404, description = "The vm image does not exist", body = crate::services::openapi::ApiError),
+    (status = 409, description = "The vm image is attached in read-write mode", body = crate::services::openapi::ApiError),
+  ),
+))]
+#[web::post("/vms/images/{name}/template")]
+pub async fn template_vm_image(
+  state: web::types::State<SystemState>,
+  path: web::types::Path<(String, String)>,
+) -> HttpResult<web::HttpResponse> {
+  let image = utils::vm_image::set_template(&path.1, true, &state).await?;
+  Ok(web::HttpResponse::Ok().json(&image))
+}
+
+/// Turn a virtual machine template back into a base image
+#[cfg_attr(feature = "dev", utoipa::path(
+  delete,
+  tag = "VmImages",
+  path = "/vms/images/{name}/template",
+  params(
+    ("name" = String, Path, description = "The name of the vm image"),
+  ),
+  responses(
+    (status = 200, description = "The image is now a base image", body = nanocl_stubs::vm_image::VmImage),
+    (status = 400, description = "The image is not a template", body = crate::services::openapi::ApiError),
+    (status = 404, description = "The vm image does not exist", body = crate::services::openapi::ApiError),
+    (status = 409, description = "The template is used by linked clones", body = crate::services::openapi::ApiError),
+  ),
+))]
+#[web::delete("/vms/images/{name}/template")]
+pub async fn untemplate_vm_image(
+  state: web::types::State<SystemState>,
+  path: web::types::Path<(String, String)>,
+) -> HttpResult<web::HttpResponse> {
+  let image = utils::vm_image::set_template(&path.1, false, &state).await?;
+  Ok(web::HttpResponse::Ok().json(&image))
+}
@@ -18,7 +18,7 @@ use crate::{
     CargoDb, ObjPsStatusDb, ProcessDb, ProcessUpdateDb, SystemState, VmDb,
   },
   repositories::generic::*,
-  vars,
+  utils, vars,
 };
 
 /// Take actions when a docker event is received
@@ -174,7 +174,7 @@ async fn exec_docker(
     .inspect_container(&id, None::<InspectContainerOptions>)
     .await
     .map_err(|err| err.map_err_context(|| "Docker event"))?;
-  let data = serde_json::to_value(instance)
+  let data = serde_json::to_value(utils::secret::redact_container(instance))
     .map_err(|err| err.map_err_context(|| "Docker event"))?;
   let new_instance = ProcessUpdateDb {
     updated_at: Some(chrono::Utc::now().naive_utc()),
@@ -4,7 +4,7 @@ use ntex::rt;
 
 use nanocl_error::io::IoResult;
 use nanocl_stubs::{
-  generic::{GenericClause, GenericFilter},
+  generic::GenericDeleteQuery,
   system::{
     Event, EventActor, EventActorKind, EventKind, NativeEventAction,
     ObjPsStatusKind,
@@ -76,7 +76,9 @@ async fn job_ttl(actor: &EventActor, state: &SystemState) -> IoResult<()> {
   rt::spawn(async move {
     log::debug!("event::job_ttl: {} will be deleted in {ttl}s", job.name);
     ntex::time::sleep(std::time::Duration::from_secs(ttl as u64)).await;
-    let _ = JobDb::del_obj_by_pk(&job.name, &(), &state).await;
+    let _ =
+      JobDb::del_obj_by_pk(&job.name, &GenericDeleteQuery::default(), &state)
+        .await;
   });
   Ok(())
 }
@@ -147,45 +149,6 @@ fn updating(
   }
 }
 
-async fn update(
-  key: &str,
-  actor: &EventActor,
-  state: &SystemState,
-) -> Option<ObjTaskFuture> {
-  match actor.kind {
-    // If a secret is updated we check for the cargoes using it and fire an update for them
-    EventActorKind::Secret => {
-      log::debug!("handling update event for secret {key}");
-      let filter = GenericFilter::new().r#where(
-        "data",
-        GenericClause::Contains(serde_json::json!({
-          "Secrets": [
-            key
-          ]
-        })),
-      );
-      let cargoes = CargoDb::transform_read_by(&filter, &state.inner.pool)
-        .await
-        .unwrap();
-      log::debug!("found {} cargoes using secret {key}", cargoes.len());
-      for cargo in &cargoes {
-        ObjPsStatusDb::update_actual_status(
-          &cargo.spec.cargo_key,
-          &ObjPsStatusKind::Updating,
-          &state.inner.pool,
-        )
-        .await
-        .ok();
-        state
-          .emit_normal_native_action_sync(cargo, NativeEventAction::Updating)
-          .await;
-      }
-      None
-    }
-    _ => None,
-  }
-}
-
 fn stopping(
   key: &str,
   actor: &EventActor,
@@ -246,7 +209,6 @@ pub async fn exec_event(e: &Event, state: &SystemState) -> IoResult<()> {
     NativeEventAction::Starting => starting(&key, actor, state),
     NativeEventAction::Stopping => stopping(&key, actor, state),
     NativeEventAction::Updating => updating(&key, actor, state),
-    NativeEventAction::Update => update(&key, actor, state).await,
     NativeEventAction::Destroying => destroying(&key, actor, state),
     NativeEventAction::Die => {
       job_ttl(actor, state).await?;
@@ -125,11 +125,17 @@ pub async fn init(conf: &DaemonConfig) -> IoResult<SystemState> {
   spawn_crond();
   set_uds_perm();
   ensure_state_dir(&conf.state_dir).await?;
+  utils::ca::load(&conf.state_dir).await?;
   let system_state = SystemState::new(conf).await?;
   let system_ptr = system_state.clone();
   NodeDb::register(&system_ptr).await?;
   utils::system::register_namespace("global", &system_ptr).await?;
   utils::system::register_namespace("system", &system_ptr).await?;
+  match utils::secret::seal_all(&system_ptr).await {
+    Ok(0) => {}
+    Ok(count) => log::info!("boot::init: {count} secrets encrypted"),
+    Err(err) => log::warn!("boot::init: unable to encrypt secrets: {err}"),
+  }
   rt::spawn(async move {
     let fut = async move {
       utils::system::sync_processes(&system_ptr).await?;
@@ -143,6 +149,10 @@ pub async fn init(conf: &DaemonConfig) -> IoResult<SystemState> {
   });
   super::docker_event::analyze(&system_state);
   super::metric::spawn(&system_state);
+  super::vm_console::spawn(&system_state);
+  super::vm_metric::spawn(&system_state);
+  super::tls_issued::spawn(&system_state);
+  super::resource_reconcile::spawn(&system_state);
   Ok(system_state)
 }
 
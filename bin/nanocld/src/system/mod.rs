@@ -2,7 +2,11 @@ mod docker_event;
 mod event;
 mod init;
 mod metric;
+mod resource_reconcile;
 mod system_state;
+mod tls_issued;
+mod vm_console;
+mod vm_metric;
 
 pub use event::exec_event;
 pub use init::init;
@@ -0,0 +1,25 @@
+use std::time::Duration;
+
+use ntex::{rt, time::interval};
+
+use crate::{models::SystemState, utils, vars};
+
+/// Spawn a background task that call again the controllers
+/// of the resources waiting to be applied or deleted
+pub fn spawn(state: &SystemState) {
+  let state = state.clone();
+  rt::spawn(async move {
+    let interval =
+      interval(Duration::from_secs(vars::RESOURCE_RECONCILE_INTERVAL));
+    loop {
+      interval.tick().await;
+      match utils::resource::reconcile(&state).await {
+        Ok(0) => {}
+        Ok(count) => {
+          log::debug!("resource_reconcile::spawn: {count} resources retried")
+        }
+        Err(err) => log::warn!("resource_reconcile::spawn: {err}"),
+      }
+    }
+  });
+}
@@ -1,4 +1,7 @@
-use std::sync::Arc;
+use std::{
+  collections::HashMap,
+  sync::{Arc, RwLock},
+};
 
 use futures::channel::mpsc;
 use futures_util::{SinkExt, StreamExt};
@@ -35,6 +38,7 @@ impl SystemState {
     )
     .map_err(|err| err.map_err_context(|| "Docker"))?;
     let pool = utils::store::init(conf).await?;
+    let secret_keys = utils::secret::load_keyring(&conf.conf_dir).await?;
     let (sx, rx) = mpsc::unbounded();
     let system_state = SystemState {
       inner: Arc::new(SystemStateInner {
@@ -44,6 +48,9 @@ impl SystemState {
         event_emitter: sx,
         event_emitter_raw: RawEventEmitter::new(),
         task_manager: TaskManager::new(),
+        secret_keys: RwLock::new(secret_keys),
+        external_secrets: RwLock::new(HashMap::new()),
+        wasm_modules: RwLock::new(HashMap::new()),
         arbiter: rt::Arbiter::new(),
       }),
     };
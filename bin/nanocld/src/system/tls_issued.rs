@@ -0,0 +1,23 @@
+use std::time::Duration;
+
+use ntex::{rt, time::interval};
+
+use crate::{models::SystemState, utils, vars};
+
+/// Spawn a background task that renew the certificates
+/// of the `nanocl.io/tls-issued` secrets before they expire
+pub fn spawn(state: &SystemState) {
+  let state = state.clone();
+  rt::spawn(async move {
+    let interval =
+      interval(Duration::from_secs(vars::TLS_ISSUED_RENEW_INTERVAL));
+    loop {
+      interval.tick().await;
+      match utils::secret::renew_issued(&state).await {
+        Ok(0) => {}
+        Ok(count) => log::info!("tls_issued::spawn: {count} secrets renewed"),
+        Err(err) => log::warn!("tls_issued::spawn: {err}"),
+      }
+    }
+  });
+}
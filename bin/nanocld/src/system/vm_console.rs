@@ -0,0 +1,49 @@
+use std::time::Duration;
+
+use ntex::{rt, time::interval};
+
+use nanocl_error::io::{FromIo, IoResult};
+
+use crate::{models::SystemState, utils, vars};
+
+/// Rotate the console logs of every VM that exceed the maximum size
+async fn rotate_logs(state: &SystemState) -> IoResult<()> {
+  let vms_dir = format!("{}/vms", state.inner.config.state_dir);
+  let mut entries = tokio::fs::read_dir(&vms_dir)
+    .await
+    .map_err(|err| err.map_err_context(|| &vms_dir))?;
+  while let Some(entry) = entries
+    .next_entry()
+    .await
+    .map_err(|err| err.map_err_context(|| &vms_dir))?
+  {
+    let vm_dir = entry.path().display().to_string();
+    let path = utils::vm_console::get_log_path(&vm_dir);
+    match utils::vm_console::rotate(
+      &path,
+      vars::VM_CONSOLE_LOG_MAX_SIZE,
+      vars::VM_CONSOLE_LOG_MAX_FILES,
+    )
+    .await
+    {
+      Ok(true) => log::debug!("vm_console::rotate_logs: {path} rotated"),
+      Ok(false) => {}
+      Err(err) => log::warn!("vm_console::rotate_logs: {err}"),
+    }
+  }
+  Ok(())
+}
+
+/// Spawn a background task that rotate the console logs of the VMs
+pub fn spawn(state: &SystemState) {
+  let state = state.clone();
+  rt::spawn(async move {
+    let interval = interval(Duration::from_secs(30));
+    loop {
+      interval.tick().await;
+      if let Err(err) = rotate_logs(&state).await {
+        log::warn!("vm_console::spawn: {err}");
+      }
+    }
+  });
+}
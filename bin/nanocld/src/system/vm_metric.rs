@@ -0,0 +1,48 @@
+use std::time::Duration;
+
+use ntex::{rt, time::interval};
+
+use nanocl_error::io::IoResult;
+use nanocl_stubs::{generic::GenericFilter, system::ObjPsStatusKind};
+
+use crate::{
+  models::{SystemState, VmDb},
+  repositories::generic::*,
+  utils, vars,
+};
+
+/// Save the statistics of every running VM of the current node
+async fn save_metrics(state: &SystemState) -> IoResult<()> {
+  let vms =
+    VmDb::transform_read_by(&GenericFilter::new(), &state.inner.pool).await?;
+  for vm in vms {
+    if vm.status.actual != ObjPsStatusKind::Start {
+      continue;
+    }
+    let vm_dir = utils::container::vm::get_state_dir(&vm.spec.vm_key, state);
+    let socket = utils::qmp::get_socket_path(&vm_dir);
+    // The VM is running on another node
+    if !std::path::Path::new(&socket).exists() {
+      continue;
+    }
+    if let Err(err) = utils::vm_stats::save(&vm, state).await {
+      log::debug!("vm_metric::save_metrics: {}: {err}", vm.spec.vm_key);
+    }
+  }
+  Ok(())
+}
+
+/// Spawn a background task that sample the statistics of the running VMs
+/// and save them as `nanocl.io/vm` metrics
+pub fn spawn(state: &SystemState) {
+  let state = state.clone();
+  rt::spawn(async move {
+    let interval = interval(Duration::from_secs(vars::VM_METRIC_INTERVAL));
+    loop {
+      interval.tick().await;
+      if let Err(err) = save_metrics(&state).await {
+        log::warn!("vm_metric::spawn: {err}");
+      }
+    }
+  });
+}
@@ -1,5 +1,4 @@
 use nanocl_error::io::IoError;
-use nanocl_stubs::process::ProcessKind;
 
 use crate::{
   models::{SystemState, VmDb},
@@ -24,8 +23,7 @@ impl ObjTaskStop for VmDb {
     let key = key.to_owned();
     let state = state.clone();
     Box::pin(async move {
-      utils::container::process::stop_instances(&key, &ProcessKind::Vm, &state)
-        .await?;
+      utils::container::vm::stop(&key, &state).await?;
       Ok::<_, IoError>(())
     })
   }
@@ -0,0 +1,254 @@
+use std::{net::IpAddr, path::Path};
+
+use openssl::{
+  asn1::{Asn1Integer, Asn1Time},
+  bn::{BigNum, MsbOption},
+  ec::{EcGroup, EcKey},
+  hash::MessageDigest,
+  nid::Nid,
+  pkey::{PKey, Private},
+  x509::{
+    extension::{
+      AuthorityKeyIdentifier, BasicConstraints, ExtendedKeyUsage, KeyUsage,
+      SubjectAlternativeName, SubjectKeyIdentifier,
+    },
+    X509Builder, X509Name, X509NameBuilder, X509,
+  },
+};
+
+use nanocl_error::io::{FromIo, IoError, IoResult};
+
+use crate::vars;
+
+/// Certificate and key of the internal CA of nanocld
+pub struct InternalCa {
+  pub cert: X509,
+  pub key: PKey<Private>,
+}
+
+/// A certificate issued by the internal CA in PEM format
+pub struct IssuedCert {
+  pub cert: String,
+  pub key: String,
+  pub ca: String,
+  pub not_after: chrono::NaiveDateTime,
+}
+
+fn map_err(err: openssl::error::ErrorStack) -> IoError {
+  IoError::other("Internal CA", err.to_string().as_str())
+}
+
+/// Get the path of the certificate and the key of the CA in the `state_dir`
+fn get_paths(state_dir: &str) -> (String, String) {
+  (
+    format!("{state_dir}/ca/ca.crt"),
+    format!("{state_dir}/ca/ca.key"),
+  )
+}
+
+fn gen_key() -> Result<PKey<Private>, openssl::error::ErrorStack> {
+  let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
+  PKey::from_ec_key(EcKey::generate(&group)?)
+}
+
+fn gen_serial() -> Result<Asn1Integer, openssl::error::ErrorStack> {
+  let mut serial = BigNum::new()?;
+  serial.rand(128, MsbOption::MAYBE_ZERO, false)?;
+  serial.to_asn1_integer()
+}
+
+fn gen_name(common_name: &str) -> Result<X509Name, openssl::error::ErrorStack> {
+  let mut name = X509NameBuilder::new()?;
+  name.append_entry_by_nid(Nid::ORGANIZATIONNAME, "nanocl")?;
+  name.append_entry_by_nid(Nid::COMMONNAME, common_name)?;
+  Ok(name.build())
+}
+
+/// Create a self signed certificate for the CA
+fn gen_ca() -> Result<InternalCa, openssl::error::ErrorStack> {
+  let key = gen_key()?;
+  let name = gen_name("nanocl internal CA")?;
+  let mut builder = X509Builder::new()?;
+  builder.set_version(2)?;
+  builder.set_serial_number(gen_serial()?.as_ref())?;
+  builder.set_subject_name(&name)?;
+  builder.set_issuer_name(&name)?;
+  builder.set_pubkey(&key)?;
+  builder.set_not_before(Asn1Time::days_from_now(0)?.as_ref())?;
+  builder
+    .set_not_after(Asn1Time::days_from_now(vars::CA_LIFETIME)?.as_ref())?;
+  builder.append_extension(BasicConstraints::new().critical().ca().build()?)?;
+  builder.append_extension(
+    KeyUsage::new()
+      .critical()
+      .key_cert_sign()
+      .crl_sign()
+      .build()?,
+  )?;
+  let key_id =
+    SubjectKeyIdentifier::new().build(&builder.x509v3_context(None, None))?;
+  builder.append_extension(key_id)?;
+  builder.sign(&key, MessageDigest::sha256())?;
+  Ok(InternalCa {
+    cert: builder.build(),
+    key,
+  })
+}
+
+/// Serialize the creation of the internal CA between the tasks of the daemon
+static CA_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// Read the internal CA stored in the `state_dir`
+async fn read(cert_path: &str, key_path: &str) -> IoResult<InternalCa> {
+  let cert = tokio::fs::read(cert_path)
+    .await
+    .map_err(|err| err.map_err_context(|| cert_path))?;
+  let key = tokio::fs::read(key_path)
+    .await
+    .map_err(|err| err.map_err_context(|| key_path))?;
+  Ok(InternalCa {
+    cert: X509::from_pem(&cert).map_err(map_err)?,
+    key: PKey::private_key_from_pem(&key).map_err(map_err)?,
+  })
+}
+
+/// Load the internal CA from the `state_dir` and create it when missing.
+/// The key is created with `create_new` so a CA is never overwritten.
+pub async fn load(state_dir: &str) -> IoResult<InternalCa> {
+  let (cert_path, key_path) = get_paths(state_dir);
+  let _lock = CA_LOCK.lock().await;
+  if Path::new(&key_path).exists() {
+    return read(&cert_path, &key_path).await;
+  }
+  log::info!("ca::load: creating internal CA in {state_dir}/ca");
+  let ca = gen_ca().map_err(map_err)?;
+  tokio::fs::create_dir_all(format!("{state_dir}/ca"))
+    .await
+    .map_err(|err| err.map_err_context(|| state_dir))?;
+  let key = ca.key.private_key_to_pem_pkcs8().map_err(map_err)?;
+  let mut file = match tokio::fs::OpenOptions::new()
+    .write(true)
+    .create_new(true)
+    .mode(0o600)
+    .open(&key_path)
+    .await
+  {
+    Ok(file) => file,
+    // Created by another process in the meantime
+    Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => {
+      return read(&cert_path, &key_path).await;
+    }
+    Err(err) => return Err(*err.map_err_context(|| &key_path)),
+  };
+  tokio::io::AsyncWriteExt::write_all(&mut file, &key)
+    .await
+    .map_err(|err| err.map_err_context(|| &key_path))?;
+  tokio::fs::write(&cert_path, ca.cert.to_pem().map_err(map_err)?)
+    .await
+    .map_err(|err| err.map_err_context(|| &cert_path))?;
+  Ok(ca)
+}
+
+/// Issue a certificate valid for client and server authentication
+/// for the given subject alternative names
+pub fn issue(
+  ca: &InternalCa,
+  sans: &[String],
+  lifetime: u32,
+) -> IoResult<IssuedCert> {
+  let Some(common_name) = sans.first() else {
+    return Err(IoError::invalid_data(
+      "Internal CA",
+      "At least one subject alternative name is required",
+    ));
+  };
+  let mut alt_names = SubjectAlternativeName::new();
+  for san in sans {
+    if san.is_empty() {
+      return Err(IoError::invalid_data(
+        "Internal CA",
+        "Subject alternative name cannot be empty",
+      ));
+    }
+    if san.parse::<IpAddr>().is_ok() {
+      alt_names.ip(san);
+    } else {
+      alt_names.dns(san);
+    }
+  }
+  let issue =
+    || -> Result<(X509, PKey<Private>), openssl::error::ErrorStack> {
+      let key = gen_key()?;
+      let mut builder = X509Builder::new()?;
+      builder.set_version(2)?;
+      builder.set_serial_number(gen_serial()?.as_ref())?;
+      builder.set_subject_name(gen_name(common_name)?.as_ref())?;
+      builder.set_issuer_name(ca.cert.subject_name())?;
+      builder.set_pubkey(&key)?;
+      builder.set_not_before(Asn1Time::days_from_now(0)?.as_ref())?;
+      builder.set_not_after(Asn1Time::days_from_now(lifetime)?.as_ref())?;
+      builder.append_extension(BasicConstraints::new().critical().build()?)?;
+      builder.append_extension(
+        KeyUsage::new()
+          .critical()
+          .digital_signature()
+          .key_encipherment()
+          .build()?,
+      )?;
+      builder.append_extension(
+        ExtendedKeyUsage::new()
+          .server_auth()
+          .client_auth()
+          .build()?,
+      )?;
+      let context = builder.x509v3_context(Some(&ca.cert), None);
+      let alt_names = alt_names.build(&context)?;
+      let authority_key_id = AuthorityKeyIdentifier::new()
+        .keyid(false)
+        .build(&builder.x509v3_context(Some(&ca.cert), None))?;
+      builder.append_extension(alt_names)?;
+      builder.append_extension(authority_key_id)?;
+      builder.sign(&ca.key, MessageDigest::sha256())?;
+      Ok((builder.build(), key))
+    };
+  let (cert, key) = issue().map_err(map_err)?;
+  let to_string = |pem: Vec<u8>| {
+    String::from_utf8(pem)
+      .map_err(|err| IoError::invalid_data("Internal CA", &err.to_string()))
+  };
+  Ok(IssuedCert {
+    cert: to_string(cert.to_pem().map_err(map_err)?)?,
+    key: to_string(key.private_key_to_pem_pkcs8().map_err(map_err)?)?,
+    ca: to_string(ca.cert.to_pem().map_err(map_err)?)?,
+    not_after: (chrono::Utc::now()
+      + chrono::Duration::days(i64::from(lifetime)))
+    .naive_utc(),
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[ntex::test]
+  async fn issue_cert() {
+    let state_dir = std::env::temp_dir()
+      .join(format!("nanocl-ca-{}", uuid::Uuid::new_v4()))
+      .display()
+      .to_string();
+    let ca = load(&state_dir).await.unwrap();
+    let sans = vec!["api.global.c".to_owned(), "10.0.0.1".to_owned()];
+    let issued = issue(&ca, &sans, 30).unwrap();
+    let cert = X509::from_pem(issued.cert.as_bytes()).unwrap();
+    assert!(cert.verify(&ca.key).unwrap());
+    let names = cert.subject_alt_names().unwrap();
+    assert_eq!(names.len(), 2);
+    assert_eq!(names.get(0).unwrap().dnsname(), Some("api.global.c"));
+    assert_eq!(names.get(1).unwrap().ipaddress(), Some(&[10, 0, 0, 1][..]));
+    assert!(issue(&ca, &[], 30).is_err());
+    // The CA is loaded back from the state dir
+    let loaded = load(&state_dir).await.unwrap();
+    assert_eq!(loaded.cert.to_pem().unwrap(), ca.cert.to_pem().unwrap());
+    std::fs::remove_dir_all(&state_dir).unwrap();
+  }
+}
@@ -0,0 +1,283 @@
+use tokio::{fs, process::Command};
+
+use nanocl_error::io::{FromIo, IoError, IoResult};
+use nanocl_stubs::vm::Vm;
+
+/// Default user created inside the vm when none is specified
+const DEFAULT_USER: &str = "cloud";
+
+/// Convert a value to a yaml document
+fn to_yaml(value: &serde_json::Value, context: &str) -> IoResult<String> {
+  let yaml = serde_yaml::to_string(value)
+    .map_err(|err| err.map_err_context(|| context))?;
+  Ok(yaml)
+}
+
+/// Generate the cloud-config user-data of a vm.
+/// When a raw user-data is set in the spec it's returned as is,
+/// otherwise it's generated from the user, password, ssh key
+/// and cloud-init options of the spec.
+pub fn gen_user_data(vm: &Vm) -> IoResult<String> {
+  let cloud_init = vm.spec.cloud_init.clone().unwrap_or_default();
+  if let Some(user_data) = cloud_init.user_data {
+    return Ok(user_data);
+  }
+  let mut ssh_keys = Vec::new();
+  if let Some(ssh_key) = &vm.spec.ssh_key {
+    ssh_keys.push(ssh_key.clone());
+  }
+  if let Some(keys) = cloud_init.ssh_authorized_keys {
+    ssh_keys.extend(keys);
+  }
+  let mut default_user = serde_json::json!({
+    "name": vm.spec.user.clone().unwrap_or(DEFAULT_USER.to_owned()),
+    "sudo": "ALL=(ALL) NOPASSWD:ALL",
+    "shell": "/bin/bash",
+    "lock_passwd": vm.spec.password.is_none(),
+  });
+  if let Some(password) = &vm.spec.password {
+    default_user["plain_text_passwd"] = password.clone().into();
+  }
+  if !ssh_keys.is_empty() {
+    default_user["ssh_authorized_keys"] = ssh_keys.into();
+  }
+  let mut users = vec![default_user];
+  for user in cloud_init.users.unwrap_or_default() {
+    let mut item = serde_json::json!({
+      "name": user.name,
+      "shell": user.shell.unwrap_or("/bin/bash".to_owned()),
+      "lock_passwd": user.password.is_none(),
+    });
+    if let Some(password) = user.password {
+      item["plain_text_passwd"] = password.into();
+    }
+    if let Some(sudo) = user.sudo {
+      item["sudo"] = sudo.into();
+    }
+    if let Some(groups) = user.groups {
+      item["groups"] = groups.join(",").into();
+    }
+    if let Some(keys) = user.ssh_authorized_keys {
+      item["ssh_authorized_keys"] = keys.into();
+    }
+    users.push(item);
+  }
+  let mut config = serde_json::json!({
+    "users": users,
+    "ssh_pwauth": vm.spec.password.is_some(),
+  });
+  if let Some(packages) = cloud_init.packages {
+    config["package_update"] = true.into();
+    config["packages"] = packages.into();
+  }
+  if let Some(runcmd) = cloud_init.runcmd {
+    config["runcmd"] = runcmd.into();
+  }
+  if let Some(write_files) = cloud_init.write_files {
+    let write_files = write_files
+      .into_iter()
+      .map(|file| {
+        let mut item = serde_json::json!({
+          "path": file.path,
+          "content": file.content,
+        });
+        if let Some(encoding) = file.encoding {
+          item["encoding"] = encoding.into();
+        }
+        if let Some(owner) = file.owner {
+          item["owner"] = owner.into();
+        }
+        if let Some(permissions) = file.permissions {
+          item["permissions"] = permissions.into();
+        }
+        item
+      })
+      .collect::<Vec<_>>();
+    config["write_files"] = write_files.into();
+  }
+  let yaml = to_yaml(&config, "cloud-init user-data")?;
+  Ok(format!("#cloud-config\n{yaml}"))
+}
+
+/// Generate the meta-data of a vm.
+/// The instance id is the vm key so cloud-init only run once per vm.
+pub fn gen_meta_data(vm: &Vm) -> IoResult<String> {
+  let hostname = vm.spec.hostname.clone().unwrap_or(vm.spec.name.clone());
+  let meta_data = serde_json::json!({
+    "instance-id": vm.spec.vm_key,
+    "local-hostname": hostname,
+  });
+  to_yaml(&meta_data, "cloud-init meta-data")
+}
+
+/// Generate the network-config of a vm.
+/// When not set in the spec, the network interface of the vm
+/// is configured with dhcp and the dns servers of the host config.
+pub fn gen_network_config(vm: &Vm) -> IoResult<String> {
+  if let Some(network_config) = vm
+    .spec
+    .cloud_init
+    .as_ref()
+    .and_then(|cloud_init| cloud_init.network_config.clone())
+  {
+    return to_yaml(&network_config, "cloud-init network-config");
+  }
+  let net_iface = vm
+    .spec
+    .host_config
+    .net_iface
+    .clone()
+    .unwrap_or("ens3".into());
+  let mut iface = serde_json::json!({
+    "dhcp4": true,
+  });
+  if let Some(mac_address) = &vm.spec.mac_address {
+    iface["match"] = serde_json::json!({ "macaddress": mac_address });
+    iface["set-name"] = net_iface.as_str().into();
+  }
+  if let Some(dns) = &vm.spec.host_config.dns {
+    iface["nameservers"] = serde_json::json!({ "addresses": dns });
+  }
+  let mut ethernets = serde_json::Map::new();
+  ethernets.insert(net_iface, iface);
+  let network_config = serde_json::json!({
+    "version": 2,
+    "ethernets": ethernets,
+  });
+  to_yaml(&network_config, "cloud-init network-config")
+}
+
+/// Create the NoCloud seed image of a vm inside the given directory.
+/// The image is an iso9660 filesystem labeled `cidata`
+/// containing the user-data, meta-data and network-config files.
+/// It return the path of the created image.
+pub async fn create_seed(vm: &Vm, dir: &str) -> IoResult<String> {
+  let seed_dir = format!("{dir}/cloud-init");
+  fs::create_dir_all(&seed_dir)
+    .await
+    .map_err(|err| err.map_err_context(|| &seed_dir))?;
+  let files = [
+    ("user-data", gen_user_data(vm)?),
+    ("meta-data", gen_meta_data(vm)?),
+    ("network-config", gen_network_config(vm)?),
+  ];
+  for (name, content) in &files {
+    let path = format!("{seed_dir}/{name}");
+    fs::write(&path, content)
+      .await
+      .map_err(|err| err.map_err_context(|| &path))?;
+  }
+  let seed_path = format!("{dir}/seed.iso");
+  let output = Command::new("genisoimage")
+    .current_dir(&seed_dir)
+    .args([
+      "-output",
+      &seed_path,
+      "-volid",
+      "cidata",
+      "-joliet",
+      "-rock",
+      "user-data",
+      "meta-data",
+      "network-config",
+    ])
+    .output()
+    .await
+    .map_err(|err| err.map_err_context(|| "genisoimage"))?;
+  if !output.status.success() {
+    return Err(IoError::interrupted(
+      "Cloud-init seed",
+      &format!(
+        "Unable to create seed image for {}: {}",
+        vm.spec.vm_key,
+        String::from_utf8_lossy(&output.stderr)
+      ),
+    ));
+  }
+  Ok(seed_path)
+}
+
+#[cfg(test)]
+mod tests {
+  use nanocl_stubs::{
+    system::ObjPsStatus,
+    vm::Vm,
+    vm_spec::{VmCloudInit, VmCloudInitFile, VmSpec},
+  };
+
+  use super::*;
+
+  fn gen_vm() -> Vm {
+    Vm {
+      namespace_name: "global".to_owned(),
+      created_at: chrono::Utc::now().naive_utc(),
+      status: ObjPsStatus::default(),
+      spec: VmSpec {
+        name: "test".to_owned(),
+        vm_key: "test.global".to_owned(),
+        user: Some("admin".to_owned()),
+        ssh_key: Some("ssh-ed25519 AAAA test".to_owned()),
+        ..Default::default()
+      },
+    }
+  }
+
+  #[test]
+  fn user_data() {
+    let mut vm = gen_vm();
+    vm.spec.cloud_init = Some(VmCloudInit {
+      packages: Some(vec!["curl".to_owned()]),
+      runcmd: Some(vec!["echo hello".to_owned()]),
+      write_files: Some(vec![VmCloudInitFile {
+        path: "/etc/test".to_owned(),
+        content: "test".to_owned(),
+        permissions: Some("0600".to_owned()),
+        ..Default::default()
+      }]),
+      ..Default::default()
+    });
+    let user_data = gen_user_data(&vm).unwrap();
+    assert!(user_data.starts_with("#cloud-config\n"));
+    let config = serde_yaml::from_str::<serde_json::Value>(&user_data).unwrap();
+    assert_eq!(config["users"][0]["name"], "admin");
+    assert_eq!(config["users"][0]["lock_passwd"], true);
+    assert_eq!(
+      config["users"][0]["ssh_authorized_keys"][0],
+      "ssh-ed25519 AAAA test"
+    );
+    assert_eq!(config["packages"][0], "curl");
+    assert_eq!(config["runcmd"][0], "echo hello");
+    assert_eq!(config["write_files"][0]["permissions"], "0600");
+  }
+
+  #[test]
+  fn raw_user_data() {
+    let mut vm = gen_vm();
+    let raw = "#cloud-config\nruncmd:\n  - echo raw\n".to_owned();
+    vm.spec.cloud_init = Some(VmCloudInit {
+      user_data: Some(raw.clone()),
+      packages: Some(vec!["curl".to_owned()]),
+      ..Default::default()
+    });
+    assert_eq!(gen_user_data(&vm).unwrap(), raw);
+  }
+
+  #[test]
+  fn network_config() {
+    let mut vm = gen_vm();
+    vm.spec.host_config.dns = Some(vec!["1.1.1.1".to_owned()]);
+    let network_config = gen_network_config(&vm).unwrap();
+    let config =
+      serde_yaml::from_str::<serde_json::Value>(&network_config).unwrap();
+    assert_eq!(config["version"], 2);
+    assert_eq!(config["ethernets"]["ens3"]["dhcp4"], true);
+    assert_eq!(
+      config["ethernets"]["ens3"]["nameservers"]["addresses"][0],
+      "1.1.1.1"
+    );
+    let meta_data = gen_meta_data(&vm).unwrap();
+    let meta = serde_yaml::from_str::<serde_json::Value>(&meta_data).unwrap();
+    assert_eq!(meta["instance-id"], "test.global");
+    assert_eq!(meta["local-hostname"], "test");
+  }
+}
@@ -20,7 +20,7 @@ use nanocl_stubs::{
 use crate::{
   models::{CargoDb, ObjPsStatusDb, ProcessDb, SystemState},
   repositories::generic::*,
-  utils,
+  utils, vars,
 };
 
 fn create_cargo_env(
@@ -54,7 +54,7 @@ async fn create_init_container(
     .unwrap_or(cargo.spec.container.image.clone().unwrap());
   let host_config = init_container.host_config.unwrap_or_default();
   init_container.image = Some(image.clone());
-  let secret_dir = utils::secret::create_tls_secrets(
+  let secret_dir = utils::secret::create_secret_files(
     &cargo.spec.cargo_key,
     &ProcessKind::Cargo,
     &cargo.spec.secrets,
@@ -81,9 +81,13 @@ async fn create_init_container(
   .await?;
   let env_secrets =
     utils::secret::load_env_secrets(&cargo.spec.secrets, state).await?;
+  let mut labels = init_container.labels.to_owned().unwrap_or_default();
+  labels.insert(
+    vars::SECRET_ENV_LABEL.to_owned(),
+    utils::secret::gen_env_label(&env_secrets),
+  );
   let env = create_cargo_env(cargo, env_secrets, 0, state);
   init_container.env = Some(env);
-  let mut labels = init_container.labels.to_owned().unwrap_or_default();
   labels.insert("io.nanocl.c".to_owned(), cargo.spec.cargo_key.to_owned());
   labels.insert("io.nanocl.n".to_owned(), cargo.namespace_name.to_owned());
   labels.insert("io.nanocl.init-c".to_owned(), "true".to_owned());
@@ -171,7 +175,7 @@ pub async fn create(
   .await?;
   let env_secrets =
     utils::secret::load_env_secrets(&cargo.spec.secrets, state).await?;
-  let secret_dir = utils::secret::create_tls_secrets(
+  let secret_dir = utils::secret::create_secret_files(
     &cargo.spec.cargo_key,
     &ProcessKind::Cargo,
     &cargo.spec.secrets,
@@ -205,6 +209,10 @@ pub async fn create(
         labels
           .insert("io.nanocl.n".to_owned(), cargo.namespace_name.to_owned());
         labels.insert("io.nanocl.not-init-c".to_owned(), "true".to_owned());
+        labels.insert(
+          vars::SECRET_ENV_LABEL.to_owned(),
+          utils::secret::gen_env_label(&env_secrets),
+        );
         labels.insert(
           "com.docker.compose.project".to_owned(),
           format!("nanocl_{}", cargo.namespace_name),
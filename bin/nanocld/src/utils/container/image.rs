@@ -8,11 +8,7 @@ use nanocl_stubs::{
   },
 };
 
-use crate::{
-  models::{SecretDb, SystemState},
-  repositories::generic::*,
-  vars,
-};
+use crate::{models::SystemState, utils, vars};
 
 /// Get the docker credentials to authenticate with the registry from the secret
 ///
@@ -22,10 +18,7 @@ async fn get_credentials(
 ) -> IoResult<Option<DockerCredentials>> {
   Ok(match secret {
     Some(secret) => {
-      let secret = SecretDb::read_by_pk(&secret, &state.inner.pool).await?;
-      serde_json::from_value::<DockerCredentials>(secret.data)
-        .map(Some)
-        .map_err(|err| err.map_err_context(|| "GetCredentials"))?
+      Some(utils::secret::load_registry_credentials(&secret, state).await?)
     }
     None => None,
   })
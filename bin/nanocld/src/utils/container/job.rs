@@ -14,7 +14,7 @@ use nanocl_stubs::{
 use crate::{
   models::{JobDb, ObjPsStatusDb, ProcessDb, SystemState},
   repositories::generic::*,
-  utils,
+  utils, vars,
 };
 
 /// Create process (container) for a job
@@ -28,16 +28,20 @@ async fn create_instance(
   let mut container = container.clone();
   let mut labels = container.labels.unwrap_or_default();
   labels.insert("io.nanocl.j".to_owned(), job.name.to_owned());
-  container.labels = Some(labels);
   let env_secrets =
     utils::secret::load_env_secrets(&job.secrets, state).await?;
-  let secret_dir = utils::secret::create_tls_secrets(
+  let secret_dir = utils::secret::create_secret_files(
     &job.name,
     &ProcessKind::Job,
     &job.secrets,
     state,
   )
   .await?;
+  labels.insert(
+    vars::SECRET_ENV_LABEL.to_owned(),
+    utils::secret::gen_env_label(&env_secrets),
+  );
+  container.labels = Some(labels);
   container.env = Some(
     container
       .env
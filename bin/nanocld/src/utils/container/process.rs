@@ -15,6 +15,7 @@ use nanocl_stubs::{
 use crate::{
   models::{ObjPsStatusDb, ProcessDb, SystemState},
   repositories::generic::*,
+  utils,
 };
 
 /// Create a process (container) based on the kind and the item
@@ -48,6 +49,7 @@ pub async fn create(
     .inspect_container(&res.id, None::<InspectContainerOptions>)
     .await
     .map_err(|err| err.map_err_context(|| "CreateProcess"))?;
+  let inspect = utils::secret::redact_container(inspect);
   let created_at = inspect.created.clone().unwrap_or_default();
   let new_instance = ProcessPartial {
     key: res.id,
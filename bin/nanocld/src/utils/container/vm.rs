@@ -1,13 +1,14 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Duration};
 
 use bollard_next::secret::{DeviceMapping, HostConfig};
 
-use nanocl_error::io::IoResult;
+use nanocl_error::io::{IoError, IoResult};
 use nanocl_stubs::{
   generic::ImagePullPolicy,
   process::{Process, ProcessKind},
-  system::NativeEventAction,
+  system::{EventActorKind, NativeEventAction},
   vm::Vm,
+  vm_spec::VmDiskBus,
 };
 
 use crate::{
@@ -16,6 +17,63 @@ use crate::{
   utils, vars,
 };
 
+/// Get the state directory of a VM where its runtime files are stored
+///
+pub fn get_state_dir(key: &str, state: &SystemState) -> String {
+  format!("{}/vms/{key}", state.inner.config.state_dir)
+}
+
+/// Generate the qemu arguments to attach the os disk and the data disks
+///
+async fn gen_disk_args(
+  vm: &Vm,
+  image: &VmImageDb,
+  state: &SystemState,
+) -> IoResult<Vec<String>> {
+  let mut args: Vec<String> = vec![
+    "-drive".into(),
+    format!(
+      "file={},format={},if=none,id=disk0",
+      image.path, image.format
+    ),
+    "-device".into(),
+    "ide-hd,drive=disk0,bootindex=1".into(),
+  ];
+  let disks = vm.spec.disks.clone().unwrap_or_default();
+  if disks
+    .iter()
+    .any(|disk| disk.bus.as_ref() == Some(&VmDiskBus::Scsi))
+  {
+    args.push("-device".into());
+    args.push("virtio-scsi-pci,id=scsi0".into());
+  }
+  for (index, disk) in disks.iter().enumerate() {
+    let id = format!("disk{}", index + 1);
+    let name = disk.image.clone().unwrap_or_default();
+    let disk_image = VmImageDb::read_by_pk(&name, &state.inner.pool).await?;
+    let mut drive = format!(
+      "file={},format={},if=none,id={id}",
+      disk_image.path, disk_image.format
+    );
+    if disk.read_only.unwrap_or_default() {
+      drive.push_str(",readonly=on");
+    }
+    let mut device = match disk.bus.clone().unwrap_or_default() {
+      VmDiskBus::Virtio => format!("virtio-blk-pci,drive={id}"),
+      VmDiskBus::Scsi => format!("scsi-hd,drive={id},bus=scsi0.0"),
+      VmDiskBus::Ide => format!("ide-hd,drive={id}"),
+    };
+    if let Some(boot_order) = disk.boot_order {
+      device.push_str(&format!(",bootindex={boot_order}"));
+    }
+    args.push("-drive".into());
+    args.push(drive);
+    args.push("-device".into());
+    args.push(device);
+  }
+  Ok(args)
+}
+
 /// Create a VM instance
 ///
 pub async fn create_instance(
@@ -26,10 +84,36 @@ pub async fn create_instance(
 ) -> IoResult<Process> {
   let mut labels: HashMap<String, String> = HashMap::new();
   let img_path = format!("{}/vms/images", state.inner.config.state_dir);
+  let vm_dir = get_state_dir(&vm.spec.vm_key, state);
+  let seed_path = utils::cloud_init::create_seed(vm, &vm_dir).await?;
   labels.insert("io.nanocl.v".to_owned(), vm.spec.vm_key.clone());
   labels.insert("io.nanocl.n".to_owned(), vm.namespace_name.clone());
-  let mut args: Vec<String> =
-    vec!["-hda".into(), image.path.clone(), "--nographic".into()];
+  let mut args = gen_disk_args(vm, image, state).await?;
+  args.push("-drive".into());
+  args.push(format!("file={seed_path},format=raw,if=virtio,readonly=on"));
+  args.push("-qmp".into());
+  args.push(format!(
+    "unix:{},server=on,wait=off",
+    utils::qmp::get_socket_path(&vm_dir)
+  ));
+  // The balloon device report the memory usage of the guest
+  args.push("-device".into());
+  args.push(format!(
+    "virtio-balloon-pci,id={}",
+    utils::vm_stats::BALLOON_ID
+  ));
+  // The serial console is muxed with the monitor on stdio
+  // and also written into a log file to keep its history
+  args.push("-chardev".into());
+  args.push(format!(
+    "stdio,id=console0,mux=on,logfile={},logappend=on",
+    utils::vm_console::get_log_path(&vm_dir)
+  ));
+  args.push("-serial".into());
+  args.push("chardev:console0".into());
+  args.push("-mon".into());
+  args.push("chardev=console0,mode=readline".into());
+  args.push("--nographic".into());
   let host_config = vm.spec.host_config.clone();
   let kvm = host_config.kvm.unwrap_or_default();
   let mut devices = vec![DeviceMapping {
@@ -116,7 +200,10 @@ pub async fn create_instance(
           .clone()
           .unwrap_or("nanoclbr0".to_owned()),
       ),
-      binds: Some(vec![format!("{img_path}:{img_path}")]),
+      binds: Some(vec![
+        format!("{img_path}:{img_path}"),
+        format!("{vm_dir}:{vm_dir}"),
+      ]),
       devices: Some(devices),
       cap_add: Some(vec!["NET_ADMIN".into()]),
       ..Default::default()
@@ -152,6 +239,176 @@ pub async fn start(key: &str, state: &SystemState) -> IoResult<()> {
   Ok(())
 }
 
+/// Check if one of the instances of the VM is still running
+///
+async fn is_running(key: &str, state: &SystemState) -> IoResult<bool> {
+  let processes =
+    ProcessDb::read_by_kind_key(key, None, &state.inner.pool).await?;
+  for process in processes {
+    let inspect = state
+      .inner
+      .docker_api
+      .inspect_container(&process.key, None)
+      .await;
+    if let Ok(inspect) = inspect {
+      if inspect.state.and_then(|s| s.running).unwrap_or_default() {
+        return Ok(true);
+      }
+    }
+  }
+  Ok(false)
+}
+
+/// Ask the guest to power off using ACPI through the QMP socket
+/// and wait for the VM to stop until the shutdown timeout is reached.
+/// It return false if the guest didn't power off in time.
+///
+async fn power_off(vm: &Vm, state: &SystemState) -> IoResult<bool> {
+  let vm_dir = get_state_dir(&vm.spec.vm_key, state);
+  let socket = utils::qmp::get_socket_path(&vm_dir);
+  utils::qmp::execute(&socket, "system_powerdown", None).await?;
+  let timeout = vm
+    .spec
+    .host_config
+    .shutdown_timeout
+    .unwrap_or(vars::VM_SHUTDOWN_TIMEOUT);
+  let deadline = std::time::Instant::now() + Duration::from_secs(timeout);
+  while std::time::Instant::now() < deadline {
+    if !is_running(&vm.spec.vm_key, state).await? {
+      return Ok(true);
+    }
+    ntex::time::sleep(Duration::from_secs(1)).await;
+  }
+  Ok(false)
+}
+
+/// Power off the guest before its process is stopped or deleted,
+/// the process is expected to be killed after when it's still running
+///
+async fn shutdown(vm: &Vm, state: &SystemState) {
+  let key = &vm.spec.vm_key;
+  match power_off(vm, state).await {
+    Ok(true) => log::debug!("vm {key} powered off"),
+    Ok(false) => log::warn!("vm {key} didn't power off in time, killing it"),
+    Err(err) => log::warn!("unable to power off vm {key}: {err}"),
+  }
+}
+
+/// Stop VM instance gracefully.
+/// The guest is powered off using ACPI and is killed only
+/// when it didn't stop before the shutdown timeout.
+///
+pub async fn stop(key: &str, state: &SystemState) -> IoResult<()> {
+  let vm = VmDb::transform_read_by_pk(&key, &state.inner.pool).await?;
+  shutdown(&vm, state).await;
+  super::process::stop_instances(key, &ProcessKind::Vm, state).await?;
+  Ok(())
+}
+
+/// Ask the guest to power off with qemu told to pause instead of exiting,
+/// then reset and resume the machine.
+/// The guest is reset anyway when it didn't power off before the timeout.
+///
+async fn power_cycle(
+  client: &mut utils::qmp::QmpClient,
+  vm: &Vm,
+) -> IoResult<()> {
+  let shutdown = serde_json::json!({ "shutdown": "pause" });
+  client.execute("set-action", Some(shutdown)).await?;
+  client.execute("system_powerdown", None).await?;
+  let timeout = vm
+    .spec
+    .host_config
+    .shutdown_timeout
+    .unwrap_or(vars::VM_SHUTDOWN_TIMEOUT);
+  let deadline = std::time::Instant::now() + Duration::from_secs(timeout);
+  loop {
+    let status = client.execute("query-status", None).await?;
+    if status["running"] == false {
+      break;
+    }
+    if std::time::Instant::now() >= deadline {
+      log::warn!(
+        "vm {} didn't power off in time, resetting it",
+        vm.spec.vm_key
+      );
+      break;
+    }
+    ntex::time::sleep(Duration::from_secs(1)).await;
+  }
+  client.execute("system_reset", None).await?;
+  client.execute("cont", None).await?;
+  Ok(())
+}
+
+/// Reboot VM instance gracefully through the QMP socket
+/// without stopping its process
+///
+pub async fn reboot(key: &str, state: &SystemState) -> IoResult<()> {
+  let vm = VmDb::transform_read_by_pk(&key, &state.inner.pool).await?;
+  let vm_dir = get_state_dir(key, state);
+  let socket = utils::qmp::get_socket_path(&vm_dir);
+  let mut client = utils::qmp::QmpClient::connect(&socket).await?;
+  let res = power_cycle(&mut client, &vm).await;
+  // Restore the default action so a guest power off stop the vm
+  let shutdown = serde_json::json!({ "shutdown": "poweroff" });
+  client.execute("set-action", Some(shutdown)).await?;
+  res?;
+  super::generic::emit(
+    key,
+    &ProcessKind::Vm,
+    NativeEventAction::Restart,
+    state,
+  )
+  .await?;
+  Ok(())
+}
+
+/// Reboot VM instance in background using the task manager
+/// since the guest can take up to its shutdown timeout to power off
+///
+pub async fn spawn_reboot(key: &str, state: &SystemState) -> IoResult<()> {
+  VmDb::read_by_pk(key, &state.inner.pool).await?;
+  let task_key = format!("{}@{key}", EventActorKind::Vm);
+  state.inner.task_manager.wait_task(&task_key).await;
+  let key = key.to_owned();
+  let state_ptr = state.clone();
+  let task = Box::pin(async move {
+    reboot(&key, &state_ptr).await?;
+    Ok::<_, IoError>(())
+  });
+  state
+    .inner
+    .task_manager
+    .add_task(
+      &task_key,
+      NativeEventAction::Restart,
+      task,
+      |err| async move {
+        log::error!("vm::spawn_reboot: {err}");
+        Ok(())
+      },
+    )
+    .await;
+  Ok(())
+}
+
+/// Reset VM instance like pressing the reset button of the machine
+///
+pub async fn reset(key: &str, state: &SystemState) -> IoResult<()> {
+  let vm_dir = get_state_dir(key, state);
+  let socket = utils::qmp::get_socket_path(&vm_dir);
+  utils::qmp::execute(&socket, "system_reset", None).await?;
+  super::generic::emit(
+    key,
+    &ProcessKind::Vm,
+    NativeEventAction::Restart,
+    state,
+  )
+  .await?;
+  Ok(())
+}
+
 /// Delete VM instance and the VM itself from the database
 ///
 pub async fn delete(key: &str, state: &SystemState) -> IoResult<()> {
@@ -166,8 +423,20 @@ pub async fn delete(key: &str, state: &SystemState) -> IoResult<()> {
     state,
   )
   .await?;
+  utils::vm_snapshot::delete_by_vm(&vm.spec.vm_key, state).await?;
   utils::vm_image::delete_by_pk(&vm.spec.disk.image, state).await?;
+  let vm_dir = get_state_dir(&vm.spec.vm_key, state);
+  if let Err(err) = tokio::fs::remove_dir_all(&vm_dir).await {
+    log::warn!("Error while deleting the vm directory {vm_dir}: {err}");
+  }
   VmDb::clear_by_pk(&vm.spec.vm_key, &state.inner.pool).await?;
+  // Data disks survive the vm, they're reused when it's created again
+  utils::vm_image::record_orphaned_disks(
+    &vm,
+    &[],
+    NativeEventAction::Destroy,
+    state,
+  );
   state
     .emit_normal_native_action_sync(&vm, NativeEventAction::Destroy)
     .await;
@@ -181,6 +450,9 @@ pub async fn update(key: &str, state: &SystemState) -> IoResult<()> {
   let container_name = format!("{}.v", &vm.spec.vm_key);
   let image =
     VmImageDb::read_by_pk(&vm.spec.disk.image, &state.inner.pool).await?;
+  if is_running(key, state).await? {
+    shutdown(&vm, state).await;
+  }
   super::process::delete_instances(&[container_name], state).await?;
   create_instance(&vm, &image, false, state).await?;
   super::process::start_instances(key, &ProcessKind::Vm, state).await?;
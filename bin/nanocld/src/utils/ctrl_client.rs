@@ -1,10 +1,22 @@
 use ntex::http::client::{ClientResponse, Connector};
 use ntex::http::{Client, StatusCode};
 use ntex::rt;
+use openssl::{
+  error::ErrorStack,
+  pkey::PKey,
+  ssl::{SslConnector, SslMethod, SslVerifyMode},
+  x509::X509,
+};
 
 use nanocl_error::http::HttpError;
 use nanocl_error::http_client::HttpClientError;
-use nanocl_error::io::FromIo;
+use nanocl_error::io::{FromIo, IoError, IoResult};
+use nanocl_stubs::{
+  proxy::ProxySslConfig,
+  resource_kind::{ResourceKindConvert, ResourceKindSpec},
+};
+
+use crate::{models::SystemState, utils, vars};
 
 /// Controller client
 pub struct CtrlClient {
@@ -17,9 +29,17 @@ pub struct CtrlClient {
 }
 
 impl CtrlClient {
-  /// Create a new controller client
-  pub fn new(name: &str, url: &str) -> Self {
+  /// Create a new controller client for an url starting with
+  /// `unix://`, `http://` or `https://`.
+  /// The ssl connector is used to call an https controller.
+  pub fn new(
+    name: &str,
+    url: &str,
+    timeout: u32,
+    ssl: Option<SslConnector>,
+  ) -> IoResult<Self> {
     log::debug!("CtrlClient::new {name}: {url}");
+    let timeout = ntex::time::Millis::from_secs(timeout);
     let (client, url) = match url {
       url if url.starts_with("unix://") => {
         let url = url.to_owned();
@@ -30,24 +50,90 @@ impl CtrlClient {
                 let path = url.trim_start_matches("unix://").to_owned();
                 async move { Ok(rt::unix_connect(path).await?) }
               }))
-              .timeout(ntex::time::Millis::from_secs(50))
+              .timeout(timeout)
               .finish(),
           )
-          .timeout(ntex::time::Millis::from_secs(50))
+          .timeout(timeout)
           .finish();
         (client, "http://localhost")
       }
       url if url.starts_with("http://") || url.starts_with("https://") => {
-        let client = Client::build().finish();
+        let mut connector = Connector::default().timeout(timeout);
+        if let Some(ssl) = ssl {
+          connector = connector.openssl(ssl);
+        }
+        let client = Client::build()
+          .connector(connector.finish())
+          .timeout(timeout)
+          .finish();
         (client, url)
       }
-      _ => panic!("Invalid url: {}", url),
+      _ => {
+        return Err(IoError::invalid_input(
+          "CtrlClient",
+          &format!(
+            "Invalid url {url} must start with unix://, http:// or https://"
+          ),
+        ))
+      }
     };
-    Self {
+    Ok(Self {
       client,
       name: name.to_owned(),
-      base_url: url.to_owned(),
+      base_url: url.trim_end_matches('/').to_owned(),
+    })
+  }
+
+  /// Create the client of the controller of a resource kind,
+  /// it's `None` when the kind have no controller
+  pub async fn from_kind(
+    name: &str,
+    spec: &ResourceKindSpec,
+    state: &SystemState,
+  ) -> IoResult<Option<Self>> {
+    let Some(url) = &spec.url else {
+      return Ok(None);
+    };
+    let controller = spec.controller.clone().unwrap_or_default();
+    let ssl = match &controller.tls {
+      None => None,
+      Some(secret) => {
+        let tls = utils::secret::load_tls(secret, state).await?;
+        Some(Self::gen_ssl_connector(&tls)?)
+      }
+    };
+    let timeout = controller.timeout.unwrap_or(vars::CTRL_CLIENT_TIMEOUT);
+    Self::new(name, url, timeout, ssl).map(Some)
+  }
+
+  /// Create an ssl connector authenticated with the certificate of a tls secret.
+  /// The controller is verified with the certificate client as CA when set
+  /// or with the CA of the system.
+  pub fn gen_ssl_connector(tls: &ProxySslConfig) -> IoResult<SslConnector> {
+    let map_err =
+      |err: ErrorStack| IoError::invalid_data("CtrlClient", &err.to_string());
+    let mut builder =
+      SslConnector::builder(SslMethod::tls()).map_err(map_err)?;
+    builder
+      .set_alpn_protos(b"\x02h2\x08http/1.1")
+      .map_err(map_err)?;
+    let certificate =
+      X509::from_pem(tls.certificate.as_bytes()).map_err(map_err)?;
+    builder.set_certificate(&certificate).map_err(map_err)?;
+    let key = PKey::private_key_from_pem(tls.certificate_key.as_bytes())
+      .map_err(map_err)?;
+    builder.set_private_key(&key).map_err(map_err)?;
+    builder.check_private_key().map_err(map_err)?;
+    if let Some(ca) = &tls.certificate_client {
+      for certificate in X509::stack_from_pem(ca.as_bytes()).map_err(map_err)? {
+        builder
+          .cert_store_mut()
+          .add_cert(certificate)
+          .map_err(map_err)?;
+      }
     }
+    builder.set_verify(SslVerifyMode::PEER);
+    Ok(builder.build())
   }
 
   /// Format url with base url
@@ -130,4 +216,71 @@ impl CtrlClient {
     self.is_api_error(&mut res, &status).await?;
     Ok(())
   }
+
+  /// Call convert method on controller
+  pub async fn convert(
+    &self,
+    payload: &ResourceKindConvert,
+  ) -> Result<serde_json::Value, HttpClientError> {
+    let url = self.format_url("/convert");
+    log::debug!("CtrlClient::convert url: {}", url);
+    let mut res = self
+      .client
+      .post(url)
+      .send_json(payload)
+      .await
+      .map_err(|err| err.map_err_context(|| self.name.to_owned()))?;
+    let status = res.status();
+    self.is_api_error(&mut res, &status).await?;
+    self.res_json(&mut res).await
+  }
+
+  /// Call the health path of the controller
+  pub async fn health(&self, path: &str) -> Result<(), HttpClientError> {
+    let url = self.format_url(path);
+    log::debug!("CtrlClient::health url: {}", url);
+    let res = self
+      .client
+      .get(url)
+      .send()
+      .await
+      .map_err(|err| err.map_err_context(|| self.name.to_owned()))?;
+    let status = res.status();
+    if !status.is_success() {
+      return Err(HttpClientError::HttpError(HttpError::new(
+        status,
+        format!("{}: health check responded {status}", self.name),
+      )));
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use ntex::web;
+
+  use super::*;
+
+  #[ntex::test]
+  async fn health() {
+    let srv = web::test::server(|| {
+      web::App::new().service(
+        web::resource("/health")
+          .route(web::get().to(|| async { web::HttpResponse::Ok().finish() })),
+      )
+    });
+    let ctrl_client = CtrlClient::new("test", &srv.url("/"), 5, None).unwrap();
+    ctrl_client.health("/health").await.unwrap();
+    assert!(ctrl_client.health("/ready").await.is_err());
+    assert!(CtrlClient::new("test", "tcp://127.0.0.1:1", 5, None).is_err());
+    let tls = ProxySslConfig {
+      certificate: "invalid".to_owned(),
+      certificate_key: "invalid".to_owned(),
+      certificate_client: None,
+      verify_client: None,
+      dhparam: None,
+    };
+    assert!(CtrlClient::gen_ssl_connector(&tls).is_err());
+  }
 }
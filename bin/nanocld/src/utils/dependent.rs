@@ -0,0 +1,357 @@
+use std::{collections::HashSet, future::Future, time::Duration};
+
+use futures::{stream::FuturesUnordered, StreamExt};
+use ntex::rt;
+
+use nanocl_error::{
+  http::{HttpError, HttpResult},
+  io::IoResult,
+};
+use nanocl_stubs::{
+  cargo::CargoDeleteQuery,
+  generic::{
+    DeletePropagation, GenericClause, GenericDeleteQuery, GenericFilter,
+  },
+  resource::{Resource, ResourceOwner, ResourcePhase},
+  system::EventActorKind,
+  vm::VmDeleteQuery,
+};
+
+use crate::{
+  models::{CargoDb, JobDb, ResourceDb, SystemState, VmDb},
+  objects::generic::*,
+  repositories::generic::*,
+  utils, vars,
+};
+
+/// An object referencing another one by its key
+pub type Dependent = (EventActorKind, String);
+
+/// Refuse to delete an object while other objects depend on it.
+/// The conflict list the dependents to remove first.
+pub fn ensure_none(
+  kind: &str,
+  key: &str,
+  dependents: &[Dependent],
+) -> HttpResult<()> {
+  if dependents.is_empty() {
+    return Ok(());
+  }
+  let dependents = dependents
+    .iter()
+    .map(|(kind, key)| format!("{kind}/{key}"))
+    .collect::<Vec<_>>()
+    .join(", ");
+  Err(HttpError::conflict(format!(
+    "{kind} {key} is used by {dependents}, remove them first or use the force option"
+  )))
+}
+
+/// List the cargoes, jobs and proxy rules using a secret
+pub async fn list_secret(
+  key: &str,
+  state: &SystemState,
+) -> IoResult<Vec<Dependent>> {
+  let consumers = utils::secret::list_consumers(key, state).await?;
+  Ok(
+    consumers
+      .into_iter()
+      .map(|consumer| (consumer.kind, consumer.key))
+      .collect(),
+  )
+}
+
+/// List the cargoes and virtual machines of a namespace
+pub async fn list_namespace(
+  name: &str,
+  state: &SystemState,
+) -> IoResult<Vec<Dependent>> {
+  let cargoes = CargoDb::read_by_namespace(name, &state.inner.pool).await?;
+  let vms = VmDb::read_by_namespace(name, &state.inner.pool).await?;
+  let dependents = cargoes
+    .into_iter()
+    .map(|cargo| (EventActorKind::Cargo, cargo.spec.cargo_key))
+    .chain(
+      vms
+        .into_iter()
+        .map(|vm| (EventActorKind::Vm, vm.spec.vm_key)),
+    )
+    .collect();
+  Ok(dependents)
+}
+
+/// List the resources of a resource kind
+pub async fn list_resource_kind(
+  key: &str,
+  state: &SystemState,
+) -> IoResult<Vec<Dependent>> {
+  let filter =
+    GenericFilter::new().r#where("kind", GenericClause::Eq(key.to_owned()));
+  let resources =
+    ResourceDb::transform_read_by(&filter, &state.inner.pool).await?;
+  Ok(
+    resources
+      .into_iter()
+      .map(|resource| (EventActorKind::Resource, resource.spec.resource_key))
+      .collect(),
+  )
+}
+
+/// Delete the cargoes and virtual machines of a namespace.
+/// Running cargoes are only deleted when `force` is set.
+pub async fn delete_namespace(
+  dependents: &[Dependent],
+  force: bool,
+  state: &SystemState,
+) -> HttpResult<()> {
+  dependents
+    .iter()
+    .map(|(kind, key)| async move {
+      match kind {
+        EventActorKind::Vm => {
+          VmDb::del_obj_by_pk(key, &VmDeleteQuery::default(), state).await?;
+        }
+        _ => {
+          let query = CargoDeleteQuery {
+            force: Some(force),
+            ..Default::default()
+          };
+          CargoDb::del_obj_by_pk(key, &query, state).await?;
+        }
+      }
+      Ok::<_, HttpError>(())
+    })
+    .collect::<FuturesUnordered<_>>()
+    .collect::<Vec<_>>()
+    .await
+    .into_iter()
+    .collect::<HttpResult<Vec<_>>>()?;
+  Ok(())
+}
+
+/// Wait for the cargoes and virtual machines of a namespace to be destroyed
+pub async fn wait_namespace(name: &str, state: &SystemState) -> HttpResult<()> {
+  let mut elapsed = 0;
+  while !list_namespace(name, state).await?.is_empty() {
+    if elapsed >= vars::CASCADE_TIMEOUT {
+      return Err(HttpError::conflict(format!(
+        "Namespace {name} still have objects being destroyed"
+      )));
+    }
+    ntex::time::sleep(Duration::from_secs(1)).await;
+    elapsed += 1;
+  }
+  Ok(())
+}
+
+/// Delete the resources of a resource kind
+pub async fn delete_resource_kind(
+  key: &str,
+  state: &SystemState,
+) -> HttpResult<()> {
+  for (_, resource_key) in list_resource_kind(key, state).await? {
+    ResourceDb::del_obj_by_pk(
+      &resource_key,
+      &GenericDeleteQuery::default(),
+      state,
+    )
+    .await?;
+  }
+  Ok(())
+}
+
+/// List the keys of the resources owning resources directly
+/// or through other resources
+async fn list_ancestors(
+  owners: &[ResourceOwner],
+  state: &SystemState,
+) -> IoResult<HashSet<String>> {
+  let mut ancestors = HashSet::new();
+  let mut queue = owners.to_vec();
+  while let Some(owner) = queue.pop() {
+    if owner.kind != EventActorKind::Resource
+      || !ancestors.insert(owner.key.clone())
+    {
+      continue;
+    }
+    if let Ok(resource) =
+      ResourceDb::transform_read_by_pk(&owner.key, &state.inner.pool).await
+    {
+      queue.extend(resource.owners);
+    }
+  }
+  Ok(ancestors)
+}
+
+/// Ensure the owners of a resource exist and can own it
+/// without owning it back through other resources
+pub async fn ensure_owners(
+  key: &str,
+  owners: &[ResourceOwner],
+  state: &SystemState,
+) -> HttpResult<()> {
+  let pool = &state.inner.pool;
+  for owner in owners {
+    let exists = match owner.kind {
+      EventActorKind::Cargo => {
+        CargoDb::read_by_pk(&owner.key, pool).await.is_ok()
+      }
+      EventActorKind::Vm => VmDb::read_by_pk(&owner.key, pool).await.is_ok(),
+      EventActorKind::Job => JobDb::read_by_pk(&owner.key, pool).await.is_ok(),
+      EventActorKind::Resource if owner.key != key => {
+        ResourceDb::read_by_pk(&owner.key, pool).await.is_ok()
+      }
+      _ => {
+        return Err(HttpError::bad_request(format!(
+          "{}/{} cannot own resource {key}",
+          owner.kind, owner.key
+        )))
+      }
+    };
+    if !exists {
+      return Err(HttpError::bad_request(format!(
+        "Owner {}/{} of resource {key} doesn't exist",
+        owner.kind, owner.key
+      )));
+    }
+  }
+  if list_ancestors(owners, state).await?.contains(key) {
+    return Err(HttpError::bad_request(format!(
+      "Owners of resource {key} cannot be owned by it"
+    )));
+  }
+  Ok(())
+}
+
+/// List the resources owned by an object
+pub async fn list_owned(
+  kind: &EventActorKind,
+  key: &str,
+  state: &SystemState,
+) -> IoResult<Vec<Resource>> {
+  let filter = GenericFilter::new().r#where(
+    "owners",
+    GenericClause::Contains(serde_json::json!([{ "Kind": kind, "Key": key }])),
+  );
+  ResourceDb::transform_read_by(&filter, &state.inner.pool).await
+}
+
+/// List the keys of the resources owning a resource being deleted.
+/// They are already being deleted and wait for it when they own it back.
+async fn list_waiting(
+  kind: &EventActorKind,
+  key: &str,
+  state: &SystemState,
+) -> IoResult<HashSet<String>> {
+  if kind != &EventActorKind::Resource {
+    return Ok(HashSet::new());
+  }
+  match ResourceDb::transform_read_by_pk(key, &state.inner.pool).await {
+    Ok(resource) => list_ancestors(&resource.owners, state).await,
+    Err(_) => Ok(HashSet::new()),
+  }
+}
+
+/// Delete the resources owned by an object with the same propagation.
+/// Resources already being deleted are skipped so ownership cycles end.
+async fn delete_owned(
+  kind: &EventActorKind,
+  key: &str,
+  propagation: &DeletePropagation,
+  state: &SystemState,
+) -> HttpResult<()> {
+  let query = GenericDeleteQuery {
+    force: None,
+    propagation: Some(propagation.clone()),
+  };
+  let waiting = list_waiting(kind, key, state).await?;
+  for resource in list_owned(kind, key, state).await? {
+    let is_deleting = matches!(
+      &resource.status,
+      Some(status) if status.phase == ResourcePhase::Deleting
+    );
+    if is_deleting || waiting.contains(&resource.spec.resource_key) {
+      continue;
+    }
+    let resource_key = resource.spec.resource_key;
+    Box::pin(ResourceDb::del_obj_by_pk(&resource_key, &query, state)).await?;
+  }
+  Ok(())
+}
+
+/// Remove the reference to an object from the resources it owns
+async fn orphan_owned(
+  kind: &EventActorKind,
+  key: &str,
+  state: &SystemState,
+) -> HttpResult<()> {
+  for resource in list_owned(kind, key, state).await? {
+    let owners = resource
+      .owners
+      .into_iter()
+      .filter(|owner| !(&owner.kind == kind && owner.key == key))
+      .collect::<Vec<_>>();
+    ResourceDb::update_owners(
+      &resource.spec.resource_key,
+      &owners,
+      &state.inner.pool,
+    )
+    .await?;
+  }
+  Ok(())
+}
+
+/// Delete an object and apply the propagation policy to the resources it owns.
+/// Foreground delete the owned resources and wait for them to be removed
+/// before deleting the object, Background delete them once the object
+/// is deleted and Orphan keep them without the reference to the object.
+pub async fn delete_with_owned<T, F>(
+  kind: EventActorKind,
+  key: &str,
+  propagation: Option<&DeletePropagation>,
+  state: &SystemState,
+  delete: F,
+) -> HttpResult<T>
+where
+  F: Future<Output = HttpResult<T>>,
+{
+  let propagation = propagation.cloned().unwrap_or_default();
+  match propagation {
+    DeletePropagation::Foreground => {
+      delete_owned(&kind, key, &propagation, state).await?;
+      let waiting = list_waiting(&kind, key, state).await?;
+      let mut elapsed = 0;
+      while list_owned(&kind, key, state)
+        .await?
+        .iter()
+        .any(|resource| !waiting.contains(&resource.spec.resource_key))
+      {
+        if elapsed >= vars::CASCADE_TIMEOUT {
+          return Err(HttpError::conflict(format!(
+            "{kind} {key} still owns resources being deleted"
+          )));
+        }
+        ntex::time::sleep(Duration::from_secs(1)).await;
+        elapsed += 1;
+      }
+      delete.await
+    }
+    DeletePropagation::Background => {
+      let output = delete.await?;
+      let key = key.to_owned();
+      let state = state.clone();
+      rt::spawn(async move {
+        if let Err(err) = delete_owned(&kind, &key, &propagation, &state).await
+        {
+          log::warn!("dependent::delete_with_owned: {kind} {key}: {err}");
+        }
+      });
+      Ok(output)
+    }
+    DeletePropagation::Orphan => {
+      let output = delete.await?;
+      orphan_owned(&kind, key, state).await?;
+      Ok(output)
+    }
+  }
+}
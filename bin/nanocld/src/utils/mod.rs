@@ -2,16 +2,29 @@ pub mod key;
 pub mod stream;
 pub mod ws;
 
+pub mod ca;
+pub mod cloud_init;
 pub mod container;
 pub mod cron;
 pub mod ctrl_client;
+pub mod dependent;
 pub mod exec;
+pub mod qmp;
 pub mod query_string;
+pub mod resource;
+pub mod resource_kind;
 pub mod secret;
+pub mod secret_backend;
 pub mod server;
 pub mod store;
 pub mod system;
+pub mod vm_console;
 pub mod vm_image;
+pub mod vm_image_pull;
+pub mod vm_snapshot;
+pub mod vm_stats;
+pub mod wasm_ctrl;
+pub mod watch;
 
 #[cfg(test)]
 pub mod tests {
@@ -50,6 +63,7 @@ pub mod tests {
       .unwrap_or_else(|_| String::from("/var/run/docker.sock"));
     let config = DaemonConfig {
       state_dir: format!("{home}/.nanocl_dev/state"),
+      conf_dir: format!("{home}/.nanocl_dev/conf"),
       docker_host,
       hostname: "nanocl.internal".to_owned(),
       store_addr: Some(
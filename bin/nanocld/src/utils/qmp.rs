@@ -0,0 +1,155 @@
+use tokio::{
+  io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+  net::{
+    unix::{OwnedReadHalf, OwnedWriteHalf},
+    UnixStream,
+  },
+};
+
+use nanocl_error::io::{FromIo, IoError, IoResult};
+
+/// Get the path of the QMP socket of a VM from its state directory
+pub fn get_socket_path(vm_dir: &str) -> String {
+  format!("{vm_dir}/qmp.sock")
+}
+
+/// Minimal client for the QEMU Machine Protocol.
+/// It's used to control a running VM (power down, reset, query stats)
+/// through the unix socket created by qemu in the VM state directory.
+pub struct QmpClient {
+  reader: BufReader<OwnedReadHalf>,
+  writer: OwnedWriteHalf,
+}
+
+impl QmpClient {
+  /// Connect to the QMP socket and negotiate the capabilities
+  pub async fn connect(path: &str) -> IoResult<Self> {
+    let stream = UnixStream::connect(path)
+      .await
+      .map_err(|err| err.map_err_context(|| path))?;
+    let (reader, writer) = stream.into_split();
+    let mut client = Self {
+      reader: BufReader::new(reader),
+      writer,
+    };
+    // The server greet us first with its version and capabilities
+    let greeting = client.read_message().await?;
+    if greeting.get("QMP").is_none() {
+      return Err(IoError::invalid_data(
+        "QMP",
+        "Unexpected greeting from the server",
+      ));
+    }
+    client.execute("qmp_capabilities", None).await?;
+    Ok(client)
+  }
+
+  /// Read the next message sent by the server
+  async fn read_message(&mut self) -> IoResult<serde_json::Value> {
+    let mut line = String::new();
+    let len = self
+      .reader
+      .read_line(&mut line)
+      .await
+      .map_err(|err| err.map_err_context(|| "QMP"))?;
+    if len == 0 {
+      return Err(IoError::interrupted("QMP", "Connection closed"));
+    }
+    let message = serde_json::from_str::<serde_json::Value>(&line)
+      .map_err(|err| err.map_err_context(|| "QMP"))?;
+    Ok(message)
+  }
+
+  /// Execute a command and wait for its result.
+  /// Asynchronous events received in the meantime are ignored.
+  pub async fn execute(
+    &mut self,
+    command: &str,
+    arguments: Option<serde_json::Value>,
+  ) -> IoResult<serde_json::Value> {
+    let mut payload = serde_json::json!({ "execute": command });
+    if let Some(arguments) = arguments {
+      payload["arguments"] = arguments;
+    }
+    let payload = format!("{payload}\n");
+    self
+      .writer
+      .write_all(payload.as_bytes())
+      .await
+      .map_err(|err| err.map_err_context(|| "QMP"))?;
+    loop {
+      let message = self.read_message().await?;
+      if message.get("event").is_some() {
+        continue;
+      }
+      if let Some(error) = message.get("error") {
+        let desc = error["desc"].as_str().unwrap_or_default();
+        return Err(IoError::interrupted("QMP", &format!("{command}: {desc}")));
+      }
+      if let Some(res) = message.get("return") {
+        return Ok(res.clone());
+      }
+    }
+  }
+}
+
+/// Connect to the QMP socket of a VM and execute a single command
+pub async fn execute(
+  path: &str,
+  command: &str,
+  arguments: Option<serde_json::Value>,
+) -> IoResult<serde_json::Value> {
+  let mut client = QmpClient::connect(path).await?;
+  client.execute(command, arguments).await
+}
+
+#[cfg(test)]
+mod tests {
+  use tokio::net::UnixListener;
+
+  use super::*;
+
+  /// Fake qemu answering the commands it receive
+  async fn serve(listener: UnixListener) {
+    let (stream, _) = listener.accept().await.unwrap();
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+    writer
+      .write_all(b"{\"QMP\": {\"version\": {}, \"capabilities\": []}}\n")
+      .await
+      .unwrap();
+    let mut line = String::new();
+    while reader.read_line(&mut line).await.unwrap() > 0 {
+      let command = serde_json::from_str::<serde_json::Value>(&line).unwrap();
+      let res = match command["execute"].as_str().unwrap() {
+        "qmp_capabilities" => "{\"return\": {}}\n".to_owned(),
+        "system_powerdown" => {
+          "{\"event\": \"POWERDOWN\"}\n{\"return\": {}}\n".to_owned()
+        }
+        cmd => format!(
+          "{{\"error\": {{\"class\": \"CommandNotFound\", \"desc\": \"{cmd}\"}}}}\n"
+        ),
+      };
+      writer.write_all(res.as_bytes()).await.unwrap();
+      line.clear();
+    }
+  }
+
+  #[ntex::test]
+  async fn execute_command() {
+    let dir = std::env::temp_dir().join(format!("qmp-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = get_socket_path(dir.to_str().unwrap());
+    let _ = std::fs::remove_file(&path);
+    let listener = UnixListener::bind(&path).unwrap();
+    let server = ntex::rt::spawn(serve(listener));
+    let mut client = QmpClient::connect(&path).await.unwrap();
+    let res = client.execute("system_powerdown", None).await.unwrap();
+    assert_eq!(res, serde_json::json!({}));
+    let err = client.execute("unknown", None).await.unwrap_err();
+    assert!(err.to_string().contains("unknown"));
+    drop(client);
+    server.await.unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+  }
+}
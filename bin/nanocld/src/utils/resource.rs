@@ -0,0 +1,397 @@
+use std::time::Duration;
+
+use nanocl_error::{
+  http::{HttpError, HttpResult},
+  io::IoResult,
+};
+use nanocl_stubs::{
+  generic::{GenericClause, GenericFilter},
+  resource::{
+    Resource, ResourceFinalizersPatch, ResourcePartial, ResourcePhase,
+    ResourceStatus, ResourceStatusPartial,
+  },
+  system::NativeEventAction,
+};
+
+use crate::{
+  models::{ResourceDb, SpecDb, SystemState},
+  repositories::generic::*,
+  vars,
+};
+
+/// Delay before calling again a controller that was unreachable
+/// for the given number of attempts
+pub fn retry_delay(retries: u32) -> Duration {
+  let factor = 2u64.saturating_pow(retries.saturating_sub(1));
+  let delay = vars::RESOURCE_RETRY_DELAY.saturating_mul(factor);
+  Duration::from_secs(delay.min(vars::RESOURCE_RETRY_MAX_DELAY))
+}
+
+/// Status of a resource applied by its controller.
+/// The observed version is set once the spec is stored.
+pub fn gen_ready_status(previous: Option<&ResourceStatus>) -> ResourceStatus {
+  ResourceStatus {
+    phase: ResourcePhase::Ready,
+    conditions: previous
+      .map(|status| status.conditions.clone())
+      .unwrap_or_default(),
+    updated_at: chrono::Utc::now().naive_utc(),
+    ..Default::default()
+  }
+}
+
+/// Status of a resource its controller refused to apply
+pub fn gen_failed_status(
+  previous: Option<&ResourceStatus>,
+  message: &str,
+) -> ResourceStatus {
+  ResourceStatus {
+    phase: ResourcePhase::Failed,
+    message: Some(message.to_owned()),
+    retries: 0,
+    next_retry_at: None,
+    updated_at: chrono::Utc::now().naive_utc(),
+    ..previous.cloned().unwrap_or_default()
+  }
+}
+
+/// Status of a resource waiting for its unreachable controller
+/// to apply or delete it
+pub fn gen_retry_status(
+  previous: Option<&ResourceStatus>,
+  phase: ResourcePhase,
+  message: &str,
+) -> ResourceStatus {
+  let previous = previous.cloned().unwrap_or_default();
+  let retries = if previous.phase == phase && previous.retries > 0 {
+    previous.retries + 1
+  } else {
+    1
+  };
+  let now = chrono::Utc::now().naive_utc();
+  let delay = chrono::Duration::from_std(retry_delay(retries))
+    .unwrap_or_else(|_| chrono::Duration::zero());
+  ResourceStatus {
+    phase,
+    message: Some(message.to_owned()),
+    retries,
+    next_retry_at: Some(now + delay),
+    updated_at: now,
+    ..previous
+  }
+}
+
+/// Merge the status sent by a controller into the current one.
+/// Conditions replace the existing ones of the same type.
+pub fn merge_status(
+  previous: Option<&ResourceStatus>,
+  partial: &ResourceStatusPartial,
+) -> ResourceStatus {
+  let mut status = previous.cloned().unwrap_or_default();
+  if let Some(phase) = &partial.phase {
+    if phase != &status.phase {
+      status.retries = 0;
+      status.next_retry_at = None;
+    }
+    status.phase = phase.clone();
+  }
+  for condition in partial.conditions.clone().unwrap_or_default() {
+    match status
+      .conditions
+      .iter_mut()
+      .find(|current| current.r#type == condition.r#type)
+    {
+      Some(current) => *current = condition,
+      None => status.conditions.push(condition),
+    }
+  }
+  if let Some(observed_version) = partial.observed_version {
+    status.observed_version = Some(observed_version);
+  }
+  if let Some(message) = &partial.message {
+    status.message = Some(message.clone());
+  }
+  status.updated_at = chrono::Utc::now().naive_utc();
+  status
+}
+
+/// Update the status of a resource from its controller
+pub async fn patch_status(
+  key: &str,
+  partial: &ResourceStatusPartial,
+  state: &SystemState,
+) -> HttpResult<Resource> {
+  let resource =
+    ResourceDb::transform_read_by_pk(key, &state.inner.pool).await?;
+  if let Some(observed_version) = partial.observed_version {
+    let spec = SpecDb::read_by_pk(&observed_version, &state.inner.pool).await;
+    if !matches!(spec, Ok(spec) if spec.kind_key == key) {
+      return Err(HttpError::bad_request(format!(
+        "Version {observed_version} is not a spec of resource {key}"
+      )));
+    }
+  }
+  let status = merge_status(resource.status.as_ref(), partial);
+  let resource =
+    ResourceDb::update_status(key, &status, &state.inner.pool).await?;
+  Ok(resource)
+}
+
+/// Delete a resource once it has no finalizers left.
+/// The resource is kept in the Deleting phase while it has finalizers
+/// or while its controller is unreachable.
+pub async fn delete(
+  resource: &Resource,
+  state: &SystemState,
+) -> IoResult<Resource> {
+  let key = &resource.spec.resource_key;
+  if !resource.finalizers.is_empty() {
+    let status = ResourceStatus {
+      phase: ResourcePhase::Deleting,
+      message: Some(format!(
+        "Waiting for finalizers {}",
+        resource.finalizers.join(", ")
+      )),
+      retries: 0,
+      next_retry_at: None,
+      updated_at: chrono::Utc::now().naive_utc(),
+      ..resource.status.clone().unwrap_or_default()
+    };
+    return ResourceDb::update_status(key, &status, &state.inner.pool).await;
+  }
+  match ResourceDb::hook_delete(resource, state).await {
+    // The controller is unreachable, the resource is deleted on retry
+    Ok(Some(status)) => {
+      return ResourceDb::update_status(key, &status, &state.inner.pool).await
+    }
+    Ok(None) => {}
+    Err(err) => log::warn!("resource::delete: {key} {err}"),
+  }
+  ResourceDb::del_by_pk(key, &state.inner.pool).await?;
+  SpecDb::del_by_kind_key(key, &state.inner.pool).await?;
+  Ok(resource.clone())
+}
+
+/// Event of a deletion, Destroying when the resource is kept
+/// until its finalizers are removed or its controller is reachable
+pub async fn get_del_event(
+  key: &str,
+  state: &SystemState,
+) -> NativeEventAction {
+  match ResourceDb::read_by_pk(key, &state.inner.pool).await {
+    Ok(_) => NativeEventAction::Destroying,
+    Err(_) => NativeEventAction::Destroy,
+  }
+}
+
+/// Add or remove finalizers of a resource.
+/// A resource being deleted is removed with its last finalizer.
+pub async fn patch_finalizers(
+  key: &str,
+  patch: &ResourceFinalizersPatch,
+  state: &SystemState,
+) -> HttpResult<Resource> {
+  let resource =
+    ResourceDb::transform_read_by_pk(key, &state.inner.pool).await?;
+  let status = resource.status.clone().unwrap_or_default();
+  let is_deleting = status.phase == ResourcePhase::Deleting;
+  if is_deleting && !patch.add.is_empty() {
+    return Err(HttpError::conflict(format!(
+      "Resource {key} is being deleted"
+    )));
+  }
+  let mut finalizers = resource
+    .finalizers
+    .into_iter()
+    .filter(|finalizer| !patch.remove.contains(finalizer))
+    .collect::<Vec<_>>();
+  for finalizer in &patch.add {
+    if !finalizers.contains(finalizer) {
+      finalizers.push(finalizer.clone());
+    }
+  }
+  let resource =
+    ResourceDb::update_finalizers(key, &finalizers, &state.inner.pool).await?;
+  if !is_deleting || !finalizers.is_empty() || status.next_retry_at.is_some() {
+    state.emit_normal_native_action(&resource, NativeEventAction::Update);
+    return Ok(resource);
+  }
+  let resource = delete(&resource, state).await?;
+  let action = get_del_event(key, state).await;
+  if action == NativeEventAction::Destroy {
+    log::info!("resource::patch_finalizers: {key} deleted");
+  }
+  state.emit_normal_native_action(&resource, action);
+  Ok(resource)
+}
+
+/// List the resources waiting for their controller in the given phase
+async fn list_by_phase(
+  phase: ResourcePhase,
+  state: &SystemState,
+) -> IoResult<Vec<Resource>> {
+  let filter = GenericFilter::new().r#where(
+    "status",
+    GenericClause::Contains(serde_json::json!({ "Phase": phase })),
+  );
+  ResourceDb::transform_read_by(&filter, &state.inner.pool).await
+}
+
+/// Call again the controller of a resource waiting to be applied
+async fn retry_apply(resource: &Resource, state: &SystemState) -> IoResult<()> {
+  let key = &resource.spec.resource_key;
+  let partial = ResourcePartial {
+    name: key.clone(),
+    kind: format!("{}/{}", resource.kind, resource.spec.version),
+    data: resource.spec.data.clone(),
+    metadata: resource.spec.metadata.clone(),
+    owners: None,
+    finalizers: None,
+  };
+  let previous = resource.status.as_ref();
+  match ResourceDb::hook_create(&partial, Some(resource), state).await {
+    Ok((_, Some(status))) if status.phase != ResourcePhase::Ready => {
+      log::warn!(
+        "resource::retry_apply: {key} controller still unreachable, retry {}",
+        status.retries
+      );
+      ResourceDb::update_status(key, &status, &state.inner.pool).await?;
+    }
+    Ok((obj, status)) => {
+      let mut status = status.unwrap_or_else(|| gen_ready_status(previous));
+      let resource = if obj.data != resource.spec.data {
+        ResourceDb::update_from_spec(&obj, Some(status), &state.inner.pool)
+          .await?
+      } else {
+        status.observed_version = Some(resource.spec.key);
+        ResourceDb::update_status(key, &status, &state.inner.pool).await?
+      };
+      log::info!("resource::retry_apply: {key} applied");
+      state.emit_normal_native_action(&resource, NativeEventAction::Update);
+    }
+    Err(err) => {
+      log::warn!("resource::retry_apply: {key} {err}");
+      let status = gen_failed_status(previous, &err.to_string());
+      ResourceDb::update_status(key, &status, &state.inner.pool).await?;
+    }
+  }
+  Ok(())
+}
+
+/// Call again the controller of a resource waiting to be deleted
+async fn retry_delete(
+  resource: &Resource,
+  state: &SystemState,
+) -> IoResult<()> {
+  let key = &resource.spec.resource_key;
+  let resource = delete(resource, state).await?;
+  if get_del_event(key, state).await == NativeEventAction::Destroying {
+    log::warn!(
+      "resource::retry_delete: {key} controller still unreachable, retry {}",
+      resource
+        .status
+        .map(|status| status.retries)
+        .unwrap_or_default()
+    );
+    return Ok(());
+  }
+  log::info!("resource::retry_delete: {key} deleted");
+  state.emit_normal_native_action(&resource, NativeEventAction::Destroy);
+  Ok(())
+}
+
+/// Call again the controllers that were unreachable
+/// for the resources whose retry delay is over.
+/// Returns the number of resources retried.
+pub async fn reconcile(state: &SystemState) -> IoResult<usize> {
+  let now = chrono::Utc::now().naive_utc();
+  let is_due = |resource: &Resource| {
+    resource
+      .status
+      .as_ref()
+      .and_then(|status| status.next_retry_at)
+      .map(|next_retry_at| next_retry_at <= now)
+      .unwrap_or(false)
+  };
+  let mut count = 0;
+  for resource in list_by_phase(ResourcePhase::Pending, state).await? {
+    if is_due(&resource) {
+      retry_apply(&resource, state).await?;
+      count += 1;
+    }
+  }
+  for resource in list_by_phase(ResourcePhase::Deleting, state).await? {
+    if is_due(&resource) {
+      retry_delete(&resource, state).await?;
+      count += 1;
+    }
+  }
+  Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+  use nanocl_stubs::resource::ResourceCondition;
+
+  use super::*;
+
+  #[test]
+  fn backoff() {
+    assert_eq!(
+      retry_delay(1),
+      Duration::from_secs(vars::RESOURCE_RETRY_DELAY)
+    );
+    assert_eq!(
+      retry_delay(3),
+      Duration::from_secs(vars::RESOURCE_RETRY_DELAY * 4)
+    );
+    assert_eq!(
+      retry_delay(u32::MAX),
+      Duration::from_secs(vars::RESOURCE_RETRY_MAX_DELAY)
+    );
+    let status = gen_retry_status(None, ResourcePhase::Pending, "down");
+    assert_eq!(status.retries, 1);
+    let status =
+      gen_retry_status(Some(&status), ResourcePhase::Pending, "down");
+    assert_eq!(status.retries, 2);
+    assert!(status.next_retry_at.unwrap() > status.updated_at);
+    let status =
+      gen_retry_status(Some(&status), ResourcePhase::Deleting, "down");
+    assert_eq!(status.retries, 1);
+  }
+
+  #[test]
+  fn merge() {
+    let reachable = ResourceCondition {
+      r#type: "Reachable".to_owned(),
+      status: false,
+      reason: None,
+      message: None,
+    };
+    let previous = ResourceStatus {
+      conditions: vec![reachable.clone()],
+      retries: 3,
+      ..Default::default()
+    };
+    let partial = ResourceStatusPartial {
+      phase: Some(ResourcePhase::Ready),
+      conditions: Some(vec![
+        ResourceCondition {
+          status: true,
+          ..reachable.clone()
+        },
+        ResourceCondition {
+          r#type: "CertificateIssued".to_owned(),
+          ..reachable
+        },
+      ]),
+      ..Default::default()
+    };
+    let status = merge_status(Some(&previous), &partial);
+    assert_eq!(status.phase, ResourcePhase::Ready);
+    assert_eq!(status.retries, 0);
+    assert_eq!(status.conditions.len(), 2);
+    assert!(status.conditions[0].status);
+    assert!(!status.conditions[1].status);
+  }
+}
@@ -0,0 +1,737 @@
+use std::collections::BTreeMap;
+
+use jsonschema::{Draft, Validator};
+
+use nanocl_error::{
+  http::{HttpError, HttpResult},
+  io::{IoError, IoResult},
+};
+use nanocl_stubs::{
+  generic::{GenericClause, GenericFilter},
+  resource::{Resource, ResourcePartial, ResourcePhase},
+  resource_kind::{
+    ResourceKind, ResourceKindConversion, ResourceKindConvert,
+    ResourceKindMapping, ResourceKindMigrate, ResourceKindMigrated,
+    ResourceKindMigration, ResourceKindPartial, ResourceKindSpec,
+    ResourceKindValidationError, ResourceKindVersionResources,
+  },
+  system::NativeEventAction,
+};
+
+use crate::{
+  models::{ResourceDb, SpecDb, SystemState},
+  repositories::generic::*,
+  utils,
+};
+
+/// Split a JSON pointer in its unescaped tokens
+fn parse_pointer(pointer: &str) -> IoResult<Vec<String>> {
+  let Some(pointer) = pointer.strip_prefix('/') else {
+    return Err(IoError::invalid_input(
+      "ResourceKindMapping",
+      &format!("Invalid pointer {pointer} must start with /"),
+    ));
+  };
+  Ok(
+    pointer
+      .split('/')
+      .map(|token| token.replace("~1", "/").replace("~0", "~"))
+      .collect(),
+  )
+}
+
+/// Remove the value at the given tokens of a JSON pointer
+fn take_pointer(
+  value: &mut serde_json::Value,
+  tokens: &[String],
+) -> Option<serde_json::Value> {
+  let (last, parents) = tokens.split_last()?;
+  let mut parent = value;
+  for token in parents {
+    parent = match parent {
+      serde_json::Value::Object(map) => map.get_mut(token)?,
+      serde_json::Value::Array(items) => {
+        items.get_mut(token.parse::<usize>().ok()?)?
+      }
+      _ => return None,
+    };
+  }
+  match parent {
+    serde_json::Value::Object(map) => map.remove(last),
+    serde_json::Value::Array(items) => {
+      let index = last.parse::<usize>().ok()?;
+      (index < items.len()).then(|| items.remove(index))
+    }
+    _ => None,
+  }
+}
+
+/// Set the value at the given tokens of a JSON pointer
+/// creating the missing objects
+fn set_pointer(
+  value: &mut serde_json::Value,
+  tokens: &[String],
+  new_value: serde_json::Value,
+) -> IoResult<()> {
+  let Some((token, tokens)) = tokens.split_first() else {
+    *value = new_value;
+    return Ok(());
+  };
+  if value.is_null() {
+    *value = serde_json::json!({});
+  }
+  let child = match value {
+    serde_json::Value::Object(map) => {
+      map.entry(token.clone()).or_insert(serde_json::Value::Null)
+    }
+    serde_json::Value::Array(items) => token
+      .parse::<usize>()
+      .ok()
+      .and_then(|index| items.get_mut(index))
+      .ok_or_else(|| {
+        IoError::invalid_data(
+          "ResourceKindMapping",
+          &format!("Index {token} out of bounds"),
+        )
+      })?,
+    _ => {
+      return Err(IoError::invalid_data(
+        "ResourceKindMapping",
+        &format!("Unable to set {token} on a scalar value"),
+      ))
+    }
+  };
+  set_pointer(child, tokens, new_value)
+}
+
+/// Ensure the mappings of a conversion are valid
+pub fn validate_mappings(mappings: &[ResourceKindMapping]) -> IoResult<()> {
+  for mapping in mappings {
+    if mapping.from.is_none() && mapping.to.is_none() {
+      return Err(IoError::invalid_input(
+        "ResourceKindMapping",
+        "From or To must be defined",
+      ));
+    }
+    for pointer in [&mapping.from, &mapping.to].into_iter().flatten() {
+      parse_pointer(pointer)?;
+    }
+  }
+  Ok(())
+}
+
+/// Ensure the conversion of a resource kind version can be used
+pub fn validate_conversion(spec: &ResourceKindSpec) -> IoResult<()> {
+  match &spec.conversion {
+    None => Ok(()),
+    Some(ResourceKindConversion::Controller) if spec.url.is_none() => {
+      Err(IoError::invalid_input(
+        "ResourceKind",
+        "A controller conversion require an url",
+      ))
+    }
+    Some(ResourceKindConversion::Controller) => Ok(()),
+    Some(ResourceKindConversion::Mappings(mappings)) => mappings
+      .values()
+      .try_for_each(|mappings| validate_mappings(mappings)),
+  }
+}
+
+/// Ensure the options of the controller of a resource kind version can be used
+pub fn validate_controller(spec: &ResourceKindSpec) -> IoResult<()> {
+  let Some(controller) = &spec.controller else {
+    return Ok(());
+  };
+  let Some(url) = &spec.url else {
+    return Err(IoError::invalid_input(
+      "ResourceKind",
+      "Controller options require an url",
+    ));
+  };
+  if controller.tls.is_some() && !url.starts_with("https://") {
+    return Err(IoError::invalid_input(
+      "ResourceKind",
+      "A tls controller require an https url",
+    ));
+  }
+  if controller.timeout == Some(0) {
+    return Err(IoError::invalid_input(
+      "ResourceKind",
+      "Controller timeout must be greater than 0",
+    ));
+  }
+  if let Some(path) = &controller.health_path {
+    if !path.starts_with('/') {
+      return Err(IoError::invalid_input(
+        "ResourceKind",
+        &format!("Invalid health path {path} must start with /"),
+      ));
+    }
+  }
+  Ok(())
+}
+
+/// Validate the WebAssembly module options of a resource kind
+pub fn validate_wasm(spec: &ResourceKindSpec) -> IoResult<()> {
+  let Some(wasm) = &spec.wasm else {
+    return Ok(());
+  };
+  if spec.url.is_some() {
+    return Err(IoError::invalid_input(
+      "ResourceKind",
+      "Url and Wasm cannot be defined together",
+    ));
+  }
+  if wasm.path.is_some() == wasm.blob.is_some() {
+    return Err(IoError::invalid_input(
+      "ResourceKind",
+      "Wasm require either a path or a blob",
+    ));
+  }
+  if wasm.fuel == Some(0) || wasm.memory == Some(0) {
+    return Err(IoError::invalid_input(
+      "ResourceKind",
+      "Wasm fuel and memory must be greater than 0",
+    ));
+  }
+  if let Some(blob) = &wasm.blob {
+    openssl::base64::decode_block(blob).map_err(|err| {
+      IoError::invalid_input(
+        "ResourceKind",
+        &format!("Invalid wasm blob {err}"),
+      )
+    })?;
+  }
+  Ok(())
+}
+
+/// Ensure the controller of a resource kind being registered can be called
+/// and is healthy when a health path is set, or that its module compiles
+pub async fn check_controller(
+  kind: &ResourceKindPartial,
+  state: &SystemState,
+) -> HttpResult<()> {
+  validate_controller(&kind.data)?;
+  validate_wasm(&kind.data)?;
+  utils::wasm_ctrl::WasmCtrl::from_kind(
+    &kind.name,
+    &kind.version,
+    &kind.data,
+    state,
+  )
+  .await?;
+  let Some(ctrl_client) =
+    utils::ctrl_client::CtrlClient::from_kind(&kind.name, &kind.data, state)
+      .await?
+  else {
+    return Ok(());
+  };
+  let health_path = kind
+    .data
+    .controller
+    .as_ref()
+    .and_then(|controller| controller.health_path.as_deref());
+  if let Some(path) = health_path {
+    ctrl_client.health(path).await.map_err(|err| {
+      HttpError::bad_gateway(format!(
+        "Controller of {} is not healthy: {err}",
+        kind.name
+      ))
+    })?;
+  }
+  Ok(())
+}
+
+/// Apply the mappings in order to a copy of the data
+pub fn apply_mappings(
+  data: &serde_json::Value,
+  mappings: &[ResourceKindMapping],
+) -> IoResult<serde_json::Value> {
+  let mut data = data.clone();
+  for mapping in mappings {
+    let value = match &mapping.from {
+      Some(from) => take_pointer(&mut data, &parse_pointer(from)?),
+      None => None,
+    }
+    .or_else(|| mapping.default.clone());
+    if let (Some(to), Some(value)) = (&mapping.to, value) {
+      set_pointer(&mut data, &parse_pointer(to)?, value)?;
+    }
+  }
+  Ok(data)
+}
+
+/// Ensure the immutable fields of a kind version are JSON pointers
+pub fn validate_immutable(spec: &ResourceKindSpec) -> IoResult<()> {
+  for pointer in spec.immutable.iter().flatten() {
+    parse_pointer(pointer)?;
+  }
+  Ok(())
+}
+
+/// Set the defaults of a schema on the missing properties of the data
+fn apply_defaults(schema: &serde_json::Value, data: &mut serde_json::Value) {
+  match data {
+    serde_json::Value::Object(map) => {
+      let Some(properties) = schema["properties"].as_object() else {
+        return;
+      };
+      for (key, property) in properties {
+        if let (false, Some(default)) =
+          (map.contains_key(key), property.get("default"))
+        {
+          map.insert(key.clone(), default.clone());
+        }
+        if let Some(value) = map.get_mut(key) {
+          apply_defaults(property, value);
+        }
+      }
+    }
+    serde_json::Value::Array(items) if schema["items"].is_object() => {
+      for item in items {
+        apply_defaults(&schema["items"], item);
+      }
+    }
+    _ => {}
+  }
+}
+
+/// Error listing every invalid field of the data of a resource
+fn gen_validation_error(errors: Vec<ResourceKindValidationError>) -> HttpError {
+  let msg = errors
+    .iter()
+    .map(|error| match error.path.as_str() {
+      "" => error.message.clone(),
+      path => format!("{path}: {}", error.message),
+    })
+    .collect::<Vec<_>>()
+    .join(", ");
+  HttpError::bad_request(format!("Invalid data {msg}"))
+    .with_details(serde_json::to_value(&errors).unwrap_or_default())
+}
+
+/// Validate the data of a resource against the schema of a kind version.
+/// Returns the data with the defaults of the schema,
+/// every invalid field is listed in the details of the error.
+pub fn validate_data(
+  spec: &ResourceKindSpec,
+  data: &serde_json::Value,
+) -> HttpResult<serde_json::Value> {
+  let mut data = data.clone();
+  let Some(schema) = &spec.schema else {
+    return Ok(data);
+  };
+  let validator: Validator = Validator::options()
+    .with_draft(Draft::Draft7)
+    .build(schema)
+    .map_err(|err| HttpError::bad_request(format!("Invalid schema {}", err)))?;
+  apply_defaults(schema, &mut data);
+  let errors = validator
+    .iter_errors(&data)
+    .map(|err| ResourceKindValidationError {
+      path: err.instance_path.to_string(),
+      message: err.to_string(),
+    })
+    .collect::<Vec<_>>();
+  if !errors.is_empty() {
+    return Err(gen_validation_error(errors));
+  }
+  Ok(data)
+}
+
+/// Ensure an update of a resource doesn't change
+/// the immutable fields of its kind version
+pub fn ensure_immutable(
+  spec: &ResourceKindSpec,
+  current: &serde_json::Value,
+  data: &serde_json::Value,
+) -> HttpResult<()> {
+  let errors = spec
+    .immutable
+    .iter()
+    .flatten()
+    .filter(|pointer| current.pointer(pointer) != data.pointer(pointer))
+    .map(|pointer| ResourceKindValidationError {
+      path: pointer.clone(),
+      message: "Field is immutable".to_owned(),
+    })
+    .collect::<Vec<_>>();
+  if !errors.is_empty() {
+    return Err(gen_validation_error(errors));
+  }
+  Ok(())
+}
+
+/// Convert the data of a resource to the version of the given kind
+pub async fn convert(
+  resource: &Resource,
+  target: &ResourceKind,
+  state: &SystemState,
+) -> HttpResult<serde_json::Value> {
+  if resource.spec.version == target.version {
+    return Ok(resource.spec.data.clone());
+  }
+  let data = match &target.data.conversion {
+    None => {
+      return Err(HttpError::bad_request(format!(
+        "Version {} of {} doesn't declare a conversion",
+        target.version, target.name
+      )))
+    }
+    Some(ResourceKindConversion::Mappings(mappings)) => {
+      let mappings = mappings.get(&resource.spec.version).ok_or_else(|| {
+        HttpError::bad_request(format!(
+          "Version {} of {} doesn't declare a conversion from {}",
+          target.version, target.name, resource.spec.version
+        ))
+      })?;
+      apply_mappings(&resource.spec.data, mappings)?
+    }
+    Some(ResourceKindConversion::Controller) => {
+      let ctrl_client = utils::ctrl_client::CtrlClient::from_kind(
+        &target.name,
+        &target.data,
+        state,
+      )
+      .await?
+      .ok_or_else(|| {
+        HttpError::bad_request(format!(
+          "Version {} of {} doesn't have a controller",
+          target.version, target.name
+        ))
+      })?;
+      let payload = ResourceKindConvert {
+        name: resource.spec.resource_key.clone(),
+        from_version: resource.spec.version.clone(),
+        to_version: target.version.clone(),
+        data: resource.spec.data.clone(),
+      };
+      ctrl_client.convert(&payload).await?
+    }
+  };
+  validate_data(&target.data, &data)
+}
+
+/// List the resources of a kind
+async fn list_resources(
+  kind: &str,
+  state: &SystemState,
+) -> IoResult<Vec<Resource>> {
+  let filter =
+    GenericFilter::new().r#where("kind", GenericClause::Eq(kind.to_owned()));
+  ResourceDb::transform_read_by(&filter, &state.inner.pool).await
+}
+
+/// List the resources of a kind grouped by the version they are stored with
+pub async fn list_versions(
+  kind: &str,
+  state: &SystemState,
+) -> HttpResult<Vec<ResourceKindVersionResources>> {
+  let mut versions = BTreeMap::<String, Vec<String>>::new();
+  for resource in list_resources(kind, state).await? {
+    versions
+      .entry(resource.spec.version)
+      .or_default()
+      .push(resource.spec.resource_key);
+  }
+  Ok(
+    versions
+      .into_iter()
+      .map(|(version, resources)| ResourceKindVersionResources {
+        version,
+        resources,
+      })
+      .collect(),
+  )
+}
+
+/// Convert the resources of a kind to a version.
+/// Every resource is converted and validated before any is updated.
+pub async fn migrate(
+  kind: &str,
+  payload: &ResourceKindMigrate,
+  state: &SystemState,
+) -> HttpResult<ResourceKindMigration> {
+  let target: ResourceKind =
+    SpecDb::get_version(kind, &payload.version, &state.inner.pool)
+      .await?
+      .try_into()?;
+  let resources = list_resources(kind, state)
+    .await?
+    .into_iter()
+    .filter(|resource| {
+      resource.spec.version != target.version
+        && payload
+          .from
+          .as_ref()
+          .map(|from| from == &resource.spec.version)
+          .unwrap_or(true)
+        && !matches!(&resource.status, Some(status) if status.phase == ResourcePhase::Deleting)
+    })
+    .collect::<Vec<_>>();
+  let mut migrated = Vec::new();
+  let mut errors = Vec::new();
+  for resource in &resources {
+    match convert(resource, &target, state).await {
+      Ok(data) => migrated.push(ResourceKindMigrated {
+        name: resource.spec.resource_key.clone(),
+        from: resource.spec.version.clone(),
+        data,
+      }),
+      Err(err) => errors.push(format!(
+        "{} ({}): {}",
+        resource.spec.resource_key, resource.spec.version, err.msg
+      )),
+    }
+  }
+  if !errors.is_empty() {
+    return Err(HttpError::bad_request(format!(
+      "Unable to migrate resources to {}: {}",
+      target.version,
+      errors.join(", ")
+    )));
+  }
+  let dry_run = payload.dry_run.unwrap_or_default();
+  if !dry_run {
+    // Controllers are called for every resource before any is stored,
+    // the resources are then updated in a single transaction
+    let mut items = Vec::new();
+    for (resource, item) in resources.iter().zip(&migrated) {
+      let new_resource = ResourcePartial {
+        name: item.name.clone(),
+        kind: format!("{}/{}", target.name, target.version),
+        data: item.data.clone(),
+        metadata: resource.spec.metadata.clone(),
+        owners: None,
+        finalizers: None,
+      };
+      items.push(
+        ResourceDb::hook_create(&new_resource, Some(resource), state).await?,
+      );
+    }
+    let updated =
+      ResourceDb::update_from_specs(&items, &state.inner.pool).await?;
+    for resource in &updated {
+      state
+        .emit_normal_native_action_sync(resource, NativeEventAction::Update)
+        .await;
+    }
+  }
+  Ok(ResourceKindMigration {
+    version: target.version,
+    dry_run,
+    resources: migrated,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use nanocl_stubs::resource_kind::{ResourceKindController, ResourceKindWasm};
+
+  use super::*;
+
+  fn gen_mapping(
+    from: Option<&str>,
+    to: Option<&str>,
+    default: Option<serde_json::Value>,
+  ) -> ResourceKindMapping {
+    ResourceKindMapping {
+      from: from.map(|from| from.to_owned()),
+      to: to.map(|to| to.to_owned()),
+      default,
+    }
+  }
+
+  #[test]
+  fn mappings() {
+    let data = serde_json::json!({
+      "Username": "nanocl",
+      "Password": "secret",
+      "Groups": ["admin", "dev"],
+      "Legacy": true,
+    });
+    let mappings = vec![
+      gen_mapping(Some("/Username"), Some("/User/Name"), None),
+      gen_mapping(Some("/Groups/1"), Some("/User/Group"), None),
+      gen_mapping(Some("/Legacy"), None, None),
+      gen_mapping(Some("/Shell"), Some("/User/Shell"), Some("sh".into())),
+      gen_mapping(None, Some("/Version~12"), Some(2.into())),
+    ];
+    validate_mappings(&mappings).unwrap();
+    let converted = apply_mappings(&data, &mappings).unwrap();
+    assert_eq!(
+      converted,
+      serde_json::json!({
+        "User": { "Name": "nanocl", "Group": "dev", "Shell": "sh" },
+        "Password": "secret",
+        "Groups": ["admin"],
+        "Version/2": 2,
+      })
+    );
+    // The source data is left untouched
+    assert_eq!(data["Username"], "nanocl");
+    assert!(validate_mappings(&[gen_mapping(None, None, None)]).is_err());
+    assert!(
+      validate_mappings(&[gen_mapping(Some("Username"), None, None)]).is_err()
+    );
+    // Nothing is set without a value
+    let unchanged =
+      apply_mappings(&data, &[gen_mapping(None, Some("/Legacy/Flag"), None)])
+        .unwrap();
+    assert_eq!(unchanged, data);
+    // A field cannot be set on a scalar
+    assert!(apply_mappings(
+      &data,
+      &[gen_mapping(None, Some("/Legacy/Flag"), Some(true.into()))],
+    )
+    .is_err());
+  }
+  #[test]
+  fn controller() {
+    let gen_spec = |url: Option<&str>, controller| ResourceKindSpec {
+      schema: None,
+      url: url.map(|url| url.to_owned()),
+      conversion: None,
+      controller: Some(controller),
+      immutable: None,
+      wasm: None,
+    };
+    let tls = ResourceKindController {
+      tls: Some("controller-client".to_owned()),
+      ..Default::default()
+    };
+    validate_controller(&gen_spec(Some("https://ctrl:8443"), tls.clone()))
+      .unwrap();
+    assert!(
+      validate_controller(&gen_spec(Some("unix:///run/ctrl.sock"), tls))
+        .is_err()
+    );
+    let health = ResourceKindController {
+      health_path: Some("health".to_owned()),
+      ..Default::default()
+    };
+    assert!(
+      validate_controller(&gen_spec(Some("http://ctrl"), health)).is_err()
+    );
+    let timeout = ResourceKindController {
+      timeout: Some(0),
+      ..Default::default()
+    };
+    assert!(
+      validate_controller(&gen_spec(Some("http://ctrl"), timeout)).is_err()
+    );
+    assert!(validate_controller(&gen_spec(None, Default::default())).is_err());
+  }
+  #[test]
+  fn wasm() {
+    let gen_spec = |url: Option<&str>, wasm| ResourceKindSpec {
+      schema: None,
+      url: url.map(|url| url.to_owned()),
+      conversion: None,
+      controller: None,
+      immutable: None,
+      wasm: Some(wasm),
+    };
+    let wasm = ResourceKindWasm {
+      path: Some("/opt/ctrl.wasm".to_owned()),
+      ..Default::default()
+    };
+    validate_wasm(&gen_spec(None, wasm.clone())).unwrap();
+    assert!(
+      validate_wasm(&gen_spec(Some("http://ctrl"), wasm.clone())).is_err()
+    );
+    let both = ResourceKindWasm {
+      blob: Some("AGFzbQEAAAA=".to_owned()),
+      ..wasm.clone()
+    };
+    assert!(validate_wasm(&gen_spec(None, both)).is_err());
+    assert!(validate_wasm(&gen_spec(None, Default::default())).is_err());
+    let fuel = ResourceKindWasm {
+      fuel: Some(0),
+      ..wasm
+    };
+    assert!(validate_wasm(&gen_spec(None, fuel)).is_err());
+    let blob = ResourceKindWasm {
+      blob: Some("not a module".to_owned()),
+      ..Default::default()
+    };
+    assert!(validate_wasm(&gen_spec(None, blob)).is_err());
+  }
+  #[test]
+  fn validation() {
+    let spec = ResourceKindSpec {
+      schema: Some(serde_json::json!({
+        "type": "object",
+        "required": ["Username", "Port"],
+        "properties": {
+          "Username": { "type": "string" },
+          "Port": { "type": "integer", "default": 80 },
+          "Tls": {
+            "type": "object",
+            "properties": {
+              "Enabled": { "type": "boolean", "default": false },
+            },
+          },
+          "Backends": {
+            "type": "array",
+            "items": {
+              "type": "object",
+              "properties": {
+                "Weight": { "type": "integer", "default": 1 },
+              },
+            },
+          },
+        },
+      })),
+      url: None,
+      conversion: None,
+      controller: None,
+      immutable: Some(vec!["/Username".to_owned()]),
+      wasm: None,
+    };
+    let data = validate_data(
+      &spec,
+      &serde_json::json!({
+        "Username": "nanocl",
+        "Tls": {},
+        "Backends": [{}, { "Weight": 3 }],
+      }),
+    )
+    .unwrap();
+    assert_eq!(
+      data,
+      serde_json::json!({
+        "Username": "nanocl",
+        "Port": 80,
+        "Tls": { "Enabled": false },
+        "Backends": [{ "Weight": 1 }, { "Weight": 3 }],
+      })
+    );
+    let err = validate_data(
+      &spec,
+      &serde_json::json!({ "Port": "80", "Backends": [{ "Weight": "1" }] }),
+    )
+    .unwrap_err();
+    let errors = serde_json::from_value::<Vec<ResourceKindValidationError>>(
+      err.details.unwrap(),
+    )
+    .unwrap();
+    let mut paths = errors
+      .iter()
+      .map(|error| error.path.as_str())
+      .collect::<Vec<_>>();
+    paths.sort();
+    assert_eq!(paths, ["", "/Backends/0/Weight", "/Port"]);
+    let mut updated = data.clone();
+    updated["Port"] = 8080.into();
+    ensure_immutable(&spec, &data, &updated).unwrap();
+    updated["Username"] = "admin".into();
+    let err = ensure_immutable(&spec, &data, &updated).unwrap_err();
+    assert!(err.msg.contains("/Username"));
+    assert!(validate_immutable(&ResourceKindSpec {
+      immutable: Some(vec!["Username".to_owned()]),
+      ..spec
+    })
+    .is_err());
+  }
+}
@@ -1,18 +1,572 @@
+use std::{
+  collections::HashMap,
+  fs::Permissions,
+  os::unix::fs::PermissionsExt,
+  path::Path,
+  time::{Duration, Instant},
+};
+
+use bollard_next::{
+  auth::DockerCredentials,
+  service::{ContainerConfig, ContainerInspectResponse},
+};
 use futures::{stream::FuturesUnordered, StreamExt};
+use openssl::{base64, rand::rand_bytes};
+use tokio::{fs, io::AsyncWriteExt};
 
-use nanocl_error::io::{IoError, IoResult};
+use nanocl_error::{
+  http::{HttpError, HttpResult},
+  io::{FromIo, IoError, IoResult},
+};
 use nanocl_stubs::{
   generic::{GenericClause, GenericFilter},
   process::ProcessKind,
   proxy::ProxySslConfig,
+  secret::{
+    Secret, SecretBackend, SecretConsumer, SecretFile, SecretInspect,
+    SecretTlsIssued, SecretUpdate,
+  },
+  system::{EventActorKind, NativeEventAction, ObjPsStatusKind},
 };
-use tokio::fs;
 
 use crate::{
-  models::{SecretDb, SystemState},
+  models::{
+    CargoDb, JobDb, ObjPsStatusDb, ResourceDb, SecretDb, SecretEnvelope,
+    SecretKeyring, SecretUpdateDb, SpecDb, SpecUpdateDb, SystemState,
+  },
   repositories::generic::*,
+  utils, vars,
 };
 
+/// Get the path of the master key used to encrypt the secrets
+fn get_master_key_path(conf_dir: &str) -> String {
+  format!("{conf_dir}/secret.key")
+}
+
+/// Get the id of a master key stored in the envelope of the secrets
+fn gen_key_id(key: &[u8]) -> String {
+  openssl::sha::sha256(key)[..8]
+    .iter()
+    .fold(String::new(), |acc, byte| format!("{acc}{byte:02x}"))
+}
+
+/// Read a base64 encoded master key
+async fn read_master_key(path: &str) -> IoResult<Vec<u8>> {
+  let content = fs::read_to_string(path)
+    .await
+    .map_err(|err| err.map_err_context(|| path))?;
+  let key = base64::decode_block(content.trim())
+    .map_err(|err| IoError::invalid_data(path, err.to_string().as_str()))?;
+  if key.len() != 32 {
+    return Err(IoError::invalid_data(path, "Master key must be 32 bytes"));
+  }
+  Ok(key)
+}
+
+/// Generate a new master key and write it only readable by its owner
+async fn create_master_key(path: &str) -> IoResult<Vec<u8>> {
+  let mut key = vec![0; 32];
+  rand_bytes(&mut key)
+    .map_err(|err| IoError::other(path, err.to_string().as_str()))?;
+  if let Some(parent) = Path::new(path).parent() {
+    fs::create_dir_all(parent)
+      .await
+      .map_err(|err| err.map_err_context(|| path))?;
+  }
+  let mut file = fs::OpenOptions::new()
+    .write(true)
+    .create_new(true)
+    .mode(0o600)
+    .open(path)
+    .await
+    .map_err(|err| err.map_err_context(|| path))?;
+  file
+    .write_all(base64::encode_block(&key).as_bytes())
+    .await
+    .map_err(|err| err.map_err_context(|| path))?;
+  Ok(key)
+}
+
+/// Get the id of the master key used to encrypt sealed data
+fn get_envelope_key_id(data: &serde_json::Value) -> Option<String> {
+  serde_json::from_value::<SecretEnvelope>(data.clone())
+    .ok()
+    .map(|envelope| envelope.key_id)
+}
+
+/// Load the master keys from the `conf_dir` and create it when missing.
+/// When a rotation was interrupted the new key is already the current one,
+/// the secrets are migrated to it on boot by [seal_all](seal_all).
+pub async fn load_keyring(conf_dir: &str) -> IoResult<SecretKeyring> {
+  let path = get_master_key_path(conf_dir);
+  let key = if Path::new(&path).exists() {
+    read_master_key(&path).await?
+  } else {
+    log::info!("secret::load_keyring: creating master key {path}");
+    create_master_key(&path).await?
+  };
+  let current = gen_key_id(&key);
+  let mut keyring = SecretKeyring {
+    current: current.clone(),
+    keys: HashMap::from([(current, key)]),
+  };
+  let new_path = format!("{path}.new");
+  if Path::new(&new_path).exists() {
+    let key = read_master_key(&new_path).await?;
+    keyring.current = gen_key_id(&key);
+    keyring.keys.insert(keyring.current.clone(), key);
+  }
+  Ok(keyring)
+}
+
+/// Get a copy of the master keys of the daemon
+pub fn get_keyring(state: &SystemState) -> IoResult<SecretKeyring> {
+  Ok(state.inner.secret_keys.read()?.clone())
+}
+
+/// Encrypt with the current master key every secret that isn't already.
+/// It migrate the secrets stored in plain text and finish the key rotations.
+/// Secrets encrypted by a master key we don't know are left untouched.
+pub async fn seal_all(state: &SystemState) -> IoResult<usize> {
+  let keyring = get_keyring(state)?;
+  let secrets =
+    SecretDb::read_by(&GenericFilter::new(), &state.inner.pool).await?;
+  let mut count = 0;
+  let mut failed = 0;
+  for secret in secrets {
+    if get_envelope_key_id(&secret.data).as_ref() == Some(&keyring.current) {
+      continue;
+    }
+    let data = match secret.unseal(&keyring) {
+      Ok(data) => data,
+      Err(err) => {
+        log::warn!("secret::seal_all: {}: {err}", secret.key);
+        failed += 1;
+        continue;
+      }
+    };
+    let update = SecretUpdateDb {
+      data: Some(SecretDb::seal(&secret.key, &data, &keyring)?),
+      ..Default::default()
+    };
+    SecretDb::update_pk(&secret.key, update, &state.inner.pool).await?;
+    count += 1;
+  }
+  // The versions kept in the histories must stay readable for a revert
+  let filter = GenericFilter::new()
+    .r#where("kind_name", GenericClause::Eq("Secret".to_owned()));
+  let histories = SpecDb::read_by(&filter, &state.inner.pool).await?;
+  for history in histories {
+    if get_envelope_key_id(&history.data).as_ref() == Some(&keyring.current) {
+      continue;
+    }
+    let data =
+      match SecretDb::unseal_data(&history.kind_key, &history.data, &keyring) {
+        Ok(data) => data,
+        Err(err) => {
+          log::warn!("secret::seal_all: {}: {err}", history.key);
+          failed += 1;
+          continue;
+        }
+      };
+    let update = SpecUpdateDb {
+      data: Some(SecretDb::seal(&history.kind_key, &data, &keyring)?),
+    };
+    SpecDb::update_pk(&history.key, update, &state.inner.pool).await?;
+  }
+  let path = get_master_key_path(&state.inner.config.conf_dir);
+  let new_path = format!("{path}.new");
+  if failed == 0 && Path::new(&new_path).exists() {
+    fs::rename(&new_path, &path)
+      .await
+      .map_err(|err| err.map_err_context(|| &path))?;
+    let mut keyring = state.inner.secret_keys.write()?;
+    let current = keyring.current.clone();
+    keyring.keys.retain(|id, _| id == &current);
+  }
+  Ok(count)
+}
+
+/// Rotate the master key used to encrypt the secrets.
+/// Every secret is encrypted again with a new data key
+/// and the returned count is the number of secrets re-encrypted.
+pub async fn rotate_key(state: &SystemState) -> HttpResult<usize> {
+  let path = get_master_key_path(&state.inner.config.conf_dir);
+  let new_path = format!("{path}.new");
+  if Path::new(&new_path).exists() {
+    return Err(HttpError::conflict(
+      "A rotation of the master key is already in progress",
+    ));
+  }
+  let key = create_master_key(&new_path).await?;
+  {
+    let id = gen_key_id(&key);
+    let mut keyring = state.inner.secret_keys.write().map_err(IoError::from)?;
+    keyring.keys.insert(id.clone(), key);
+    keyring.current = id;
+  }
+  let count = seal_all(state).await?;
+  if Path::new(&new_path).exists() {
+    return Err(HttpError::internal_server_error(format!(
+      "Some secrets couldn't be re-encrypted, {count} secrets migrated"
+    )));
+  }
+  Ok(count)
+}
+
+/// Read secrets by a filter and decrypt their data
+async fn read_unsealed_by(
+  filter: &GenericFilter,
+  state: &SystemState,
+) -> IoResult<Vec<Secret>> {
+  let keyring = get_keyring(state)?;
+  SecretDb::read_by(filter, &state.inner.pool)
+    .await?
+    .into_iter()
+    .map(|secret| {
+      let data = secret.unseal(&keyring)?;
+      let mut secret: Secret = secret.try_into()?;
+      secret.data = data;
+      Ok(secret)
+    })
+    .collect()
+}
+
+/// Get a secret with its data decrypted for an explicit inspect
+pub async fn inspect_by_pk(pk: &str, state: &SystemState) -> IoResult<Secret> {
+  let filter =
+    GenericFilter::new().r#where("key", GenericClause::Eq(pk.to_owned()));
+  read_unsealed_by(&filter, state)
+    .await?
+    .pop()
+    .ok_or_else(|| IoError::not_found("Secret", pk))
+}
+
+/// Filters matching the objects using a secret in their `Secrets`
+/// or as their `ImagePullSecret`
+fn gen_consumer_filters(key: &str) -> [GenericFilter; 2] {
+  [
+    GenericFilter::new().r#where(
+      "data",
+      GenericClause::Contains(serde_json::json!({ "Secrets": [key] })),
+    ),
+    GenericFilter::new().r#where(
+      "data",
+      GenericClause::Contains(serde_json::json!({ "ImagePullSecret": key })),
+    ),
+  ]
+}
+
+/// Filters matching the proxy rules using a secret as ssl config
+/// of a rule or of an upstream target
+fn gen_rule_consumer_filters(key: &str) -> [GenericFilter; 3] {
+  [
+    serde_json::json!({ "Rules": [{ "Ssl": key }] }),
+    serde_json::json!({ "Rules": [{ "Target": { "Ssl": key } }] }),
+    serde_json::json!({ "Rules": [{ "Locations": [{ "Target": { "Ssl": key } }] }] }),
+  ]
+  .map(|data| {
+    GenericFilter::new()
+      .r#where("kind", GenericClause::Eq("ncproxy.io/rule".to_owned()))
+      .r#where("data", GenericClause::Contains(data))
+  })
+}
+
+/// List the cargoes, jobs and proxy rules using a secret
+pub async fn list_consumers(
+  key: &str,
+  state: &SystemState,
+) -> IoResult<Vec<SecretConsumer>> {
+  let mut consumers = Vec::new();
+  for filter in gen_consumer_filters(key) {
+    for cargo in CargoDb::transform_read_by(&filter, &state.inner.pool).await? {
+      consumers.push(SecretConsumer {
+        kind: EventActorKind::Cargo,
+        key: cargo.spec.cargo_key,
+      });
+    }
+    for job in JobDb::transform_read_by(&filter, &state.inner.pool).await? {
+      consumers.push(SecretConsumer {
+        kind: EventActorKind::Job,
+        key: job.name,
+      });
+    }
+  }
+  for filter in gen_rule_consumer_filters(key) {
+    for resource in
+      ResourceDb::transform_read_by(&filter, &state.inner.pool).await?
+    {
+      consumers.push(SecretConsumer {
+        kind: EventActorKind::Resource,
+        key: resource.spec.resource_key,
+      });
+    }
+  }
+  let mut unique = Vec::new();
+  for consumer in consumers {
+    if !unique.contains(&consumer) {
+      unique.push(consumer);
+    }
+  }
+  Ok(unique)
+}
+
+/// Get a secret with its data decrypted and the objects using it
+pub async fn inspect(
+  pk: &str,
+  reveal: bool,
+  state: &SystemState,
+) -> IoResult<SecretInspect> {
+  let mut secret = inspect_by_pk(pk, state).await?;
+  if !reveal {
+    secret = mask_secret(secret);
+  }
+  let consumers = list_consumers(pk, state).await?;
+  Ok(SecretInspect {
+    name: secret.name,
+    created_at: secret.created_at,
+    updated_at: secret.updated_at,
+    kind: secret.kind,
+    immutable: secret.immutable,
+    metadata: secret.metadata,
+    data: secret.data,
+    consumers,
+  })
+}
+
+/// Mask the values of the data of a secret.
+/// The names of the env variables are kept to know which ones are defined.
+pub fn mask(kind: &str, data: &serde_json::Value) -> serde_json::Value {
+  match data {
+    serde_json::Value::String(value) if kind == "nanocl.io/env" => {
+      match value.split_once('=') {
+        Some((name, _)) => format!("{name}={}", vars::SECRET_MASK).into(),
+        None => vars::SECRET_MASK.into(),
+      }
+    }
+    serde_json::Value::String(_) => vars::SECRET_MASK.into(),
+    serde_json::Value::Array(items) => {
+      items.iter().map(|item| mask(kind, item)).collect()
+    }
+    serde_json::Value::Object(map) => map
+      .iter()
+      .map(|(key, value)| (key.clone(), mask(kind, value)))
+      .collect::<serde_json::Map<_, _>>()
+      .into(),
+    value => value.clone(),
+  }
+}
+
+/// Replace the data of a secret with masked values
+pub fn mask_secret(secret: Secret) -> Secret {
+  Secret {
+    data: mask(&secret.kind, &secret.data),
+    ..secret
+  }
+}
+
+/// Value of the label listing the names of the env variables of secrets
+pub fn gen_env_label(envs: &[String]) -> String {
+  envs
+    .iter()
+    .map(|env| env.split_once('=').map(|(name, _)| name).unwrap_or(env))
+    .collect::<Vec<_>>()
+    .join(",")
+}
+
+/// Check if an env variable is sourced from a secret
+/// using the label of the container config
+fn is_secret_env(env: &str, config: &ContainerConfig) -> bool {
+  let Some(label) = config
+    .labels
+    .as_ref()
+    .and_then(|labels| labels.get(vars::SECRET_ENV_LABEL))
+  else {
+    return false;
+  };
+  let name = env.split_once('=').map(|(name, _)| name).unwrap_or(env);
+  label.split(',').any(|secret_env| secret_env == name)
+}
+
+/// Mask the env variables sourced from secrets in a container config
+/// before it's stored or returned by the api
+pub fn redact_env(config: &mut ContainerConfig) {
+  let Some(envs) = config.env.clone() else {
+    return;
+  };
+  let envs = envs
+    .into_iter()
+    .map(|env| {
+      if !is_secret_env(&env, config) {
+        return env;
+      }
+      match env.split_once('=') {
+        Some((name, _)) => format!("{name}={}", vars::SECRET_MASK),
+        None => env,
+      }
+    })
+    .collect();
+  config.env = Some(envs);
+}
+
+/// Mask the env variables sourced from secrets in the inspect of a container
+pub fn redact_container(
+  mut container: ContainerInspectResponse,
+) -> ContainerInspectResponse {
+  if let Some(config) = container.config.as_mut() {
+    redact_env(config);
+  }
+  container
+}
+
+/// Remove the env variables sourced from secrets in a container config
+pub fn strip_env(config: &mut ContainerConfig) {
+  let Some(envs) = config.env.clone() else {
+    return;
+  };
+  let envs = envs
+    .into_iter()
+    .filter(|env| !is_secret_env(env, config))
+    .collect();
+  config.env = Some(envs);
+}
+
+/// Fire a rolling update of the running cargoes having the secret
+/// in their `Secrets` so their instances get the new env and files.
+/// Return the number of cargoes updated.
+pub async fn rollout(key: &str, state: &SystemState) -> IoResult<usize> {
+  let filter = GenericFilter::new()
+    .r#where(
+      "data",
+      GenericClause::Contains(serde_json::json!({ "Secrets": [key] })),
+    )
+    .r#where(
+      "status.wanted",
+      GenericClause::Eq(ObjPsStatusKind::Start.to_string()),
+    );
+  let cargoes = CargoDb::transform_read_by(&filter, &state.inner.pool).await?;
+  log::debug!("secret::rollout: {} cargoes using {key}", cargoes.len());
+  for cargo in &cargoes {
+    ObjPsStatusDb::update_actual_status(
+      &cargo.spec.cargo_key,
+      &ObjPsStatusKind::Updating,
+      &state.inner.pool,
+    )
+    .await?;
+    state
+      .emit_normal_native_action_sync(cargo, NativeEventAction::Updating)
+      .await;
+  }
+  Ok(cargoes.len())
+}
+
+/// Get the docker credentials to authenticate with a registry from a secret
+pub async fn load_registry_credentials(
+  name: &str,
+  state: &SystemState,
+) -> IoResult<DockerCredentials> {
+  let secret = inspect_by_pk(name, state).await?;
+  let credentials = serde_json::from_value::<DockerCredentials>(secret.data)
+    .map_err(|err| err.map_err_context(|| "GetCredentials"))?;
+  Ok(credentials)
+}
+
+/// Read the value of a `nanocl.io/external` secret from its backend
+/// and convert it to a secret of the resolved kind.
+/// The value is reused until its refresh interval is elapsed.
+async fn resolve_external(
+  secret: Secret,
+  state: &SystemState,
+) -> IoResult<Secret> {
+  let external = utils::secret_backend::validate(&secret.data)?;
+  let cache_key = format!("{}@{}", secret.name, secret.updated_at);
+  let cached = state
+    .inner
+    .external_secrets
+    .read()
+    .map_err(IoError::from)?
+    .get(&cache_key)
+    .filter(|(resolved_at, _)| {
+      resolved_at.elapsed() < Duration::from_secs(external.refresh.unwrap_or(0))
+    })
+    .map(|(_, value)| value.clone());
+  let value = match cached {
+    Some(value) => value,
+    None => {
+      let value = match &external.backend {
+        SecretBackend::File => {
+          let keyring = get_keyring(state)?;
+          utils::secret_backend::read_file(
+            &external.path,
+            &state.inner.config.state_dir,
+            &keyring,
+          )
+          .await
+        }
+        SecretBackend::Vault(vault) => {
+          let token = inspect_by_pk(&vault.token_secret, state).await?;
+          let token = token.data["Token"].as_str().ok_or_else(|| {
+            IoError::invalid_data(
+              vault.token_secret.as_str(),
+              "Missing Token in the data",
+            )
+          })?;
+          utils::secret_backend::read_vault(vault, &external.path, token).await
+        }
+      }
+      .map_err(|err| {
+        IoError::other(
+          "nanocl.io/external",
+          &format!("Unable to resolve secret {}: {err}", secret.name),
+        )
+      })?;
+      let mut cache = state
+        .inner
+        .external_secrets
+        .write()
+        .map_err(IoError::from)?;
+      let prefix = format!("{}@", secret.name);
+      cache.retain(|key, _| !key.starts_with(&prefix));
+      cache.insert(cache_key, (Instant::now(), value.clone()));
+      value
+    }
+  };
+  let data = utils::secret_backend::convert(&external, value)?;
+  Ok(Secret {
+    kind: external.kind,
+    data,
+    ..secret
+  })
+}
+
+/// Read secrets by a filter including the `nanocl.io/external` secrets
+/// and keep the ones of the given kinds once resolved
+async fn read_resolved_by(
+  secrets: &[String],
+  kinds: &[&str],
+  state: &SystemState,
+) -> IoResult<Vec<Secret>> {
+  let mut filter_kinds = kinds
+    .iter()
+    .map(|kind| kind.to_string())
+    .collect::<Vec<_>>();
+  filter_kinds.push("nanocl.io/external".to_owned());
+  let filter = GenericFilter::new()
+    .r#where("key", GenericClause::In(secrets.to_vec()))
+    .r#where("kind", GenericClause::In(filter_kinds));
+  let mut resolved = Vec::new();
+  for secret in read_unsealed_by(&filter, state).await? {
+    let secret = match secret.kind.as_str() {
+      "nanocl.io/external" => resolve_external(secret, state).await?,
+      _ => secret,
+    };
+    if kinds.contains(&secret.kind.as_str()) {
+      resolved.push(secret);
+    }
+  }
+  Ok(resolved)
+}
+
 /// Transform and optional vector of secrets to a vector of envs from the database
 ///
 pub async fn load_env_secrets(
@@ -21,10 +575,7 @@ pub async fn load_env_secrets(
 ) -> IoResult<Vec<String>> {
   let mut env_secrets: Vec<String> = Vec::new();
   if let Some(secrets) = &secrets {
-    let filter = GenericFilter::new()
-      .r#where("key", GenericClause::In(secrets.clone()))
-      .r#where("kind", GenericClause::Eq("nanocl.io/env".to_owned()));
-    let secrets = SecretDb::transform_read_by(&filter, &state.inner.pool)
+    let secrets = read_resolved_by(secrets, &["nanocl.io/env"], state)
       .await?
       .into_iter()
       .map(|secret| {
@@ -38,9 +589,253 @@ pub async fn load_env_secrets(
   Ok(env_secrets)
 }
 
-/// Load tls secrets from the database and create them as file to be mount inside a container
+/// Decode the files of a `nanocl.io/file` secret.
+/// The targets must be plain file names to stay inside the secret directory.
+pub fn parse_files(
+  data: &serde_json::Value,
+) -> IoResult<Vec<(SecretFile, Vec<u8>)>> {
+  let files = serde_json::from_value::<Vec<SecretFile>>(data.clone())
+    .map_err(|err| err.map_err_context(|| "nanocl.io/file"))?;
+  let mut targets = Vec::new();
+  files
+    .into_iter()
+    .map(|file| {
+      let target = file.target.as_str();
+      if target.is_empty()
+        || target == "."
+        || target == ".."
+        || target.contains('/')
+      {
+        return Err(IoError::invalid_data(
+          "nanocl.io/file",
+          &format!("Invalid target {target}"),
+        ));
+      }
+      if let Some(mode) = file.mode.filter(|mode| *mode > 0o777) {
+        return Err(IoError::invalid_data(
+          "nanocl.io/file",
+          &format!("Invalid mode {mode:o} for {target} expected at most 777"),
+        ));
+      }
+      if targets.contains(&file.target) {
+        return Err(IoError::invalid_data(
+          "nanocl.io/file",
+          &format!("Duplicated target {target}"),
+        ));
+      }
+      targets.push(file.target.clone());
+      let content = base64::decode_block(&file.data).map_err(|err| {
+        IoError::invalid_data(
+          "nanocl.io/file",
+          &format!("Invalid data for {target}: {err}"),
+        )
+      })?;
+      Ok((file, content))
+    })
+    .collect()
+}
+
+/// Validate the data of a secret for its kind
+pub fn validate_data(kind: &str, data: &serde_json::Value) -> HttpResult<()> {
+  match kind {
+    "nanocl.io/file" => {
+      parse_files(data).map_err(HttpError::bad_request)?;
+    }
+    "nanocl.io/external" => {
+      utils::secret_backend::validate(data).map_err(HttpError::bad_request)?;
+    }
+    "nanocl.io/tls-issued" => {
+      serde_json::from_value::<SecretTlsIssued>(data.clone()).map_err(
+        |err| HttpError::bad_request(format!("nanocl.io/tls-issued: {err}")),
+      )?;
+    }
+    _ => {}
+  }
+  Ok(())
+}
+
+/// Restore a secret to a version of its history.
+/// The data of the version is validated for the kind of the secret
+/// like when the secret is updated.
+pub async fn revert(
+  key: &str,
+  history: &SpecDb,
+  state: &SystemState,
+) -> HttpResult<Secret> {
+  if history.kind_name != "Secret" || history.kind_key != key {
+    return Err(HttpError::not_found(format!(
+      "Secret history {} doesn't exist",
+      history.key
+    )));
+  }
+  let secret = SecretDb::read_by_pk(key, &state.inner.pool).await?;
+  let keyring = get_keyring(state)?;
+  let data = SecretDb::unseal_data(key, &history.data, &keyring)?;
+  validate_data(&secret.kind, &data)?;
+  let secret = SecretDb::revert(key, history, &state.inner.pool)
+    .await?
+    .try_into()?;
+  Ok(secret)
+}
+
+/// Issue with the internal CA the certificate of a `nanocl.io/tls-issued`
+/// secret and return its data with the certificate
+pub async fn issue_tls(
+  data: &serde_json::Value,
+  state: &SystemState,
+) -> IoResult<serde_json::Value> {
+  let mut tls = serde_json::from_value::<SecretTlsIssued>(data.clone())
+    .map_err(|err| {
+      IoError::invalid_data("nanocl.io/tls-issued", &err.to_string())
+    })?;
+  let ca = utils::ca::load(&state.inner.config.state_dir).await?;
+  let lifetime = tls.lifetime.unwrap_or(vars::TLS_ISSUED_LIFETIME);
+  let issued = utils::ca::issue(&ca, &tls.sans, lifetime)?;
+  tls.certificate = Some(issued.cert);
+  tls.certificate_key = Some(issued.key);
+  tls.certificate_client = Some(issued.ca);
+  tls.not_after = Some(issued.not_after);
+  Ok(serde_json::to_value(tls)?)
+}
+
+/// An issued certificate is renewed when less than a third of its lifetime remain
+fn need_renewal(tls: &SecretTlsIssued, now: chrono::NaiveDateTime) -> bool {
+  let Some(not_after) = tls.not_after else {
+    return true;
+  };
+  let lifetime = tls.lifetime.unwrap_or(vars::TLS_ISSUED_LIFETIME);
+  let threshold = chrono::Duration::days(i64::from(lifetime)) / 3;
+  not_after - now < threshold
+}
+
+/// Renew a `nanocl.io/tls-issued` secret when it's close to its expiry
+/// and update the cargoes using it, returns if it has been renewed
+async fn renew(
+  secret: SecretDb,
+  now: chrono::NaiveDateTime,
+  state: &SystemState,
+) -> IoResult<bool> {
+  let keyring = get_keyring(state)?;
+  let data = secret.unseal(&keyring)?;
+  let mut secret: Secret = secret.try_into()?;
+  secret.data = data;
+  let tls = serde_json::from_value::<SecretTlsIssued>(secret.data.clone())?;
+  if !need_renewal(&tls, now) {
+    return Ok(false);
+  }
+  log::info!("secret::renew_issued: renewing {}", secret.name);
+  let update = SecretUpdate {
+    data: issue_tls(&secret.data, state).await?,
+    metadata: secret.metadata.clone(),
+  };
+  let secret: Secret =
+    SecretDb::update_sealed(&secret.name, &update, &keyring, &state.inner.pool)
+      .await?
+      .try_into()?;
+  state
+    .emit_normal_native_action_sync(&secret, NativeEventAction::Update)
+    .await;
+  rollout(&secret.name, state).await?;
+  Ok(true)
+}
+
+/// Renew the `nanocl.io/tls-issued` secrets close to their expiry.
+/// An update event is emitted for each renewed secret
+/// and the cargoes using them are updated to mount the new certificate.
+/// A secret failing to renew is logged and retried on the next run,
+/// the number of renewed secrets is returned.
+pub async fn renew_issued(state: &SystemState) -> IoResult<usize> {
+  let filter = GenericFilter::new()
+    .r#where("kind", GenericClause::Eq("nanocl.io/tls-issued".to_owned()));
+  let now = chrono::Utc::now().naive_utc();
+  let mut count = 0;
+  for secret in SecretDb::read_by(&filter, &state.inner.pool).await? {
+    let name = secret.key.clone();
+    match renew(secret, now, state).await {
+      Ok(true) => count += 1,
+      Ok(false) => {}
+      Err(err) => {
+        log::error!("secret::renew_issued: {name}: {err}");
+      }
+    }
+  }
+  Ok(count)
+}
+
+/// Parse the certificates of a tls or issued tls secret
+fn parse_tls(secret: &Secret) -> IoResult<ProxySslConfig> {
+  let tls = match secret.kind.as_str() {
+    "nanocl.io/tls-issued" => {
+      serde_json::from_value::<SecretTlsIssued>(secret.data.clone())?
+        .to_ssl_config()
+        .ok_or_else(|| IoError::not_found("Issued certificate", &secret.name))?
+    }
+    _ => serde_json::from_value::<ProxySslConfig>(secret.data.clone())?,
+  };
+  Ok(tls)
+}
+
+/// Load the certificates of a tls, issued tls or resolved external secret
+pub async fn load_tls(
+  key: &str,
+  state: &SystemState,
+) -> IoResult<ProxySslConfig> {
+  let kinds = ["nanocl.io/tls", "nanocl.io/tls-issued"];
+  let secret = read_resolved_by(&[key.to_owned()], &kinds, state)
+    .await?
+    .into_iter()
+    .next()
+    .ok_or_else(|| IoError::not_found("Tls secret", key))?;
+  parse_tls(&secret)
+}
+
+/// Write a tls secret as `.crt`, `.key` and `.ca` files
+async fn write_tls_secret(secret_dir: &str, secret: Secret) -> IoResult<()> {
+  let tls = parse_tls(&secret)?;
+  fs::write(format!("{secret_dir}/{}.crt", secret.name), tls.certificate)
+    .await?;
+  fs::write(
+    format!("{secret_dir}/{}.key", secret.name),
+    tls.certificate_key,
+  )
+  .await?;
+  if let Some(certificate_client) = tls.certificate_client {
+    fs::write(
+      format!("{secret_dir}/{}.ca", secret.name),
+      certificate_client,
+    )
+    .await?;
+  }
+  Ok(())
+}
+
+/// Write the files of a file secret in a directory named as the secret.
+/// Files are owned by root with a 0o400 mode unless the secret says otherwise.
+async fn write_file_secret(secret_dir: &str, secret: Secret) -> IoResult<()> {
+  let dir = format!("{secret_dir}/{}", secret.name);
+  fs::create_dir_all(&dir)
+    .await
+    .map_err(|err| err.map_err_context(|| &dir))?;
+  for (file, content) in parse_files(&secret.data)? {
+    let path = format!("{dir}/{}", file.target);
+    fs::write(&path, content)
+      .await
+      .map_err(|err| err.map_err_context(|| &path))?;
+    let mode = file.mode.unwrap_or(0o400);
+    fs::set_permissions(&path, Permissions::from_mode(mode))
+      .await
+      .map_err(|err| err.map_err_context(|| &path))?;
+    if file.uid.is_some() || file.gid.is_some() {
+      std::os::unix::fs::chown(&path, file.uid, file.gid)
+        .map_err(|err| err.map_err_context(|| &path))?;
+    }
+  }
+  Ok(())
+}
+
+/// Load tls, issued tls, file and resolved external secrets from the database and create them as file to be mount inside a container
 ///
-pub async fn create_tls_secrets(
+pub async fn create_secret_files(
   key: &str,
   kind: &ProcessKind,
   secrets: &Option<Vec<String>>,
@@ -48,41 +843,100 @@ pub async fn create_tls_secrets(
 ) -> IoResult<String> {
   let secret_dir =
     format!("{}/secrets/{}/{}", state.inner.config.state_dir, kind, key);
+  fs::create_dir_all(&secret_dir)
+    .await
+    .map_err(|err| err.map_err_context(|| &secret_dir))?;
   if let Some(secrets) = &secrets {
-    let filter = GenericFilter::new()
-      .r#where("key", GenericClause::In(secrets.clone()))
-      .r#where("kind", GenericClause::Eq("nanocl.io/tls".to_owned()));
-    let secrets =
-      SecretDb::transform_read_by(&filter, &state.inner.pool).await?;
+    let kinds = ["nanocl.io/tls", "nanocl.io/tls-issued", "nanocl.io/file"];
+    let secrets = read_resolved_by(secrets, &kinds, state).await?;
     secrets
       .into_iter()
       .map(|secret| {
-        let secrets_dir = secret_dir.clone();
+        let secret_dir = secret_dir.clone();
         async move {
-          let tls = serde_json::from_value::<ProxySslConfig>(secret.data)?;
-          fs::write(
-            format!("{secrets_dir}/{}.crt", secret.name),
-            tls.certificate,
-          )
-          .await?;
-          fs::write(
-            format!("{secrets_dir}/{}.key", secret.name),
-            tls.certificate_key,
-          )
-          .await?;
-          if let Some(certificate_client) = tls.certificate_client {
-            fs::write(
-              format!("{secrets_dir}/{}.ca", secret.name),
-              certificate_client,
-            )
-            .await?;
+          match secret.kind.as_str() {
+            "nanocl.io/file" => write_file_secret(&secret_dir, secret).await,
+            _ => write_tls_secret(&secret_dir, secret).await,
           }
-          Ok::<_, IoError>(())
         }
       })
       .collect::<FuturesUnordered<_>>()
       .collect::<Vec<_>>()
-      .await;
+      .await
+      .into_iter()
+      .collect::<IoResult<Vec<_>>>()?;
   }
   Ok(secret_dir)
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn files() {
+    let data = serde_json::json!([
+      { "Target": "credentials.json", "Data": "e30=" },
+      { "Target": "id_rsa", "Data": "a2V5", "Mode": 0o600 },
+    ]);
+    let files = parse_files(&data).unwrap();
+    assert_eq!(files[0].1, b"{}");
+    assert_eq!(files[1].0.mode, Some(0o600));
+    let data = serde_json::json!([{ "Target": "../id_rsa", "Data": "a2V5" }]);
+    assert!(parse_files(&data).is_err());
+    let data = serde_json::json!([{ "Target": "id_rsa", "Data": "a2V5", "Mode": 0o4755 }]);
+    assert!(parse_files(&data).is_err());
+    let data = serde_json::json!([
+      { "Target": "id_rsa", "Data": "a2V5" },
+      { "Target": "id_rsa", "Data": "a2V5" },
+    ]);
+    assert!(parse_files(&data).is_err());
+    let data = serde_json::json!([{ "Target": "id_rsa", "Data": "%%" }]);
+    assert!(parse_files(&data).is_err());
+  }
+
+  #[test]
+  fn redaction() {
+    let data = serde_json::json!(["PASSWORD=secret", "EMPTY"]);
+    assert_eq!(
+      mask("nanocl.io/env", &data),
+      serde_json::json!(["PASSWORD=********", "********"])
+    );
+    let data = serde_json::json!({ "Password": "secret", "Mode": 256 });
+    assert_eq!(
+      mask("nanocl.io/generic", &data),
+      serde_json::json!({ "Password": "********", "Mode": 256 })
+    );
+    let envs = vec!["PASSWORD=secret".to_owned(), "TOKEN=abc".to_owned()];
+    let label = gen_env_label(&envs);
+    assert_eq!(label, "PASSWORD,TOKEN");
+    let mut config = ContainerConfig {
+      env: Some(vec!["PORT=80".to_owned(), "PASSWORD=secret".to_owned()]),
+      labels: Some(HashMap::from([(vars::SECRET_ENV_LABEL.to_owned(), label)])),
+      ..Default::default()
+    };
+    let mut stripped = config.clone();
+    redact_env(&mut config);
+    assert_eq!(
+      config.env,
+      Some(vec!["PORT=80".to_owned(), "PASSWORD=********".to_owned()])
+    );
+    strip_env(&mut stripped);
+    assert_eq!(stripped.env, Some(vec!["PORT=80".to_owned()]));
+  }
+
+  #[test]
+  fn renewal() {
+    let now = chrono::Utc::now().naive_utc();
+    let mut tls = SecretTlsIssued {
+      sans: vec!["api.global.c".to_owned()],
+      lifetime: Some(90),
+      ..Default::default()
+    };
+    assert!(need_renewal(&tls, now));
+    tls.not_after = Some(now + chrono::Duration::days(89));
+    assert!(!need_renewal(&tls, now));
+    tls.not_after = Some(now + chrono::Duration::days(29));
+    assert!(need_renewal(&tls, now));
+  }
+}
@@ -0,0 +1,334 @@
+use std::path::{Component, Path};
+
+use ntex::http::Client;
+use openssl::base64;
+use tokio::fs;
+
+use nanocl_error::io::{FromIo, IoError, IoResult};
+use nanocl_stubs::secret::{
+  SecretBackend, SecretExternal, SecretSeal, SecretVault,
+};
+
+use crate::{
+  models::{SecretDb, SecretEnvelope, SecretKeyring},
+  vars,
+};
+
+/// Kinds an external secret can be resolved to
+const RESOLVED_KINDS: [&str; 3] =
+  ["nanocl.io/env", "nanocl.io/tls", "nanocl.io/file"];
+
+/// Get the directory of the sealed files of the file backend
+fn get_file_dir(state_dir: &str) -> String {
+  format!("{state_dir}/secrets/external")
+}
+
+/// Ensure a path of the file backend stay inside its directory
+fn check_path(path: &str) -> IoResult<()> {
+  let is_relative = !path.is_empty()
+    && Path::new(path)
+      .components()
+      .all(|component| matches!(component, Component::Normal(_)));
+  if !is_relative {
+    return Err(IoError::invalid_input(
+      "nanocl.io/external",
+      &format!("Invalid path {path}"),
+    ));
+  }
+  Ok(())
+}
+
+/// Validate the reference of an external secret
+pub fn validate(data: &serde_json::Value) -> IoResult<SecretExternal> {
+  let external = serde_json::from_value::<SecretExternal>(data.clone())
+    .map_err(|err| {
+      IoError::invalid_data("nanocl.io/external", &err.to_string())
+    })?;
+  if !RESOLVED_KINDS.contains(&external.kind.as_str()) {
+    return Err(IoError::invalid_data(
+      "nanocl.io/external",
+      &format!("Unsupported kind {}", external.kind),
+    ));
+  }
+  match &external.backend {
+    SecretBackend::File => check_path(&external.path)?,
+    SecretBackend::Vault(_) => {
+      if external.path.trim_matches('/').is_empty() {
+        return Err(IoError::invalid_data(
+          "nanocl.io/external",
+          "Path is required",
+        ));
+      }
+    }
+  }
+  Ok(external)
+}
+
+/// Seal a value to be stored by the file backend.
+/// The path is bound to the value so the file cannot be moved.
+pub fn seal_file(
+  seal: &SecretSeal,
+  keyring: &SecretKeyring,
+) -> IoResult<serde_json::Value> {
+  check_path(&seal.path)?;
+  SecretDb::seal(&seal.path, &seal.data, keyring)
+}
+
+/// Read a value sealed with the master key from the file backend
+pub async fn read_file(
+  path: &str,
+  state_dir: &str,
+  keyring: &SecretKeyring,
+) -> IoResult<serde_json::Value> {
+  check_path(path)?;
+  let file = format!("{}/{path}", get_file_dir(state_dir));
+  let content = fs::read(&file)
+    .await
+    .map_err(|err| err.map_err_context(|| &file))?;
+  let data = serde_json::from_slice::<serde_json::Value>(&content)
+    .map_err(|err| err.map_err_context(|| &file))?;
+  if serde_json::from_value::<SecretEnvelope>(data.clone()).is_err() {
+    return Err(IoError::invalid_data(
+      file.as_str(),
+      "The file is not sealed with the master key",
+    ));
+  }
+  SecretDb::unseal_data(path, &data, keyring)
+}
+
+/// Read a value from a Vault KV v2 engine with the given token
+pub async fn read_vault(
+  vault: &SecretVault,
+  path: &str,
+  token: &str,
+) -> IoResult<serde_json::Value> {
+  let mount = vault.mount.as_deref().unwrap_or(vars::VAULT_DEFAULT_MOUNT);
+  let url = format!(
+    "{}/v1/{}/data/{}",
+    vault.address.trim_end_matches('/'),
+    mount.trim_matches('/'),
+    path.trim_matches('/'),
+  );
+  let client = Client::build()
+    .timeout(ntex::time::Millis::from_secs(vars::SECRET_BACKEND_TIMEOUT))
+    .finish();
+  let mut res = client
+    .get(&url)
+    .header("X-Vault-Token", token)
+    .send()
+    .await
+    .map_err(|err| err.map_err_context(|| &url))?;
+  let status = res.status();
+  let body = res
+    .body()
+    .limit(4 * 1024 * 1024)
+    .await
+    .map_err(|err| err.map_err_context(|| &url))?;
+  let body =
+    serde_json::from_slice::<serde_json::Value>(&body).unwrap_or_default();
+  if !status.is_success() {
+    let errors = body["errors"]
+      .as_array()
+      .map(|errors| {
+        errors
+          .iter()
+          .filter_map(|error| error.as_str())
+          .collect::<Vec<_>>()
+          .join(", ")
+      })
+      .unwrap_or_default();
+    return Err(IoError::other(
+      url.as_str(),
+      &format!("Vault responded {status} {errors}"),
+    ));
+  }
+  body
+    .pointer("/data/data")
+    .filter(|data| data.is_object())
+    .cloned()
+    .ok_or_else(|| IoError::invalid_data(url.as_str(), "Missing data"))
+}
+
+/// Get a scalar value as a string
+fn to_string(value: &serde_json::Value) -> String {
+  match value {
+    serde_json::Value::String(value) => value.clone(),
+    _ => value.to_string(),
+  }
+}
+
+/// Convert a value read from a backend to the data of the resolved kind.
+/// Objects are converted to `KEY=value` for `nanocl.io/env`
+/// and to one file per key for `nanocl.io/file`.
+pub fn convert(
+  external: &SecretExternal,
+  value: serde_json::Value,
+) -> IoResult<serde_json::Value> {
+  let value = match &external.key {
+    None => value,
+    Some(key) => {
+      let value = value.get(key).cloned().ok_or_else(|| {
+        IoError::not_found(external.path.as_str(), key.as_str())
+      })?;
+      if external.kind == "nanocl.io/tls" {
+        return Ok(value);
+      }
+      serde_json::json!({ key: value })
+    }
+  };
+  let data = match (external.kind.as_str(), value) {
+    ("nanocl.io/env", serde_json::Value::Object(values)) => values
+      .iter()
+      .map(|(name, value)| format!("{name}={}", to_string(value)))
+      .collect::<Vec<_>>()
+      .into(),
+    ("nanocl.io/file", serde_json::Value::Object(values)) => values
+      .iter()
+      .map(|(target, value)| {
+        serde_json::json!({
+          "Target": target,
+          "Data": base64::encode_block(to_string(value).as_bytes()),
+        })
+      })
+      .collect::<Vec<_>>()
+      .into(),
+    (_, value) => value,
+  };
+  Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::HashMap;
+
+  use ntex::web;
+
+  use super::*;
+
+  fn gen_external(kind: &str, key: Option<&str>) -> SecretExternal {
+    SecretExternal {
+      kind: kind.to_owned(),
+      backend: SecretBackend::File,
+      path: "app/db".to_owned(),
+      key: key.map(|key| key.to_owned()),
+      refresh: None,
+    }
+  }
+
+  #[test]
+  fn conversion() {
+    let value = serde_json::json!({ "USER": "nanocl", "PORT": 5432 });
+    let env = gen_external("nanocl.io/env", None);
+    assert_eq!(
+      convert(&env, value.clone()).unwrap(),
+      serde_json::json!(["PORT=5432", "USER=nanocl"])
+    );
+    let env = gen_external("nanocl.io/env", Some("USER"));
+    assert_eq!(
+      convert(&env, value.clone()).unwrap(),
+      serde_json::json!(["USER=nanocl"])
+    );
+    let env = gen_external("nanocl.io/env", Some("PASSWORD"));
+    assert!(convert(&env, value.clone()).is_err());
+    let file = gen_external("nanocl.io/file", Some("USER"));
+    assert_eq!(
+      convert(&file, value).unwrap(),
+      serde_json::json!([{ "Target": "USER", "Data": "bmFub2Ns" }])
+    );
+    assert!(validate(&serde_json::json!({
+      "Kind": "nanocl.io/env",
+      "Backend": "File",
+      "Path": "../secret.key",
+    }))
+    .is_err());
+    assert!(validate(&serde_json::json!({
+      "Kind": "nanocl.io/container-registry",
+      "Backend": "File",
+      "Path": "registry",
+    }))
+    .is_err());
+  }
+
+  #[ntex::test]
+  async fn file() {
+    let state_dir = std::env::temp_dir()
+      .join(format!("secret-backend-{}", std::process::id()))
+      .display()
+      .to_string();
+    std::fs::create_dir_all(format!("{}/app", get_file_dir(&state_dir)))
+      .unwrap();
+    let keyring = SecretKeyring {
+      current: "test".to_owned(),
+      keys: HashMap::from([("test".to_owned(), vec![7; 32])]),
+    };
+    let seal = SecretSeal {
+      path: "app/db".to_owned(),
+      data: serde_json::json!({ "PASSWORD": "nanocl" }),
+    };
+    let sealed = seal_file(&seal, &keyring).unwrap();
+    let path = format!("{}/app/db", get_file_dir(&state_dir));
+    std::fs::write(&path, sealed.to_string()).unwrap();
+    let data = read_file("app/db", &state_dir, &keyring).await.unwrap();
+    assert_eq!(data, seal.data);
+    // A sealed file moved to another path cannot be read
+    std::fs::rename(&path, format!("{}/app/api", get_file_dir(&state_dir)))
+      .unwrap();
+    assert!(read_file("app/api", &state_dir, &keyring).await.is_err());
+    // A plain file is refused
+    std::fs::write(&path, seal.data.to_string()).unwrap();
+    assert!(read_file("app/db", &state_dir, &keyring).await.is_err());
+    std::fs::remove_dir_all(&state_dir).unwrap();
+  }
+
+  /// Mock of the KV v2 read endpoint of Vault
+  async fn read_kv(req: web::HttpRequest) -> web::HttpResponse {
+    let token = req
+      .headers()
+      .get("X-Vault-Token")
+      .and_then(|token| token.to_str().ok());
+    if token != Some("root") {
+      return web::HttpResponse::Forbidden()
+        .json(&serde_json::json!({ "errors": ["permission denied"] }));
+    }
+    web::HttpResponse::Ok().json(&serde_json::json!({
+      "data": {
+        "data": { "PASSWORD": "nanocl" },
+        "metadata": { "version": 1 },
+      },
+    }))
+  }
+
+  #[ntex::test]
+  async fn vault() {
+    let srv = web::test::server(|| {
+      web::App::new().service(
+        web::resource("/v1/kv/data/app/db").route(web::get().to(read_kv)),
+      )
+    });
+    let vault = SecretVault {
+      address: srv.url(""),
+      mount: Some("kv".to_owned()),
+      token_secret: "vault-token".to_owned(),
+    };
+    let data = read_vault(&vault, "app/db", "root").await.unwrap();
+    assert_eq!(data, serde_json::json!({ "PASSWORD": "nanocl" }));
+    let err = read_vault(&vault, "app/db", "wrong").await.unwrap_err();
+    assert!(err.to_string().contains("permission denied"));
+    assert!(read_vault(&vault, "app/api", "root").await.is_err());
+  }
+
+  /// Run against a Vault dev server with `VAULT_ADDR` and `VAULT_TOKEN` set
+  /// and a value written with `vault kv put secret/nanocl PASSWORD=nanocl`
+  #[ignore]
+  #[ntex::test]
+  async fn vault_dev_server() {
+    let vault = SecretVault {
+      address: std::env::var("VAULT_ADDR").unwrap(),
+      mount: None,
+      token_secret: "vault-token".to_owned(),
+    };
+    let token = std::env::var("VAULT_TOKEN").unwrap();
+    let data = read_vault(&vault, "nanocl", &token).await.unwrap();
+    assert_eq!(data["PASSWORD"], "nanocl");
+  }
+}
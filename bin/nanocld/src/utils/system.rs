@@ -38,13 +38,14 @@ pub async fn sync_process(
   let created_at = instance.created.clone().unwrap_or_default();
   let name = instance.name.clone().unwrap_or_default().replace('/', "");
   log::trace!("system::sync_process: {name}");
-  let container_instance_data = serde_json::to_value(instance)
+  let instance = utils::secret::redact_container(instance.clone());
+  let container_instance_data = serde_json::to_value(&instance)
     .map_err(|err| err.map_err_context(|| "Process"))?;
   let current_res =
     ProcessDb::transform_read_by_pk(&id, &state.inner.pool).await;
   match current_res {
     Ok(current_instance) => {
-      if current_instance.data == *instance {
+      if current_instance.data == instance {
         log::info!("system::sync_process: {name} is up to date");
         return Ok(());
       }
@@ -190,7 +191,9 @@ pub async fn sync_processes(state: &SystemState) -> IoResult<()> {
       if cargo_inspected.contains_key(key) {
         continue;
       }
-      let config = container.config.clone().unwrap_or_default();
+      let mut config = container.config.clone().unwrap_or_default();
+      // Env sourced from secrets must not be copied in the cargo spec
+      utils::secret::strip_env(&mut config);
       let mut config: bollard_next::container::Config = config.into();
       config.host_config.clone_from(&container.host_config);
       let new_cargo = CargoSpecPartial {
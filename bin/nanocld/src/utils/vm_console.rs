@@ -0,0 +1,193 @@
+use std::{io::SeekFrom, time::Duration};
+
+use ntex::{channel::mpsc, rt};
+use tokio::{
+  fs,
+  io::{AsyncReadExt, AsyncSeekExt},
+};
+
+use nanocl_error::io::{FromIo, IoError, IoResult};
+use nanocl_stubs::{
+  process::{OutputKind, OutputLog},
+  vm::VmLogQuery,
+};
+
+/// Get the path of the serial console log of a VM from its state directory
+pub fn get_log_path(vm_dir: &str) -> String {
+  format!("{vm_dir}/console.log")
+}
+
+/// Rotate the console log when it's bigger than `max_size`.
+/// The content is copied into `{path}.1` and the file is truncated
+/// so qemu can keep writing into it without being restarted.
+/// Older files are shifted up to `max_files`.
+/// It return true if the file has been rotated.
+pub async fn rotate(
+  path: &str,
+  max_size: u64,
+  max_files: usize,
+) -> IoResult<bool> {
+  let Ok(metadata) = fs::metadata(path).await else {
+    return Ok(false);
+  };
+  if metadata.len() < max_size || max_files == 0 {
+    return Ok(false);
+  }
+  for index in (1..max_files).rev() {
+    let from = format!("{path}.{index}");
+    if fs::metadata(&from).await.is_ok() {
+      fs::rename(&from, format!("{path}.{}", index + 1))
+        .await
+        .map_err(|err| err.map_err_context(|| &from))?;
+    }
+  }
+  fs::copy(path, format!("{path}.1"))
+    .await
+    .map_err(|err| err.map_err_context(|| path))?;
+  fs::OpenOptions::new()
+    .write(true)
+    .truncate(true)
+    .open(path)
+    .await
+    .map_err(|err| err.map_err_context(|| path))?;
+  Ok(true)
+}
+
+/// Read the content of the console log from `offset` to the end
+async fn read_from(path: &str, offset: u64) -> IoResult<(String, u64)> {
+  let mut file = fs::File::open(path)
+    .await
+    .map_err(|err| err.map_err_context(|| path))?;
+  file
+    .seek(SeekFrom::Start(offset))
+    .await
+    .map_err(|err| err.map_err_context(|| path))?;
+  let mut buf = Vec::new();
+  let len = file
+    .read_to_end(&mut buf)
+    .await
+    .map_err(|err| err.map_err_context(|| path))?;
+  Ok((
+    String::from_utf8_lossy(&buf).to_string(),
+    offset + len as u64,
+  ))
+}
+
+/// Only keep the last `tail` lines of the given content
+fn tail_lines(content: &str, tail: Option<usize>) -> String {
+  let Some(tail) = tail else {
+    return content.to_owned();
+  };
+  let lines = content.split_inclusive('\n').collect::<Vec<_>>();
+  let start = lines.len().saturating_sub(tail);
+  lines[start..].concat()
+}
+
+/// Create a stream of the console logs of a VM.
+/// The last lines are sent first and when `follow` is set
+/// the file is watched to send the new lines as they are written.
+pub fn stream_logs(
+  path: &str,
+  query: &VmLogQuery,
+) -> IoResult<mpsc::Receiver<IoResult<OutputLog>>> {
+  let tail = match query.tail.as_deref() {
+    None | Some("all") => None,
+    Some(tail) => Some(tail.parse::<usize>().map_err(|err| {
+      IoError::invalid_input("tail", &format!("{tail}: {err}"))
+    })?),
+  };
+  let follow = query.follow.unwrap_or_default();
+  let path = path.to_owned();
+  let (tx, rx) = mpsc::channel();
+  rt::spawn(async move {
+    let (content, mut offset) = match read_from(&path, 0).await {
+      Ok(res) => res,
+      // The VM never booted yet so there is nothing to read
+      Err(_) if fs::metadata(&path).await.is_err() => (String::new(), 0),
+      Err(err) => {
+        let _ = tx.send(Err(err));
+        return;
+      }
+    };
+    let content = tail_lines(&content, tail);
+    if !content.is_empty()
+      && tx
+        .send(Ok(OutputLog {
+          kind: OutputKind::Console,
+          data: content,
+        }))
+        .is_err()
+    {
+      return;
+    }
+    while follow && !tx.is_closed() {
+      ntex::time::sleep(Duration::from_millis(500)).await;
+      let len = match fs::metadata(&path).await {
+        Ok(metadata) => metadata.len(),
+        Err(_) => continue,
+      };
+      // The file have been rotated
+      if len < offset {
+        offset = 0;
+      }
+      if len == offset {
+        continue;
+      }
+      let data = match read_from(&path, offset).await {
+        Ok((data, new_offset)) => {
+          offset = new_offset;
+          data
+        }
+        Err(err) => {
+          let _ = tx.send(Err(err));
+          return;
+        }
+      };
+      let log = OutputLog {
+        kind: OutputKind::Console,
+        data,
+      };
+      if tx.send(Ok(log)).is_err() {
+        return;
+      }
+    }
+  });
+  Ok(rx)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn tail() {
+    let content = "a\nb\nc\n";
+    assert_eq!(tail_lines(content, None), content);
+    assert_eq!(tail_lines(content, Some(2)), "b\nc\n");
+    assert_eq!(tail_lines(content, Some(10)), content);
+    assert_eq!(tail_lines(content, Some(0)), "");
+  }
+
+  #[ntex::test]
+  async fn rotate_file() {
+    let dir =
+      std::env::temp_dir().join(format!("vm-console-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = get_log_path(dir.to_str().unwrap());
+    std::fs::write(&path, "first\n").unwrap();
+    assert!(!rotate(&path, 1024, 2).await.unwrap());
+    assert!(rotate(&path, 4, 2).await.unwrap());
+    std::fs::write(&path, "second\n").unwrap();
+    assert!(rotate(&path, 4, 2).await.unwrap());
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "");
+    assert_eq!(
+      std::fs::read_to_string(format!("{path}.1")).unwrap(),
+      "second\n"
+    );
+    assert_eq!(
+      std::fs::read_to_string(format!("{path}.2")).unwrap(),
+      "first\n"
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+  }
+}
@@ -1,14 +1,23 @@
-use std::process::Stdio;
+use std::{fs::Permissions, os::unix::fs::PermissionsExt, process::Stdio};
 
 use ntex::{channel::mpsc::Receiver, rt, util::Bytes, web};
 use tokio::{fs, io::AsyncReadExt, process::Command};
 
 use nanocl_error::http::{HttpError, HttpResult};
 
-use nanocl_stubs::vm_image::{VmImageCloneStream, VmImageResizePayload};
+use nanocl_stubs::{
+  generic::GenericFilter,
+  system::{NativeEventAction, ObjPsStatusKind},
+  vm::Vm,
+  vm_image::{VmImageCloneStream, VmImageExportQuery, VmImageResizePayload},
+  vm_spec::{VmDataDisk, VmDiskBus},
+};
 
 use crate::{
-  models::{Pool, QemuImgInfo, SystemState, VmImageDb, VmImageUpdateDb},
+  models::{
+    Pool, QemuImgInfo, SystemState, VmDb, VmImageDb, VmImageUpdateDb,
+    VmSnapshotDb,
+  },
   repositories::generic::*,
   utils,
 };
@@ -18,8 +27,13 @@ pub async fn delete_by_pk(pk: &str, state: &SystemState) -> HttpResult<()> {
   let vm_image = VmImageDb::read_by_pk(pk, &state.inner.pool).await?;
   let children = VmImageDb::read_by_parent(pk, &state.inner.pool).await?;
   if !children.is_empty() {
+    let children = children
+      .iter()
+      .map(|child| child.name.clone())
+      .collect::<Vec<_>>()
+      .join(", ");
     return Err(HttpError::conflict(format!(
-      "Vm image {pk} has children images please delete them first"
+      "Vm image {pk} is used by linked clones please delete or flatten them first: {children}"
     )));
   }
   let filepath = vm_image.path.clone();
@@ -32,15 +46,27 @@ pub async fn delete_by_pk(pk: &str, state: &SystemState) -> HttpResult<()> {
 
 /// Get the info of a vm image using qemu-img info command and parse the output
 pub async fn get_info(path: &str) -> HttpResult<QemuImgInfo> {
-  let output = Command::new("qemu-img")
-    .args(["info", "--output=json", path])
-    .output()
-    .await
-    .map_err(|err| {
-      HttpError::internal_server_error(format!(
-        "Failed to get info of {path}: {err}"
-      ))
-    })?;
+  exec_info(&["info", "--output=json", path], path).await
+}
+
+/// Same as `get_info` but read the image as the given format
+/// instead of letting qemu-img probe it, used for untrusted content
+pub async fn get_info_as(path: &str, format: &str) -> HttpResult<QemuImgInfo> {
+  exec_info(&["info", "--output=json", "-f", format, path], path).await
+}
+
+/// Run qemu-img info with the given args and parse the output
+async fn exec_info(args: &[&str], path: &str) -> HttpResult<QemuImgInfo> {
+  let output =
+    Command::new("qemu-img")
+      .args(args)
+      .output()
+      .await
+      .map_err(|err| {
+        HttpError::internal_server_error(format!(
+          "Failed to get info of {path}: {err}"
+        ))
+      })?;
   if !output.status.success() {
     return Err(HttpError::internal_server_error(format!(
       "Failed to get info of {path}: {output:#?}"
@@ -257,12 +283,142 @@ pub async fn clone(
   Ok(rx)
 }
 
+/// Stream a file to the client and remove it after when `remove` is set
+fn stream_file(path: String, remove: bool) -> Receiver<HttpResult<Bytes>> {
+  let (tx, rx) = ntex::channel::mpsc::channel::<HttpResult<Bytes>>();
+  rt::spawn(async move {
+    let mut file = match fs::File::open(&path).await {
+      Ok(file) => file,
+      Err(err) => {
+        let _ = tx.send(Err(HttpError::internal_server_error(format!(
+          "Unable to open {path}: {err}"
+        ))));
+        return;
+      }
+    };
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+      match file.read(&mut buf).await {
+        Ok(0) => break,
+        Ok(n) => {
+          if tx.send(Ok(Bytes::copy_from_slice(&buf[..n]))).is_err() {
+            break;
+          }
+        }
+        Err(err) => {
+          let _ = tx.send(Err(HttpError::internal_server_error(format!(
+            "Unable to read {path}: {err}"
+          ))));
+          break;
+        }
+      }
+    }
+    if remove {
+      if let Err(err) = fs::remove_file(&path).await {
+        log::warn!("Error while deleting the file {path}: {err}");
+      }
+    }
+  });
+  rx
+}
+
+/// Run a qemu-img command used to export a vm image
+async fn exec_export(args: &[&str], image: &VmImageDb) -> HttpResult<()> {
+  let output =
+    Command::new("qemu-img")
+      .args(args)
+      .output()
+      .await
+      .map_err(|err| {
+        HttpError::internal_server_error(format!(
+          "Unable to export vm image {}: {err}",
+          image.name
+        ))
+      })?;
+  if !output.status.success() {
+    return Err(HttpError::internal_server_error(format!(
+      "Unable to export vm image {}: {}",
+      image.name,
+      String::from_utf8_lossy(&output.stderr)
+    )));
+  }
+  Ok(())
+}
+
+/// Export a vm image as a qcow2 stream.
+/// When flatten is asked the image is converted into a temporary file
+/// merging its backing chain, otherwise an image with a parent is exported
+/// as an overlay referencing the file name of its parent so it can be used
+/// next to the exported parent.
+pub async fn export(
+  image: &VmImageDb,
+  query: &VmImageExportQuery,
+  state: &SystemState,
+) -> HttpResult<Receiver<HttpResult<Bytes>>> {
+  let flatten = query.flatten.unwrap_or_default();
+  let compress = query.compress.unwrap_or_default();
+  let parent = match (&image.parent, flatten) {
+    (Some(parent), false) => {
+      Some(VmImageDb::read_by_pk(parent, &state.inner.pool).await?)
+    }
+    _ => None,
+  };
+  if parent.is_none() && !flatten && !compress && image.format == "qcow2" {
+    return Ok(stream_file(image.path.clone(), false));
+  }
+  let tmp_path = format!(
+    "{}/vms/images/{}.{}.export",
+    state.inner.config.state_dir,
+    image.name,
+    uuid::Uuid::new_v4()
+  );
+  let mut args = vec!["convert", "-U", "-O", "qcow2"];
+  if compress {
+    args.push("-c");
+  }
+  if let Some(parent) = &parent {
+    args.extend(["-B", &parent.path, "-F", &parent.format]);
+  }
+  args.push(&image.path);
+  args.push(&tmp_path);
+  let mut res = exec_export(&args, image).await;
+  if let (Ok(_), Some(parent)) = (&res, &parent) {
+    let backing = std::path::Path::new(&parent.path)
+      .file_name()
+      .map(|name| name.to_string_lossy().to_string())
+      .unwrap_or_default();
+    let args = [
+      "rebase",
+      "-u",
+      "-f",
+      "qcow2",
+      "-b",
+      &backing,
+      "-F",
+      &parent.format,
+      &tmp_path,
+    ];
+    res = exec_export(&args, image).await;
+  }
+  if let Err(err) = res {
+    let _ = fs::remove_file(&tmp_path).await;
+    return Err(err);
+  }
+  Ok(stream_file(tmp_path, true))
+}
+
 /// Resize a vm image to a new size
 pub async fn resize(
   image: &VmImageDb,
   payload: &VmImageResizePayload,
   pool: &Pool,
 ) -> HttpResult<VmImageDb> {
+  if image.kind == "Template" {
+    return Err(HttpError::bad_request(format!(
+      "Vm image {} is a template and cannot be resized",
+      image.name
+    )));
+  }
   let img_path = image.path.clone();
   let size = format!("{}G", payload.size);
   let mut args = vec!["resize"];
@@ -288,15 +444,9 @@ pub async fn resize(
     )));
   }
   let img_info = get_info(&img_path).await?;
-  let res = VmImageDb::update_pk(
-    &image.name,
-    VmImageUpdateDb {
-      size_actual: img_info.actual_size,
-      size_virtual: img_info.virtual_size,
-    },
-    pool,
-  )
-  .await?;
+  let res =
+    VmImageDb::update_pk(&image.name, VmImageUpdateDb::from(img_info), pool)
+      .await?;
   Ok(res)
 }
 
@@ -339,3 +489,346 @@ pub async fn create(
   let image = VmImageDb::create_from(vm_image, &state.inner.pool).await?;
   Ok(image)
 }
+
+/// Mark a `Base` vm image as a read-only template or turn a template
+/// back into a `Base` image.
+/// A template cannot be modified, the vms created from it use linked clones.
+/// It can only be turned back into a `Base` image when no clone depends on it.
+pub async fn set_template(
+  name: &str,
+  template: bool,
+  state: &SystemState,
+) -> HttpResult<VmImageDb> {
+  let image = VmImageDb::read_by_pk(name, &state.inner.pool).await?;
+  let (from, to, mode) = if template {
+    ("Base", "Template", 0o444)
+  } else {
+    ("Template", "Base", 0o644)
+  };
+  if image.kind == to {
+    return Ok(image);
+  }
+  if image.kind != from {
+    return Err(HttpError::bad_request(format!(
+      "Vm image {name} is a {} image and not a {from} image",
+      image.kind
+    )));
+  }
+  if template {
+    let used_by = read_attached_vms(name, state)
+      .await?
+      .into_iter()
+      .filter(|vm| {
+        vm.spec
+          .disks
+          .clone()
+          .unwrap_or_default()
+          .iter()
+          .any(|disk| {
+            disk.image.as_deref() == Some(name)
+              && !disk.read_only.unwrap_or_default()
+          })
+      })
+      .map(|vm| vm.spec.vm_key)
+      .collect::<Vec<_>>();
+    if !used_by.is_empty() {
+      return Err(HttpError::conflict(format!(
+        "Vm image {name} is attached in read-write mode to vms: {}",
+        used_by.join(", ")
+      )));
+    }
+  } else {
+    let children = VmImageDb::read_by_parent(name, &state.inner.pool).await?;
+    if !children.is_empty() {
+      let children = children
+        .iter()
+        .map(|child| child.name.clone())
+        .collect::<Vec<_>>()
+        .join(", ");
+      return Err(HttpError::conflict(format!(
+        "Vm template {name} is used by linked clones: {children}"
+      )));
+    }
+  }
+  fs::set_permissions(&image.path, Permissions::from_mode(mode))
+    .await
+    .map_err(|err| {
+      HttpError::internal_server_error(format!(
+        "Unable to change permissions of {}: {err}",
+        image.path
+      ))
+    })?;
+  let image = VmImageDb::update_pk(
+    name,
+    VmImageUpdateDb {
+      kind: Some(to.to_owned()),
+      ..VmImageUpdateDb::default()
+    },
+    &state.inner.pool,
+  )
+  .await?;
+  Ok(image)
+}
+
+/// Detach a linked clone from the image it's based on.
+/// The data of the parent image is merged into the clone
+/// and into the oldest snapshots of the vms using it.
+/// The vms using the clone must be stopped.
+pub async fn flatten(name: &str, state: &SystemState) -> HttpResult<VmImageDb> {
+  let image = VmImageDb::read_by_pk(name, &state.inner.pool).await?;
+  let Some(parent) = &image.parent else {
+    return Err(HttpError::bad_request(format!(
+      "Vm image {name} is not a linked clone"
+    )));
+  };
+  let parent = VmImageDb::read_by_pk(parent, &state.inner.pool).await?;
+  let vms = read_attached_vms(name, state).await?;
+  if let Some(vm) = vms
+    .iter()
+    .find(|vm| vm.status.actual == ObjPsStatusKind::Start)
+  {
+    return Err(HttpError::conflict(format!(
+      "Vm image {name} is used by the running vm {} please stop it first",
+      vm.spec.vm_key
+    )));
+  }
+  let mut paths = vec![image.path.clone()];
+  for vm in &vms {
+    let snapshots =
+      VmSnapshotDb::read_by_vm(&vm.spec.vm_key, &state.inner.pool).await?;
+    paths.extend(snapshots.into_iter().map(|snapshot| snapshot.path));
+  }
+  for path in paths {
+    let info = get_info(&path).await?;
+    if info.backing_filename.as_deref() != Some(parent.path.as_str()) {
+      continue;
+    }
+    let output = Command::new("qemu-img")
+      .args(["rebase", "-f", "qcow2", "-b", "", &path])
+      .output()
+      .await
+      .map_err(|err| {
+        HttpError::internal_server_error(format!(
+          "Unable to flatten {path}: {err}"
+        ))
+      })?;
+    if !output.status.success() {
+      return Err(HttpError::internal_server_error(format!(
+        "Unable to flatten {path}: {}",
+        String::from_utf8_lossy(&output.stderr)
+      )));
+    }
+  }
+  let info = get_info(&image.path).await?;
+  let image = VmImageDb::update_pk(
+    name,
+    VmImageUpdateDb {
+      parent: Some(None),
+      ..info.into()
+    },
+    &state.inner.pool,
+  )
+  .await?;
+  Ok(image)
+}
+
+/// Get the name of the image created for a data disk of a VM
+pub fn gen_data_name(disk_name: &str, vm_key: &str) -> String {
+  format!("{disk_name}.{vm_key}")
+}
+
+/// Record with a warning the data disks created for a vm that are no longer
+/// attached to it. Their images are kept to not lose data,
+/// they can be attached again or deleted like any other image.
+pub fn record_orphaned_disks(
+  vm: &Vm,
+  kept: &[VmDataDisk],
+  action: NativeEventAction,
+  state: &SystemState,
+) {
+  for disk in vm.spec.disks.iter().flatten() {
+    let name = gen_data_name(&disk.name, &vm.spec.vm_key);
+    let is_created = disk.image.as_deref() == Some(name.as_str());
+    if !is_created || kept.iter().any(|k| k.image == disk.image) {
+      continue;
+    }
+    let note = format!(
+      "Data disk {name} is kept detached, delete it with its image when it's no longer needed"
+    );
+    log::info!("vm_image::record_orphaned_disks: {note}");
+    state.emit_warning_native_action(vm, action.clone(), Some(note));
+  }
+}
+
+/// Create an empty qcow2 image as a `Data` image.
+/// Stored in the state directory and added to the database.
+/// It's used as a data disk of a VM.
+pub async fn create_data(
+  name: &str,
+  size: u64,
+  state: &SystemState,
+) -> HttpResult<VmImageDb> {
+  if VmImageDb::read_by_pk(name, &state.inner.pool).await.is_ok() {
+    return Err(HttpError::conflict(format!("Vm image {name} already used")));
+  }
+  let path =
+    format!("{}/vms/images/{}.img", state.inner.config.state_dir, name);
+  let size = format!("{size}G");
+  let output = Command::new("qemu-img")
+    .args(["create", "-f", "qcow2", &path, &size])
+    .output()
+    .await
+    .map_err(|err| {
+      HttpError::internal_server_error(format!(
+        "Failed to create data image {name}: {err}"
+      ))
+    })?;
+  output.status.success().then_some(()).ok_or(
+    HttpError::internal_server_error(format!(
+      "Failed to create data image {name}: {output:#?}"
+    )),
+  )?;
+  let img_info = get_info(&path).await?;
+  let image = VmImageDb {
+    name: name.to_owned(),
+    node_name: state.inner.config.hostname.clone(),
+    created_at: chrono::Utc::now().naive_utc(),
+    kind: "Data".into(),
+    path,
+    format: img_info.format,
+    size_actual: img_info.actual_size,
+    size_virtual: img_info.virtual_size,
+    parent: None,
+  };
+  let image = VmImageDb::create_from(image, &state.inner.pool).await?;
+  Ok(image)
+}
+
+/// Get the vms using the image as os or data disk
+pub async fn read_attached_vms(
+  name: &str,
+  state: &SystemState,
+) -> HttpResult<Vec<Vm>> {
+  let vms =
+    VmDb::transform_read_by(&GenericFilter::new(), &state.inner.pool).await?;
+  let vms = vms
+    .into_iter()
+    .filter(|vm| {
+      vm.spec.disk.image == name
+        || vm
+          .spec
+          .disks
+          .clone()
+          .unwrap_or_default()
+          .iter()
+          .any(|disk| disk.image.as_deref() == Some(name))
+    })
+    .collect::<Vec<_>>();
+  Ok(vms)
+}
+
+/// Return a conflict error if the image is attached to a vm
+pub async fn ensure_detached(
+  name: &str,
+  state: &SystemState,
+) -> HttpResult<()> {
+  let vms = read_attached_vms(name, state).await?;
+  if vms.is_empty() {
+    return Ok(());
+  }
+  let vms = vms
+    .iter()
+    .map(|vm| vm.spec.vm_key.clone())
+    .collect::<Vec<_>>()
+    .join(", ");
+  Err(HttpError::conflict(format!(
+    "Vm image {name} is attached to vms: {vms}"
+  )))
+}
+
+/// Validate the data disks of a vm and create the images
+/// of the disks that doesn't reference an existing one.
+/// It return the disks with the image to use set.
+pub async fn prepare_data_disks(
+  vm_key: &str,
+  disks: &[VmDataDisk],
+  state: &SystemState,
+) -> HttpResult<Vec<VmDataDisk>> {
+  let mut names = Vec::new();
+  let mut boot_orders = vec![1];
+  for disk in disks {
+    if names.contains(&disk.name) {
+      return Err(HttpError::bad_request(format!(
+        "Disk {} is defined more than once",
+        disk.name
+      )));
+    }
+    names.push(disk.name.clone());
+    if let Some(boot_order) = disk.boot_order {
+      if boot_orders.contains(&boot_order) {
+        return Err(HttpError::bad_request(format!(
+          "Boot order {boot_order} of disk {} is already used",
+          disk.name
+        )));
+      }
+      boot_orders.push(boot_order);
+    }
+  }
+  let mut prepared = Vec::new();
+  for disk in disks {
+    let mut disk = disk.clone();
+    let name = match &disk.image {
+      Some(image) => image.clone(),
+      None => {
+        let name = gen_data_name(&disk.name, vm_key);
+        if VmImageDb::read_by_pk(&name, &state.inner.pool)
+          .await
+          .is_err()
+        {
+          create_data(&name, disk.size.unwrap_or(20), state).await?;
+        }
+        disk.image = Some(name.clone());
+        name
+      }
+    };
+    let image = VmImageDb::read_by_pk(&name, &state.inner.pool).await?;
+    if image.kind == "Snapshot" {
+      return Err(HttpError::bad_request(format!(
+        "Image {name} is the os disk of a vm and cannot be attached"
+      )));
+    }
+    let read_only = disk.read_only.unwrap_or_default();
+    if image.kind == "Template" && !read_only {
+      return Err(HttpError::bad_request(format!(
+        "Image {name} is a template and can only be attached read-only"
+      )));
+    }
+    if read_only && disk.bus == Some(VmDiskBus::Ide) {
+      return Err(HttpError::bad_request(format!(
+        "Disk {} cannot be read-only on an ide bus",
+        disk.name
+      )));
+    }
+    let used_by = read_attached_vms(&name, state)
+      .await?
+      .into_iter()
+      .filter(|vm| vm.spec.vm_key != vm_key)
+      .filter(|vm| {
+        !read_only
+          || vm.spec.disks.clone().unwrap_or_default().iter().any(|d| {
+            d.image.as_deref() == Some(name.as_str())
+              && !d.read_only.unwrap_or_default()
+          })
+      })
+      .map(|vm| vm.spec.vm_key)
+      .collect::<Vec<_>>();
+    if !used_by.is_empty() {
+      return Err(HttpError::conflict(format!(
+        "Image {name} is already attached in read-write mode to vms: {}",
+        used_by.join(", ")
+      )));
+    }
+    prepared.push(disk);
+  }
+  Ok(prepared)
+}
@@ -0,0 +1,465 @@
+use std::process::Stdio;
+
+use futures::StreamExt;
+use ntex::{
+  channel::mpsc::{self, Sender},
+  http::{header, Client, StatusCode},
+  rt,
+};
+use openssl::hash::{Hasher, MessageDigest};
+use tokio::{
+  fs,
+  io::{AsyncReadExt, AsyncWriteExt},
+  process::Command,
+};
+
+use nanocl_error::http::{HttpError, HttpResult};
+use nanocl_stubs::{
+  system::{EventActor, EventActorKind, EventKind, NativeEventAction},
+  vm_image::{
+    VmImage, VmImageDownloadProgress, VmImagePullPayload, VmImagePullProgress,
+  },
+};
+
+use crate::{
+  models::{SystemState, VmImageDb},
+  repositories::generic::*,
+  utils,
+};
+
+/// Maximum number of redirections followed when downloading an image
+const MAX_REDIRECTS: usize = 10;
+/// Minimum number of bytes downloaded between two progress messages
+const PROGRESS_STEP: u64 = 1024 * 1024;
+
+/// Parse a checksum in the form `sha256:<hex>` or `sha512:<hex>`.
+/// A bare hex digest is also accepted and its algorithm guessed by its length.
+fn parse_checksum(checksum: &str) -> HttpResult<(MessageDigest, String)> {
+  let (algorithm, digest) = match checksum.split_once(':') {
+    Some((algorithm, digest)) => (algorithm.to_lowercase(), digest),
+    None if checksum.len() == 64 => ("sha256".to_owned(), checksum),
+    None if checksum.len() == 128 => ("sha512".to_owned(), checksum),
+    None => {
+      return Err(HttpError::bad_request(format!(
+        "Invalid checksum {checksum} expected sha256:<hex> or sha512:<hex>"
+      )))
+    }
+  };
+  let (md, len) = match algorithm.as_str() {
+    "sha256" => (MessageDigest::sha256(), 64),
+    "sha512" => (MessageDigest::sha512(), 128),
+    _ => {
+      return Err(HttpError::bad_request(format!(
+        "Unsupported checksum algorithm {algorithm}"
+      )))
+    }
+  };
+  if digest.len() != len || !digest.chars().all(|c| c.is_ascii_hexdigit()) {
+    return Err(HttpError::bad_request(format!(
+      "Invalid {algorithm} checksum {digest}"
+    )));
+  }
+  Ok((md, digest.to_lowercase()))
+}
+
+/// Parse the progress printed by `qemu-img convert -p` eg: `(42.00/100%)`
+fn parse_convert_progress(output: &str) -> Option<f32> {
+  output
+    .split('\r')
+    .filter_map(|line| {
+      let line = line.trim().strip_prefix('(')?;
+      let (progress, _) = line.split_once('/')?;
+      progress.parse::<f32>().ok()
+    })
+    .last()
+}
+
+/// Guess the format of an image from its first bytes,
+/// anything unknown is considered a raw disk
+fn detect_format(header: &[u8]) -> &'static str {
+  if header.starts_with(b"QFI\xfb") {
+    "qcow2"
+  } else if header.starts_with(b"KDMV") {
+    "vmdk"
+  } else if header.starts_with(b"vhdxfile") {
+    "vhdx"
+  } else {
+    "raw"
+  }
+}
+
+/// Read the header of the downloaded image to guess its format
+async fn read_format(path: &str) -> HttpResult<&'static str> {
+  let mut file = fs::File::open(path).await.map_err(|err| {
+    HttpError::internal_server_error(format!("Unable to open {path}: {err}"))
+  })?;
+  let mut header = Vec::with_capacity(8);
+  (&mut file)
+    .take(8)
+    .read_to_end(&mut header)
+    .await
+    .map_err(|err| {
+      HttpError::internal_server_error(format!("Unable to read {path}: {err}"))
+    })?;
+  Ok(detect_format(&header))
+}
+
+/// Send the request and follow the redirections until a final response
+async fn fetch(url: &str) -> HttpResult<ntex::http::client::ClientResponse> {
+  let client = Client::build()
+    .timeout(ntex::time::Millis::from_secs(30))
+    .finish();
+  let mut url = url.to_owned();
+  for _ in 0..MAX_REDIRECTS {
+    let res = client.get(&url).send().await.map_err(|err| {
+      HttpError::bad_gateway(format!("Unable to download {url}: {err}"))
+    })?;
+    let status = res.status();
+    if status.is_redirection() {
+      let location = res
+        .header(header::LOCATION)
+        .and_then(|value| value.to_str().ok())
+        .ok_or_else(|| {
+          HttpError::bad_gateway(format!("Invalid redirection from {url}"))
+        })?;
+      url = url::Url::parse(&url)
+        .and_then(|base| base.join(location))
+        .map_err(|err| {
+          HttpError::bad_gateway(format!(
+            "Invalid redirection {location}: {err}"
+          ))
+        })?
+        .to_string();
+      continue;
+    }
+    if status != StatusCode::OK {
+      return Err(HttpError::bad_gateway(format!(
+        "Unable to download {url}: {status}"
+      )));
+    }
+    return Ok(res);
+  }
+  Err(HttpError::bad_gateway(format!(
+    "Unable to download {url}: too many redirections"
+  )))
+}
+
+/// Download the file at `url` into `path` and return its hex digest
+/// when a message digest is given
+async fn download(
+  url: &str,
+  path: &str,
+  md: Option<MessageDigest>,
+  tx: &Sender<VmImagePullProgress>,
+) -> HttpResult<Option<String>> {
+  let mut res = fetch(url).await?;
+  let total = res
+    .header(header::CONTENT_LENGTH)
+    .and_then(|value| value.to_str().ok())
+    .and_then(|value| value.parse::<u64>().ok());
+  let mut file = fs::File::create(path).await.map_err(|err| {
+    HttpError::internal_server_error(format!("Unable to create {path}: {err}"))
+  })?;
+  let mut hasher = match md {
+    Some(md) => Some(Hasher::new(md).map_err(|err| {
+      HttpError::internal_server_error(format!("Unable to hash image: {err}"))
+    })?),
+    None => None,
+  };
+  let mut current = 0;
+  let mut last_progress = 0;
+  while let Some(chunk) = res.next().await {
+    let chunk = chunk.map_err(|err| {
+      HttpError::bad_gateway(format!("Unable to download {url}: {err}"))
+    })?;
+    file.write_all(&chunk).await.map_err(|err| {
+      HttpError::internal_server_error(format!("Unable to write {path}: {err}"))
+    })?;
+    if let Some(hasher) = hasher.as_mut() {
+      hasher.update(&chunk).map_err(|err| {
+        HttpError::internal_server_error(format!("Unable to hash image: {err}"))
+      })?;
+    }
+    current += chunk.len() as u64;
+    if current - last_progress >= PROGRESS_STEP {
+      last_progress = current;
+      let _ = tx.send(VmImagePullProgress::Download(VmImageDownloadProgress {
+        current,
+        total,
+      }));
+    }
+  }
+  file.flush().await.map_err(|err| {
+    HttpError::internal_server_error(format!("Unable to write {path}: {err}"))
+  })?;
+  let _ = tx.send(VmImagePullProgress::Download(VmImageDownloadProgress {
+    current,
+    total: Some(current),
+  }));
+  let Some(mut hasher) = hasher else {
+    return Ok(None);
+  };
+  let digest = hasher.finish().map_err(|err| {
+    HttpError::internal_server_error(format!("Unable to hash image: {err}"))
+  })?;
+  let digest = digest
+    .iter()
+    .fold(String::new(), |acc, byte| format!("{acc}{byte:02x}"));
+  Ok(Some(digest))
+}
+
+/// Convert the downloaded image into a qcow2 image
+async fn convert(
+  from: &str,
+  format: &str,
+  to: &str,
+  tx: &Sender<VmImagePullProgress>,
+) -> HttpResult<()> {
+  let mut child = Command::new("qemu-img")
+    .args(["convert", "-p", "-f", format, "-O", "qcow2", from, to])
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .spawn()
+    .map_err(|err| {
+      HttpError::internal_server_error(format!(
+        "Unable to convert {from}: {err}"
+      ))
+    })?;
+  if let Some(mut stdout) = child.stdout.take() {
+    let tx = tx.clone();
+    rt::spawn(async move {
+      let mut buf = [0; 1024];
+      while let Ok(n) = stdout.read(&mut buf).await {
+        if n == 0 {
+          break;
+        }
+        let output = String::from_utf8_lossy(&buf[..n]);
+        if let Some(progress) = parse_convert_progress(&output) {
+          let _ = tx.send(VmImagePullProgress::Convert(progress));
+        }
+      }
+    });
+  }
+  let output = child.wait_with_output().await.map_err(|err| {
+    HttpError::internal_server_error(format!("Unable to convert {from}: {err}"))
+  })?;
+  if !output.status.success() {
+    return Err(HttpError::internal_server_error(format!(
+      "Unable to convert {from}: {}",
+      String::from_utf8_lossy(&output.stderr)
+    )));
+  }
+  Ok(())
+}
+
+/// Download, verify and convert the image then register it as a `Base` image
+async fn pull_image(
+  name: &str,
+  payload: &VmImagePullPayload,
+  checksum: Option<(MessageDigest, String)>,
+  download_path: &str,
+  state: &SystemState,
+  tx: &Sender<VmImagePullProgress>,
+) -> HttpResult<VmImageDb> {
+  let md = checksum.as_ref().map(|(md, _)| *md);
+  let digest = download(&payload.url, download_path, md, tx).await?;
+  if let (Some((_, expected)), Some(digest)) = (&checksum, &digest) {
+    let _ = tx.send(VmImagePullProgress::Verify);
+    if expected != digest {
+      return Err(HttpError::bad_request(format!(
+        "Checksum mismatch for {} expected {expected} got {digest}",
+        payload.url
+      )));
+    }
+  }
+  // Never let qemu-img probe downloaded content,
+  // a raw disk could otherwise be read as an image with a backing file
+  let format = read_format(download_path).await?;
+  let info = utils::vm_image::get_info_as(download_path, format).await?;
+  if let Some(backing) = info.backing_filename {
+    return Err(HttpError::bad_request(format!(
+      "Unsupported vm image with a backing file {backing}"
+    )));
+  }
+  let image_path =
+    format!("{}/vms/images/{name}.img", state.inner.config.state_dir);
+  match info.format.as_str() {
+    "qcow2" => {
+      fs::rename(download_path, &image_path)
+        .await
+        .map_err(|err| {
+          HttpError::internal_server_error(format!(
+            "Unable to move {download_path} to {image_path}: {err}"
+          ))
+        })?;
+    }
+    "raw" | "vmdk" | "vhdx" => {
+      convert(download_path, &info.format, &image_path, tx).await?;
+      let _ = fs::remove_file(download_path).await;
+    }
+    format => {
+      return Err(HttpError::bad_request(format!(
+        "Unsupported vm image format {format}"
+      )))
+    }
+  }
+  utils::vm_image::create(name, &image_path, state).await
+}
+
+/// Pull a vm image from an http(s) url.
+/// The image is downloaded, verified against the given checksum
+/// and converted to qcow2 when needed before being registered as a `Base` image.
+/// The progress of the operation is emitted as `Downloading` events of the image
+/// and a `Download` event is emitted once it's registered.
+pub async fn pull(
+  name: &str,
+  payload: &VmImagePullPayload,
+  state: &SystemState,
+) -> HttpResult<VmImageDb> {
+  utils::key::validate_name(name)?;
+  if VmImageDb::read_by_pk(name, &state.inner.pool).await.is_ok() {
+    return Err(HttpError::conflict(format!("Vm image {name} already used")));
+  }
+  let url = url::Url::parse(&payload.url).map_err(|err| {
+    HttpError::bad_request(format!("Invalid url {}: {err}", payload.url))
+  })?;
+  if !matches!(url.scheme(), "http" | "https") {
+    return Err(HttpError::bad_request(format!(
+      "Unsupported url scheme {} expected http or https",
+      url.scheme()
+    )));
+  }
+  let checksum = payload
+    .checksum
+    .as_deref()
+    .map(parse_checksum)
+    .transpose()?;
+  let actor = EventActor {
+    key: Some(name.to_owned()),
+    kind: EventActorKind::VmImage,
+    attributes: None,
+  };
+  let (tx, mut rx) = mpsc::channel::<VmImagePullProgress>();
+  let state_ptr = state.clone();
+  let actor_ptr = actor.clone();
+  rt::spawn(async move {
+    while let Some(progress) = rx.next().await {
+      state_ptr.emit_action(
+        &actor_ptr,
+        NativeEventAction::Downloading,
+        EventKind::Normal,
+        "state_sync",
+        None,
+        Some(serde_json::json!({
+          "state": progress,
+        })),
+      );
+    }
+  });
+  let name = name.to_owned();
+  let payload = payload.clone();
+  let state = state.clone();
+  // The pull run in its own task so a disconnected client doesn't abort it
+  rt::spawn(async move {
+    let download_path = format!(
+      "{}/vms/images/{name}.download",
+      state.inner.config.state_dir
+    );
+    let res =
+      pull_image(&name, &payload, checksum, &download_path, &state, &tx).await;
+    match &res {
+      Ok(image) => {
+        state.emit_normal_native_action(
+          &VmImage::from(image.clone()),
+          NativeEventAction::Download,
+        );
+      }
+      Err(err) => {
+        log::warn!("vm_image_pull::pull: {name} {err}");
+        let _ = fs::remove_file(&download_path).await;
+        state.emit_error_native_action(
+          &actor,
+          NativeEventAction::Downloading,
+          Some(err.to_string()),
+        );
+      }
+    }
+    res
+  })
+  .await
+  .map_err(|err| {
+    HttpError::internal_server_error(format!("Unable to pull image: {err}"))
+  })?
+}
+
+#[cfg(test)]
+mod tests {
+  use ntex::web;
+
+  use super::*;
+
+  #[test]
+  fn checksum() {
+    let sha256 = "a".repeat(64);
+    let (md, digest) = parse_checksum(&format!("sha256:{sha256}")).unwrap();
+    assert!(md == MessageDigest::sha256());
+    assert_eq!(digest, sha256);
+    let (md, _) = parse_checksum(&"B".repeat(128)).unwrap();
+    assert!(md == MessageDigest::sha512());
+    assert!(parse_checksum("md5:abc").is_err());
+    assert!(parse_checksum("sha256:zz").is_err());
+  }
+
+  #[test]
+  fn format() {
+    assert_eq!(detect_format(b"QFI\xfb\0\0\0\x03"), "qcow2");
+    assert_eq!(detect_format(b"KDMV\x01\0\0\0"), "vmdk");
+    assert_eq!(detect_format(b"vhdxfile"), "vhdx");
+    assert_eq!(detect_format(b"\0\0\0\0"), "raw");
+    assert_eq!(detect_format(b""), "raw");
+  }
+
+  #[test]
+  fn convert_progress() {
+    assert_eq!(parse_convert_progress("    (0.00/100%)\r"), Some(0.0));
+    assert_eq!(
+      parse_convert_progress("    (1.01/100%)\r    (42.50/100%)\r"),
+      Some(42.5)
+    );
+    assert_eq!(parse_convert_progress("garbage"), None);
+  }
+
+  #[ntex::test]
+  async fn download_file() {
+    let srv = web::test::server(|| {
+      web::App::new()
+        .service(web::resource("/image.img").to(|| async { "nanocl image" }))
+        .service(web::resource("/redirect").to(|| async {
+          web::HttpResponse::Found()
+            .header(header::LOCATION, "/image.img")
+            .finish()
+        }))
+    });
+    let path = std::env::temp_dir()
+      .join(format!("vm-image-pull-{}", std::process::id()))
+      .display()
+      .to_string();
+    let (tx, _rx) = mpsc::channel::<VmImagePullProgress>();
+    let digest = download(
+      &srv.url("/redirect"),
+      &path,
+      Some(MessageDigest::sha256()),
+      &tx,
+    )
+    .await
+    .unwrap();
+    let expected = openssl::sha::sha256(b"nanocl image")
+      .iter()
+      .fold(String::new(), |acc, byte| format!("{acc}{byte:02x}"));
+    assert_eq!(digest.unwrap(), expected);
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "nanocl image");
+    std::fs::remove_file(&path).unwrap();
+    let res = download(&srv.url("/missing"), &path, None, &tx).await;
+    assert!(res.is_err());
+  }
+}
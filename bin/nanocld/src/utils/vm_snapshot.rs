@@ -0,0 +1,349 @@
+use tokio::{fs, process::Command};
+
+use nanocl_error::http::{HttpError, HttpResult};
+use nanocl_stubs::{
+  generic::{GenericClause, GenericFilter},
+  process::ProcessKind,
+  system::{NativeEventAction, ObjPsStatusKind},
+  vm::Vm,
+  vm_snapshot::{VmSnapshot, VmSnapshotPartial},
+};
+
+use crate::{
+  models::{
+    SystemState, VmDb, VmImageDb, VmImageUpdateDb, VmSnapshotDb,
+    VmSnapshotUpdateDb,
+  },
+  repositories::generic::*,
+  utils,
+};
+
+/// Run a qemu-img command and return an error if it failed
+async fn qemu_img(args: &[&str], context: &str) -> HttpResult<()> {
+  let output =
+    Command::new("qemu-img")
+      .args(args)
+      .output()
+      .await
+      .map_err(|err| {
+        HttpError::internal_server_error(format!("{context}: {err}"))
+      })?;
+  if !output.status.success() {
+    return Err(HttpError::internal_server_error(format!(
+      "{context}: {}",
+      String::from_utf8_lossy(&output.stderr)
+    )));
+  }
+  Ok(())
+}
+
+/// Create a qcow2 overlay at `path` backed by `backing`
+async fn create_overlay(backing: &str, path: &str) -> HttpResult<()> {
+  qemu_img(
+    &["create", "-f", "qcow2", "-F", "qcow2", "-b", backing, path],
+    &format!("Unable to create overlay {path}"),
+  )
+  .await
+}
+
+/// Rebase a qcow2 image on a new backing file, the data of the old
+/// backing file that is not in the new one is merged into the image
+async fn rebase(path: &str, backing: &str) -> HttpResult<()> {
+  qemu_img(
+    &["rebase", "-f", "qcow2", "-F", "qcow2", "-b", backing, path],
+    &format!("Unable to rebase {path}"),
+  )
+  .await
+}
+
+/// Refresh the size of the disk of the VM after its backing file changed
+async fn sync_disk(disk: &VmImageDb, state: &SystemState) -> HttpResult<()> {
+  let info = utils::vm_image::get_info(&disk.path).await?;
+  VmImageDb::update_pk(
+    &disk.name,
+    VmImageUpdateDb::from(info),
+    &state.inner.pool,
+  )
+  .await?;
+  Ok(())
+}
+
+/// Get the snapshot of a VM by its key
+async fn read_by_vm(
+  vm: &Vm,
+  key: &str,
+  state: &SystemState,
+) -> HttpResult<VmSnapshotDb> {
+  let uuid = uuid::Uuid::parse_str(key).map_err(|err| {
+    HttpError::bad_request(format!("Invalid snapshot key {key}: {err}"))
+  })?;
+  let filter = GenericFilter::new()
+    .r#where("key", GenericClause::Eq(uuid.to_string()))
+    .r#where("vm_key", GenericClause::Eq(vm.spec.vm_key.clone()));
+  VmSnapshotDb::read_by(&filter, &state.inner.pool)
+    .await?
+    .into_iter()
+    .next()
+    .ok_or_else(|| {
+      HttpError::not_found(format!(
+        "Snapshot {key} not found for vm {}",
+        vm.spec.name
+      ))
+    })
+}
+
+/// Inspect a snapshot of a VM by its key
+pub async fn inspect(
+  key: &str,
+  snapshot_key: &str,
+  state: &SystemState,
+) -> HttpResult<VmSnapshot> {
+  let vm = VmDb::transform_read_by_pk(key, &state.inner.pool).await?;
+  let snapshot = read_by_vm(&vm, snapshot_key, state).await?;
+  Ok(snapshot.into())
+}
+
+/// Stop the VM if it's running and return whether it was running
+async fn stop_vm(vm: &Vm, state: &SystemState) -> HttpResult<bool> {
+  if vm.status.actual != ObjPsStatusKind::Start {
+    return Ok(false);
+  }
+  utils::container::vm::stop(&vm.spec.vm_key, state).await?;
+  Ok(true)
+}
+
+/// Start the VM again if it was running before the operation
+async fn restore_vm_status(
+  vm: &Vm,
+  was_running: bool,
+  state: &SystemState,
+) -> HttpResult<()> {
+  if !was_running {
+    return Ok(());
+  }
+  utils::container::process::start_instances(
+    &vm.spec.vm_key,
+    &ProcessKind::Vm,
+    state,
+  )
+  .await?;
+  Ok(())
+}
+
+/// List the snapshots of a VM
+pub async fn list(
+  key: &str,
+  state: &SystemState,
+) -> HttpResult<Vec<VmSnapshot>> {
+  let vm = VmDb::transform_read_by_pk(key, &state.inner.pool).await?;
+  let snapshots =
+    VmSnapshotDb::read_by_vm(&vm.spec.vm_key, &state.inner.pool).await?;
+  Ok(snapshots.into_iter().map(VmSnapshot::from).collect())
+}
+
+/// Freeze the current disk of the VM into a new snapshot.
+/// The disk is moved into the snapshot directory of the VM
+/// and replaced by a new overlay backed by it.
+async fn freeze_disk(
+  vm: &Vm,
+  payload: &VmSnapshotPartial,
+  state: &SystemState,
+) -> HttpResult<VmSnapshotDb> {
+  let disk =
+    VmImageDb::read_by_pk(&vm.spec.disk.image, &state.inner.pool).await?;
+  let disk_info = utils::vm_image::get_info(&disk.path).await?;
+  let snapshots =
+    VmSnapshotDb::read_by_vm(&vm.spec.vm_key, &state.inner.pool).await?;
+  let parent = snapshots
+    .iter()
+    .find(|snapshot| {
+      Some(&snapshot.path) == disk_info.backing_filename.as_ref()
+    })
+    .map(|snapshot| snapshot.key);
+  let key = uuid::Uuid::new_v4();
+  let snapshot_dir = format!(
+    "{}/snapshots",
+    utils::container::vm::get_state_dir(&vm.spec.vm_key, state)
+  );
+  fs::create_dir_all(&snapshot_dir).await.map_err(|err| {
+    HttpError::internal_server_error(format!(
+      "Unable to create snapshot directory {snapshot_dir}: {err}"
+    ))
+  })?;
+  let path = format!("{snapshot_dir}/{key}.qcow2");
+  fs::rename(&disk.path, &path).await.map_err(|err| {
+    HttpError::internal_server_error(format!(
+      "Unable to move disk {} to {path}: {err}",
+      disk.path
+    ))
+  })?;
+  if let Err(err) = create_overlay(&path, &disk.path).await {
+    if let Err(err) = fs::rename(&path, &disk.path).await {
+      log::error!("Unable to restore disk {}: {err}", disk.path);
+    }
+    return Err(err);
+  }
+  sync_disk(&disk, state).await?;
+  let info = utils::vm_image::get_info(&path).await?;
+  let snapshot = VmSnapshotDb {
+    key,
+    name: payload.name.clone(),
+    vm_key: vm.spec.vm_key.clone(),
+    created_at: chrono::Utc::now().naive_utc(),
+    path,
+    parent,
+    size_actual: info.actual_size,
+    size_virtual: info.virtual_size,
+  };
+  let snapshot = VmSnapshotDb::create_from(snapshot, &state.inner.pool).await?;
+  Ok(snapshot)
+}
+
+/// Create a new snapshot of a VM.
+/// The VM is stopped while the snapshot is taken and started again after.
+pub async fn create(
+  key: &str,
+  payload: &VmSnapshotPartial,
+  state: &SystemState,
+) -> HttpResult<VmSnapshot> {
+  let vm = VmDb::transform_read_by_pk(key, &state.inner.pool).await?;
+  let snapshots =
+    VmSnapshotDb::read_by_vm(&vm.spec.vm_key, &state.inner.pool).await?;
+  if snapshots
+    .iter()
+    .any(|snapshot| snapshot.name == payload.name)
+  {
+    return Err(HttpError::conflict(format!(
+      "Snapshot {} already exists for vm {}",
+      payload.name, vm.spec.name
+    )));
+  }
+  let was_running = stop_vm(&vm, state).await?;
+  let res = freeze_disk(&vm, payload, state).await;
+  restore_vm_status(&vm, was_running, state).await?;
+  let snapshot: VmSnapshot = res?.into();
+  state
+    .emit_normal_native_action_sync(&snapshot, NativeEventAction::Create)
+    .await;
+  Ok(snapshot)
+}
+
+/// Replace the disk of the VM by a new overlay backed by the snapshot
+async fn reset_disk(
+  vm: &Vm,
+  snapshot: &VmSnapshotDb,
+  state: &SystemState,
+) -> HttpResult<()> {
+  let disk =
+    VmImageDb::read_by_pk(&vm.spec.disk.image, &state.inner.pool).await?;
+  let tmp_path = format!("{}.restore", disk.path);
+  create_overlay(&snapshot.path, &tmp_path).await?;
+  fs::rename(&tmp_path, &disk.path).await.map_err(|err| {
+    HttpError::internal_server_error(format!(
+      "Unable to replace disk {}: {err}",
+      disk.path
+    ))
+  })?;
+  sync_disk(&disk, state).await?;
+  Ok(())
+}
+
+/// Restore a VM to a snapshot.
+/// The current state of the disk is discarded,
+/// the VM is stopped during the restore and started again after.
+pub async fn restore(
+  key: &str,
+  snapshot_key: &str,
+  state: &SystemState,
+) -> HttpResult<VmSnapshot> {
+  let vm = VmDb::transform_read_by_pk(key, &state.inner.pool).await?;
+  let snapshot = read_by_vm(&vm, snapshot_key, state).await?;
+  let was_running = stop_vm(&vm, state).await?;
+  let res = reset_disk(&vm, &snapshot, state).await;
+  restore_vm_status(&vm, was_running, state).await?;
+  res?;
+  let snapshot: VmSnapshot = snapshot.into();
+  state
+    .emit_normal_native_action_sync(&snapshot, NativeEventAction::Restore)
+    .await;
+  Ok(snapshot)
+}
+
+/// Remove a snapshot from the chain by merging its data
+/// into the images directly based on it
+async fn unlink(
+  snapshot: &VmSnapshotDb,
+  disk: &VmImageDb,
+  disk_backing: Option<&String>,
+  state: &SystemState,
+) -> HttpResult<()> {
+  let info = utils::vm_image::get_info(&snapshot.path).await?;
+  let backing = info.backing_filename.ok_or_else(|| {
+    HttpError::internal_server_error(format!(
+      "Snapshot {} has no backing file",
+      snapshot.key
+    ))
+  })?;
+  let children =
+    VmSnapshotDb::read_by_parent(&snapshot.key, &state.inner.pool).await?;
+  for child in children {
+    rebase(&child.path, &backing).await?;
+    let info = utils::vm_image::get_info(&child.path).await?;
+    VmSnapshotDb::update_pk(
+      &child.key,
+      VmSnapshotUpdateDb {
+        parent: snapshot.parent,
+        size_actual: info.actual_size,
+        size_virtual: info.virtual_size,
+      },
+      &state.inner.pool,
+    )
+    .await?;
+  }
+  if disk_backing == Some(&snapshot.path) {
+    rebase(&disk.path, &backing).await?;
+    sync_disk(disk, state).await?;
+  }
+  if let Err(err) = fs::remove_file(&snapshot.path).await {
+    log::warn!("Error while deleting the file {}: {err}", snapshot.path);
+  }
+  VmSnapshotDb::del_by_pk(&snapshot.key, &state.inner.pool).await?;
+  Ok(())
+}
+
+/// Delete a snapshot of a VM.
+/// The VM is only stopped when its disk is directly based on the snapshot.
+pub async fn delete(
+  key: &str,
+  snapshot_key: &str,
+  state: &SystemState,
+) -> HttpResult<()> {
+  let vm = VmDb::transform_read_by_pk(key, &state.inner.pool).await?;
+  let snapshot = read_by_vm(&vm, snapshot_key, state).await?;
+  let disk =
+    VmImageDb::read_by_pk(&vm.spec.disk.image, &state.inner.pool).await?;
+  let disk_info = utils::vm_image::get_info(&disk.path).await.ok();
+  let disk_backing = disk_info.and_then(|info| info.backing_filename);
+  let was_running = if disk_backing.as_ref() == Some(&snapshot.path) {
+    stop_vm(&vm, state).await?
+  } else {
+    false
+  };
+  let res = unlink(&snapshot, &disk, disk_backing.as_ref(), state).await;
+  restore_vm_status(&vm, was_running, state).await?;
+  res?;
+  let snapshot: VmSnapshot = snapshot.into();
+  state
+    .emit_normal_native_action_sync(&snapshot, NativeEventAction::Destroy)
+    .await;
+  Ok(())
+}
+
+/// Delete all the snapshots of a VM from the database.
+/// The files are removed with the state directory of the VM.
+pub async fn delete_by_vm(vm_key: &str, state: &SystemState) -> HttpResult<()> {
+  let filter = GenericFilter::new()
+    .r#where("vm_key", GenericClause::Eq(vm_key.to_owned()));
+  VmSnapshotDb::del_by(&filter, &state.inner.pool).await?;
+  Ok(())
+}
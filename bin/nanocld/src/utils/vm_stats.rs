@@ -0,0 +1,307 @@
+use std::collections::HashMap;
+
+use tokio::fs;
+
+use nanocl_error::io::IoResult;
+use nanocl_stubs::vm::{
+  Vm, VmBlockStats, VmCpuStats, VmMemoryStats, VmNetStats, VmStats,
+};
+
+use crate::{
+  models::{MetricDb, MetricNodePartial, ProcessDb, SystemState},
+  repositories::generic::*,
+  utils, vars,
+};
+
+/// Id of the balloon device added to every VM
+pub const BALLOON_ID: &str = "balloon0";
+
+/// Parse the result of the `query-cpus-fast` QMP command
+fn parse_cpus(value: &serde_json::Value) -> Vec<VmCpuStats> {
+  value
+    .as_array()
+    .cloned()
+    .unwrap_or_default()
+    .iter()
+    .map(|cpu| VmCpuStats {
+      cpu_index: cpu["cpu-index"].as_i64().unwrap_or_default(),
+      thread_id: cpu["thread-id"].as_i64().unwrap_or_default(),
+      time: None,
+    })
+    .collect()
+}
+
+/// Parse the result of the `query-balloon` QMP command
+/// and the `guest-stats` property of the balloon device.
+/// The guest report -1 for the values it doesn't know yet.
+fn parse_balloon(
+  balloon: &serde_json::Value,
+  guest: Option<&serde_json::Value>,
+) -> Option<VmMemoryStats> {
+  let actual = balloon["actual"].as_u64()?;
+  let stats = guest.map(|guest| &guest["stats"]);
+  let get = |name: &str| stats.and_then(|stats| stats[name].as_u64());
+  Some(VmMemoryStats {
+    actual,
+    total: get("stat-total-memory"),
+    free: get("stat-free-memory"),
+    available: get("stat-available-memory"),
+  })
+}
+
+/// Parse the result of the `query-blockstats` QMP command
+fn parse_blockstats(value: &serde_json::Value) -> Vec<VmBlockStats> {
+  value
+    .as_array()
+    .cloned()
+    .unwrap_or_default()
+    .iter()
+    .map(|block| {
+      let device = match block["device"].as_str() {
+        Some(device) if !device.is_empty() => device,
+        _ => block["qdev"].as_str().unwrap_or_default(),
+      };
+      let stats = &block["stats"];
+      VmBlockStats {
+        device: device.to_owned(),
+        rd_bytes: stats["rd_bytes"].as_u64().unwrap_or_default(),
+        wr_bytes: stats["wr_bytes"].as_u64().unwrap_or_default(),
+        rd_operations: stats["rd_operations"].as_u64().unwrap_or_default(),
+        wr_operations: stats["wr_operations"].as_u64().unwrap_or_default(),
+      }
+    })
+    .collect()
+}
+
+/// Parse the content of a `/proc/<pid>/net/dev` file
+fn parse_net_dev(content: &str) -> Vec<VmNetStats> {
+  content
+    .lines()
+    .skip(2)
+    .filter_map(|line| {
+      let (iface, counters) = line.split_once(':')?;
+      let iface = iface.trim();
+      if iface == "lo" {
+        return None;
+      }
+      let counters = counters
+        .split_whitespace()
+        .map(|counter| counter.parse::<u64>().unwrap_or_default())
+        .collect::<Vec<_>>();
+      Some(VmNetStats {
+        iface: iface.to_owned(),
+        rx_bytes: *counters.first()?,
+        rx_packets: *counters.get(1)?,
+        tx_bytes: *counters.get(8)?,
+        tx_packets: *counters.get(9)?,
+      })
+    })
+    .collect()
+}
+
+/// Get the id of a thread in the pid namespace of qemu
+/// from the content of its `/proc/<pid>/task/<tid>/status` file
+fn parse_nspid(status: &str) -> Option<i64> {
+  status
+    .lines()
+    .find_map(|line| line.strip_prefix("NSpid:"))?
+    .split_whitespace()
+    .last()?
+    .parse::<i64>()
+    .ok()
+}
+
+/// Get the cpu time in nanoseconds of the threads of a process
+/// indexed by their id in the pid namespace of the process
+async fn read_cpu_times(pid: i64) -> HashMap<i64, u64> {
+  let mut times = HashMap::new();
+  let Ok(mut tasks) = fs::read_dir(format!("/proc/{pid}/task")).await else {
+    return times;
+  };
+  while let Ok(Some(task)) = tasks.next_entry().await {
+    let path = task.path().display().to_string();
+    let Ok(status) = fs::read_to_string(format!("{path}/status")).await else {
+      continue;
+    };
+    let Ok(schedstat) = fs::read_to_string(format!("{path}/schedstat")).await
+    else {
+      continue;
+    };
+    let time = schedstat
+      .split_whitespace()
+      .next()
+      .and_then(|time| time.parse::<u64>().ok());
+    if let (Some(tid), Some(time)) = (parse_nspid(&status), time) {
+      times.insert(tid, time);
+    }
+  }
+  times
+}
+
+/// Get the pid on the host of the running qemu process of a VM
+async fn get_pid(key: &str, state: &SystemState) -> Option<i64> {
+  let processes = ProcessDb::read_by_kind_key(key, None, &state.inner.pool)
+    .await
+    .ok()?;
+  for process in processes {
+    let Ok(inspect) = state
+      .inner
+      .docker_api
+      .inspect_container(&process.key, None)
+      .await
+    else {
+      continue;
+    };
+    let pid = inspect
+      .state
+      .and_then(|state| state.pid)
+      .unwrap_or_default();
+    if pid > 0 {
+      return Some(pid);
+    }
+  }
+  None
+}
+
+/// Sample the guest level statistics of a running VM.
+/// The vcpus, memory and disks are queried through QMP.
+/// QMP doesn't expose the time of the vcpus and the network counters,
+/// they are read from `/proc` for the qemu process and its network namespace
+/// and left empty when the daemon can't see the process.
+pub async fn sample(vm: &Vm, state: &SystemState) -> IoResult<VmStats> {
+  let vm_dir = utils::container::vm::get_state_dir(&vm.spec.vm_key, state);
+  let mut qmp =
+    utils::qmp::QmpClient::connect(&utils::qmp::get_socket_path(&vm_dir))
+      .await?;
+  let mut cpus = parse_cpus(&qmp.execute("query-cpus-fast", None).await?);
+  let memory = match qmp.execute("query-balloon", None).await {
+    Ok(balloon) => {
+      let path = format!("/machine/peripheral/{BALLOON_ID}");
+      // The guest only report its memory usage once polling is enabled
+      let _ = qmp
+        .execute(
+          "qom-set",
+          Some(serde_json::json!({
+            "path": path,
+            "property": "guest-stats-polling-interval",
+            "value": vars::VM_METRIC_INTERVAL,
+          })),
+        )
+        .await;
+      let guest = qmp
+        .execute(
+          "qom-get",
+          Some(serde_json::json!({
+            "path": path,
+            "property": "guest-stats",
+          })),
+        )
+        .await
+        .ok();
+      parse_balloon(&balloon, guest.as_ref())
+    }
+    Err(_) => None,
+  };
+  let block = parse_blockstats(&qmp.execute("query-blockstats", None).await?);
+  let mut net = Vec::new();
+  if let Some(pid) = get_pid(&vm.spec.vm_key, state).await {
+    let times = read_cpu_times(pid).await;
+    for cpu in cpus.iter_mut() {
+      cpu.time = times.get(&cpu.thread_id).copied();
+    }
+    if let Ok(content) =
+      fs::read_to_string(format!("/proc/{pid}/net/dev")).await
+    {
+      net = parse_net_dev(&content);
+    }
+  }
+  Ok(VmStats {
+    name: vm.spec.name.clone(),
+    namespace: vm.namespace_name.clone(),
+    cpus,
+    memory,
+    block,
+    net,
+  })
+}
+
+/// Sample the statistics of a running VM
+/// and save them as a `nanocl.io/vm` metric for the current node
+pub async fn save(vm: &Vm, state: &SystemState) -> IoResult<()> {
+  let stats = sample(vm, state).await?;
+  let cpu_time = stats.cpus.iter().filter_map(|cpu| cpu.time).sum::<u64>();
+  let memory = stats
+    .memory
+    .as_ref()
+    .map(|memory| memory.actual / 1024 / 1024)
+    .unwrap_or_default();
+  let metric = MetricNodePartial {
+    kind: vars::VM_METRIC_KIND.to_owned(),
+    node_name: state.inner.config.hostname.clone(),
+    data: serde_json::to_value(&stats)?,
+    note: Some(format!(
+      "CPU {}s | MEMORY {memory} MB",
+      cpu_time / 1_000_000_000
+    )),
+  };
+  MetricDb::create_from(&metric, &state.inner.pool).await?;
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn qmp_results() {
+    let cpus = serde_json::json!([
+      { "cpu-index": 0, "thread-id": 42, "qom-path": "/machine/unattached/device[0]" },
+      { "cpu-index": 1, "thread-id": 43, "qom-path": "/machine/unattached/device[1]" },
+    ]);
+    let cpus = parse_cpus(&cpus);
+    assert_eq!(cpus.len(), 2);
+    assert_eq!(cpus[1].cpu_index, 1);
+    assert_eq!(cpus[1].thread_id, 43);
+    let balloon = serde_json::json!({ "actual": 536870912 });
+    let guest = serde_json::json!({
+      "stats": {
+        "stat-total-memory": 503635968,
+        "stat-free-memory": 302342144,
+        "stat-available-memory": -1,
+      },
+      "last-update": 1700000000,
+    });
+    let memory = parse_balloon(&balloon, Some(&guest)).unwrap();
+    assert_eq!(memory.actual, 536870912);
+    assert_eq!(memory.total, Some(503635968));
+    assert_eq!(memory.free, Some(302342144));
+    assert_eq!(memory.available, None);
+    let blocks = serde_json::json!([
+      { "device": "disk0", "stats": { "rd_bytes": 1024, "wr_bytes": 512, "rd_operations": 2, "wr_operations": 1 } },
+      { "device": "", "qdev": "/machine/peripheral-anon/device[1]", "stats": { "rd_bytes": 0, "wr_bytes": 0, "rd_operations": 0, "wr_operations": 0 } },
+    ]);
+    let blocks = parse_blockstats(&blocks);
+    assert_eq!(blocks[0].device, "disk0");
+    assert_eq!(blocks[0].rd_bytes, 1024);
+    assert_eq!(blocks[0].wr_operations, 1);
+    assert_eq!(blocks[1].device, "/machine/peripheral-anon/device[1]");
+  }
+
+  #[test]
+  fn proc_files() {
+    let net_dev = "Inter-|   Receive                                                |  Transmit
+ face |bytes    packets errs drop fifo frame compressed multicast|bytes    packets errs drop fifo colls carrier compressed
+    lo:     100       1    0    0    0     0          0         0      100       1    0    0    0     0       0          0
+  tap0:    2048      16    0    0    0     0          0         0     4096      32    0    0    0     0       0          0
+";
+    let net = parse_net_dev(net_dev);
+    assert_eq!(net.len(), 1);
+    assert_eq!(net[0].iface, "tap0");
+    assert_eq!(net[0].rx_bytes, 2048);
+    assert_eq!(net[0].rx_packets, 16);
+    assert_eq!(net[0].tx_bytes, 4096);
+    assert_eq!(net[0].tx_packets, 32);
+    let status = "Name:\tqemu-system-x86\nTgid:\t1200\nNSpid:\t1234\t17\n";
+    assert_eq!(parse_nspid(status), Some(17));
+  }
+}
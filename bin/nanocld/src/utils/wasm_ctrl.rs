@@ -0,0 +1,505 @@
+use diesel::prelude::*;
+use openssl::base64;
+use serde::Serialize;
+use wasmi::{
+  AsContext, AsContextMut, Caller, Config, Engine, Extern, Linker, Memory,
+  Module, Store, StoreLimits, StoreLimitsBuilder, TypedFunc,
+};
+
+use nanocl_error::{
+  http::{HttpError, HttpResult},
+  io::{FromIo, IoError, IoResult},
+};
+use nanocl_stubs::{
+  generic::{GenericClause, GenericFilter},
+  resource_kind::{
+    ResourceKindSpec, ResourceKindWasm, ResourceKindWasmEvent,
+    ResourceKindWasmInput, ResourceKindWasmOutput,
+  },
+  system::{EventActor, EventActorKind, EventPartial},
+};
+
+use crate::{
+  models::{CargoDb, JobDb, Pool, ResourceDb, SecretDb, SystemState, VmDb},
+  repositories::generic::*,
+  utils,
+};
+
+/// Data of the store of a hook call
+struct WasmHost {
+  limits: StoreLimits,
+  pool: Pool,
+  events: Vec<ResourceKindWasmEvent>,
+}
+
+/// Output of a hook call with the events emitted by the module
+type WasmCall = (Option<ResourceKindWasmOutput>, Vec<ResourceKindWasmEvent>);
+
+/// Pack a pointer and a length of the memory of a module in a single value
+fn pack(ptr: i32, len: i32) -> i64 {
+  ((ptr as u32 as i64) << 32) | len as u32 as i64
+}
+
+/// Unpack a pointer and a length packed by a module
+fn unpack(value: i64) -> (i32, i32) {
+  ((value >> 32) as i32, value as i32)
+}
+
+/// Get the memory and the allocator exported by a module
+fn get_exports(
+  ctx: impl AsContext,
+  get_export: impl Fn(&str) -> Option<Extern>,
+) -> Result<(Memory, TypedFunc<i32, i32>), wasmi::Error> {
+  let memory = get_export("memory")
+    .and_then(Extern::into_memory)
+    .ok_or_else(|| wasmi::Error::new("Missing memory export"))?;
+  let alloc = get_export("alloc")
+    .and_then(Extern::into_func)
+    .ok_or_else(|| wasmi::Error::new("Missing alloc export"))?
+    .typed::<i32, i32>(&ctx)?;
+  Ok((memory, alloc))
+}
+
+/// Read bytes from the memory of a module
+fn read_bytes(
+  ctx: impl AsContext,
+  memory: Memory,
+  ptr: i32,
+  len: i32,
+) -> Result<Vec<u8>, wasmi::Error> {
+  let (ptr, len) = (ptr as u32 as usize, len as u32 as usize);
+  if ptr.saturating_add(len) > memory.data_size(&ctx) {
+    return Err(wasmi::Error::new("Out of bounds memory access"));
+  }
+  let mut buffer = vec![0; len];
+  memory.read(&ctx, ptr, &mut buffer)?;
+  Ok(buffer)
+}
+
+/// Copy bytes to the memory of a module allocated with its allocator
+fn write_bytes(
+  mut ctx: impl AsContextMut,
+  memory: Memory,
+  alloc: TypedFunc<i32, i32>,
+  bytes: &[u8],
+) -> Result<(i32, i32), wasmi::Error> {
+  let len = i32::try_from(bytes.len())
+    .map_err(|_| wasmi::Error::new("Payload too large"))?;
+  let ptr = alloc.call(&mut ctx, len)?;
+  memory.write(&mut ctx, ptr as u32 as usize, bytes)?;
+  Ok((ptr, len))
+}
+
+/// Read an object by its primary key from the blocking thread of a call
+fn read_sync<T>(key: &str, pool: &Pool) -> IoResult<Option<serde_json::Value>>
+where
+  T: RepositoryReadByTransform,
+  T::Output: Sized + 'static,
+  T::NewOutput: Serialize,
+{
+  let mut conn = utils::store::get_pool_conn(pool)?;
+  let filter = GenericFilter::new()
+    .r#where(T::get_pk(), GenericClause::Eq(key.to_owned()));
+  let item = T::gen_read_query(&filter, false)
+    .get_result::<T::Output>(&mut conn)
+    .optional()
+    .map_err(T::map_err)?;
+  let Some(item) = item else {
+    return Ok(None);
+  };
+  let item = serde_json::to_value(T::transform(item)?)
+    .map_err(|err| err.map_err_context(|| key))?;
+  Ok(Some(item))
+}
+
+/// Read an object for a module, secrets are read without their data
+fn read_object(
+  actor: &EventActor,
+  pool: &Pool,
+) -> IoResult<Option<serde_json::Value>> {
+  let Some(key) = &actor.key else {
+    return Ok(None);
+  };
+  match actor.kind {
+    EventActorKind::Cargo => read_sync::<CargoDb>(key, pool),
+    EventActorKind::Vm => read_sync::<VmDb>(key, pool),
+    EventActorKind::Job => read_sync::<JobDb>(key, pool),
+    EventActorKind::Resource => read_sync::<ResourceDb>(key, pool),
+    EventActorKind::Secret => {
+      let secret = read_sync::<SecretDb>(key, pool)?;
+      Ok(secret.map(|mut secret| {
+        secret["Data"] = serde_json::Value::Null;
+        secret
+      }))
+    }
+    _ => Err(IoError::invalid_input(
+      "ResourceKindWasm",
+      &format!("Cannot read {}", actor.kind),
+    )),
+  }
+}
+
+/// Host function `nanocl.read` to read an object
+fn host_read(
+  mut caller: Caller<'_, WasmHost>,
+  ptr: i32,
+  len: i32,
+) -> Result<i64, wasmi::Error> {
+  let (memory, alloc) = get_exports(&caller, |name| caller.get_export(name))?;
+  let input = read_bytes(&caller, memory, ptr, len)?;
+  let actor = serde_json::from_slice::<EventActor>(&input)
+    .map_err(|err| wasmi::Error::new(format!("Invalid read: {err}")))?;
+  let object = read_object(&actor, &caller.data().pool)
+    .map_err(|err| wasmi::Error::new(err.to_string()))?;
+  let Some(object) = object else {
+    return Ok(0);
+  };
+  let bytes = serde_json::to_vec(&object)
+    .map_err(|err| wasmi::Error::new(err.to_string()))?;
+  let (ptr, len) = write_bytes(&mut caller, memory, alloc, &bytes)?;
+  Ok(pack(ptr, len))
+}
+
+/// Host function `nanocl.emit` to emit an event about the resource
+fn host_emit(
+  mut caller: Caller<'_, WasmHost>,
+  ptr: i32,
+  len: i32,
+) -> Result<(), wasmi::Error> {
+  let (memory, _) = get_exports(&caller, |name| caller.get_export(name))?;
+  let input = read_bytes(&caller, memory, ptr, len)?;
+  let event = serde_json::from_slice::<ResourceKindWasmEvent>(&input)
+    .map_err(|err| wasmi::Error::new(format!("Invalid event: {err}")))?;
+  caller.data_mut().events.push(event);
+  Ok(())
+}
+
+/// Instantiate a module with its limits and call one of its hooks.
+/// The output is `None` when the module doesn't export the hook.
+fn run(
+  engine: &Engine,
+  module: &Module,
+  hook: &str,
+  limits: (u64, u32),
+  pool: Pool,
+  input: &[u8],
+) -> Result<WasmCall, wasmi::Error> {
+  let (fuel, memory) = limits;
+  let host = WasmHost {
+    limits: StoreLimitsBuilder::new()
+      .memory_size(memory as usize * 1024 * 1024)
+      .build(),
+    pool,
+    events: Vec::new(),
+  };
+  let mut store = Store::new(engine, host);
+  store.limiter(|host| &mut host.limits);
+  store.set_fuel(fuel)?;
+  let mut linker = Linker::<WasmHost>::new(engine);
+  linker.func_wrap("nanocl", "read", host_read)?;
+  linker.func_wrap("nanocl", "emit", host_emit)?;
+  let instance = linker.instantiate(&mut store, module)?.start(&mut store)?;
+  let Some(func) = instance.get_func(&store, hook) else {
+    return Ok((None, Vec::new()));
+  };
+  let func = func.typed::<(i32, i32), i64>(&store)?;
+  let (memory, alloc) =
+    get_exports(&store, |name| instance.get_export(&store, name))?;
+  let (ptr, len) = write_bytes(&mut store, memory, alloc, input)?;
+  let packed = func.call(&mut store, (ptr, len))?;
+  let output = if packed == 0 {
+    ResourceKindWasmOutput::default()
+  } else {
+    let (ptr, len) = unpack(packed);
+    let output = read_bytes(&store, memory, ptr, len)?;
+    serde_json::from_slice(&output)
+      .map_err(|err| wasmi::Error::new(format!("Invalid output: {err}")))?
+  };
+  Ok((Some(output), store.into_data().events))
+}
+
+/// Read the module of a WebAssembly controller from its path or its blob
+async fn read_module(wasm: &ResourceKindWasm) -> IoResult<Vec<u8>> {
+  match (&wasm.path, &wasm.blob) {
+    (Some(path), None) => tokio::fs::read(path)
+      .await
+      .map_err(|err| *err.map_err_context(|| path)),
+    (None, Some(blob)) => base64::decode_block(blob).map_err(|err| {
+      IoError::invalid_data("ResourceKindWasm", &format!("Invalid blob {err}"))
+    }),
+    _ => Err(IoError::invalid_input(
+      "ResourceKindWasm",
+      "Path or Blob must be defined",
+    )),
+  }
+}
+
+/// Compile the module of a resource kind and ensure it exports
+/// the memory and the allocator
+async fn compile(
+  name: &str,
+  wasm: &ResourceKindWasm,
+) -> IoResult<(Engine, Module)> {
+  let bytes = read_module(wasm).await?;
+  let mut config = Config::default();
+  config.consume_fuel(true);
+  let engine = Engine::new(&config);
+  let module = Module::new(&engine, &bytes).map_err(|err| {
+    IoError::invalid_data("ResourceKindWasm", &format!("{name}: {err}"))
+  })?;
+  for export in ["memory", "alloc"] {
+    if module.get_export(export).is_none() {
+      return Err(IoError::invalid_data(
+        "ResourceKindWasm",
+        &format!("{name}: Missing export {export}"),
+      ));
+    }
+  }
+  Ok((engine, module))
+}
+
+/// Controller of a resource kind executed in a WebAssembly sandbox
+/// with a fuel and memory limit for each hook call
+pub struct WasmCtrl {
+  name: String,
+  engine: Engine,
+  module: Module,
+  fuel: u64,
+  memory: u32,
+  state: SystemState,
+}
+
+impl WasmCtrl {
+  /// Get the WebAssembly module of a resource kind version if it has one,
+  /// modules are compiled once and cached in the state
+  pub async fn from_kind(
+    name: &str,
+    version: &str,
+    spec: &ResourceKindSpec,
+    state: &SystemState,
+  ) -> IoResult<Option<Self>> {
+    let Some(wasm) = &spec.wasm else {
+      return Ok(None);
+    };
+    let cache_key = (name.to_owned(), version.to_owned());
+    let cached = state
+      .inner
+      .wasm_modules
+      .read()
+      .map_err(|err| {
+        IoError::interrupted("ResourceKindWasm", &err.to_string())
+      })?
+      .get(&cache_key)
+      .filter(|(cached, _, _)| cached == wasm)
+      .map(|(_, engine, module)| (engine.clone(), module.clone()));
+    let (engine, module) = match cached {
+      Some(cached) => cached,
+      None => {
+        let (engine, module) = compile(name, wasm).await?;
+        state
+          .inner
+          .wasm_modules
+          .write()
+          .map_err(|err| {
+            IoError::interrupted("ResourceKindWasm", &err.to_string())
+          })?
+          .insert(cache_key, (wasm.clone(), engine.clone(), module.clone()));
+        (engine, module)
+      }
+    };
+    Ok(Some(Self {
+      name: name.to_owned(),
+      engine,
+      module,
+      fuel: wasm.fuel.unwrap_or(crate::vars::WASM_CTRL_FUEL),
+      memory: wasm.memory.unwrap_or(crate::vars::WASM_CTRL_MEMORY),
+      state: state.clone(),
+    }))
+  }
+
+  /// Call a hook in a blocking thread and emit the events of the module.
+  /// An error returned by the module refuse the resource.
+  async fn call(
+    &self,
+    hook: &'static str,
+    version: &str,
+    name: &str,
+    data: &serde_json::Value,
+  ) -> HttpResult<Option<ResourceKindWasmOutput>> {
+    let input = ResourceKindWasmInput {
+      name: name.to_owned(),
+      version: version.to_owned(),
+      data: data.clone(),
+    };
+    let input = serde_json::to_vec(&input)
+      .map_err(|err| err.map_err_context(|| &self.name))?;
+    let engine = self.engine.clone();
+    let module = self.module.clone();
+    let limits = (self.fuel, self.memory);
+    let pool = self.state.inner.pool.clone();
+    let context = format!("{} {hook}", self.name);
+    let (output, events) = ntex::rt::spawn_blocking(move || {
+      run(&engine, &module, hook, limits, pool, &input).map_err(|err| {
+        IoError::invalid_data(context.as_str(), &err.to_string())
+      })
+    })
+    .await
+    .map_err(|err| {
+      IoError::interrupted("ResourceKindWasm", &err.to_string())
+    })??;
+    for event in events {
+      self.state.spawn_emit_event(EventPartial {
+        reporting_controller: self.name.clone(),
+        reporting_node: self.state.inner.config.hostname.clone(),
+        kind: event.kind,
+        action: event.action,
+        related: None,
+        reason: event.reason,
+        note: event.note,
+        metadata: None,
+        actor: Some(EventActor {
+          key: Some(name.to_owned()),
+          kind: EventActorKind::Resource,
+          attributes: None,
+        }),
+      });
+    }
+    if let Some(error) = output.as_ref().and_then(|output| output.error.clone())
+    {
+      return Err(HttpError::bad_request(format!(
+        "Resource {name} refused by {}: {error}",
+        self.name
+      )));
+    }
+    Ok(output)
+  }
+
+  /// Call the `mutate` hook returning the data to validate and apply
+  pub async fn mutate(
+    &self,
+    version: &str,
+    name: &str,
+    data: &serde_json::Value,
+  ) -> HttpResult<serde_json::Value> {
+    let output = self.call("mutate", version, name, data).await?;
+    Ok(
+      output
+        .and_then(|output| output.data)
+        .unwrap_or(data.clone()),
+    )
+  }
+
+  /// Call the `validate` hook
+  pub async fn validate(
+    &self,
+    version: &str,
+    name: &str,
+    data: &serde_json::Value,
+  ) -> HttpResult<()> {
+    self.call("validate", version, name, data).await?;
+    Ok(())
+  }
+
+  /// Call the `apply` hook returning the data to store
+  pub async fn apply(
+    &self,
+    version: &str,
+    name: &str,
+    data: &serde_json::Value,
+  ) -> HttpResult<serde_json::Value> {
+    let output = self.call("apply", version, name, data).await?;
+    Ok(
+      output
+        .and_then(|output| output.data)
+        .unwrap_or(data.clone()),
+    )
+  }
+
+  /// Call the `delete` hook
+  pub async fn delete(
+    &self,
+    version: &str,
+    name: &str,
+    data: &serde_json::Value,
+  ) -> HttpResult<()> {
+    self.call("delete", version, name, data).await?;
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use diesel::{r2d2::ConnectionManager, PgConnection};
+
+  use super::*;
+
+  const APPLIED: &str = r#"{"Data":{"Applied":true}}"#;
+  const REFUSED: &str = r#"{"Error":"Port is required"}"#;
+  const EVENT: &str = r#"{"Kind":"normal","Action":"apply","Reason":"test"}"#;
+
+  /// Module with a bump allocator returning constant outputs
+  fn gen_module(engine: &Engine, memory: u32) -> Module {
+    let escape = |value: &str| value.replace('"', "\\\"");
+    let wat = format!(
+      r#"(module
+        (import "nanocl" "emit" (func $emit (param i32 i32)))
+        (memory (export "memory") {memory})
+        (global $heap (mut i32) (i32.const 1024))
+        (data (i32.const 0) "{applied}")
+        (data (i32.const 256) "{refused}")
+        (data (i32.const 512) "{event}")
+        (func (export "alloc") (param $len i32) (result i32)
+          (global.get $heap)
+          (global.set $heap (i32.add (global.get $heap) (local.get $len))))
+        (func (export "apply") (param i32 i32) (result i64)
+          (call $emit (i32.const 512) (i32.const {event_len}))
+          (i64.const {applied_len}))
+        (func (export "validate") (param i32 i32) (result i64)
+          (i64.or (i64.const {refused_ptr}) (i64.const {refused_len})))
+        (func (export "delete") (param i32 i32) (result i64)
+          (loop $loop (br $loop))
+          (i64.const 0)))"#,
+      applied = escape(APPLIED),
+      refused = escape(REFUSED),
+      event = escape(EVENT),
+      applied_len = APPLIED.len(),
+      refused_ptr = 256i64 << 32,
+      refused_len = REFUSED.len(),
+      event_len = EVENT.len(),
+    );
+    Module::new(engine, wat::parse_str(wat).unwrap()).unwrap()
+  }
+
+  #[test]
+  fn hooks() {
+    let mut config = Config::default();
+    config.consume_fuel(true);
+    let engine = Engine::new(&config);
+    let module = gen_module(&engine, 1);
+    let pool = Pool::builder()
+      .build_unchecked(ConnectionManager::<PgConnection>::new("postgres://"));
+    let limits = (1_000_000, 1);
+    let input = br#"{"Name":"test","Version":"v1","Data":{}}"#;
+    let (output, events) =
+      run(&engine, &module, "apply", limits, pool.clone(), input).unwrap();
+    assert_eq!(
+      output.unwrap().data,
+      Some(serde_json::json!({ "Applied": true }))
+    );
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].action, "apply");
+    let (output, _) =
+      run(&engine, &module, "validate", limits, pool.clone(), input).unwrap();
+    assert_eq!(output.unwrap().error, Some("Port is required".to_owned()));
+    let (output, _) =
+      run(&engine, &module, "mutate", limits, pool.clone(), input).unwrap();
+    assert!(output.is_none());
+    // The delete hook never ends and run out of fuel
+    assert!(
+      run(&engine, &module, "delete", limits, pool.clone(), input).is_err()
+    );
+    // The memory of the module is above the limit
+    let module = gen_module(&engine, 32);
+    assert!(run(&engine, &module, "apply", limits, pool, input).is_err());
+  }
+}
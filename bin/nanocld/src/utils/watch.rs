@@ -0,0 +1,480 @@
+use std::{collections::HashMap, future::Future, str::FromStr};
+
+use ntex::{http, rt, util::Bytes, web};
+use serde::Serialize;
+use tokio::sync::mpsc;
+
+use nanocl_error::{
+  http::{HttpError, HttpResult},
+  io::IoResult,
+};
+use nanocl_stubs::{
+  generic::{GenericClause, GenericFilter, WatchEvent, WatchEventType},
+  system::{Event, EventActorKind, NativeEventAction},
+};
+
+use crate::{
+  models::{EventDb, RawEventReceiver, SystemState},
+  repositories::generic::*,
+  vars,
+};
+
+/// Items of a list by key as they are serialized
+type Snapshot = Vec<(String, serde_json::Value)>;
+
+/// Change of an item with its key and its value when known
+type Change = (WatchEventType, String, Option<serde_json::Value>);
+
+/// Serialize the items of a list by key
+fn to_snapshot<T>(
+  items: Vec<T>,
+  get_key: fn(&T) -> String,
+) -> IoResult<Snapshot>
+where
+  T: Serialize,
+{
+  items
+    .into_iter()
+    .map(|item| Ok((get_key(&item), serde_json::to_value(&item)?)))
+    .collect()
+}
+
+/// Compute the changes between the previous and the current items of a list
+fn diff(previous: &Snapshot, current: &Snapshot) -> Vec<Change> {
+  let previous_by_key = previous.iter().cloned().collect::<HashMap<_, _>>();
+  let current_by_key = current.iter().cloned().collect::<HashMap<_, _>>();
+  let mut changes = current
+    .iter()
+    .filter_map(|(key, item)| match previous_by_key.get(key) {
+      None => Some((WatchEventType::Added, key.clone(), Some(item.clone()))),
+      Some(previous) if previous != item => {
+        Some((WatchEventType::Modified, key.clone(), Some(item.clone())))
+      }
+      Some(_) => None,
+    })
+    .collect::<Vec<_>>();
+  changes.extend(
+    previous
+      .iter()
+      .filter(|(key, _)| !current_by_key.contains_key(key))
+      .map(|(key, item)| {
+        (WatchEventType::Deleted, key.clone(), Some(item.clone()))
+      }),
+  );
+  changes
+}
+
+/// Events of a replay about an item
+#[derive(Default)]
+struct Replayed {
+  created: bool,
+  destroyed: bool,
+}
+
+/// Compute the changes of the items affected by events replayed from a cursor.
+/// The current items are listed with the filter of the watch, an item missing
+/// from them is only reported as deleted when it has been destroyed and
+/// existed at the cursor, items not matching the filter are skipped.
+fn replay(
+  kind: &EventActorKind,
+  events: &[Event],
+  current: &Snapshot,
+) -> Vec<Change> {
+  let create = NativeEventAction::Create.to_string();
+  let destroy = NativeEventAction::Destroy.to_string();
+  let mut keys: Vec<(String, Replayed)> = Vec::new();
+  for event in events {
+    let actors = [(&event.actor, true), (&event.related, false)];
+    for (actor, is_actor) in actors {
+      let Some(actor) = actor else {
+        continue;
+      };
+      let Some(key) = &actor.key else {
+        continue;
+      };
+      if &actor.kind != kind {
+        continue;
+      }
+      let index = match keys.iter().position(|(k, _)| k == key) {
+        Some(index) => index,
+        None => {
+          keys.push((key.clone(), Replayed::default()));
+          keys.len() - 1
+        }
+      };
+      let replayed = &mut keys[index].1;
+      // Actions about the related object only modify it
+      if is_actor && event.action == create {
+        replayed.created = true;
+        replayed.destroyed = false;
+      } else if is_actor && event.action == destroy {
+        replayed.destroyed = true;
+      }
+    }
+  }
+  keys
+    .into_iter()
+    .filter_map(|(key, replayed)| {
+      match current.iter().find(|(k, _)| k == &key) {
+        Some((_, item)) if replayed.created => {
+          Some((WatchEventType::Added, key, Some(item.clone())))
+        }
+        Some((_, item)) => {
+          Some((WatchEventType::Modified, key, Some(item.clone())))
+        }
+        None if replayed.destroyed && !replayed.created => {
+          Some((WatchEventType::Deleted, key, None))
+        }
+        None => None,
+      }
+    })
+    .collect()
+}
+
+/// Get the keys of a kind of object an event is about
+fn related_keys(kind: &EventActorKind, event: &Event) -> Vec<String> {
+  let mut keys = Vec::new();
+  for actor in [&event.actor, &event.related].into_iter().flatten() {
+    match &actor.key {
+      Some(key) if &actor.kind == kind && !keys.contains(key) => {
+        keys.push(key.clone())
+      }
+      _ => {}
+    }
+  }
+  keys
+}
+
+/// Restrict the filter of a watch to a single item.
+/// Return none when the filter already use the key column or is paginated,
+/// the whole list is then compared.
+fn filter_by_key(
+  filter: &GenericFilter,
+  key_column: &str,
+  key: &str,
+) -> Option<GenericFilter> {
+  if filter.limit.is_some() || filter.offset.is_some() {
+    return None;
+  }
+  if let Some(r#where) = &filter.r#where {
+    let mut conditions =
+      std::iter::once(&r#where.conditions).chain(r#where.or.iter().flatten());
+    if conditions.any(|conditions| conditions.contains_key(key_column)) {
+      return None;
+    }
+  }
+  Some(
+    filter
+      .clone()
+      .r#where(key_column, GenericClause::Eq(key.to_owned())),
+  )
+}
+
+/// Compute the change of a single item
+fn diff_item(
+  current: &mut Snapshot,
+  key: &str,
+  item: Option<serde_json::Value>,
+) -> Option<Change> {
+  let index = current.iter().position(|(k, _)| k == key);
+  match (index, item) {
+    (None, Some(item)) => {
+      current.push((key.to_owned(), item.clone()));
+      Some((WatchEventType::Added, key.to_owned(), Some(item)))
+    }
+    (Some(index), Some(item)) if current[index].1 != item => {
+      current[index].1 = item.clone();
+      Some((WatchEventType::Modified, key.to_owned(), Some(item)))
+    }
+    (Some(index), None) => {
+      let (_, item) = current.remove(index);
+      Some((WatchEventType::Deleted, key.to_owned(), Some(item)))
+    }
+    _ => None,
+  }
+}
+
+/// Get the key of the last emitted event as the cursor of a snapshot
+async fn get_cursor(state: &SystemState) -> IoResult<Option<String>> {
+  let filter = GenericFilter::new().limit(1);
+  let events = EventDb::transform_read_by(&filter, &state.inner.pool).await?;
+  Ok(events.first().map(|event| event.key.to_string()))
+}
+
+/// Read the events emitted after a cursor
+async fn read_since(
+  cursor: &str,
+  state: &SystemState,
+) -> HttpResult<Vec<Event>> {
+  let key = uuid::Uuid::from_str(cursor)
+    .map_err(|_| HttpError::bad_request(format!("Invalid cursor {cursor}")))?;
+  let expired = || {
+    HttpError::new(
+      http::StatusCode::GONE,
+      format!("Cursor {cursor} expired, watch again without a cursor"),
+    )
+  };
+  let event = EventDb::transform_read_by_pk(&key, &state.inner.pool)
+    .await
+    .map_err(|_| expired())?;
+  let since =
+    format!("{} +00:00", event.created_at.format("%Y-%m-%d %H:%M:%S%.f"));
+  let mut filter = GenericFilter::new()
+    .r#where("created_at", GenericClause::Gt(since))
+    .limit(vars::WATCH_REPLAY_LIMIT);
+  filter.order_by = Some(vec!["created_at asc".to_owned()]);
+  let events = EventDb::transform_read_by(&filter, &state.inner.pool).await?;
+  if events.len() >= vars::WATCH_REPLAY_LIMIT {
+    return Err(expired());
+  }
+  Ok(events)
+}
+
+/// Serialize a change as a line of the watch stream
+fn to_bytes(change: Change, cursor: &Option<String>) -> IoResult<Bytes> {
+  let (r#type, key, object) = change;
+  let event = WatchEvent {
+    r#type,
+    key,
+    cursor: cursor.clone(),
+    object,
+  };
+  let mut data = serde_json::to_vec(&event)?;
+  data.push(b'\n');
+  Ok(Bytes::from(data))
+}
+
+/// Changes of the items affected by an event.
+/// Each item is listed again alone with the filter of the watch
+/// unless the filter prevents it, the whole list is then compared.
+async fn list_changes<T, F, Fut>(
+  keys: Vec<String>,
+  filter: &GenericFilter,
+  key_column: &str,
+  get_key: fn(&T) -> String,
+  list: &F,
+  current: &mut Snapshot,
+  state: &SystemState,
+) -> HttpResult<Vec<Change>>
+where
+  T: Serialize,
+  F: Fn(SystemState, GenericFilter) -> Fut,
+  Fut: Future<Output = HttpResult<Vec<T>>>,
+{
+  let mut changes = Vec::new();
+  for key in keys {
+    let Some(filter) = filter_by_key(filter, key_column, &key) else {
+      let items =
+        to_snapshot(list(state.clone(), filter.clone()).await?, get_key)?;
+      let changes = diff(current, &items);
+      *current = items;
+      return Ok(changes);
+    };
+    let item = to_snapshot(list(state.clone(), filter).await?, get_key)?
+      .into_iter()
+      .find(|(k, _)| k == &key)
+      .map(|(_, item)| item);
+    changes.extend(diff_item(current, &key, item));
+  }
+  Ok(changes)
+}
+
+/// Stream the items returned by `list` followed by their changes.
+/// Without cursor every item is sent as added, with a cursor only the items
+/// affected by the events emitted since are sent.
+/// The items affected by an event about their kind are listed again
+/// so the filter of the list operation keeps applying to the changes.
+/// `key_column` is the column of the key of the items used to list them alone.
+pub async fn stream<T, F, Fut>(
+  kind: EventActorKind,
+  cursor: Option<String>,
+  filter: GenericFilter,
+  key_column: &'static str,
+  get_key: fn(&T) -> String,
+  list: F,
+  state: &SystemState,
+) -> HttpResult<web::HttpResponse>
+where
+  T: Serialize + 'static,
+  F: Fn(SystemState, GenericFilter) -> Fut + 'static,
+  Fut: Future<Output = HttpResult<Vec<T>>>,
+{
+  let mut events = state.subscribe_raw(None).await?;
+  let mut current =
+    to_snapshot(list(state.clone(), filter.clone()).await?, get_key)?;
+  let (mut cursor, changes) = match cursor {
+    None => {
+      let changes = current
+        .iter()
+        .map(|(key, item)| {
+          (WatchEventType::Added, key.clone(), Some(item.clone()))
+        })
+        .collect::<Vec<_>>();
+      (get_cursor(state).await?, changes)
+    }
+    Some(cursor) => {
+      let events = read_since(&cursor, state).await?;
+      let changes = replay(&kind, &events, &current);
+      let cursor = events
+        .last()
+        .map(|event| event.key.to_string())
+        .unwrap_or(cursor);
+      (Some(cursor), changes)
+    }
+  };
+  let (tx, rx) = mpsc::channel(100);
+  let state = state.clone();
+  rt::spawn(async move {
+    for change in changes {
+      let Ok(bytes) = to_bytes(change, &cursor) else {
+        continue;
+      };
+      if tx.send(bytes).await.is_err() {
+        return;
+      }
+    }
+    while let Some(bytes) = events.0.recv().await {
+      // Forward the heartbeat of the event emitter to detect disconnection
+      if bytes.is_empty() {
+        if tx.send(bytes).await.is_err() {
+          break;
+        }
+        continue;
+      }
+      let Ok(event) = serde_json::from_slice::<Event>(&bytes) else {
+        continue;
+      };
+      cursor = Some(event.key.to_string());
+      let keys = related_keys(&kind, &event);
+      if keys.is_empty() {
+        continue;
+      }
+      let changes = match list_changes(
+        keys,
+        &filter,
+        key_column,
+        get_key,
+        &list,
+        &mut current,
+        &state,
+      )
+      .await
+      {
+        Ok(changes) => changes,
+        Err(err) => {
+          log::warn!("watch::stream: {kind} {err}");
+          continue;
+        }
+      };
+      for change in changes {
+        let Ok(bytes) = to_bytes(change, &cursor) else {
+          continue;
+        };
+        if tx.send(bytes).await.is_err() {
+          return;
+        }
+      }
+    }
+  });
+  Ok(
+    web::HttpResponse::Ok()
+      .content_type("text/event-stream")
+      .streaming(RawEventReceiver(rx)),
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use nanocl_stubs::system::EventActor;
+
+  fn gen_event(action: NativeEventAction, key: &str) -> Event {
+    Event {
+      key: uuid::Uuid::new_v4(),
+      created_at: chrono::Utc::now().naive_utc(),
+      expires_at: chrono::Utc::now().naive_utc(),
+      reporting_node: "test".to_owned(),
+      reporting_controller: vars::CONTROLLER_NAME.to_owned(),
+      kind: nanocl_stubs::system::EventKind::Normal,
+      action: action.to_string(),
+      reason: "state_sync".to_owned(),
+      note: None,
+      actor: Some(EventActor {
+        key: Some(key.to_owned()),
+        kind: EventActorKind::Secret,
+        attributes: None,
+      }),
+      related: None,
+      metadata: None,
+    }
+  }
+
+  #[test]
+  fn changes() {
+    let previous = vec![
+      ("a".to_owned(), serde_json::json!({ "Value": 1 })),
+      ("b".to_owned(), serde_json::json!({ "Value": 1 })),
+    ];
+    let current = vec![
+      ("b".to_owned(), serde_json::json!({ "Value": 2 })),
+      ("c".to_owned(), serde_json::json!({ "Value": 1 })),
+    ];
+    let changes = diff(&previous, &current)
+      .into_iter()
+      .map(|(r#type, key, _)| (r#type, key))
+      .collect::<Vec<_>>();
+    assert_eq!(
+      changes,
+      vec![
+        (WatchEventType::Modified, "b".to_owned()),
+        (WatchEventType::Added, "c".to_owned()),
+        (WatchEventType::Deleted, "a".to_owned()),
+      ]
+    );
+    assert!(diff(&current, &current).is_empty());
+    let events = vec![
+      gen_event(NativeEventAction::Destroy, "a"),
+      gen_event(NativeEventAction::Update, "b"),
+      gen_event(NativeEventAction::Create, "c"),
+      gen_event(NativeEventAction::Update, "c"),
+    ];
+    let changes = replay(&EventActorKind::Secret, &events, &current);
+    assert_eq!(changes[0], (WatchEventType::Deleted, "a".to_owned(), None));
+    assert_eq!(changes[1].0, WatchEventType::Modified);
+    assert_eq!(changes[2].0, WatchEventType::Added);
+    assert_eq!(changes.len(), 3);
+    assert!(replay(&EventActorKind::Cargo, &events, &current).is_empty());
+    // Items filtered out or created and destroyed since the cursor
+    // have never been sent to the watcher
+    let events = vec![
+      gen_event(NativeEventAction::Update, "d"),
+      gen_event(NativeEventAction::Create, "e"),
+      gen_event(NativeEventAction::Destroy, "e"),
+    ];
+    assert!(replay(&EventActorKind::Secret, &events, &current).is_empty());
+    let mut snapshot = current.clone();
+    let item = serde_json::json!({ "Value": 3 });
+    let change = diff_item(&mut snapshot, "b", Some(item.clone())).unwrap();
+    assert_eq!(change.0, WatchEventType::Modified);
+    assert!(diff_item(&mut snapshot, "b", Some(item)).is_none());
+    let change = diff_item(&mut snapshot, "c", None).unwrap();
+    assert_eq!(change.0, WatchEventType::Deleted);
+    assert_eq!(snapshot.len(), 1);
+  }
+
+  #[test]
+  fn filter_key() {
+    let filter = GenericFilter::new()
+      .r#where("kind", GenericClause::Eq("nanocl.io/env".to_owned()));
+    let filter_key = filter_by_key(&filter, "key", "a").unwrap();
+    let conditions = filter_key.r#where.unwrap().conditions;
+    assert_eq!(conditions.len(), 2);
+    assert!(conditions.contains_key("key"));
+    let filter = filter.r#where("key", GenericClause::Like("test%".to_owned()));
+    assert!(filter_by_key(&filter, "key", "a").is_none());
+    assert!(
+      filter_by_key(&GenericFilter::new().limit(10), "key", "a").is_none()
+    );
+  }
+}
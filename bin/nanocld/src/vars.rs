@@ -17,3 +17,46 @@ pub const CHANNEL: &str = env!("CHANNEL");
 pub const CONTROLLER_NAME: &str = "nanocl.io/core";
 /// Default Virtual Machine runtime
 pub const VM_RUNTIME: &str = "ghcr.io/next-hat/nanocl-qemu:8.0.2.0";
+/// Default seconds to wait for a Virtual Machine to power off
+pub const VM_SHUTDOWN_TIMEOUT: u64 = 60;
+/// Size in bytes after which the console log of a Virtual Machine is rotated
+pub const VM_CONSOLE_LOG_MAX_SIZE: u64 = 10 * 1024 * 1024;
+/// Number of rotated console log files kept for a Virtual Machine
+pub const VM_CONSOLE_LOG_MAX_FILES: usize = 5;
+/// Default key sequence to detach from a Virtual Machine console
+pub const VM_DETACH_KEYS: &str = "ctrl-c";
+/// Kind of the metrics storing the statistics of the Virtual Machines
+pub const VM_METRIC_KIND: &str = "nanocl.io/vm";
+/// Seconds between two samples of the statistics of a Virtual Machine
+pub const VM_METRIC_INTERVAL: u64 = 30;
+/// Days the internal CA used to issue `nanocl.io/tls-issued` secrets is valid
+pub const CA_LIFETIME: u32 = 3650;
+/// Default days a certificate of a `nanocl.io/tls-issued` secret is valid
+pub const TLS_ISSUED_LIFETIME: u32 = 90;
+/// Seconds between two checks of the `nanocl.io/tls-issued` secrets to renew
+pub const TLS_ISSUED_RENEW_INTERVAL: u64 = 3600;
+/// Value replacing the data of the secrets when not revealed
+pub const SECRET_MASK: &str = "********";
+/// Label of the containers listing the env variables sourced from secrets
+pub const SECRET_ENV_LABEL: &str = "io.nanocl.secret-env";
+/// Default mount path of the Vault KV v2 engine of external secrets
+pub const VAULT_DEFAULT_MOUNT: &str = "secret";
+/// Seconds before a request to an external secret backend is aborted
+pub const SECRET_BACKEND_TIMEOUT: u32 = 10;
+/// Seconds to wait for the objects of a namespace to be destroyed on cascade
+pub const CASCADE_TIMEOUT: u64 = 60;
+/// Seconds between two retries of the unreachable resource controllers
+pub const RESOURCE_RECONCILE_INTERVAL: u64 = 5;
+/// Seconds to wait before calling again an unreachable resource controller,
+/// doubled on each failed attempt
+pub const RESOURCE_RETRY_DELAY: u64 = 2;
+/// Maximum seconds to wait before calling again a resource controller
+pub const RESOURCE_RETRY_MAX_DELAY: u64 = 300;
+/// Default seconds to wait for the controller of a resource kind to respond
+pub const CTRL_CLIENT_TIMEOUT: u32 = 50;
+/// Maximum events replayed to resume a watch from a cursor
+pub const WATCH_REPLAY_LIMIT: usize = 1000;
+/// Default instructions a hook of a WebAssembly controller can execute
+pub const WASM_CTRL_FUEL: u64 = 100_000_000;
+/// Default memory in MiB a hook of a WebAssembly controller can use
+pub const WASM_CTRL_MEMORY: u32 = 64;
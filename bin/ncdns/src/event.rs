@@ -21,6 +21,10 @@ async fn ensure_self_config(client: &NanocldClient) -> IoResult<()> {
     data: ResourceKindSpec {
       schema: None,
       url: Some("unix:///run/nanocl/dns.sock".to_owned()),
+      conversion: None,
+      controller: None,
+      immutable: None,
+      wasm: None,
     },
   };
   if client
@@ -8,6 +8,18 @@ pub struct Cli {
   /// Path to state directory
   #[clap(long)]
   pub state_dir: String,
+  /// Url of the ACME directory used to request certificates
+  #[clap(
+    long,
+    default_value = "https://acme-v02.api.letsencrypt.org/directory"
+  )]
+  pub acme_directory: String,
+  /// Contact email registered with the ACME account
+  #[clap(long)]
+  pub acme_email: Option<String>,
+  /// Path to a CA certificate to trust when connecting to the ACME directory
+  #[clap(long)]
+  pub acme_ca: Option<String>,
 }
 
 #[cfg(test)]
@@ -28,6 +40,24 @@ mod tests {
     let args = Cli::parse_from(["ncproxy", "--state-dir", "/test/state"]);
     assert_eq!(args.nginx_dir, "/etc/nginx");
     assert_eq!(args.state_dir, "/test/state");
+    assert_eq!(
+      args.acme_directory,
+      "https://acme-v02.api.letsencrypt.org/directory"
+    );
+    let args = Cli::parse_from([
+      "ncproxy",
+      "--state-dir",
+      "/test/state",
+      "--acme-directory",
+      "https://localhost:14000/dir",
+      "--acme-email",
+      "admin@test.io",
+      "--acme-ca",
+      "/test/pebble.minica.pem",
+    ]);
+    assert_eq!(args.acme_directory, "https://localhost:14000/dir");
+    assert_eq!(args.acme_email, Some("admin@test.io".to_owned()));
+    assert_eq!(args.acme_ca, Some("/test/pebble.minica.pem".to_owned()));
     let _ = Cli::try_parse();
   }
 }
@@ -0,0 +1,60 @@
+use serde::{Deserialize, Serialize};
+
+/// Configuration of the ACME client used to request certificates
+#[derive(Clone, Debug)]
+pub struct AcmeConfig {
+  /// Url of the directory of the ACME certificate authority
+  pub directory: String,
+  /// Contact email registered with the ACME account
+  pub email: Option<String>,
+  /// Path to a CA certificate to trust when connecting to the directory
+  pub ca: Option<String>,
+}
+
+/// Urls exposed by the directory of an ACME server
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AcmeDirectory {
+  pub new_nonce: String,
+  pub new_account: String,
+  pub new_order: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AcmeIdentifier {
+  #[serde(rename = "type")]
+  pub kind: String,
+  pub value: String,
+}
+
+/// An order of certificate for a list of identifiers
+#[derive(Debug, Deserialize)]
+pub struct AcmeOrder {
+  pub status: String,
+  pub authorizations: Vec<String>,
+  pub finalize: String,
+  pub certificate: Option<String>,
+}
+
+/// A challenge proving the control of an identifier
+#[derive(Debug, Deserialize)]
+pub struct AcmeChallenge {
+  #[serde(rename = "type")]
+  pub kind: String,
+  pub url: String,
+  pub token: String,
+}
+
+/// The authorization of an identifier for an order
+#[derive(Debug, Deserialize)]
+pub struct AcmeAuthorization {
+  pub status: String,
+  pub identifier: AcmeIdentifier,
+  pub challenges: Vec<AcmeChallenge>,
+}
+
+/// A certificate chain and its private key in PEM format
+pub struct AcmeCertificate {
+  pub certificate: String,
+  pub certificate_key: String,
+}
@@ -1,7 +1,9 @@
+mod acme;
 mod store;
 mod system;
 mod template;
 
+pub use acme::*;
 pub use store::*;
 pub use system::*;
 pub use template::*;
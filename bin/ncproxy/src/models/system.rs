@@ -1,4 +1,7 @@
-use std::sync::Arc;
+use std::{
+  collections::HashSet,
+  sync::{Arc, Mutex},
+};
 
 use futures::{channel::mpsc, SinkExt, StreamExt};
 use ntex::rt;
@@ -9,7 +12,7 @@ use nanocld_client::NanocldClient;
 
 use crate::utils;
 
-use super::Store;
+use super::{AcmeConfig, Store};
 
 /// Shared state of the program
 #[derive(Clone)]
@@ -18,6 +21,9 @@ pub struct SystemState {
   pub client: NanocldClient,
   pub event_emitter: EventEmitter,
   pub nginx_dir: String,
+  pub acme: AcmeConfig,
+  /// Rules with a pending certificate request
+  pub acme_pending: Arc<Mutex<HashSet<String>>>,
 }
 
 pub type SystemStateRef = Arc<SystemState>;
@@ -14,6 +14,14 @@ pub struct LocationTemplate {
   pub version: Option<f64>,
   pub headers: Option<Vec<String>>,
   pub ssl: Option<ProxySslConfig>,
+  pub keepalive: Option<bool>,
+}
+
+/// Server of an upstream with its weight
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpstreamServerTemplate {
+  pub address: String,
+  pub weight: Option<u32>,
 }
 
 pub struct Template<'a> {
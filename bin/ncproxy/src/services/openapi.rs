@@ -1,10 +1,10 @@
 use utoipa::OpenApi;
 
 use nanocld_client::stubs::proxy::{
-  HttpTarget, LocationTarget, ProxyHttpLocation, ProxyRule, ProxyRuleHttp,
-  ProxyRuleStream, ProxySsl, ProxySslConfig, ProxyStreamProtocol,
-  ResourceProxyRule, StreamTarget, UnixTarget, UpstreamTarget, UriTarget,
-  UrlRedirect,
+  HttpTarget, LocationTarget, ProxyAcmeConfig, ProxyHttpLocation, ProxyRule,
+  ProxyRuleHttp, ProxyRuleStream, ProxySsl, ProxySslAcme, ProxySslConfig,
+  ProxyStreamProtocol, ResourceProxyRule, StreamTarget, UnixTarget,
+  UpstreamPolicy, UpstreamTarget, UriTarget, UrlRedirect,
 };
 
 use super::rule;
@@ -46,10 +46,13 @@ impl utoipa::Modify for VersionModifier {
     ProxyHttpLocation,
     ProxySsl,
     ProxySslConfig,
+    ProxySslAcme,
+    ProxyAcmeConfig,
     ProxyStreamProtocol,
     StreamTarget,
     LocationTarget,
     UpstreamTarget,
+    UpstreamPolicy,
     HttpTarget,
     UriTarget,
     UrlRedirect,
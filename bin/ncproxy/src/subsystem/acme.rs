@@ -0,0 +1,50 @@
+use std::{sync::Arc, time::Duration};
+
+use ntex::rt;
+
+use nanocl_error::io::IoResult;
+
+use nanocld_client::stubs::{
+  generic::{GenericClause, GenericFilter},
+  resource::ResourcePartial,
+};
+
+use crate::{models::SystemStateRef, utils, vars};
+
+/// Request or renew the certificates of the rules using ACME
+async fn renew(state: &SystemStateRef) -> IoResult<()> {
+  let filter = GenericFilter::new()
+    .r#where("kind", GenericClause::Eq(vars::RULE_KEY.to_owned()))
+    .r#where(
+      "data",
+      GenericClause::Contains(
+        serde_json::json!({ "Rules": [ { "Ssl": { "Acme": {} } } ] }),
+      ),
+    );
+  let resources = state.client.list_resource(Some(&filter)).await?;
+  for resource in resources {
+    let resource: ResourcePartial = resource.into();
+    let rule = utils::resource::serialize(&resource.data)?;
+    if let Err(err) = utils::acme::sync_rule(&resource.name, &rule, state).await
+    {
+      log::warn!("acme::renew: {} {err}", resource.name);
+    }
+  }
+  Ok(())
+}
+
+/// Spawn a thread checking periodically the certificates to renew
+pub(crate) fn spawn(state: &SystemStateRef) {
+  let state = Arc::clone(state);
+  rt::Arbiter::new().exec_fn(move || {
+    ntex::rt::spawn(async move {
+      loop {
+        ntex::time::sleep(Duration::from_secs(vars::ACME_RENEW_INTERVAL)).await;
+        log::info!("acme::spawn: checking certificates to renew");
+        if let Err(err) = renew(&state).await {
+          log::warn!("acme::spawn: {err}");
+        }
+      }
+    });
+  });
+}
@@ -132,6 +132,10 @@ async fn ensure_self_config(client: &NanocldClient) -> IoResult<()> {
     data: ResourceKindSpec {
       schema: None,
       url: Some("unix:///run/nanocl/proxy.sock".to_owned()),
+      conversion: None,
+      controller: None,
+      immutable: None,
+      wasm: None,
     },
   };
   if client
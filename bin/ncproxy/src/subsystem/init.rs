@@ -6,10 +6,10 @@ use nanocld_client::NanocldClient;
 
 use crate::{
   cli::Cli,
-  models::{EventEmitter, Store, SystemState, SystemStateRef},
+  models::{AcmeConfig, EventEmitter, Store, SystemState, SystemStateRef},
 };
 
-use super::{event, metric};
+use super::{acme, event, metric};
 
 pub async fn init(cli: &Cli) -> IoResult<SystemStateRef> {
   #[allow(unused)]
@@ -28,8 +28,15 @@ pub async fn init(cli: &Cli) -> IoResult<SystemStateRef> {
     event_emitter,
     store: Store::new(&cli.state_dir),
     nginx_dir: cli.nginx_dir.clone(),
+    acme: AcmeConfig {
+      directory: cli.acme_directory.clone(),
+      email: cli.acme_email.clone(),
+      ca: cli.acme_ca.clone(),
+    },
+    acme_pending: Default::default(),
   });
   event::spawn(&state);
   metric::spawn(&state);
+  acme::spawn(&state);
   Ok(state)
 }
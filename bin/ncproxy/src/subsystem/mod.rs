@@ -1,3 +1,4 @@
+mod acme;
 mod event;
 mod init;
 mod metric;
@@ -0,0 +1,684 @@
+//! Minimal ACME client (RFC 8555) requesting certificates
+//! for the domain of http rules with the HTTP-01 challenge.
+//! Challenges are written in `{state_dir}/acme/challenges`
+//! which is served by nginx under `/.well-known/acme-challenge/`.
+use std::{cmp::Ordering, sync::Arc, time::Duration};
+
+use ntex::{
+  http::{
+    self,
+    client::{Client, ClientResponse, Connector},
+  },
+  rt,
+  util::Bytes,
+};
+use openssl::{
+  asn1::Asn1Time,
+  bn::{BigNum, BigNumContext},
+  ec::{EcGroup, EcKey, EcKeyRef},
+  ecdsa::EcdsaSig,
+  hash::{hash, MessageDigest},
+  nid::Nid,
+  pkey::{PKey, Private},
+  ssl::{SslConnector, SslMethod},
+  stack::Stack,
+  x509::{
+    extension::SubjectAlternativeName, X509NameBuilder, X509ReqBuilder, X509,
+  },
+};
+
+use nanocl_error::{
+  http_client::HttpClientError,
+  io::{FromIo, IoError, IoResult},
+};
+
+use nanocld_client::stubs::{
+  proxy::{
+    ProxyRule, ProxySsl, ProxySslAcme, ProxySslConfig, ResourceProxyRule,
+  },
+  secret::{SecretInspectQuery, SecretPartial, SecretUpdate},
+};
+
+use crate::{
+  models::{
+    AcmeAuthorization, AcmeCertificate, AcmeConfig, AcmeDirectory,
+    AcmeIdentifier, AcmeOrder, SystemStateRef,
+  },
+  vars,
+};
+
+fn map_err(err: openssl::error::ErrorStack) -> IoError {
+  IoError::other("Acme", err.to_string().as_str())
+}
+
+/// Encode data in base64url without padding as required by JWS
+pub fn b64(data: &[u8]) -> String {
+  openssl::base64::encode_block(data)
+    .trim_end_matches('=')
+    .replace('+', "-")
+    .replace('/', "_")
+}
+
+/// Path of the directory containing the challenges served by nginx
+pub fn challenge_dir(state_dir: &str) -> String {
+  format!("{state_dir}/acme/challenges")
+}
+
+fn gen_key() -> Result<EcKey<Private>, openssl::error::ErrorStack> {
+  let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
+  EcKey::generate(&group)
+}
+
+/// Load the key of the ACME account from the `state_dir` and create it when missing
+async fn load_account_key(state_dir: &str) -> IoResult<EcKey<Private>> {
+  let path = format!("{state_dir}/acme/account.key");
+  if let Ok(pem) = tokio::fs::read(&path).await {
+    return EcKey::private_key_from_pem(&pem).map_err(map_err);
+  }
+  log::info!("acme::load_account_key: creating account key {path}");
+  let key = gen_key().map_err(map_err)?;
+  tokio::fs::create_dir_all(format!("{state_dir}/acme"))
+    .await
+    .map_err(|err| err.map_err_context(|| state_dir))?;
+  let pem = key.private_key_to_pem().map_err(map_err)?;
+  let mut file = tokio::fs::OpenOptions::new()
+    .write(true)
+    .create(true)
+    .truncate(true)
+    .mode(0o600)
+    .open(&path)
+    .await
+    .map_err(|err| err.map_err_context(|| &path))?;
+  tokio::io::AsyncWriteExt::write_all(&mut file, &pem)
+    .await
+    .map_err(|err| err.map_err_context(|| &path))?;
+  Ok(key)
+}
+
+/// Public JSON web key of an EC P-256 key with its members in lexicographic order
+pub fn jwk(key: &EcKeyRef<Private>) -> IoResult<String> {
+  let coordinates =
+    || -> Result<(Vec<u8>, Vec<u8>), openssl::error::ErrorStack> {
+      let mut ctx = BigNumContext::new()?;
+      let mut x = BigNum::new()?;
+      let mut y = BigNum::new()?;
+      key.public_key().affine_coordinates(
+        key.group(),
+        &mut x,
+        &mut y,
+        &mut ctx,
+      )?;
+      Ok((x.to_vec_padded(32)?, y.to_vec_padded(32)?))
+    };
+  let (x, y) = coordinates().map_err(map_err)?;
+  Ok(format!(
+    r#"{{"crv":"P-256","kty":"EC","x":"{}","y":"{}"}}"#,
+    b64(&x),
+    b64(&y)
+  ))
+}
+
+/// Thumbprint of a JSON web key (RFC 7638)
+pub fn thumbprint(jwk: &str) -> IoResult<String> {
+  let digest =
+    hash(MessageDigest::sha256(), jwk.as_bytes()).map_err(map_err)?;
+  Ok(b64(&digest))
+}
+
+/// Content served for the HTTP-01 challenge of the `token`
+pub fn key_authorization(token: &str, thumbprint: &str) -> String {
+  format!("{token}.{thumbprint}")
+}
+
+/// Sign the `payload` with the ES256 algorithm and return the flattened JWS
+pub fn sign(
+  key: &EcKeyRef<Private>,
+  protected: &serde_json::Value,
+  payload: &str,
+) -> IoResult<serde_json::Value> {
+  let protected = b64(protected.to_string().as_bytes());
+  let payload = b64(payload.as_bytes());
+  let signature = || -> Result<Vec<u8>, openssl::error::ErrorStack> {
+    let digest = hash(
+      MessageDigest::sha256(),
+      format!("{protected}.{payload}").as_bytes(),
+    )?;
+    let signature = EcdsaSig::sign(&digest, key)?;
+    let mut raw = signature.r().to_vec_padded(32)?;
+    raw.extend(signature.s().to_vec_padded(32)?);
+    Ok(raw)
+  };
+  Ok(serde_json::json!({
+    "protected": protected,
+    "payload": payload,
+    "signature": b64(&signature().map_err(map_err)?),
+  }))
+}
+
+/// Check if the certificate expires in less than `days` days
+pub fn need_renewal(certificate: &str, days: u32) -> IoResult<bool> {
+  let cert = X509::from_pem(certificate.as_bytes()).map_err(map_err)?;
+  let threshold = Asn1Time::days_from_now(days).map_err(map_err)?;
+  let ordering = cert.not_after().compare(&threshold).map_err(map_err)?;
+  Ok(ordering == Ordering::Less)
+}
+
+/// Create a private key and a certificate signing request for the `domain`
+fn gen_csr(domain: &str) -> IoResult<(Vec<u8>, String)> {
+  let gen = || -> Result<(Vec<u8>, Vec<u8>), openssl::error::ErrorStack> {
+    let key = PKey::from_ec_key(gen_key()?)?;
+    let mut name = X509NameBuilder::new()?;
+    name.append_entry_by_nid(Nid::COMMONNAME, domain)?;
+    let mut builder = X509ReqBuilder::new()?;
+    builder.set_subject_name(name.build().as_ref())?;
+    builder.set_pubkey(&key)?;
+    let mut extensions = Stack::new()?;
+    extensions.push(
+      SubjectAlternativeName::new()
+        .dns(domain)
+        .build(&builder.x509v3_context(None))?,
+    )?;
+    builder.add_extensions(&extensions)?;
+    builder.sign(&key, MessageDigest::sha256())?;
+    Ok((builder.build().to_der()?, key.private_key_to_pem_pkcs8()?))
+  };
+  let (csr, key) = gen().map_err(map_err)?;
+  let key = String::from_utf8(key)
+    .map_err(|err| IoError::invalid_data("Acme", err.to_string().as_str()))?;
+  Ok((csr, key))
+}
+
+/// A session with an ACME server using a registered account
+pub struct AcmeClient {
+  client: Client,
+  directory: AcmeDirectory,
+  key: EcKey<Private>,
+  jwk: String,
+  kid: Option<String>,
+  nonce: Option<String>,
+}
+
+impl AcmeClient {
+  /// Fetch the directory of the server and register the account of the key
+  pub async fn new(config: &AcmeConfig, key: EcKey<Private>) -> IoResult<Self> {
+    let mut builder =
+      SslConnector::builder(SslMethod::tls()).map_err(map_err)?;
+    if let Some(ca) = &config.ca {
+      builder.set_ca_file(ca).map_err(map_err)?;
+    }
+    let client = Client::build()
+      .connector(Connector::default().openssl(builder.build()).finish())
+      .timeout(ntex::time::Millis::from_secs(30))
+      .finish();
+    let mut res = client
+      .get(&config.directory)
+      .send()
+      .await
+      .map_err(|err| err.map_err_context(|| &config.directory))?;
+    let directory = res
+      .json::<AcmeDirectory>()
+      .await
+      .map_err(|err| err.map_err_context(|| &config.directory))?;
+    let jwk = jwk(&key)?;
+    let mut acme = Self {
+      client,
+      directory,
+      key,
+      jwk,
+      kid: None,
+      nonce: None,
+    };
+    let mut account = serde_json::json!({ "termsOfServiceAgreed": true });
+    if let Some(email) = &config.email {
+      account["contact"] = serde_json::json!([format!("mailto:{email}")]);
+    }
+    let url = acme.directory.new_account.clone();
+    let (kid, _) = acme.post(&url, Some(&account)).await?;
+    acme.kid = Some(kid.ok_or_else(|| {
+      IoError::invalid_data("Acme", "Missing account location")
+    })?);
+    Ok(acme)
+  }
+
+  async fn new_nonce(&self) -> IoResult<String> {
+    let url = &self.directory.new_nonce;
+    let res = self
+      .client
+      .head(url)
+      .send()
+      .await
+      .map_err(|err| err.map_err_context(|| url))?;
+    get_nonce(&res)
+      .ok_or_else(|| IoError::invalid_data("Acme", "Missing replay nonce"))
+  }
+
+  /// Send a signed request, a `None` payload is a POST-as-GET request
+  async fn post(
+    &mut self,
+    url: &str,
+    payload: Option<&serde_json::Value>,
+  ) -> IoResult<(Option<String>, Bytes)> {
+    let payload = payload.map(|p| p.to_string()).unwrap_or_default();
+    let mut retry = true;
+    loop {
+      let nonce = match self.nonce.take() {
+        Some(nonce) => nonce,
+        None => self.new_nonce().await?,
+      };
+      let mut protected = serde_json::json!({
+        "alg": "ES256",
+        "nonce": nonce,
+        "url": url,
+      });
+      match &self.kid {
+        Some(kid) => protected["kid"] = serde_json::json!(kid),
+        None => {
+          protected["jwk"] = serde_json::from_str(&self.jwk)?;
+        }
+      }
+      let body = sign(&self.key, &protected, &payload)?;
+      let mut res = self
+        .client
+        .post(url)
+        .header("Content-Type", "application/jose+json")
+        .send_body(body.to_string())
+        .await
+        .map_err(|err| err.map_err_context(|| url))?;
+      self.nonce = get_nonce(&res);
+      let location = res
+        .header("Location")
+        .and_then(|location| location.to_str().ok())
+        .map(|location| location.to_owned());
+      let status = res.status();
+      let body = res
+        .body()
+        .limit(4 * 1024 * 1024)
+        .await
+        .map_err(|err| err.map_err_context(|| url))?;
+      if status.is_success() {
+        return Ok((location, body));
+      }
+      let problem =
+        serde_json::from_slice::<serde_json::Value>(&body).unwrap_or_default();
+      if retry && problem["type"] == "urn:ietf:params:acme:error:badNonce" {
+        retry = false;
+        continue;
+      }
+      return Err(IoError::other(
+        url,
+        &format!("{status} {}", String::from_utf8_lossy(&body)),
+      ));
+    }
+  }
+
+  async fn post_json<T: serde::de::DeserializeOwned>(
+    &mut self,
+    url: &str,
+    payload: Option<&serde_json::Value>,
+  ) -> IoResult<(Option<String>, T)> {
+    let (location, body) = self.post(url, payload).await?;
+    let data = serde_json::from_slice::<T>(&body)
+      .map_err(|err| err.map_err_context(|| url))?;
+    Ok((location, data))
+  }
+
+  /// Fulfill the HTTP-01 challenge of an authorization
+  async fn authorize(
+    &mut self,
+    url: &str,
+    challenge_dir: &str,
+  ) -> IoResult<()> {
+    let (_, authz) = self.post_json::<AcmeAuthorization>(url, None).await?;
+    if authz.status == "valid" {
+      return Ok(());
+    }
+    let challenge = authz
+      .challenges
+      .iter()
+      .find(|challenge| challenge.kind == "http-01")
+      .ok_or_else(|| {
+        IoError::not_found("Challenge http-01", &authz.identifier.value)
+      })?;
+    if challenge.token.is_empty()
+      || !challenge
+        .token
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+      return Err(IoError::invalid_data("Challenge token", &challenge.token));
+    }
+    let path = format!("{challenge_dir}/{}", challenge.token);
+    let content = key_authorization(&challenge.token, &thumbprint(&self.jwk)?);
+    tokio::fs::create_dir_all(challenge_dir)
+      .await
+      .map_err(|err| err.map_err_context(|| challenge_dir))?;
+    tokio::fs::write(&path, content)
+      .await
+      .map_err(|err| err.map_err_context(|| &path))?;
+    let challenge_url = challenge.url.clone();
+    let res = async {
+      self
+        .post(&challenge_url, Some(&serde_json::json!({})))
+        .await?;
+      for _ in 0..vars::ACME_POLL_ATTEMPTS {
+        let (_, authz) = self.post_json::<AcmeAuthorization>(url, None).await?;
+        match authz.status.as_str() {
+          "valid" => return Ok(()),
+          "pending" | "processing" => {
+            ntex::time::sleep(Duration::from_secs(2)).await
+          }
+          status => {
+            return Err(IoError::other(
+              "Authorization",
+              &format!("{} is {status}", authz.identifier.value),
+            ))
+          }
+        }
+      }
+      Err(IoError::interrupted("Authorization", "Timeout"))
+    }
+    .await;
+    let _ = tokio::fs::remove_file(&path).await;
+    res
+  }
+
+  /// Order a certificate for the `domain`
+  pub async fn order(
+    &mut self,
+    domain: &str,
+    challenge_dir: &str,
+  ) -> IoResult<AcmeCertificate> {
+    let identifier = AcmeIdentifier {
+      kind: "dns".to_owned(),
+      value: domain.to_owned(),
+    };
+    let url = self.directory.new_order.clone();
+    let (order_url, order) = self
+      .post_json::<AcmeOrder>(
+        &url,
+        Some(&serde_json::json!({ "identifiers": [identifier] })),
+      )
+      .await?;
+    let order_url = order_url
+      .ok_or_else(|| IoError::invalid_data("Acme", "Missing order location"))?;
+    for authz in &order.authorizations {
+      self.authorize(authz, challenge_dir).await?;
+    }
+    let (csr, certificate_key) = gen_csr(domain)?;
+    let (_, mut order) = self
+      .post_json::<AcmeOrder>(
+        &order.finalize,
+        Some(&serde_json::json!({ "csr": b64(&csr) })),
+      )
+      .await?;
+    for _ in 0..vars::ACME_POLL_ATTEMPTS {
+      if order.status != "processing" && order.status != "ready" {
+        break;
+      }
+      ntex::time::sleep(Duration::from_secs(2)).await;
+      (_, order) = self.post_json::<AcmeOrder>(&order_url, None).await?;
+    }
+    let Some(certificate_url) = order.certificate else {
+      return Err(IoError::other(
+        "Order",
+        &format!("{domain} is {}", order.status),
+      ));
+    };
+    let (_, certificate) = self.post(&certificate_url, None).await?;
+    let certificate = String::from_utf8(certificate.to_vec())
+      .map_err(|err| IoError::invalid_data("Acme", err.to_string().as_str()))?;
+    Ok(AcmeCertificate {
+      certificate,
+      certificate_key,
+    })
+  }
+}
+
+fn get_nonce(res: &ClientResponse) -> Option<String> {
+  res
+    .header("Replay-Nonce")
+    .and_then(|nonce| nonce.to_str().ok())
+    .map(|nonce| nonce.to_owned())
+}
+
+/// List the domains of a rule requesting a certificate with ACME
+pub fn list_domains(rule: &ResourceProxyRule) -> Vec<(String, ProxySslAcme)> {
+  rule
+    .rules
+    .iter()
+    .filter_map(|rule| match rule {
+      ProxyRule::Http(http) => match (&http.domain, &http.ssl) {
+        (Some(domain), Some(ProxySsl::Acme(acme))) => {
+          Some((domain.clone(), acme.clone()))
+        }
+        _ => None,
+      },
+      _ => None,
+    })
+    .collect()
+}
+
+/// Request a certificate for the `domain` when missing or expiring
+/// and store it in a `nanocl.io/tls` secret.
+/// Return true when a new certificate has been issued
+pub async fn ensure_cert(
+  domain: &str,
+  acme: &ProxySslAcme,
+  state: &SystemStateRef,
+) -> IoResult<bool> {
+  let secret_name = acme.secret_name(domain);
+  let query = SecretInspectQuery { reveal: Some(true) };
+  let res = state
+    .client
+    .inspect_secret(&secret_name, Some(&query))
+    .await;
+  let secret = match res {
+    Ok(secret) => Some(secret),
+    Err(HttpClientError::HttpError(err))
+      if err.status == http::StatusCode::NOT_FOUND =>
+    {
+      None
+    }
+    Err(err) => {
+      let err = err
+        .map_err_context(|| format!("Unable to inspect secret {secret_name}"));
+      return Err(err.into());
+    }
+  };
+  if let Some(secret) = &secret {
+    if secret.kind != vars::ACME_SECRET_KIND {
+      return Err(IoError::invalid_data(
+        "Secret",
+        &format!("{secret_name} is not a {}", vars::ACME_SECRET_KIND),
+      ));
+    }
+    let ssl = serde_json::from_value::<ProxySslConfig>(secret.data.clone())?;
+    if !need_renewal(&ssl.certificate, vars::ACME_RENEW_BEFORE)? {
+      return Ok(false);
+    }
+    log::info!("acme::ensure_cert: renewing certificate of {domain}");
+  } else {
+    log::info!("acme::ensure_cert: requesting certificate of {domain}");
+  }
+  let key = load_account_key(&state.store.dir).await?;
+  let mut client = AcmeClient::new(&state.acme, key).await?;
+  let cert = client
+    .order(domain, &challenge_dir(&state.store.dir))
+    .await?;
+  let data = serde_json::to_value(ProxySslConfig {
+    certificate: cert.certificate,
+    certificate_key: cert.certificate_key,
+    certificate_client: None,
+    verify_client: None,
+    dhparam: None,
+  })?;
+  let metadata = Some(serde_json::json!({
+    "ncproxy.io/acme": {
+      "Domain": domain,
+      "Directory": state.acme.directory,
+    }
+  }));
+  match secret {
+    Some(_) => {
+      let update = SecretUpdate { metadata, data };
+      state
+        .client
+        .patch_secret(&secret_name, &update, None)
+        .await?;
+    }
+    None => {
+      let secret = SecretPartial {
+        name: secret_name,
+        kind: vars::ACME_SECRET_KIND.to_owned(),
+        immutable: false,
+        metadata,
+        data,
+      };
+      state.client.create_secret(&secret).await?;
+    }
+  }
+  log::info!("acme::ensure_cert: certificate of {domain} issued");
+  Ok(true)
+}
+
+/// Request or renew the certificates of a rule and apply it again when changed
+pub async fn sync_rule(
+  name: &str,
+  rule: &ResourceProxyRule,
+  state: &SystemStateRef,
+) -> IoResult<()> {
+  if !state.acme_pending.lock()?.insert(name.to_owned()) {
+    return Ok(());
+  }
+  let mut changed = false;
+  for (domain, acme) in list_domains(rule) {
+    match ensure_cert(&domain, &acme, state).await {
+      Err(err) => log::warn!("acme::sync_rule: {name} {domain} {err}"),
+      Ok(issued) => changed |= issued,
+    }
+  }
+  state.acme_pending.lock()?.remove(name);
+  if changed {
+    super::nginx::add_rule(name, rule, state).await?;
+    state.event_emitter.emit_reload().await;
+  }
+  Ok(())
+}
+
+/// Reload nginx to serve the challenges of the rule
+/// and request its missing certificates in background
+pub fn spawn_sync(
+  name: &str,
+  rule: &ResourceProxyRule,
+  state: &SystemStateRef,
+) {
+  let name = name.to_owned();
+  let rule = rule.clone();
+  let state = Arc::clone(state);
+  rt::spawn(async move {
+    if let Err(err) = super::nginx::reload(&state.client).await {
+      log::warn!("acme::spawn_sync: {err}");
+    }
+    if let Err(err) = sync_rule(&name, &rule, &state).await {
+      log::warn!("acme::spawn_sync: {name} {err}");
+    }
+  });
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn b64url() {
+    assert_eq!(b64(&[0xfb, 0xff]), "-_8");
+    assert_eq!(b64(b"nanocl"), "bmFub2Ns");
+  }
+
+  #[test]
+  fn jws() {
+    let key = gen_key().unwrap();
+    let jwk = jwk(&key).unwrap();
+    let value = serde_json::from_str::<serde_json::Value>(&jwk).unwrap();
+    assert_eq!(value["kty"], "EC");
+    assert_eq!(value["x"].as_str().unwrap().len(), 43);
+    let thumbprint = thumbprint(&jwk).unwrap();
+    assert_eq!(thumbprint.len(), 43);
+    assert_eq!(
+      key_authorization("token", &thumbprint),
+      format!("token.{thumbprint}")
+    );
+    let protected = serde_json::json!({ "alg": "ES256", "url": "http://acme" });
+    let body = sign(&key, &protected, "{}").unwrap();
+    let signature = body["signature"].as_str().unwrap();
+    // Decode the r||s signature to verify it, 64 bytes need 2 padding chars
+    let raw = openssl::base64::decode_block(&format!(
+      "{}==",
+      signature.replace('-', "+").replace('_', "/")
+    ))
+    .unwrap();
+    assert_eq!(raw.len(), 64);
+    let sig = EcdsaSig::from_private_components(
+      BigNum::from_slice(&raw[..32]).unwrap(),
+      BigNum::from_slice(&raw[32..]).unwrap(),
+    )
+    .unwrap();
+    let digest = hash(
+      MessageDigest::sha256(),
+      format!(
+        "{}.{}",
+        body["protected"].as_str().unwrap(),
+        body["payload"].as_str().unwrap()
+      )
+      .as_bytes(),
+    )
+    .unwrap();
+    assert!(sig.verify(&digest, &key).unwrap());
+  }
+
+  #[test]
+  fn renewal() {
+    let key = PKey::from_ec_key(gen_key().unwrap()).unwrap();
+    let mut builder = openssl::x509::X509Builder::new().unwrap();
+    builder.set_pubkey(&key).unwrap();
+    builder
+      .set_not_before(Asn1Time::days_from_now(0).unwrap().as_ref())
+      .unwrap();
+    builder
+      .set_not_after(Asn1Time::days_from_now(10).unwrap().as_ref())
+      .unwrap();
+    builder.sign(&key, MessageDigest::sha256()).unwrap();
+    let pem = String::from_utf8(builder.build().to_pem().unwrap()).unwrap();
+    assert!(need_renewal(&pem, 30).unwrap());
+    assert!(!need_renewal(&pem, 5).unwrap());
+  }
+
+  /// Order a certificate from a local Pebble server started with:
+  /// `docker run -p 14000:14000 -e PEBBLE_VA_ALWAYS_VALID=1 ghcr.io/letsencrypt/pebble`
+  /// and the path of its `pebble.minica.pem` in `PEBBLE_CA`
+  #[ntex::test]
+  #[ignore]
+  async fn pebble() {
+    let config = AcmeConfig {
+      directory: std::env::var("PEBBLE_DIRECTORY")
+        .unwrap_or("https://localhost:14000/dir".to_owned()),
+      email: Some("test@nanocl.io".to_owned()),
+      ca: std::env::var("PEBBLE_CA").ok(),
+    };
+    let dir = std::env::temp_dir()
+      .join(format!("ncproxy-acme-{}", std::process::id()))
+      .display()
+      .to_string();
+    let key = load_account_key(&dir).await.unwrap();
+    let mut client = AcmeClient::new(&config, key).await.unwrap();
+    let cert = client
+      .order("test.nanocl.io", &challenge_dir(&dir))
+      .await
+      .unwrap();
+    let x509 = X509::from_pem(cert.certificate.as_bytes()).unwrap();
+    let names = x509.subject_alt_names().unwrap();
+    assert_eq!(names.get(0).unwrap().dnsname(), Some("test.nanocl.io"));
+    assert!(!need_renewal(&cert.certificate, 1).unwrap());
+    std::fs::remove_dir_all(&dir).unwrap();
+  }
+}
@@ -1,3 +1,4 @@
+pub mod acme;
 pub mod nginx;
 pub mod resource;
 pub mod rule;
@@ -87,6 +88,9 @@ pub(crate) mod tests {
     let options = crate::cli::Cli {
       state_dir: format!("{home}/.nanocl_dev/state/proxy"),
       nginx_dir: "/etc/nginx".to_owned(),
+      acme_directory: "https://localhost:14000/dir".to_owned(),
+      acme_email: None,
+      acme_ca: None,
     };
     let system_state = crate::subsystem::init(&options).await.unwrap();
     // Create test server
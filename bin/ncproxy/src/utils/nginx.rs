@@ -1,4 +1,4 @@
-use std::{fs, sync::Arc};
+use std::{collections::HashSet, fs, sync::Arc};
 
 use futures::StreamExt;
 use ntex::web;
@@ -7,7 +7,7 @@ use nanocl_error::io::{IoError, IoResult};
 
 use nanocld_client::{
   bollard_next::exec::{CreateExecOptions, StartExecOptions},
-  stubs::proxy::{LocationTarget, ProxyRule, ResourceProxyRule},
+  stubs::proxy::{LocationTarget, ProxyRule, ProxySsl, ResourceProxyRule},
   NanocldClient,
 };
 
@@ -31,6 +31,7 @@ pub async fn ensure_conf(state: &SystemStateRef) -> IoResult<()> {
       "streams-enabled",
       "log",
       "secrets",
+      "acme/challenges",
     ]
     .iter()
     .map(|name| {
@@ -102,6 +103,9 @@ pub async fn add_rule(
 ) -> IoResult<()> {
   let mut stream_conf = String::new();
   let mut http_conf = String::new();
+  let mut acme_missing = false;
+  // Upstreams owned by the rule, written once in its conf files
+  let mut upstreams = HashSet::new();
   for rule in &rule.rules {
     match rule {
       ProxyRule::Stream(stream_rule) => {
@@ -113,6 +117,7 @@ pub async fn add_rule(
         .await?;
         let upstream_key = match super::rule::gen_stream_upstream_key(
           &stream_rule.target,
+          name,
           state,
         )
         .await
@@ -121,7 +126,14 @@ pub async fn add_rule(
             log::warn!("{err} {:#?}", stream_rule.target);
             continue;
           }
-          Ok(upstream_key) => upstream_key,
+          Ok((upstream_key, conf)) => {
+            if let Some(conf) = conf {
+              if upstreams.insert(upstream_key.clone()) {
+                stream_conf += &conf;
+              }
+            }
+            upstream_key
+          }
         };
         let ssl = match &stream_rule.ssl {
           Some(ssl) => match super::rule::gen_ssl_config(ssl, state).await {
@@ -159,15 +171,39 @@ pub async fn add_rule(
           &state.client,
         )
         .await?;
-        let ssl = match &http_rule.ssl {
-          Some(ssl) => match super::rule::gen_ssl_config(ssl, state).await {
-            Err(err) => {
-              log::warn!("Not ssl found for {name} {ssl:#?} {err}");
-              None
+        let listen_acme =
+          super::rule::get_network_addr(&http_rule.network, 80, &state.client)
+            .await?;
+        let acme = match (&http_rule.ssl, &http_rule.domain) {
+          (Some(ProxySsl::Acme(acme)), Some(_)) => Some(acme),
+          (Some(ProxySsl::Acme(_)), None) => {
+            log::warn!("Acme requires a domain for {name}");
+            continue;
+          }
+          _ => None,
+        };
+        let ssl = match (&http_rule.ssl, &http_rule.domain) {
+          (Some(ProxySsl::Acme(acme)), Some(domain)) => {
+            let secret = ProxySsl::Secret(acme.secret_name(domain));
+            match super::rule::gen_ssl_config(&secret, state).await {
+              Err(err) => {
+                log::info!("Requesting acme certificate for {domain}: {err}");
+                acme_missing = true;
+                None
+              }
+              Ok(ssl) => Some(ssl),
             }
-            Ok(ssl) => Some(ssl),
-          },
-          None => None,
+          }
+          (Some(ssl), _) => {
+            match super::rule::gen_ssl_config(ssl, state).await {
+              Err(err) => {
+                log::warn!("Not ssl found for {name} {ssl:#?} {err}");
+                None
+              }
+              Ok(ssl) => Some(ssl),
+            }
+          }
+          (None, _) => None,
         };
         for location in &http_rule.locations {
           match &location.target {
@@ -175,6 +211,7 @@ pub async fn add_rule(
               let upstream_key = match super::rule::gen_upstream(
                 upstream,
                 &NginxRuleKind::Site,
+                name,
                 state,
               )
               .await
@@ -183,7 +220,14 @@ pub async fn add_rule(
                   log::warn!("{err} {:#?}", upstream);
                   continue;
                 }
-                Ok(upstream_key) => upstream_key,
+                Ok((upstream_key, conf)) => {
+                  if let Some(conf) = conf {
+                    if upstreams.insert(upstream_key.clone()) {
+                      http_conf += &conf;
+                    }
+                  }
+                  upstream_key
+                }
               };
               let ssl = match &upstream.ssl {
                 Some(ssl) => {
@@ -207,10 +251,18 @@ pub async fn add_rule(
                 },
                 redirect: None,
                 upstream_path: upstream.path.clone().unwrap_or("/".to_owned()),
-                version: location.version,
+                // Keepalive connections to the upstream require http 1.1
+                version: location.version.or(upstream.keepalive.map(|_| 1.1)),
                 allowed_ips: location.allowed_ips.clone(),
                 headers: location.headers.clone(),
                 ssl,
+                // Clear the connection header to reuse the connections
+                // unless it's set by the location like for websockets
+                keepalive: upstream.keepalive.map(|_| {
+                  !location.headers.iter().flatten().any(|header| {
+                    header.to_lowercase().starts_with("connection ")
+                  })
+                }),
               };
               locations.push(location);
             }
@@ -231,6 +283,7 @@ pub async fn add_rule(
                 allowed_ips: location.allowed_ips.clone(),
                 headers: location.headers.clone(),
                 ssl: None,
+                keepalive: None,
               };
               locations.push(location);
             }
@@ -245,6 +298,7 @@ pub async fn add_rule(
                 headers: location.headers.clone(),
                 redirect: http.redirect.clone().map(|r| format!("{r}")),
                 ssl: None,
+                keepalive: None,
               };
               locations.push(location);
             }
@@ -259,6 +313,10 @@ pub async fn add_rule(
           "locations": locations,
           "ssl": ssl,
           "hide_upstream": http_rule.ssl.is_some() && ssl.is_none(),
+          "acme": acme.is_some(),
+          "hide_server": acme.is_some() && ssl.is_none(),
+          "listen_acme": listen_acme,
+          "acme_challenge": super::acme::challenge_dir(&state.store.dir),
         }))?;
         http_conf += &data;
       }
@@ -280,6 +338,9 @@ pub async fn add_rule(
     let _ = del_rule(name, state).await;
     return Err(err);
   }
+  if acme_missing {
+    super::acme::spawn_sync(name, rule, state);
+  }
   Ok(())
 }
 
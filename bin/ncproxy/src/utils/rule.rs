@@ -1,3 +1,5 @@
+use std::collections::{BTreeMap, HashMap};
+
 use nanocl_error::io::{FromIo, IoError, IoResult};
 
 use nanocld_client::{
@@ -5,14 +7,17 @@ use nanocld_client::{
     generic::NetworkKind,
     process::Process,
     proxy::{
-      ProxySsl, ProxySslConfig, StreamTarget, UnixTarget, UpstreamTarget,
+      ProxySsl, ProxySslConfig, StreamTarget, UnixTarget, UpstreamPolicy,
+      UpstreamTarget,
     },
+    secret::{SecretInspectQuery, SecretTlsIssued},
   },
   NanocldClient,
 };
 
 use crate::models::{
-  NginxRuleKind, SystemStateRef, UNIX_UPSTREAM_TEMPLATE, UPSTREAM_TEMPLATE,
+  NginxRuleKind, SystemStateRef, UpstreamServerTemplate,
+  UNIX_UPSTREAM_TEMPLATE, UPSTREAM_TEMPLATE,
 };
 
 /// Get public address of host
@@ -55,13 +60,33 @@ fn parse_upstream_target(key: &str) -> IoResult<(String, String, String)> {
   Ok((name, namespace, kind))
 }
 
-pub async fn get_addresses(
+/// Get the replica index of a process, cargo instances get theirs
+/// from the `NANOCL_CARGO_INSTANCE` env and other processes are single
+fn get_replica_index(process: &Process) -> String {
+  process
+    .data
+    .config
+    .as_ref()
+    .and_then(|config| config.env.as_ref())
+    .and_then(|env| {
+      env
+        .iter()
+        .find_map(|env| env.strip_prefix("NANOCL_CARGO_INSTANCE="))
+    })
+    .unwrap_or("0")
+    .to_owned()
+}
+
+/// Get the servers of the processes on a network with their weight
+/// looked up by replica index
+pub async fn get_servers(
   processes: &[Process],
   network: &str,
-) -> IoResult<Vec<String>> {
-  let mut addresses = vec![];
+  weights: &HashMap<String, u32>,
+) -> IoResult<Vec<UpstreamServerTemplate>> {
+  let mut servers = vec![];
   for process in processes {
-    log::debug!("get_addresses from: {}", process.name);
+    log::debug!("get_servers from: {}", process.name);
     if process.name.starts_with("tmp-") {
       continue;
     }
@@ -82,15 +107,142 @@ pub async fn get_addresses(
     if ip_address.is_empty() {
       continue;
     }
-    addresses.push(ip_address);
+    servers.push(UpstreamServerTemplate {
+      address: ip_address,
+      weight: weights.get(&get_replica_index(process)).copied(),
+    });
   }
-  if addresses.is_empty() {
+  if servers.is_empty() {
     return Err(IoError::invalid_data(
       "Process",
       &format!("No address found for {network} are processes running ?"),
     ));
   }
-  Ok(addresses)
+  Ok(servers)
+}
+
+/// Convert a header or a cookie name to the suffix of its nginx variable
+fn gen_variable_name(name: &str, allow_dash: bool) -> IoResult<String> {
+  let is_valid = !name.is_empty()
+    && name.chars().all(|c| {
+      c.is_ascii_alphanumeric() || c == '_' || (allow_dash && c == '-')
+    });
+  if !is_valid {
+    return Err(IoError::invalid_input(
+      "UpstreamPolicy",
+      &format!("Invalid name {name}"),
+    ));
+  }
+  Ok(name.to_lowercase().replace('-', "_"))
+}
+
+/// Generate the load balancing directive of an upstream.
+/// Stream rules hash the client address since `ip_hash` is http only.
+fn gen_policy(
+  policy: &UpstreamPolicy,
+  kind: &NginxRuleKind,
+) -> IoResult<Option<String>> {
+  let is_http = matches!(kind, NginxRuleKind::Site);
+  let directive = match policy {
+    UpstreamPolicy::RoundRobin => return Ok(None),
+    UpstreamPolicy::LeastConn => "least_conn".to_owned(),
+    UpstreamPolicy::IpHash if is_http => "ip_hash".to_owned(),
+    UpstreamPolicy::IpHash => "hash $remote_addr consistent".to_owned(),
+    UpstreamPolicy::Header(name) if is_http => {
+      format!("hash $http_{} consistent", gen_variable_name(name, true)?)
+    }
+    UpstreamPolicy::Cookie(name) if is_http => {
+      format!(
+        "hash $cookie_{} consistent",
+        gen_variable_name(name, false)?
+      )
+    }
+    UpstreamPolicy::Header(_) | UpstreamPolicy::Cookie(_) => {
+      return Err(IoError::invalid_input(
+        "UpstreamPolicy",
+        "Header and Cookie are only supported by http rules",
+      ))
+    }
+    UpstreamPolicy::RandomTwo => "random two least_conn".to_owned(),
+  };
+  Ok(Some(directive))
+}
+
+/// Generate the key of an upstream, targets with balancing options
+/// get an upstream owned by their rule so they don't override the default one
+fn gen_upstream_key(
+  base: &str,
+  owner: &str,
+  target: &UpstreamTarget,
+  kind: &NginxRuleKind,
+) -> String {
+  if target.policy.is_none()
+    && target.weights.is_none()
+    && target.keepalive.is_none()
+  {
+    return base.to_owned();
+  }
+  let weights = target
+    .weights
+    .clone()
+    .map(|weights| weights.into_iter().collect::<BTreeMap<_, _>>());
+  let options = format!(
+    "{owner} {kind} {:?}",
+    (&target.policy, weights, target.keepalive)
+  );
+  let hash = openssl::sha::sha256(options.as_bytes());
+  let suffix = u32::from_be_bytes([hash[0], hash[1], hash[2], hash[3]]);
+  format!("{base}-{suffix:08x}")
+}
+
+/// Render the upstream of a target with its balancing options
+fn gen_upstream_conf(
+  key: &str,
+  target: &UpstreamTarget,
+  servers: &[UpstreamServerTemplate],
+  kind: &NginxRuleKind,
+) -> IoResult<String> {
+  let policy = match &target.policy {
+    Some(policy) => gen_policy(policy, kind)?,
+    None => None,
+  };
+  if target.keepalive.is_some() && matches!(kind, NginxRuleKind::Stream) {
+    return Err(IoError::invalid_input(
+      "UpstreamTarget",
+      "Keepalive is only supported by http rules",
+    ));
+  }
+  if target.keepalive == Some(0)
+    || target
+      .weights
+      .iter()
+      .flatten()
+      .any(|(_, weight)| *weight == 0)
+  {
+    return Err(IoError::invalid_input(
+      "UpstreamTarget",
+      "Keepalive and weights must be greater than 0",
+    ));
+  }
+  if let Some(key) = target
+    .weights
+    .iter()
+    .flatten()
+    .map(|(key, _)| key)
+    .find(|key| key.parse::<usize>().is_err())
+  {
+    return Err(IoError::invalid_input(
+      "UpstreamTarget",
+      &format!("Invalid weight key {key} expected a replica index"),
+    ));
+  }
+  UPSTREAM_TEMPLATE.compile(&liquid::object!({
+    "key": key,
+    "port": target.port,
+    "policy": policy,
+    "servers": servers,
+    "keepalive": target.keepalive,
+  }))
 }
 
 pub async fn get_network_addr(
@@ -119,12 +271,28 @@ pub async fn gen_ssl_config(
 ) -> IoResult<ProxySslConfig> {
   match ssl {
     ProxySsl::Config(ssl_config) => Ok(ssl_config.clone()),
+    ProxySsl::Acme(_) => Err(IoError::invalid_input(
+      "Acme",
+      "only supported by http rules with a domain",
+    )),
     ProxySsl::Secret(secret) => {
-      let secret = state.client.inspect_secret(secret).await?;
-      let mut ssl_config =
-        serde_json::from_value::<ProxySslConfig>(secret.data).map_err(
+      let query = SecretInspectQuery { reveal: Some(true) };
+      let secret = state.client.inspect_secret(secret, Some(&query)).await?;
+      let mut ssl_config = match secret.kind.as_str() {
+        "nanocl.io/tls-issued" => {
+          serde_json::from_value::<SecretTlsIssued>(secret.data)
+            .map_err(|err| {
+              err.map_err_context(|| "Unable to deserialize SecretTlsIssued")
+            })?
+            .to_ssl_config()
+            .ok_or_else(|| {
+              IoError::not_found("Issued certificate", &secret.name)
+            })?
+        }
+        _ => serde_json::from_value::<ProxySslConfig>(secret.data).map_err(
           |err| err.map_err_context(|| "Unable to deserialize ProxySslConfig"),
-        )?;
+        )?,
+      };
       let secret_path = format!("{}/secrets/{}", state.store.dir, secret.name);
       let cert_path = format!("{secret_path}.cert");
       tokio::fs::write(&cert_path, ssl_config.certificate.clone()).await?;
@@ -147,15 +315,21 @@ pub async fn gen_ssl_config(
   }
 }
 
+/// Generate the upstream of a target and return its key.
+/// Upstreams with balancing options are owned by the rule `owner`,
+/// their conf is returned to be written in the rule file so it's removed
+/// with the rule or when its options change.
 pub async fn gen_upstream(
   target: &UpstreamTarget,
   kind: &NginxRuleKind,
+  owner: &str,
   state: &SystemStateRef,
-) -> IoResult<String> {
+) -> IoResult<(String, Option<String>)> {
   let (target_name, target_namespace, target_kind) =
     parse_upstream_target(&target.key)?;
   let port = target.port;
-  let (key, content) = match target_kind.as_str() {
+  let weights = target.weights.clone().unwrap_or_default();
+  let (key, servers) = match target_kind.as_str() {
     "c" => {
       let cargo = state
         .client
@@ -166,14 +340,10 @@ pub async fn gen_upstream(
             format!("Unable to inspect cargo {target_name}")
           })
         })?;
-      let addresses = get_addresses(&cargo.instances, "nanoclbr0").await?;
+      let servers =
+        get_servers(&cargo.instances, "nanoclbr0", &weights).await?;
       let key = format!("{}-{}-cargo", cargo.spec.cargo_key, port);
-      let data = UPSTREAM_TEMPLATE.compile(&liquid::object!({
-        "key": key,
-        "port": port,
-        "addresses": addresses,
-      }))?;
-      (key, data)
+      (key, servers)
     }
     "v" => {
       let vm = state
@@ -183,14 +353,9 @@ pub async fn gen_upstream(
         .map_err(|err| {
           err.map_err_context(|| format!("Unable to inspect vm {target_name}"))
         })?;
-      let addresses = get_addresses(&vm.instances, "nanoclbr0").await?;
+      let servers = get_servers(&vm.instances, "nanoclbr0", &weights).await?;
       let key = format!("{}-{}-vm", vm.spec.vm_key, port);
-      let data = UPSTREAM_TEMPLATE.compile(&liquid::object!({
-        "key": key,
-        "port": port,
-        "addresses": addresses,
-      }))?;
-      (key, data)
+      (key, servers)
     }
     _ => {
       return Err(IoError::invalid_data(
@@ -199,8 +364,13 @@ pub async fn gen_upstream(
       ))
     }
   };
+  let upstream_key = gen_upstream_key(&key, owner, target, kind);
+  let content = gen_upstream_conf(&upstream_key, target, &servers, kind)?;
+  if upstream_key != key {
+    return Ok((upstream_key, Some(content)));
+  }
   state.store.write_conf_file(&key, &content, kind).await?;
-  Ok(key)
+  Ok((key, None))
 }
 
 pub async fn gen_unix_target_key(
@@ -222,17 +392,115 @@ pub async fn gen_unix_target_key(
 
 pub async fn gen_stream_upstream_key(
   target: &StreamTarget,
+  owner: &str,
   state: &SystemStateRef,
-) -> IoResult<String> {
+) -> IoResult<(String, Option<String>)> {
   match target {
     StreamTarget::Upstream(upstream) => {
-      gen_upstream(upstream, &NginxRuleKind::Stream, state).await
+      gen_upstream(upstream, &NginxRuleKind::Stream, owner, state).await
     }
     StreamTarget::Unix(unix) => {
-      gen_unix_target_key(unix, &NginxRuleKind::Stream, state).await
+      let key =
+        gen_unix_target_key(unix, &NginxRuleKind::Stream, state).await?;
+      Ok((key, None))
     }
     StreamTarget::Uri(_) => {
       Err(IoError::invalid_input("StreamTarget", "uri not supported"))
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn gen_target(policy: Option<UpstreamPolicy>) -> UpstreamTarget {
+    UpstreamTarget {
+      key: "api.global.c".to_owned(),
+      port: 9000,
+      path: None,
+      disable_logging: None,
+      ssl: None,
+      policy,
+      weights: None,
+      keepalive: None,
+    }
+  }
+
+  #[test]
+  fn upstream() {
+    let servers = vec![
+      UpstreamServerTemplate {
+        address: "10.0.0.2".to_owned(),
+        weight: Some(3),
+      },
+      UpstreamServerTemplate {
+        address: "10.0.0.3".to_owned(),
+        weight: None,
+      },
+    ];
+    let target = gen_target(None);
+    assert_eq!(
+      gen_upstream_key("api", "rule", &target, &NginxRuleKind::Site),
+      "api"
+    );
+    let conf =
+      gen_upstream_conf("api", &target, &servers, &NginxRuleKind::Site)
+        .unwrap();
+    assert!(conf.contains("server 10.0.0.2:9000 weight=3;"));
+    assert!(conf.contains("server 10.0.0.3:9000;"));
+    assert!(!conf.contains("keepalive"));
+    let target = UpstreamTarget {
+      keepalive: Some(16),
+      ..gen_target(Some(UpstreamPolicy::Header("X-User-Id".to_owned())))
+    };
+    let key = gen_upstream_key("api", "rule", &target, &NginxRuleKind::Site);
+    assert_ne!(key, "api");
+    assert_eq!(
+      key,
+      gen_upstream_key("api", "rule", &target.clone(), &NginxRuleKind::Site)
+    );
+    assert_ne!(
+      key,
+      gen_upstream_key("api", "other", &target, &NginxRuleKind::Site)
+    );
+    let conf =
+      gen_upstream_conf(&key, &target, &servers, &NginxRuleKind::Site).unwrap();
+    assert!(conf.contains("hash $http_x_user_id consistent;"));
+    assert!(conf.contains("keepalive 16;"));
+    assert!(
+      gen_upstream_conf(&key, &target, &servers, &NginxRuleKind::Stream)
+        .is_err()
+    );
+    let target = gen_target(Some(UpstreamPolicy::IpHash));
+    let conf =
+      gen_upstream_conf("api", &target, &servers, &NginxRuleKind::Stream)
+        .unwrap();
+    assert!(conf.contains("hash $remote_addr consistent;"));
+    let target = gen_target(Some(UpstreamPolicy::Cookie("id;".to_owned())));
+    assert!(
+      gen_upstream_conf("api", &target, &servers, &NginxRuleKind::Site)
+        .is_err()
+    );
+    let target = gen_target(Some(UpstreamPolicy::RandomTwo));
+    let conf =
+      gen_upstream_conf("api", &target, &servers, &NginxRuleKind::Site)
+        .unwrap();
+    assert!(conf.contains("random two least_conn;"));
+    let target = UpstreamTarget {
+      weights: Some(HashMap::from([("api-x1y2z3.global.c".to_owned(), 3)])),
+      ..gen_target(None)
+    };
+    assert!(
+      gen_upstream_conf("api", &target, &servers, &NginxRuleKind::Site)
+        .is_err()
+    );
+    let target = UpstreamTarget {
+      weights: Some(HashMap::from([("1".to_owned(), 3)])),
+      ..gen_target(None)
+    };
+    assert!(
+      gen_upstream_conf("api", &target, &servers, &NginxRuleKind::Site).is_ok()
+    );
+  }
+}
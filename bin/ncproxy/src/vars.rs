@@ -3,3 +3,11 @@ pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 pub const COMMIT_ID: &str = env!("GIT_HASH");
 pub const CHANNEL: &str = env!("CHANNEL");
 pub const RULE_KEY: &str = "ncproxy.io/rule";
+/// Kind of the secrets storing the certificates issued with ACME
+pub const ACME_SECRET_KIND: &str = "nanocl.io/tls";
+/// Renew the certificates expiring in less than this number of days
+pub const ACME_RENEW_BEFORE: u32 = 30;
+/// Interval in seconds between two checks of the certificates to renew
+pub const ACME_RENEW_INTERVAL: u64 = 43200;
+/// Number of times an authorization or an order is polled before giving up
+pub const ACME_POLL_ATTEMPTS: usize = 30;
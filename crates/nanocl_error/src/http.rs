@@ -7,6 +7,8 @@ use ntex::web;
 pub struct HttpError {
   pub msg: String,
   pub status: http::StatusCode,
+  /// Structured details returned with the message ex: validation errors
+  pub details: Option<serde_json::Value>,
 }
 
 #[cfg(feature = "backtrace")]
@@ -15,11 +17,16 @@ pub struct HttpError {
   pub backtrace: std::backtrace::Backtrace,
   pub msg: String,
   pub status: http::StatusCode,
+  /// Structured details returned with the message ex: validation errors
+  pub details: Option<serde_json::Value>,
 }
 
 impl Clone for HttpError {
   fn clone(&self) -> Self {
-    Self::new(self.status, self.msg.clone())
+    Self {
+      details: self.details.clone(),
+      ..Self::new(self.status, self.msg.clone())
+    }
   }
 }
 
@@ -35,6 +42,7 @@ impl HttpError {
     Self {
       status,
       msg: msg.to_string(),
+      details: None,
     }
   }
 
@@ -47,9 +55,16 @@ impl HttpError {
       backtrace: std::backtrace::Backtrace::capture(),
       status,
       msg: msg.to_string(),
+      details: None,
     }
   }
 
+  /// Set the structured details returned with the message
+  pub fn with_details(mut self, details: serde_json::Value) -> Self {
+    self.details = Some(details);
+    self
+  }
+
   /// Create a new HttpError with status BadRequest - 400
   pub fn bad_request<T>(msg: T) -> Self
   where
@@ -118,7 +133,13 @@ impl std::error::Error for HttpError {}
 /// Helper function to convert an HttpError into a ntex::web::HttpResponse
 impl web::WebResponseError for HttpError {
   fn error_response(&self, _: &web::HttpRequest) -> web::HttpResponse {
-    let err_json = serde_json::json!({ "msg": self.msg });
+    let err_json = match &self.details {
+      None => serde_json::json!({ "msg": self.msg }),
+      Some(details) => serde_json::json!({
+        "msg": self.msg,
+        "details": details,
+      }),
+    };
     web::HttpResponse::build(self.status).json(&err_json)
   }
 }
@@ -179,7 +200,10 @@ impl crate::io::FromIo<HttpError> for HttpError {
   where
     C: ToString + std::fmt::Display,
   {
-    HttpError::new(self.status, format!("{}: {}", context(), self.msg))
+    HttpError {
+      details: self.details,
+      ..HttpError::new(self.status, format!("{}: {}", context(), self.msg))
+    }
   }
 }
 
@@ -5,6 +5,7 @@ use bollard_next::container::KillContainerOptions;
 
 use crate::{
   cargo_spec::CargoSpecPartial,
+  generic::DeletePropagation,
   process::Process,
   system::{EventActor, EventActorKind, ObjPsStatus},
 };
@@ -142,4 +143,10 @@ pub struct CargoDeleteQuery {
   pub namespace: Option<String>,
   /// Delete cargo even if it is running
   pub force: Option<bool>,
+  /// What happens to the resources owned by the cargo, default to Background
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub propagation: Option<DeletePropagation>,
 }
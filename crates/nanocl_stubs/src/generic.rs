@@ -23,6 +23,61 @@ impl GenericNspQuery {
   }
 }
 
+/// What happens to the resources owned by an object when it's deleted
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum DeletePropagation {
+  /// The owned resources are deleted before the owner
+  Foreground,
+  /// The owner is deleted then the owned resources in the background
+  #[default]
+  Background,
+  /// The owned resources are kept without the reference to the owner
+  Orphan,
+}
+
+impl std::str::FromStr for DeletePropagation {
+  type Err = std::io::Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.to_ascii_lowercase().as_str() {
+      "foreground" => Ok(Self::Foreground),
+      "background" => Ok(Self::Background),
+      "orphan" => Ok(Self::Orphan),
+      _ => Err(std::io::Error::new(
+        std::io::ErrorKind::InvalidInput,
+        format!("Invalid propagation {s}"),
+      )),
+    }
+  }
+}
+
+impl std::fmt::Display for DeletePropagation {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::Foreground => write!(f, "Foreground"),
+      Self::Background => write!(f, "Background"),
+      Self::Orphan => write!(f, "Orphan"),
+    }
+  }
+}
+
+/// Generic query used when deleting an object other objects depend on
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GenericDeleteQuery {
+  /// Delete the object even if other objects depend on it,
+  /// the dependents it owns are deleted with it
+  pub force: Option<bool>,
+  /// What happens to the resources owned by the object, default to Background
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub propagation: Option<DeletePropagation>,
+}
+
 /// Generic count response
 #[derive(Debug)]
 #[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
@@ -135,6 +190,10 @@ pub struct GenericFilter {
 pub struct GenericListQuery {
   /// A json as string as GenericFilter
   pub filter: Option<String>,
+  /// Stream the items followed by their changes
+  pub watch: Option<bool>,
+  /// Cursor of a previous watch to resume from
+  pub cursor: Option<String>,
 }
 
 impl TryFrom<GenericFilter> for GenericListQuery {
@@ -143,6 +202,7 @@ impl TryFrom<GenericFilter> for GenericListQuery {
   fn try_from(filter: GenericFilter) -> Result<Self, Self::Error> {
     Ok(Self {
       filter: Some(serde_json::to_string(&filter)?),
+      ..Default::default()
     })
   }
 }
@@ -179,6 +239,10 @@ pub struct GenericListQueryNsp {
   /// A json as string as GenericFilter
   pub filter: Option<String>,
   pub namespace: Option<String>,
+  /// Stream the items followed by their changes
+  pub watch: Option<bool>,
+  /// Cursor of a previous watch to resume from
+  pub cursor: Option<String>,
 }
 
 #[derive(Default, Debug, Clone)]
@@ -215,6 +279,7 @@ impl TryFrom<GenericFilterNsp> for GenericListQueryNsp {
     Ok(Self {
       filter: formatted_filter,
       namespace: filter.namespace,
+      ..Default::default()
     })
   }
 }
@@ -274,6 +339,41 @@ impl GenericFilter {
   }
 }
 
+/// Type of change sent by the watch mode of list operations
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "UPPERCASE"))]
+pub enum WatchEventType {
+  Added,
+  Modified,
+  Deleted,
+}
+
+/// Change of an item sent by the watch mode of list operations
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "PascalCase"))]
+pub struct WatchEvent<T> {
+  /// Type of change
+  pub r#type: WatchEventType,
+  /// Key of the item
+  pub key: String,
+  /// Cursor to resume the watch after this change
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub cursor: Option<String>,
+  /// The item as returned by the list operation.
+  /// Deletions replayed from a cursor only have the key of the item.
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub object: Option<T>,
+}
+
 /// Policy for pulling images related to process objects (job, cargo, vm)
 #[derive(Default, Debug, Clone, PartialEq)]
 #[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
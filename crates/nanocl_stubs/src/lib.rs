@@ -17,4 +17,5 @@ pub mod secret;
 pub mod statefile;
 pub mod vm;
 pub mod vm_image;
+pub mod vm_snapshot;
 pub mod vm_spec;
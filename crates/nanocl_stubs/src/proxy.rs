@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
@@ -49,6 +51,50 @@ pub struct ProxySslConfig {
   pub dhparam: Option<String>,
 }
 
+/// Options to request a certificate for the domain of an http rule
+/// from an ACME certificate authority using the HTTP-01 challenge
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub struct ProxyAcmeConfig {
+  /// Name of the `nanocl.io/tls` secret storing the certificate
+  /// (default to `acme.{domain}`)
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub secret: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub struct ProxySslAcme {
+  /// The ACME options
+  pub acme: ProxyAcmeConfig,
+}
+
+impl ProxySslAcme {
+  /// Name of the secret storing the certificate issued for the `domain`
+  pub fn secret_name(&self, domain: &str) -> String {
+    self
+      .acme
+      .secret
+      .clone()
+      .unwrap_or_else(|| format!("acme.{domain}"))
+  }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 #[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
@@ -57,6 +103,35 @@ pub struct ProxySslConfig {
 pub enum ProxySsl {
   Config(ProxySslConfig),
   Secret(String),
+  /// Certificate requested and renewed by ncproxy using ACME
+  Acme(ProxySslAcme),
+}
+
+/// Load balancing policy of the servers of an upstream target
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub enum UpstreamPolicy {
+  /// Send the requests to the servers in turn
+  RoundRobin,
+  /// Send the requests to the server with the least active connections
+  LeastConn,
+  /// Send the requests of a client address to the same server
+  IpHash,
+  /// Send the requests with the same value of a header to the same server
+  /// (http only)
+  Header(String),
+  /// Send the requests with the same value of a cookie to the same server
+  /// (http only)
+  Cookie(String),
+  /// Pick two random servers and send the requests to the one
+  /// with the least active connections
+  RandomTwo,
 }
 
 /// Config for targeting a cargo or a vm
@@ -91,6 +166,26 @@ pub struct UpstreamTarget {
     serde(skip_serializing_if = "Option::is_none")
   )]
   pub ssl: Option<ProxySsl>,
+  /// Load balancing policy of the servers (default to round robin)
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub policy: Option<UpstreamPolicy>,
+  /// Weight of the servers by replica index (`0`, `1`, ...),
+  /// the others have a weight of 1
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub weights: Option<HashMap<String, u32>>,
+  /// Number of idle connections to the servers kept open by each worker
+  /// (http only)
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub keepalive: Option<usize>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -144,6 +239,7 @@ pub struct HttpTarget {
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[cfg_attr(feature = "serde", serde(untagged, rename_all = "PascalCase"))]
+#[allow(clippy::large_enum_variant)]
 pub enum LocationTarget {
   /// Target an existing cargo
   Upstream(UpstreamTarget),
@@ -183,6 +279,7 @@ pub struct UnixTarget {
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[cfg_attr(feature = "serde", serde(untagged, rename_all = "PascalCase"))]
+#[allow(clippy::large_enum_variant)]
 pub enum StreamTarget {
   /// Target an existing cargo
   Upstream(UpstreamTarget),
@@ -30,6 +30,19 @@ pub struct ResourcePartial {
     serde(skip_serializing_if = "Option::is_none")
   )]
   pub metadata: Option<serde_json::Value>,
+  /// Objects owning the resource, it's deleted with them
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub owners: Option<Vec<ResourceOwner>>,
+  /// Finalizers blocking the deletion of the resource
+  /// until their controller remove them
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub finalizers: Option<Vec<String>>,
 }
 
 /// Payload used to update a resource
@@ -52,6 +65,18 @@ pub struct ResourceUpdate {
     serde(skip_serializing_if = "Option::is_none")
   )]
   pub metadata: Option<serde_json::Value>,
+  /// Objects owning the resource, unchanged when not set
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub owners: Option<Vec<ResourceOwner>>,
+  /// Finalizers of the resource, unchanged when not set
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub finalizers: Option<Vec<String>>,
 }
 
 /// Convert a ResourcePartial into a Resource
@@ -60,6 +85,8 @@ impl From<ResourcePartial> for ResourceUpdate {
     Self {
       data: resource.data,
       metadata: resource.metadata,
+      owners: resource.owners,
+      finalizers: resource.finalizers,
     }
   }
 }
@@ -110,6 +137,198 @@ pub struct Resource {
   pub created_at: chrono::NaiveDateTime,
   /// Specification of the ressource
   pub spec: ResourceSpec,
+  /// Status reported by nanocld and the controller of the kind
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub status: Option<ResourceStatus>,
+  /// Objects owning the resource
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Vec::is_empty")
+  )]
+  pub owners: Vec<ResourceOwner>,
+  /// Finalizers blocking the deletion of the resource
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Vec::is_empty")
+  )]
+  pub finalizers: Vec<String>,
+}
+
+/// Reference to an object owning a resource
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub struct ResourceOwner {
+  /// Kind of the owner (Cargo, Vm, Job, Resource)
+  pub kind: EventActorKind,
+  /// Key of the owner
+  pub key: String,
+}
+
+/// Payload used by a controller to add or remove finalizers of a resource.
+/// Once the last finalizer of a deleted resource is removed it's deleted.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub struct ResourceFinalizersPatch {
+  /// Finalizers to add
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Vec::is_empty")
+  )]
+  pub add: Vec<String>,
+  /// Finalizers to remove
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Vec::is_empty")
+  )]
+  pub remove: Vec<String>,
+}
+
+/// Phase of a resource in its controller
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ResourcePhase {
+  /// The controller haven't applied the resource yet
+  #[default]
+  Pending,
+  /// The controller applied the resource
+  Ready,
+  /// The controller refused or failed to apply the resource
+  Failed,
+  /// The resource is deleted once the controller removed it
+  Deleting,
+}
+
+impl std::fmt::Display for ResourcePhase {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      ResourcePhase::Pending => write!(f, "Pending"),
+      ResourcePhase::Ready => write!(f, "Ready"),
+      ResourcePhase::Failed => write!(f, "Failed"),
+      ResourcePhase::Deleting => write!(f, "Deleting"),
+    }
+  }
+}
+
+/// A condition observed by the controller of a resource
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub struct ResourceCondition {
+  /// Type of the condition ex: `Reachable`, `CertificateIssued`
+  pub r#type: String,
+  /// Whether the condition is met
+  pub status: bool,
+  /// Short machine readable reason of the last transition
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub reason: Option<String>,
+  /// Human readable message of the last transition
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub message: Option<String>,
+}
+
+/// Status of a resource
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "PascalCase"))]
+pub struct ResourceStatus {
+  /// Phase of the resource
+  pub phase: ResourcePhase,
+  /// Conditions observed by the controller
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Vec::is_empty")
+  )]
+  pub conditions: Vec<ResourceCondition>,
+  /// Key of the last spec applied by the controller
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub observed_version: Option<uuid::Uuid>,
+  /// Message of the last transition
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub message: Option<String>,
+  /// Failed attempts to reach the controller
+  #[cfg_attr(feature = "serde", serde(default))]
+  pub retries: u32,
+  /// When the controller will be called again
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub next_retry_at: Option<chrono::NaiveDateTime>,
+  /// When the status have been updated
+  pub updated_at: chrono::NaiveDateTime,
+}
+
+/// Payload used by a controller to update the status of a resource.
+/// Conditions replace the existing ones of the same type.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub struct ResourceStatusPartial {
+  /// Phase of the resource
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub phase: Option<ResourcePhase>,
+  /// Conditions observed by the controller
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub conditions: Option<Vec<ResourceCondition>>,
+  /// Key of the spec applied by the controller
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub observed_version: Option<uuid::Uuid>,
+  /// Message of the transition
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub message: Option<String>,
 }
 
 /// Convert a Resource into an EventActor
@@ -136,6 +355,9 @@ impl From<Resource> for ResourcePartial {
       kind: resource.kind,
       data: resource.spec.data,
       metadata: resource.spec.metadata,
+      owners: (!resource.owners.is_empty()).then_some(resource.owners),
+      finalizers: (!resource.finalizers.is_empty())
+        .then_some(resource.finalizers),
     }
   }
 }
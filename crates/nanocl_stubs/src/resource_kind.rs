@@ -1,9 +1,13 @@
+use std::collections::HashMap;
+
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
 #[cfg(feature = "utoipa")]
 use super::generic::Any;
 
+use super::system::EventKind;
+
 /// Specification of a resource kind.
 /// Depending on the spec it will validate a JSONSchema or call a service.
 #[derive(Clone, Debug)]
@@ -28,6 +32,315 @@ pub struct ResourceKindSpec {
     serde(skip_serializing_if = "Option::is_none")
   )]
   pub url: Option<String>,
+  /// How resources of other versions are converted to this version
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub conversion: Option<ResourceKindConversion>,
+  /// How the controller at the url is called
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub controller: Option<ResourceKindController>,
+  /// Fields of the resources that cannot be changed once created,
+  /// fields are JSON pointers ex: `/Spec/Username`
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub immutable: Option<Vec<String>>,
+  /// WebAssembly module executed by the daemon as controller instead of an url
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub wasm: Option<ResourceKindWasm>,
+}
+
+/// An error of the data of a resource validated against its kind,
+/// returned in the details of the error response
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "PascalCase"))]
+pub struct ResourceKindValidationError {
+  /// JSON pointer of the invalid field, empty for the whole data
+  pub path: String,
+  /// Why the field is invalid
+  pub message: String,
+}
+
+/// Options to call the controller of a resource kind.
+/// The url can target a unix socket with `unix:///run/ctrl.sock`
+/// or an https endpoint authenticated with a client certificate.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub struct ResourceKindController {
+  /// Secret of kind `nanocl.io/tls` with the client certificate and key,
+  /// its certificate client is the CA used to verify an https controller
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub tls: Option<String>,
+  /// Seconds to wait for the controller to respond default to 50
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub timeout: Option<u32>,
+  /// Path called when the kind is registered to ensure the controller is healthy ex: `/health`
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub health_path: Option<String>,
+}
+
+/// WebAssembly module controlling the resources of a kind.
+/// The module is loaded from a path on the host or from a base64 blob,
+/// it must export its `memory` and an `alloc(len: i32) -> i32` function.
+/// The hooks `validate`, `mutate`, `apply` and `delete` are optional exports
+/// called with the json of a `ResourceKindWasmInput` as `(ptr: i32, len: i32)`
+/// returning the json of a `ResourceKindWasmOutput` as `(ptr << 32) | len`.
+/// The module can import from the `nanocl` namespace
+/// `read(ptr: i32, len: i32) -> i64` called with the json of an `EventActor`
+/// returning the json of the object or 0 when it doesn't exist,
+/// and `emit(ptr: i32, len: i32)` called with a `ResourceKindWasmEvent`.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub struct ResourceKindWasm {
+  /// Path of the module on the host
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub path: Option<String>,
+  /// Module encoded in base64
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub blob: Option<String>,
+  /// Instructions a hook can execute before being stopped default to 100000000
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub fuel: Option<u64>,
+  /// Memory in MiB a hook can use default to 64
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub memory: Option<u32>,
+}
+
+/// Resource given to the hooks of a WebAssembly controller
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "PascalCase"))]
+pub struct ResourceKindWasmInput {
+  /// Name of the resource
+  pub name: String,
+  /// Version of the resource kind
+  pub version: String,
+  /// Data of the resource
+  #[cfg_attr(feature = "utoipa", schema(value_type = HashMap<String, Any>))]
+  pub data: serde_json::Value,
+}
+
+/// Result of a hook of a WebAssembly controller.
+/// The data replace the data of the resource for `mutate` and `apply`,
+/// an error refuse the resource.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "PascalCase"))]
+pub struct ResourceKindWasmOutput {
+  /// New data of the resource
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  #[cfg_attr(feature = "utoipa", schema(value_type = HashMap<String, Any>))]
+  pub data: Option<serde_json::Value>,
+  /// Why the resource is refused
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub error: Option<String>,
+}
+
+/// Event emitted by a WebAssembly controller about the resource of a hook
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "PascalCase"))]
+pub struct ResourceKindWasmEvent {
+  /// Kind of event (error, normal, warning)
+  pub kind: EventKind,
+  /// What action was taken on the resource
+  pub action: String,
+  /// Why the action was taken
+  pub reason: String,
+  /// Human-readable description of the action
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub note: Option<String>,
+}
+
+/// Move a field of a resource when converting it to another version.
+/// Fields are JSON pointers ex: `/Spec/Username`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub struct ResourceKindMapping {
+  /// Field of the resource to convert, only the default is used when not set
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub from: Option<String>,
+  /// Field of the converted resource, the field is removed when not set
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub to: Option<String>,
+  /// Value to set when the field is missing
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  #[cfg_attr(feature = "utoipa", schema(value_type = Any))]
+  pub default: Option<serde_json::Value>,
+}
+
+/// Conversion of the resources of other versions to a version of a kind
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ResourceKindConversion {
+  /// Call `POST {Url}/convert` on the controller of the version
+  /// with a `ResourceKindConvert` and use the returned data
+  Controller,
+  /// Apply the mappings of the version of the resource in order
+  /// to a copy of its data, keyed by the version converted from
+  Mappings(HashMap<String, Vec<ResourceKindMapping>>),
+}
+
+/// Payload sent to a controller to convert a resource to another version
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "PascalCase"))]
+pub struct ResourceKindConvert {
+  /// Name of the resource
+  pub name: String,
+  /// Version of the resource data
+  pub from_version: String,
+  /// Version to convert the resource data to
+  pub to_version: String,
+  /// Data of the resource
+  #[cfg_attr(feature = "utoipa", schema(value_type = HashMap<String, Any>))]
+  pub data: serde_json::Value,
+}
+
+/// Resources of a kind stored with a version
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "PascalCase"))]
+pub struct ResourceKindVersionResources {
+  /// Version of the kind
+  pub version: String,
+  /// Names of the resources
+  pub resources: Vec<String>,
+}
+
+/// Payload to migrate the resources of a kind to a version
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub struct ResourceKindMigrate {
+  /// Version to migrate the resources to
+  pub version: String,
+  /// Only migrate the resources of this version
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub from: Option<String>,
+  /// Convert and validate the resources without updating them
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub dry_run: Option<bool>,
+}
+
+/// A resource converted to another version
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "PascalCase"))]
+pub struct ResourceKindMigrated {
+  /// Name of the resource
+  pub name: String,
+  /// Version the resource was converted from
+  pub from: String,
+  /// Converted data of the resource
+  #[cfg_attr(feature = "utoipa", schema(value_type = HashMap<String, Any>))]
+  pub data: serde_json::Value,
+}
+
+/// Result of the migration of the resources of a kind
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "PascalCase"))]
+pub struct ResourceKindMigration {
+  /// Version the resources have been migrated to
+  pub version: String,
+  /// Whether the resources have been left unchanged
+  pub dry_run: bool,
+  /// Resources converted to the version
+  pub resources: Vec<ResourceKindMigrated>,
 }
 
 /// This structure is a partial representation of a resource kind.
@@ -4,7 +4,10 @@ use serde::{Deserialize, Serialize};
 #[cfg(feature = "utoipa")]
 use super::generic::Any;
 
-use crate::system::{EventActor, EventActorKind};
+use crate::{
+  proxy::ProxySslConfig,
+  system::{EventActor, EventActorKind},
+};
 
 /// A partial secret object. This is used to create a secret.
 /// A secret is a key/value pair that can be used by the user to store
@@ -64,7 +67,11 @@ pub struct Secret {
   )]
   #[cfg_attr(feature = "utoipa", schema(value_type = HashMap<String, Any>))]
   pub metadata: Option<serde_json::Value>,
-  /// The secret data
+  /// The secret data, omitted when listing and masked unless revealed
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "serde_json::Value::is_null")
+  )]
   #[cfg_attr(feature = "utoipa", schema(value_type = HashMap<String, Any>))]
   pub data: serde_json::Value,
 }
@@ -95,6 +102,298 @@ impl From<Secret> for EventActor {
   }
 }
 
+/// A file of a `nanocl.io/file` secret.
+/// The data of the secret is a list of files written in the secrets directory
+/// mounted at `/opt/nanocl.io/secrets/{secret}/{target}` inside the containers.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub struct SecretFile {
+  /// Name of the file inside the directory of the secret
+  pub target: String,
+  /// Content of the file encoded in base64
+  pub data: String,
+  /// Unix permissions of the file at most 0o777 default to 0o400
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub mode: Option<u32>,
+  /// Owner uid of the file default to root,
+  /// set it when the container doesn't run as root
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub uid: Option<u32>,
+  /// Owner gid of the file default to root
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub gid: Option<u32>,
+}
+
+/// Data of a `nanocl.io/tls-issued` secret.
+/// The certificate is signed by the internal CA of nanocld
+/// and renewed before its expiry, the CA is set as `CertificateClient`.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub struct SecretTlsIssued {
+  /// Subject alternative names, dns names or ip addresses
+  pub sans: Vec<String>,
+  /// Lifetime of the certificate in days default to 90
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub lifetime: Option<u32>,
+  /// Enable or disable client verification
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub verify_client: Option<bool>,
+  /// Issued certificate
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub certificate: Option<String>,
+  /// Key of the issued certificate
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub certificate_key: Option<String>,
+  /// Certificate of the internal CA
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub certificate_client: Option<String>,
+  /// Expiry date of the issued certificate
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub not_after: Option<chrono::NaiveDateTime>,
+}
+
+impl SecretTlsIssued {
+  /// Get the ssl config of the issued certificate if any
+  pub fn to_ssl_config(&self) -> Option<ProxySslConfig> {
+    Some(ProxySslConfig {
+      certificate: self.certificate.clone()?,
+      certificate_key: self.certificate_key.clone()?,
+      certificate_client: self.certificate_client.clone(),
+      verify_client: self.verify_client,
+      dhparam: None,
+    })
+  }
+}
+
+/// Settings of a HashiCorp Vault KV v2 backend
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub struct SecretVault {
+  /// Address of the Vault server ex: `http://vault.internal:8200`
+  pub address: String,
+  /// Mount path of the KV v2 engine default to `secret`
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub mount: Option<String>,
+  /// Name of the secret storing the Vault token under its `Token` key
+  pub token_secret: String,
+}
+
+/// Backend resolving the data of a `nanocl.io/external` secret
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SecretBackend {
+  /// A json document sealed with the master key of the node
+  /// and stored in `{state_dir}/secrets/external`
+  File,
+  /// A HashiCorp Vault KV v2 engine
+  Vault(SecretVault),
+}
+
+/// Data of a `nanocl.io/external` secret.
+/// The secret only hold a reference, the value is read from the backend
+/// when a cargo or a job is created and converted to the resolved kind.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub struct SecretExternal {
+  /// Kind of the resolved secret
+  /// `nanocl.io/env`, `nanocl.io/tls` or `nanocl.io/file`
+  pub kind: String,
+  /// Backend storing the value
+  pub backend: SecretBackend,
+  /// Path of the value inside the backend
+  pub path: String,
+  /// Only keep this key of the value
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub key: Option<String>,
+  /// Number of seconds a resolved value is reused before being read again
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub refresh: Option<u64>,
+}
+
+/// Payload to seal a value for the file backend of external secrets
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub struct SecretSeal {
+  /// Path of the file relative to `{state_dir}/secrets/external`
+  pub path: String,
+  /// The value to seal
+  #[cfg_attr(feature = "utoipa", schema(value_type = HashMap<String, Any>))]
+  pub data: serde_json::Value,
+}
+
+/// An object that use a secret
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "PascalCase"))]
+pub struct SecretConsumer {
+  /// Kind of the object (Cargo, Job, Resource)
+  pub kind: EventActorKind,
+  /// Key of the object
+  pub key: String,
+}
+
+/// Detailed information about a secret
+/// with the list of objects that consume it
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "PascalCase"))]
+pub struct SecretInspect {
+  /// The name of the secret
+  pub name: String,
+  /// The creation date
+  pub created_at: chrono::NaiveDateTime,
+  /// The last update date
+  pub updated_at: chrono::NaiveDateTime,
+  /// The kind of secret
+  pub kind: String,
+  /// The secret cannot be updated
+  pub immutable: bool,
+  // The metadata (user defined)
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  #[cfg_attr(feature = "utoipa", schema(value_type = HashMap<String, Any>))]
+  pub metadata: Option<serde_json::Value>,
+  /// The secret data
+  #[cfg_attr(feature = "utoipa", schema(value_type = HashMap<String, Any>))]
+  pub data: serde_json::Value,
+  /// Cargoes and jobs using the secret
+  pub consumers: Vec<SecretConsumer>,
+}
+
+impl From<SecretInspect> for Secret {
+  fn from(inspect: SecretInspect) -> Self {
+    Secret {
+      name: inspect.name,
+      created_at: inspect.created_at,
+      updated_at: inspect.updated_at,
+      kind: inspect.kind,
+      immutable: inspect.immutable,
+      metadata: inspect.metadata,
+      data: inspect.data,
+    }
+  }
+}
+
+impl From<SecretInspect> for SecretPartial {
+  fn from(inspect: SecretInspect) -> Self {
+    Secret::from(inspect).into()
+  }
+}
+
+/// A version of a secret kept in its history.
+/// The data of the version stay encrypted and is not returned.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "PascalCase"))]
+pub struct SecretHistory {
+  /// Key of the history record
+  pub key: uuid::Uuid,
+  /// Name of the secret
+  pub secret_key: String,
+  /// When the version have been created
+  pub created_at: chrono::NaiveDateTime,
+  /// Revision number of the secret
+  pub version: String,
+  /// The metadata of the version (user defined)
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  #[cfg_attr(feature = "utoipa", schema(value_type = HashMap<String, Any>))]
+  pub metadata: Option<serde_json::Value>,
+}
+
+/// Query used when updating or reverting a secret
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SecretUpdateQuery {
+  /// Rolling update the cargoes using the secret (default to true),
+  /// set it to false to keep them running with the previous data
+  pub rollout: Option<bool>,
+}
+
+/// Query used when inspecting a secret
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SecretInspectQuery {
+  /// Return the data in clear instead of masked values
+  pub reveal: Option<bool>,
+}
+
 /// This structure is used to update a secret.
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
@@ -175,12 +175,15 @@ pub struct BinaryInfo {
 /// Kind is the type of event related to the actor kind
 #[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[cfg_attr(feature = "serde", serde(rename_all = "PascalCase"))]
 pub enum EventActorKind {
   Namespace,
   Cargo,
   Vm,
+  VmSnapshot,
+  VmImage,
   Job,
   Resource,
   Secret,
@@ -194,6 +197,8 @@ impl std::fmt::Display for EventActorKind {
       EventActorKind::Namespace => write!(f, "Namespace"),
       EventActorKind::Cargo => write!(f, "Cargo"),
       EventActorKind::Vm => write!(f, "Vm"),
+      EventActorKind::VmSnapshot => write!(f, "VmSnapshot"),
+      EventActorKind::VmImage => write!(f, "VmImage"),
       EventActorKind::Job => write!(f, "Job"),
       EventActorKind::Resource => write!(f, "Resource"),
       EventActorKind::Secret => write!(f, "Secret"),
@@ -219,6 +224,7 @@ pub enum NativeEventAction {
   Stopping,
   Stop,
   Restart,
+  Restore,
   Finish,
   Fail,
   Die,
@@ -242,6 +248,7 @@ impl FromStr for NativeEventAction {
       "stopping" => Ok(NativeEventAction::Stopping),
       "stop" => Ok(NativeEventAction::Stop),
       "restart" => Ok(NativeEventAction::Restart),
+      "restore" => Ok(NativeEventAction::Restore),
       "finish" => Ok(NativeEventAction::Finish),
       "fail" => Ok(NativeEventAction::Fail),
       "die" => Ok(NativeEventAction::Die),
@@ -265,6 +272,7 @@ impl std::fmt::Display for NativeEventAction {
       NativeEventAction::Destroying => write!(f, "destroying"),
       NativeEventAction::Destroy => write!(f, "destroy"),
       NativeEventAction::Restart => write!(f, "restart"),
+      NativeEventAction::Restore => write!(f, "restore"),
       NativeEventAction::Finish => write!(f, "finish"),
       NativeEventAction::Fail => write!(f, "fail"),
       NativeEventAction::Die => write!(f, "die"),
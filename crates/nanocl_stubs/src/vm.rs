@@ -2,6 +2,7 @@
 use serde::{Deserialize, Serialize};
 
 use crate::{
+  generic::DeletePropagation,
   process::Process,
   system::{EventActor, EventActorKind, ObjPsStatus},
   vm_spec::{VmSpec, VmSpecPartial},
@@ -95,3 +96,172 @@ pub struct VmInspect {
   /// List of instances
   pub instances: Vec<Process>,
 }
+
+/// Delete virtual machine query
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct VmDeleteQuery {
+  /// Name of the namespace
+  pub namespace: Option<String>,
+  /// What happens to the resources owned by the virtual machine, default to Background
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub propagation: Option<DeletePropagation>,
+}
+
+/// Query to read the console logs of a virtual machine
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct VmLogQuery {
+  /// Name of the namespace
+  pub namespace: Option<String>,
+  /// If integer only return last n lines, if "all" returns all logs
+  pub tail: Option<String>,
+  /// Bool, if set keep the stream open and send new lines when written
+  pub follow: Option<bool>,
+}
+
+/// Query to attach to the console of a virtual machine
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct VmAttachQuery {
+  /// Name of the namespace
+  pub namespace: Option<String>,
+  /// Key sequence used to detach from the console (default: ctrl-c)
+  pub detach_keys: Option<String>,
+  /// Type `stty rows <height> cols <width>` on the console when it's resized.
+  /// A serial console can't tell its size to the guest so without it
+  /// a resize only apply to the qemu process and not inside the vm.
+  /// It's meant to be used while the console is at a shell prompt.
+  pub resize_guest: Option<bool>,
+}
+
+/// Size of the terminal attached to a virtual machine console.
+/// It's sent as json in a binary frame during an attach session
+/// to resize the console of the virtual machine,
+/// the guest only see it when `resize_guest` is set on the attach query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "PascalCase"))]
+pub struct VmConsoleSize {
+  /// Number of columns
+  pub width: u16,
+  /// Number of rows
+  pub height: u16,
+}
+
+/// Time spent by a virtual cpu of a virtual machine
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "PascalCase"))]
+pub struct VmCpuStats {
+  /// Index of the virtual cpu
+  pub cpu_index: i64,
+  /// Id of the qemu thread running the virtual cpu
+  pub thread_id: i64,
+  /// Cpu time used by the virtual cpu in nanoseconds
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub time: Option<u64>,
+}
+
+/// Memory of a virtual machine reported by its balloon device
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "PascalCase"))]
+pub struct VmMemoryStats {
+  /// Memory given to the guest in bytes
+  pub actual: u64,
+  /// Total memory seen by the guest in bytes
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub total: Option<u64>,
+  /// Free memory seen by the guest in bytes
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub free: Option<u64>,
+  /// Available memory seen by the guest in bytes
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub available: Option<u64>,
+}
+
+/// I/O counters of a disk of a virtual machine
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "PascalCase"))]
+pub struct VmBlockStats {
+  /// Id of the drive
+  pub device: String,
+  /// Number of bytes read
+  pub rd_bytes: u64,
+  /// Number of bytes written
+  pub wr_bytes: u64,
+  /// Number of read operations
+  pub rd_operations: u64,
+  /// Number of write operations
+  pub wr_operations: u64,
+}
+
+/// Counters of a network interface of a virtual machine
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "PascalCase"))]
+pub struct VmNetStats {
+  /// Name of the interface
+  pub iface: String,
+  /// Number of bytes received
+  pub rx_bytes: u64,
+  /// Number of packets received
+  pub rx_packets: u64,
+  /// Number of bytes transmitted
+  pub tx_bytes: u64,
+  /// Number of packets transmitted
+  pub tx_packets: u64,
+}
+
+/// Guest level statistics of a virtual machine.
+/// They are sampled by the daemon and stored as `nanocl.io/vm` metrics.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "PascalCase"))]
+pub struct VmStats {
+  /// Name of the virtual machine
+  pub name: String,
+  /// Namespace of the virtual machine
+  pub namespace: String,
+  /// Virtual cpus of the virtual machine
+  pub cpus: Vec<VmCpuStats>,
+  /// Memory of the virtual machine if a balloon device is available
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub memory: Option<VmMemoryStats>,
+  /// Disks of the virtual machine
+  pub block: Vec<VmBlockStats>,
+  /// Network interfaces of the virtual machine
+  pub net: Vec<VmNetStats>,
+}
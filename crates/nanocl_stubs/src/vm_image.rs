@@ -1,6 +1,8 @@
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
+use crate::system::{EventActor, EventActorKind};
+
 #[derive(Debug, Clone, PartialEq)]
 #[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
@@ -21,6 +23,23 @@ pub struct VmImage {
   pub size_actual: i64,
   /// The virtual size of the image in bytes
   pub size_virtual: i64,
+  /// The image this one is based on for snapshots and linked clones
+  #[cfg_attr(
+    feature = "serde",
+    serde(default, skip_serializing_if = "Option::is_none")
+  )]
+  pub parent: Option<String>,
+}
+
+/// Convert a VmImage into an EventActor
+impl From<VmImage> for EventActor {
+  fn from(image: VmImage) -> Self {
+    Self {
+      key: Some(image.name),
+      kind: EventActorKind::VmImage,
+      attributes: None,
+    }
+  }
 }
 
 #[derive(Debug, Clone)]
@@ -49,3 +68,66 @@ pub enum VmImageCloneStream {
   /// The result of the clone operation
   Done(VmImage),
 }
+
+/// Payload to pull a vm image from an http(s) url
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub struct VmImagePullPayload {
+  /// The url of the image to download
+  pub url: String,
+  /// Checksum to verify the downloaded image eg: `sha256:<hex>` or `sha512:<hex>`
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub checksum: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "PascalCase"))]
+pub struct VmImageDownloadProgress {
+  /// Number of bytes downloaded
+  pub current: u64,
+  /// Total size of the image in bytes if known
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub total: Option<u64>,
+}
+
+/// Progress of a vm image pull, sent in the metadata of the `Downloading`
+/// events of the image as `{ "state": <progress> }`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "PascalCase"))]
+pub enum VmImagePullProgress {
+  /// The progress of the download
+  Download(VmImageDownloadProgress),
+  /// The checksum of the downloaded image is verified
+  Verify,
+  /// The progress of the conversion to qcow2 in percent
+  Convert(f32),
+}
+
+/// Query to export a vm image
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct VmImageExportQuery {
+  /// Merge the backing chain into the exported image, otherwise an image
+  /// with a parent reference it by its file name
+  pub flatten: Option<bool>,
+  /// Compress the exported image
+  pub compress: Option<bool>,
+}
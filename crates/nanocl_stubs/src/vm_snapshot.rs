@@ -0,0 +1,63 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::system::{EventActor, EventActorKind};
+
+/// A point-in-time snapshot of a virtual machine disk.
+/// Snapshots are stored as a chain of qcow2 overlays,
+/// the disk of the virtual machine being the head of the chain.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "PascalCase"))]
+pub struct VmSnapshot {
+  /// Unique identifier of the snapshot
+  pub key: uuid::Uuid,
+  /// Name of the snapshot
+  pub name: String,
+  /// Key of the virtual machine the snapshot belongs to
+  pub vm_key: String,
+  /// When the snapshot was created
+  pub created_at: chrono::NaiveDateTime,
+  /// The path to the qcow2 file of the snapshot
+  pub path: String,
+  /// The snapshot this one is based on
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub parent: Option<uuid::Uuid>,
+  /// The actual size of the snapshot in bytes
+  pub size_actual: i64,
+  /// The virtual size of the snapshot in bytes
+  pub size_virtual: i64,
+}
+
+/// Payload to create a new snapshot of a virtual machine
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub struct VmSnapshotPartial {
+  /// Name of the snapshot
+  pub name: String,
+}
+
+/// Convert a VmSnapshot into an EventActor
+impl From<VmSnapshot> for EventActor {
+  fn from(snapshot: VmSnapshot) -> Self {
+    Self {
+      key: Some(snapshot.key.to_string()),
+      kind: EventActorKind::VmSnapshot,
+      attributes: Some(serde_json::json!({
+        "Name": snapshot.name,
+        "Vm": snapshot.vm_key,
+      })),
+    }
+  }
+}
@@ -26,6 +26,204 @@ pub struct VmDisk {
   pub size: Option<u64>,
 }
 
+/// Bus used to attach a disk to a VM
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum VmDiskBus {
+  /// Paravirtualized block device (best performance)
+  #[default]
+  Virtio,
+  /// Virtio scsi controller
+  Scsi,
+  /// Emulated ide controller (best compatibility)
+  Ide,
+}
+
+/// Additional disk of a VM, used to attach data volumes
+/// that are kept when the os image of the VM change
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub struct VmDataDisk {
+  /// Name of the disk unique for the VM
+  pub name: String,
+  /// Name of an existing image to attach,
+  /// when not set a new qcow2 image of the given size is created.
+  /// The created image is kept when the VM is deleted or the disk removed.
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub image: Option<String>,
+  /// Size in GB of the image to create (default: 20)
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub size: Option<u64>,
+  /// Bus used to attach the disk (default: Virtio)
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub bus: Option<VmDiskBus>,
+  /// Attach the disk in read-only mode
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub read_only: Option<bool>,
+  /// Boot order of the disk, the os disk has the boot order 1
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub boot_order: Option<u32>,
+}
+
+/// A user to create inside the vm using cloud-init
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub struct VmCloudInitUser {
+  /// Name of the user
+  pub name: String,
+  /// Plain text password of the user
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub password: Option<String>,
+  /// Sudo rule of the user (eg: ALL=(ALL) NOPASSWD:ALL)
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub sudo: Option<String>,
+  /// Login shell of the user (default: /bin/bash)
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub shell: Option<String>,
+  /// Additional groups of the user
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub groups: Option<Vec<String>>,
+  /// Ssh public keys allowed to login as the user
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub ssh_authorized_keys: Option<Vec<String>>,
+}
+
+/// A file to write inside the vm using cloud-init
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub struct VmCloudInitFile {
+  /// Absolute path of the file inside the vm
+  pub path: String,
+  /// Content of the file
+  pub content: String,
+  /// Encoding of the content (eg: b64, gzip)
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub encoding: Option<String>,
+  /// Owner of the file (eg: root:root)
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub owner: Option<String>,
+  /// Permissions of the file in octal (eg: 0644)
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub permissions: Option<String>,
+}
+
+/// Cloud-init configuration of a vm
+/// It's used to generate the NoCloud seed image attached to the vm
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+  feature = "serde",
+  serde(deny_unknown_fields, rename_all = "PascalCase")
+)]
+pub struct VmCloudInit {
+  /// Raw cloud-config user-data, when set it's used as is
+  /// and the other user-data options are ignored
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub user_data: Option<String>,
+  /// Additional users to create
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub users: Option<Vec<VmCloudInitUser>>,
+  /// Additional ssh public keys for the default user
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub ssh_authorized_keys: Option<Vec<String>>,
+  /// Packages to install on first boot
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub packages: Option<Vec<String>>,
+  /// Commands to run on first boot
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub runcmd: Option<Vec<String>>,
+  /// Files to write on first boot
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub write_files: Option<Vec<VmCloudInitFile>>,
+  /// Network config version 2, when not set the network interface
+  /// of the vm is configured with dhcp
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  #[cfg_attr(feature = "utoipa", schema(value_type = HashMap<String, Any>))]
+  pub network_config: Option<serde_json::Value>,
+}
+
 /// A vm's resources (cpu, memory, network)
 #[derive(Debug, Clone, PartialEq)]
 #[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
@@ -74,6 +272,12 @@ pub struct VmHostConfig {
   pub runtime_network: Option<String>,
   /// Use host tun device
   pub host_tun: Option<bool>,
+  /// Seconds to wait for the guest to power off before forcing the stop (default: 60)
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub shutdown_timeout: Option<u64>,
 }
 
 impl Default for VmHostConfig {
@@ -88,6 +292,7 @@ impl Default for VmHostConfig {
       host_tun: None,
       link_net_iface: None,
       runtime_network: None,
+      shutdown_timeout: None,
     }
   }
 }
@@ -135,8 +340,20 @@ pub struct VmSpecPartial {
     serde(skip_serializing_if = "Option::is_none")
   )]
   pub ssh_key: Option<String>,
+  /// Cloud-init configuration used to generate the seed image
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub cloud_init: Option<VmCloudInit>,
   /// Disk config of the vm (image, size) required
   pub disk: VmDisk,
+  /// Additional data disks of the vm
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub disks: Option<Vec<VmDataDisk>>,
   /// Mac address of the vm (default: generated)
   #[cfg_attr(
     feature = "serde",
@@ -209,6 +426,18 @@ pub struct VmSpecUpdate {
     serde(skip_serializing_if = "Option::is_none")
   )]
   pub ssh_key: Option<String>,
+  /// Cloud-init configuration used to generate the seed image
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub cloud_init: Option<VmCloudInit>,
+  /// Additional data disks of the vm
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub disks: Option<Vec<VmDataDisk>>,
   /// User-defined key/value metadata.
   #[cfg_attr(
     feature = "serde",
@@ -233,6 +462,8 @@ impl From<VmSpecPartial> for VmSpecUpdate {
       host_config: spec.host_config,
       password: spec.password,
       ssh_key: spec.ssh_key,
+      cloud_init: spec.cloud_init,
+      disks: spec.disks,
       metadata: spec.metadata,
     }
   }
@@ -282,6 +513,12 @@ pub struct VmSpec {
     serde(skip_serializing_if = "Option::is_none")
   )]
   pub ssh_key: Option<String>,
+  /// Cloud-init configuration used to generate the seed image
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub cloud_init: Option<VmCloudInit>,
   /// Default user of the vm (cloud)
   #[cfg_attr(
     feature = "serde",
@@ -290,6 +527,12 @@ pub struct VmSpec {
   pub user: Option<String>,
   /// Disk config of the vm
   pub disk: VmDisk,
+  /// Additional data disks of the vm
+  #[cfg_attr(
+    feature = "serde",
+    serde(skip_serializing_if = "Option::is_none")
+  )]
+  pub disks: Option<Vec<VmDataDisk>>,
   /// Mac address of the vm
   #[cfg_attr(
     feature = "serde",
@@ -316,6 +559,8 @@ impl From<VmSpec> for VmSpecUpdate {
       host_config: Some(spec.host_config),
       password: spec.password,
       ssh_key: spec.ssh_key,
+      cloud_init: spec.cloud_init,
+      disks: spec.disks,
       metadata: spec.metadata,
     }
   }
@@ -331,8 +576,10 @@ impl From<VmSpec> for VmSpecPartial {
       host_config: Some(spec.host_config),
       password: spec.password,
       ssh_key: spec.ssh_key,
+      cloud_init: spec.cloud_init,
       metadata: spec.metadata,
       disk: spec.disk,
+      disks: spec.disks,
       mac_address: spec.mac_address,
     }
   }
@@ -1,11 +1,13 @@
 use bollard_next::service::ContainerSummary;
 
-use nanocl_error::http_client::HttpClientResult;
+use ntex::channel::mpsc::Receiver;
+
+use nanocl_error::{http::HttpResult, http_client::HttpClientResult};
 
 use nanocl_stubs::{
   cargo::{Cargo, CargoDeleteQuery, CargoInspect, CargoSummary},
   cargo_spec::{CargoSpec, CargoSpecPartial, CargoSpecUpdate},
-  generic::{GenericFilterNsp, GenericNspQuery},
+  generic::{GenericFilterNsp, GenericNspQuery, WatchEvent},
 };
 
 use super::http_client::NanocldClient;
@@ -111,6 +113,27 @@ impl NanocldClient {
     Self::res_json(res).await
   }
 
+  /// Watch the cargoes of a namespace followed by their changes
+  ///
+  /// ## Example
+  ///
+  /// ```no_run,ignore
+  /// use nanocld_client::NanocldClient;
+  ///
+  /// let client = NanocldClient::connect_to("http://localhost:8585", None);
+  /// let mut stream = client.watch_cargo(None, None).await?;
+  /// while let Some(event) = stream.next().await {
+  ///  println!("{:?}", event);
+  /// }
+  /// ```
+  pub async fn watch_cargo(
+    &self,
+    query: Option<&GenericFilterNsp>,
+    cursor: Option<&str>,
+  ) -> HttpClientResult<Receiver<HttpResult<WatchEvent<CargoSummary>>>> {
+    self.send_watch(Self::CARGO_PATH, query, cursor).await
+  }
+
   /// Patch a cargo by it's name
   /// This will update the cargo's spec by merging current spec with new spec and creating an history entry
   ///
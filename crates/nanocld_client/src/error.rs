@@ -18,10 +18,11 @@ pub(crate) async fn is_api_error(
       .unwrap_or(&default)
       .as_str()
       .unwrap_or_default();
-    return Err(HttpClientError::HttpError(HttpError::new(
-      *status,
-      msg.to_owned(),
-    )));
+    let mut http_err = HttpError::new(*status, msg.to_owned());
+    if let Some(details) = err.get("details") {
+      http_err = http_err.with_details(details.clone());
+    }
+    return Err(HttpClientError::HttpError(http_err));
   }
   Ok(())
 }
@@ -1,7 +1,10 @@
 use std::error::Error;
 
 use nanocl_error::io::IoResult;
-use nanocl_stubs::{generic::GenericListQueryNsp, system::SslConfig};
+use nanocl_stubs::{
+  generic::{GenericListQueryNsp, WatchEvent},
+  system::SslConfig,
+};
 use ntex::{http, rt};
 
 use futures::{StreamExt, TryStreamExt};
@@ -9,7 +12,7 @@ use ntex::channel::mpsc::Receiver;
 use ntex::util::{Bytes, Stream};
 
 use nanocl_error::{
-  http::HttpError,
+  http::{HttpError, HttpResult},
   http_client::HttpClientError,
   io::{FromIo, IoError},
 };
@@ -287,6 +290,29 @@ impl NanocldClient {
     Ok(query)
   }
 
+  /// Watch the items of a list endpoint followed by their changes.
+  /// Changes after the cursor of a previous watch are sent instead of the
+  /// items when a cursor is given.
+  pub async fn send_watch<Q, T>(
+    &self,
+    url: &str,
+    query: Option<&Q>,
+    cursor: Option<&str>,
+  ) -> Result<Receiver<HttpResult<WatchEvent<T>>>, HttpClientError>
+  where
+    Q: Clone + Default + TryInto<GenericListQueryNsp>,
+    Q::Error: ToString,
+    T: serde::de::DeserializeOwned + Send + 'static,
+  {
+    let query = GenericListQueryNsp {
+      watch: Some(true),
+      cursor: cursor.map(|cursor| cursor.to_owned()),
+      ..Self::convert_query(query)?
+    };
+    let res = self.send_get(url, Some(query)).await?;
+    Ok(Self::res_stream(res).await)
+  }
+
   pub async fn send_post<Q, B>(
     &self,
     url: &str,
@@ -1,7 +1,9 @@
-use nanocl_error::http_client::HttpClientResult;
+use ntex::channel::mpsc::Receiver;
+
+use nanocl_error::{http::HttpResult, http_client::HttpClientResult};
 
 use nanocl_stubs::{
-  generic::GenericFilter,
+  generic::{GenericDeleteQuery, GenericFilter, WatchEvent},
   job::{Job, JobInspect, JobPartial, JobSummary},
 };
 
@@ -30,6 +32,27 @@ impl NanocldClient {
     Self::res_json(res).await
   }
 
+  /// Watch the jobs followed by their changes
+  ///
+  /// ## Example
+  ///
+  /// ```no_run,ignore
+  /// use nanocld_client::NanocldClient;
+  ///
+  /// let client = NanocldClient::connect_to("http://localhost:8585", None);
+  /// let mut stream = client.watch_job(None, None).await?;
+  /// while let Some(event) = stream.next().await {
+  ///  println!("{:?}", event);
+  /// }
+  /// ```
+  pub async fn watch_job(
+    &self,
+    query: Option<&GenericFilter>,
+    cursor: Option<&str>,
+  ) -> HttpClientResult<Receiver<HttpResult<WatchEvent<JobSummary>>>> {
+    self.send_watch(Self::JOB_PATH, query, cursor).await
+  }
+
   /// Get information about a job by it's name
   ///
   /// ## Example
@@ -84,11 +107,15 @@ impl NanocldClient {
   /// use nanocld_client::NanocldClient;
   ///
   /// let client = NanocldClient::connect_to("http://localhost:8585", None);
-  /// let res = client.delete_job("my_job").await;
+  /// let res = client.delete_job("my_job", None).await;
   /// ```
-  pub async fn delete_job(&self, name: &str) -> HttpClientResult<()> {
+  pub async fn delete_job(
+    &self,
+    name: &str,
+    query: Option<&GenericDeleteQuery>,
+  ) -> HttpClientResult<()> {
     self
-      .send_delete(&format!("{}/{name}", Self::JOB_PATH), None::<String>)
+      .send_delete(&format!("{}/{name}", Self::JOB_PATH), query)
       .await?;
     Ok(())
   }
@@ -142,6 +169,6 @@ mod tests {
     client.start_process("job", &job.name, None).await.unwrap();
     while let Some(Ok(_)) = stream.next().await {}
     let job = client.inspect_job(&job.name).await.unwrap();
-    client.delete_job(&job.spec.name).await.unwrap();
+    client.delete_job(&job.spec.name, None).await.unwrap();
   }
 }
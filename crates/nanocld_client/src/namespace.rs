@@ -1,7 +1,7 @@
 use nanocl_error::http_client::HttpClientResult;
 
 use nanocl_stubs::{
-  generic::GenericFilter,
+  generic::{GenericDeleteQuery, GenericFilter},
   namespace::{
     Namespace, NamespaceInspect, NamespacePartial, NamespaceSummary,
   },
@@ -70,7 +70,8 @@ impl NanocldClient {
     Self::res_json(res).await
   }
 
-  /// Delete a namespace by it's name
+  /// Delete a namespace by it's name with its cargoes and vms,
+  /// `force` is required when some cargoes are running
   ///
   /// ## Example
   ///
@@ -78,11 +79,15 @@ impl NanocldClient {
   /// use nanocld_client::NanocldClient;
   ///
   /// let client = NanocldClient::connect_to("http://localhost:8585", None);
-  /// let res = client.delete_namespace("my-namespace").await;
+  /// let res = client.delete_namespace("my-namespace", None).await;
   /// ```
-  pub async fn delete_namespace(&self, name: &str) -> HttpClientResult<()> {
+  pub async fn delete_namespace(
+    &self,
+    name: &str,
+    query: Option<&GenericDeleteQuery>,
+  ) -> HttpClientResult<()> {
     self
-      .send_delete(&format!("{}/{name}", Self::NAMESPACE_PATH), None::<String>)
+      .send_delete(&format!("{}/{name}", Self::NAMESPACE_PATH), query)
       .await?;
     Ok(())
   }
@@ -107,6 +112,6 @@ mod tests {
     assert_eq!(namespace.name, NAMESPACE);
     let namespace = client.inspect_namespace(NAMESPACE).await.unwrap();
     assert_eq!(namespace.name, NAMESPACE);
-    client.delete_namespace(NAMESPACE).await.unwrap();
+    client.delete_namespace(NAMESPACE, None).await.unwrap();
   }
 }
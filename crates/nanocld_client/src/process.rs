@@ -4,7 +4,7 @@ use nanocl_error::{http::HttpResult, http_client::HttpClientResult};
 
 use nanocl_stubs::{
   cargo::CargoKillOptions,
-  generic::{GenericFilter, GenericNspQuery},
+  generic::{GenericFilter, GenericNspQuery, WatchEvent},
   process::{
     Process, ProcessLogQuery, ProcessOutputLog, ProcessStats,
     ProcessStatsQuery, ProcessWaitQuery, ProcessWaitResponse,
@@ -36,6 +36,27 @@ impl NanocldClient {
     Self::res_json(res).await
   }
 
+  /// Watch the processes followed by their changes
+  ///
+  /// ## Example
+  ///
+  /// ```no_run,ignore
+  /// use nanocld_client::NanocldClient;
+  ///
+  /// let client = NanocldClient::connect_to("http://localhost:8585", None);
+  /// let mut stream = client.watch_process(None, None).await?;
+  /// while let Some(event) = stream.next().await {
+  ///  println!("{:?}", event);
+  /// }
+  /// ```
+  pub async fn watch_process(
+    &self,
+    query: Option<&GenericFilter>,
+    cursor: Option<&str>,
+  ) -> HttpClientResult<Receiver<HttpResult<WatchEvent<Process>>>> {
+    self.send_watch(Self::PROCESS_PATH, query, cursor).await
+  }
+
   /// Get Log of a single process by it's name or id
   /// Cargoes, jobs, can have multiple instances, this endpoint get logs of a single instance
   ///
@@ -119,6 +140,58 @@ impl NanocldClient {
     Ok(())
   }
 
+  /// Reboot a virtual machine gracefully by it's name and namespace
+  ///
+  /// ## Example
+  ///
+  /// ```no_run,ignore
+  /// use nanocld_client::NanocldClient;
+  ///
+  /// let client = NanocldClient::connect_to("http://localhost:8585", None);
+  /// let res = client.reboot_process("vm", "my-vm", None).await;
+  /// ```
+  pub async fn reboot_process(
+    &self,
+    kind: &str,
+    name: &str,
+    namespace: Option<&str>,
+  ) -> HttpClientResult<()> {
+    self
+      .send_post(
+        &format!("{}/{kind}/{name}/reboot", Self::PROCESS_PATH),
+        None::<String>,
+        Some(GenericNspQuery::new(namespace)),
+      )
+      .await?;
+    Ok(())
+  }
+
+  /// Reset a virtual machine by it's name and namespace
+  ///
+  /// ## Example
+  ///
+  /// ```no_run,ignore
+  /// use nanocld_client::NanocldClient;
+  ///
+  /// let client = NanocldClient::connect_to("http://localhost:8585", None);
+  /// let res = client.reset_process("vm", "my-vm", None).await;
+  /// ```
+  pub async fn reset_process(
+    &self,
+    kind: &str,
+    name: &str,
+    namespace: Option<&str>,
+  ) -> HttpClientResult<()> {
+    self
+      .send_post(
+        &format!("{}/{kind}/{name}/reset", Self::PROCESS_PATH),
+        None::<String>,
+        Some(GenericNspQuery::new(namespace)),
+      )
+      .await?;
+    Ok(())
+  }
+
   /// Stop a process by it's kind and name and namespace
   ///
   /// ## Example
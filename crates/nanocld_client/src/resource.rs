@@ -1,8 +1,11 @@
-use nanocl_error::http_client::HttpClientResult;
+use ntex::channel::mpsc::Receiver;
 
-use nanocl_stubs::generic::GenericFilter;
+use nanocl_error::{http::HttpResult, http_client::HttpClientResult};
+
+use nanocl_stubs::generic::{GenericDeleteQuery, GenericFilter, WatchEvent};
 use nanocl_stubs::resource::{
-  Resource, ResourcePartial, ResourceSpec, ResourceUpdate,
+  Resource, ResourceFinalizersPatch, ResourcePartial, ResourceSpec,
+  ResourceStatusPartial, ResourceUpdate,
 };
 
 use super::http_client::NanocldClient;
@@ -31,6 +34,27 @@ impl NanocldClient {
     Self::res_json(res).await
   }
 
+  /// Watch the resources followed by their changes
+  ///
+  /// ## Example
+  ///
+  /// ```no_run,ignore
+  /// use nanocld_client::NanocldClient;
+  ///
+  /// let client = NanocldClient::connect_to("http://localhost:8585", None);
+  /// let mut stream = client.watch_resource(None, None).await?;
+  /// while let Some(event) = stream.next().await {
+  ///  println!("{:?}", event);
+  /// }
+  /// ```
+  pub async fn watch_resource(
+    &self,
+    query: Option<&GenericFilter>,
+    cursor: Option<&str>,
+  ) -> HttpClientResult<Receiver<HttpResult<WatchEvent<Resource>>>> {
+    self.send_watch(Self::RESOURCE_PATH, query, cursor).await
+  }
+
   /// Create a new resource from a partial resource in the system.
   ///
   /// ## Example
@@ -113,11 +137,15 @@ impl NanocldClient {
   /// use nanocld_client::NanocldClient;
   ///
   /// let client = NanocldClient::connect_to("http://localhost:8585", None);
-  /// let res = client.delete_resource("my-resource").await;
+  /// let res = client.delete_resource("my-resource", None).await;
   /// ```
-  pub async fn delete_resource(&self, key: &str) -> HttpClientResult<()> {
+  pub async fn delete_resource(
+    &self,
+    key: &str,
+    query: Option<&GenericDeleteQuery>,
+  ) -> HttpClientResult<()> {
     self
-      .send_delete(&format!("{}/{key}", Self::RESOURCE_PATH), None::<String>)
+      .send_delete(&format!("{}/{key}", Self::RESOURCE_PATH), query)
       .await?;
     Ok(())
   }
@@ -170,4 +198,64 @@ impl NanocldClient {
       .await?;
     Self::res_json(res).await
   }
+
+  /// Update the status of a resource, used by the controller of its kind
+  /// to report whether the resource converged
+  ///
+  /// ## Example
+  ///
+  /// ```no_run,ignore
+  /// use nanocld_client::NanocldClient;
+  /// use nanocl_stubs::resource::{ResourcePhase, ResourceStatusPartial};
+  ///
+  /// let client = NanocldClient::connect_to("http://localhost:8585", None);
+  /// let res = client.patch_resource_status("my-resource", &ResourceStatusPartial {
+  ///   phase: Some(ResourcePhase::Ready),
+  ///   ..Default::default()
+  /// }).await;
+  /// ```
+  pub async fn patch_resource_status(
+    &self,
+    name: &str,
+    status: &ResourceStatusPartial,
+  ) -> HttpClientResult<Resource> {
+    let res = self
+      .send_patch(
+        &format!("{}/{name}/status", Self::RESOURCE_PATH),
+        Some(status),
+        None::<String>,
+      )
+      .await?;
+    Self::res_json(res).await
+  }
+
+  /// Add or remove finalizers of a resource, used by controllers
+  /// to block its deletion until their cleanup is done
+  ///
+  /// ## Example
+  ///
+  /// ```no_run,ignore
+  /// use nanocld_client::NanocldClient;
+  /// use nanocl_stubs::resource::ResourceFinalizersPatch;
+  ///
+  /// let client = NanocldClient::connect_to("http://localhost:8585", None);
+  /// let res = client.patch_resource_finalizers("my-resource", &ResourceFinalizersPatch {
+  ///   remove: vec!["example.com/cleanup".to_owned()],
+  ///   ..Default::default()
+  /// }).await;
+  /// ```
+  pub async fn patch_resource_finalizers(
+    &self,
+    name: &str,
+    patch: &ResourceFinalizersPatch,
+  ) -> HttpClientResult<Resource> {
+    let res = self
+      .send_patch(
+        &format!("{}/{name}/finalizers", Self::RESOURCE_PATH),
+        Some(patch),
+        None::<String>,
+      )
+      .await?;
+    Self::res_json(res).await
+  }
 }
@@ -1,9 +1,11 @@
 use nanocl_error::http_client::HttpClientResult;
 
 use nanocl_stubs::{
-  generic::GenericFilter,
+  generic::{GenericDeleteQuery, GenericFilter},
   resource_kind::{
-    ResourceKind, ResourceKindInspect, ResourceKindPartial, ResourceKindVersion,
+    ResourceKind, ResourceKindInspect, ResourceKindMigrate,
+    ResourceKindMigration, ResourceKindPartial, ResourceKindVersion,
+    ResourceKindVersionResources,
   },
 };
 
@@ -95,7 +97,8 @@ impl NanocldClient {
     Self::res_json(res).await
   }
 
-  /// Delete a resource kind by it's key
+  /// Delete a resource kind by it's key,
+  /// it's refused while it has resources unless `force` is set
   ///
   /// ## Example
   ///
@@ -103,16 +106,69 @@ impl NanocldClient {
   /// use nanocld_client::NanocldClient;
   ///
   /// let client = NanocldClient::connect_to("http://localhost:8585", None);
-  /// client.delete_resource_kind("ncproxy.io/rule").await?;
+  /// client.delete_resource_kind("ncproxy.io/rule", None).await?;
   /// ```
-  pub async fn delete_resource_kind(&self, key: &str) -> HttpClientResult<()> {
+  pub async fn delete_resource_kind(
+    &self,
+    key: &str,
+    query: Option<&GenericDeleteQuery>,
+  ) -> HttpClientResult<()> {
     self
-      .send_delete(
-        &format!("{}/{key}", Self::RESOURCE_KIND_PATH),
+      .send_delete(&format!("{}/{key}", Self::RESOURCE_KIND_PATH), query)
+      .await?;
+    Ok(())
+  }
+
+  /// List the resources of a kind grouped by the version they are stored with
+  ///
+  /// ## Example
+  ///
+  /// ```no_run,ignore
+  /// use nanocld_client::NanocldClient;
+  ///
+  /// let client = NanocldClient::connect_to("http://localhost:8585", None);
+  /// let versions = client.list_resource_kind_resources("ncproxy.io/rule").await?;
+  /// ```
+  pub async fn list_resource_kind_resources(
+    &self,
+    key: &str,
+  ) -> HttpClientResult<Vec<ResourceKindVersionResources>> {
+    let res = self
+      .send_get(
+        &format!("{}/{key}/resources", Self::RESOURCE_KIND_PATH),
         None::<String>,
       )
       .await?;
-    Ok(())
+    Self::res_json(res).await
+  }
+
+  /// Convert the resources of a kind to a version and update them
+  ///
+  /// ## Example
+  ///
+  /// ```no_run,ignore
+  /// use nanocld_client::NanocldClient;
+  /// use nanocl_stubs::resource_kind::ResourceKindMigrate;
+  ///
+  /// let client = NanocldClient::connect_to("http://localhost:8585", None);
+  /// let migration = client.migrate_resource_kind("ncproxy.io/rule", &ResourceKindMigrate {
+  ///   version: "v0.2".to_owned(),
+  ///   ..Default::default()
+  /// }).await?;
+  /// ```
+  pub async fn migrate_resource_kind(
+    &self,
+    key: &str,
+    payload: &ResourceKindMigrate,
+  ) -> HttpClientResult<ResourceKindMigration> {
+    let res = self
+      .send_post(
+        &format!("{}/{key}/migrate", Self::RESOURCE_KIND_PATH),
+        Some(payload),
+        None::<String>,
+      )
+      .await?;
+    Self::res_json(res).await
   }
 }
 
@@ -140,6 +196,10 @@ mod tests {
       data: ResourceKindSpec {
         schema: None,
         url: Some("unix:///run/nanocl/proxy.sock".to_owned()),
+        conversion: None,
+        controller: None,
+        immutable: None,
+        wasm: None,
       },
     };
     let resource_kind =
@@ -155,8 +215,13 @@ mod tests {
       .inspect_resource_kind_version(RESOURCE_KIND_NAME, RESOURCE_KIND_VERSION)
       .await
       .unwrap();
+    let versions = client
+      .list_resource_kind_resources(RESOURCE_KIND_NAME)
+      .await
+      .unwrap();
+    assert!(versions.is_empty());
     client
-      .delete_resource_kind(RESOURCE_KIND_NAME)
+      .delete_resource_kind(RESOURCE_KIND_NAME, None)
       .await
       .unwrap();
   }
@@ -1,7 +1,14 @@
-use nanocl_error::http_client::HttpClientResult;
+use ntex::channel::mpsc::Receiver;
 
-use nanocl_stubs::generic::GenericFilter;
-use nanocl_stubs::secret::{Secret, SecretPartial, SecretUpdate};
+use nanocl_error::{http::HttpResult, http_client::HttpClientResult};
+
+use nanocl_stubs::generic::{
+  GenericCount, GenericDeleteQuery, GenericFilter, WatchEvent,
+};
+use nanocl_stubs::secret::{
+  Secret, SecretHistory, SecretInspect, SecretInspectQuery, SecretPartial,
+  SecretSeal, SecretUpdate, SecretUpdateQuery,
+};
 
 use super::http_client::NanocldClient;
 
@@ -9,7 +16,7 @@ impl NanocldClient {
   /// ## Default path for secrets
   const SECRET_PATH: &'static str = "/secrets";
 
-  /// List existing secrets in the system.
+  /// List existing secrets in the system, their data is omitted.
   ///
   /// ## Example
   ///
@@ -28,6 +35,27 @@ impl NanocldClient {
     Self::res_json(res).await
   }
 
+  /// Watch the secrets without their data followed by their changes
+  ///
+  /// ## Example
+  ///
+  /// ```no_run,ignore
+  /// use nanocld_client::NanocldClient;
+  ///
+  /// let client = NanocldClient::connect_to("http://localhost:8585", None);
+  /// let mut stream = client.watch_secret(None, None).await?;
+  /// while let Some(event) = stream.next().await {
+  ///  println!("{:?}", event);
+  /// }
+  /// ```
+  pub async fn watch_secret(
+    &self,
+    query: Option<&GenericFilter>,
+    cursor: Option<&str>,
+  ) -> HttpClientResult<Receiver<HttpResult<WatchEvent<Secret>>>> {
+    self.send_watch(Self::SECRET_PATH, query, cursor).await
+  }
+
   /// Create a new secret
   pub async fn create_secret(
     &self,
@@ -39,23 +67,115 @@ impl NanocldClient {
     Self::res_json(res).await
   }
 
-  /// Patch a secret by it's key to update it with new data
+  /// Patch a secret by it's key to update it with new data.
+  /// The query can trigger a rolling update of the cargoes using it.
   pub async fn patch_secret(
     &self,
     key: &str,
     item: &SecretUpdate,
+    query: Option<&SecretUpdateQuery>,
+  ) -> HttpClientResult<Secret> {
+    let res = self
+      .send_patch(&format!("{}/{key}", Self::SECRET_PATH), Some(item), query)
+      .await?;
+    Self::res_json(res).await
+  }
+
+  /// Inspect a secret by it's key to get more information about it.
+  /// The data is masked unless the query reveal it.
+  ///
+  /// ## Example
+  ///
+  /// ```no_run,ignore
+  /// use nanocld_client::NanocldClient;
+  ///
+  /// let client = NanocldClient::connect_to("http://localhost:8585", None);
+  /// let secret = client.inspect_secret("my-secret", None).await?;
+  /// ```
+  pub async fn inspect_secret(
+    &self,
+    key: &str,
+    query: Option<&SecretInspectQuery>,
+  ) -> HttpClientResult<SecretInspect> {
+    let res = self
+      .send_get(&format!("{}/{key}/inspect", Self::SECRET_PATH), query)
+      .await?;
+    Self::res_json(res).await
+  }
+
+  /// Delete a secret by it's key,
+  /// it's refused while objects use it unless `force` is set
+  ///
+  /// ## Example
+  ///
+  /// ```no_run,ignore
+  /// use nanocld_client::NanocldClient;
+  ///
+  /// let client = NanocldClient::connect_to("http://localhost:8585", None);
+  /// client.delete_secret("my-secret", None).await?;
+  /// ```
+  pub async fn delete_secret(
+    &self,
+    key: &str,
+    query: Option<&GenericDeleteQuery>,
+  ) -> HttpClientResult<()> {
+    self
+      .send_delete(&format!("{}/{key}", Self::SECRET_PATH), query)
+      .await?;
+    Ok(())
+  }
+
+  /// List the versions kept in the history of a secret
+  ///
+  /// ## Example
+  ///
+  /// ```no_run,ignore
+  /// use nanocld_client::NanocldClient;
+  ///
+  /// let client = NanocldClient::connect_to("http://localhost:8585", None);
+  /// let histories = client.list_history_secret("my-secret").await?;
+  /// ```
+  pub async fn list_history_secret(
+    &self,
+    key: &str,
+  ) -> HttpClientResult<Vec<SecretHistory>> {
+    let res = self
+      .send_get(
+        &format!("{}/{key}/histories", Self::SECRET_PATH),
+        None::<String>,
+      )
+      .await?;
+    Self::res_json(res).await
+  }
+
+  /// Revert a secret to a specific history
+  ///
+  /// ## Example
+  ///
+  /// ```no_run,ignore
+  /// use nanocld_client::NanocldClient;
+  ///
+  /// let client = NanocldClient::connect_to("http://localhost:8585", None);
+  /// let secret = client.revert_secret("my-secret", "my-history-id", None).await?;
+  /// ```
+  pub async fn revert_secret(
+    &self,
+    key: &str,
+    id: &str,
+    query: Option<&SecretUpdateQuery>,
   ) -> HttpClientResult<Secret> {
     let res = self
       .send_patch(
-        &format!("{}/{key}", Self::SECRET_PATH),
-        Some(item),
+        &format!("{}/{key}/histories/{id}/revert", Self::SECRET_PATH),
         None::<String>,
+        query,
       )
       .await?;
     Self::res_json(res).await
   }
 
-  /// Inspect a secret by it's key to get more information about it
+  /// Rotate the master key used to encrypt the secrets at rest
+  /// and return the number of secrets re-encrypted
   ///
   /// ## Example
   ///
@@ -63,33 +183,48 @@ impl NanocldClient {
   /// use nanocld_client::NanocldClient;
   ///
   /// let client = NanocldClient::connect_to("http://localhost:8585", None);
-  /// let secret = client.inspect_secret("my-secret").await?;
+  /// let res = client.rotate_secret_key().await;
   /// ```
-  pub async fn inspect_secret(&self, key: &str) -> HttpClientResult<Secret> {
+  pub async fn rotate_secret_key(&self) -> HttpClientResult<GenericCount> {
     let res = self
-      .send_get(
-        &format!("{}/{key}/inspect", Self::SECRET_PATH),
+      .send_post(
+        &format!("{}/rotate-key", Self::SECRET_PATH),
+        None::<String>,
         None::<String>,
       )
       .await?;
     Self::res_json(res).await
   }
 
-  /// Delete a secret by it's key
+  /// Seal a value with the master key of the node.
+  /// The result is written in `{state_dir}/secrets/external/{path}`
+  /// to be read by the file backend of the `nanocl.io/external` secrets
   ///
   /// ## Example
   ///
   /// ```no_run,ignore
   /// use nanocld_client::NanocldClient;
+  /// use nanocld_client::stubs::secret::SecretSeal;
   ///
   /// let client = NanocldClient::connect_to("http://localhost:8585", None);
-  /// client.delete_secret("my-secret").await?;
+  /// let seal = SecretSeal {
+  ///   path: "app/db".into(),
+  ///   data: serde_json::json!({ "PASSWORD": "nanocl" }),
+  /// };
+  /// let res = client.seal_secret(&seal).await;
   /// ```
-  pub async fn delete_secret(&self, key: &str) -> HttpClientResult<()> {
-    self
-      .send_delete(&format!("{}/{key}", Self::SECRET_PATH), None::<String>)
+  pub async fn seal_secret(
+    &self,
+    seal: &SecretSeal,
+  ) -> HttpClientResult<serde_json::Value> {
+    let res = self
+      .send_post(
+        &format!("{}/seal", Self::SECRET_PATH),
+        Some(seal),
+        None::<String>,
+      )
       .await?;
-    Ok(())
+    Self::res_json(res).await
   }
 }
 
@@ -117,8 +252,23 @@ mod tests {
     };
     let secret = client.create_secret(&secret).await.unwrap();
     assert_eq!(secret.name, SECRET_NAME);
-    let secret = client.inspect_secret(SECRET_NAME).await.unwrap();
+    let secret = client.inspect_secret(SECRET_NAME, None).await.unwrap();
     assert_eq!(secret.name, SECRET_NAME);
-    client.delete_secret(SECRET_NAME).await.unwrap();
+    assert_eq!(secret.data, serde_json::json!({"key": "********"}));
+    let query = SecretInspectQuery { reveal: Some(true) };
+    let secret = client
+      .inspect_secret(SECRET_NAME, Some(&query))
+      .await
+      .unwrap();
+    assert_eq!(secret.data, serde_json::json!({"key": "value"}));
+    let histories = client.list_history_secret(SECRET_NAME).await.unwrap();
+    assert_eq!(histories.len(), 1);
+    let seal = SecretSeal {
+      path: "test/client".to_owned(),
+      data: serde_json::json!({"key": "value"}),
+    };
+    let sealed = client.seal_secret(&seal).await.unwrap();
+    assert_ne!(sealed, seal.data);
+    client.delete_secret(SECRET_NAME, None).await.unwrap();
   }
 }
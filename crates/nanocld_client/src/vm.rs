@@ -1,10 +1,15 @@
-use ntex::{io, rt, ws};
+use ntex::{channel::mpsc::Receiver, io, rt, ws};
 
+use nanocl_error::http::HttpResult;
 use nanocl_error::http_client::HttpClientResult;
 use nanocl_error::io::FromIo;
 
-use nanocl_stubs::generic::{GenericFilterNsp, GenericNspQuery};
-use nanocl_stubs::vm::{Vm, VmInspect, VmSummary};
+use nanocl_stubs::generic::{GenericFilterNsp, GenericNspQuery, WatchEvent};
+use nanocl_stubs::process::OutputLog;
+use nanocl_stubs::vm::{
+  Vm, VmAttachQuery, VmDeleteQuery, VmInspect, VmLogQuery, VmSummary,
+};
+use nanocl_stubs::vm_snapshot::{VmSnapshot, VmSnapshotPartial};
 use nanocl_stubs::vm_spec::{VmSpecPartial, VmSpecUpdate};
 
 use crate::NanocldClient;
@@ -48,6 +53,27 @@ impl NanocldClient {
     Self::res_json(res).await
   }
 
+  /// Watch the virtual machines of a namespace followed by their changes
+  ///
+  /// ## Example
+  ///
+  /// ```no_run,ignore
+  /// use nanocld_client::NanocldClient;
+  ///
+  /// let client = NanocldClient::connect_to("http://localhost:8585", None);
+  /// let mut stream = client.watch_vm(None, None).await?;
+  /// while let Some(event) = stream.next().await {
+  ///  println!("{:?}", event);
+  /// }
+  /// ```
+  pub async fn watch_vm(
+    &self,
+    query: Option<&GenericFilterNsp>,
+    cursor: Option<&str>,
+  ) -> HttpClientResult<Receiver<HttpResult<WatchEvent<VmSummary>>>> {
+    self.send_watch(Self::VM_PATH, query, cursor).await
+  }
+
   /// Delete a vm by it's name and namespace
   ///
   /// ## Example
@@ -61,13 +87,10 @@ impl NanocldClient {
   pub async fn delete_vm(
     &self,
     name: &str,
-    namespace: Option<&str>,
+    query: Option<&VmDeleteQuery>,
   ) -> HttpClientResult<()> {
     self
-      .send_delete(
-        &format!("{}/{name}", Self::VM_PATH),
-        Some(&GenericNspQuery::new(namespace)),
-      )
+      .send_delete(&format!("{}/{name}", Self::VM_PATH), query)
       .await?;
     Ok(())
   }
@@ -128,12 +151,19 @@ impl NanocldClient {
   pub async fn attach_vm(
     &self,
     name: &str,
-    namespace: Option<&str>,
+    query: Option<&VmAttachQuery>,
   ) -> HttpClientResult<ws::WsConnection<io::Base>> {
-    let qs = if let Some(namespace) = namespace {
-      format!("?Namespace={}", namespace)
-    } else {
+    let mut params = Vec::new();
+    if let Some(namespace) = query.and_then(|q| q.namespace.as_deref()) {
+      params.push(format!("namespace={namespace}"));
+    }
+    if let Some(keys) = query.and_then(|q| q.detach_keys.as_deref()) {
+      params.push(format!("detach_keys={keys}"));
+    }
+    let qs = if params.is_empty() {
       "".to_owned()
+    } else {
+      format!("?{}", params.join("&"))
     };
     let url = format!("{}/{}/vms/{name}/attach{qs}", self.url, &self.version);
     // open websockets connection over http transport
@@ -169,4 +199,115 @@ impl NanocldClient {
       Ok(con)
     }
   }
+
+  /// Get the serial console logs of a vm by it's name
+  ///
+  /// ## Example
+  ///
+  /// ```no_run,ignore
+  /// use nanocld_client::NanocldClient;
+  ///
+  /// let client = NanocldClient::connect_to("http://localhost:8585", None);
+  /// let stream = client.logs_vm("my-vm", None).await;
+  /// ```
+  pub async fn logs_vm(
+    &self,
+    name: &str,
+    query: Option<&VmLogQuery>,
+  ) -> HttpClientResult<Receiver<HttpResult<OutputLog>>> {
+    let res = self
+      .send_get(&format!("{}/{name}/logs", Self::VM_PATH), query)
+      .await?;
+    Ok(Self::res_stream(res).await)
+  }
+
+  /// List snapshots of a vm by it's name and namespace
+  ///
+  /// ## Example
+  ///
+  /// ```no_run,ignore
+  /// use nanocld_client::NanocldClient;
+  ///
+  /// let client = NanocldClient::connect_to("http://localhost:8585", None);
+  /// let res = client.list_vm_snapshot("my-vm", None).await;
+  /// ```
+  pub async fn list_vm_snapshot(
+    &self,
+    name: &str,
+    namespace: Option<&str>,
+  ) -> HttpClientResult<Vec<VmSnapshot>> {
+    let res = self
+      .send_get(
+        &format!("{}/{name}/snapshots", Self::VM_PATH),
+        Some(&GenericNspQuery::new(namespace)),
+      )
+      .await?;
+    Self::res_json(res).await
+  }
+
+  /// Create a snapshot of a vm by it's name and namespace
+  pub async fn create_vm_snapshot(
+    &self,
+    name: &str,
+    snapshot: &VmSnapshotPartial,
+    namespace: Option<&str>,
+  ) -> HttpClientResult<VmSnapshot> {
+    let res = self
+      .send_post(
+        &format!("{}/{name}/snapshots", Self::VM_PATH),
+        Some(snapshot),
+        Some(&GenericNspQuery::new(namespace)),
+      )
+      .await?;
+    Self::res_json(res).await
+  }
+
+  /// Inspect a snapshot of a vm by it's key
+  pub async fn inspect_vm_snapshot(
+    &self,
+    name: &str,
+    key: &str,
+    namespace: Option<&str>,
+  ) -> HttpClientResult<VmSnapshot> {
+    let res = self
+      .send_get(
+        &format!("{}/{name}/snapshots/{key}", Self::VM_PATH),
+        Some(&GenericNspQuery::new(namespace)),
+      )
+      .await?;
+    Self::res_json(res).await
+  }
+
+  /// Delete a snapshot of a vm by it's key
+  pub async fn delete_vm_snapshot(
+    &self,
+    name: &str,
+    key: &str,
+    namespace: Option<&str>,
+  ) -> HttpClientResult<()> {
+    self
+      .send_delete(
+        &format!("{}/{name}/snapshots/{key}", Self::VM_PATH),
+        Some(&GenericNspQuery::new(namespace)),
+      )
+      .await?;
+    Ok(())
+  }
+
+  /// Restore a vm to one of its snapshots
+  pub async fn restore_vm_snapshot(
+    &self,
+    name: &str,
+    key: &str,
+    namespace: Option<&str>,
+  ) -> HttpClientResult<VmSnapshot> {
+    let res = self
+      .send_post(
+        &format!("{}/{name}/snapshots/{key}/restore", Self::VM_PATH),
+        None::<String>,
+        Some(&GenericNspQuery::new(namespace)),
+      )
+      .await?;
+    Self::res_json(res).await
+  }
 }
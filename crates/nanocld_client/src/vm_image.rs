@@ -1,15 +1,18 @@
 use std::error::Error;
 
-use futures::Stream;
+use futures::{Stream, StreamExt};
 use ntex::channel::mpsc::Receiver;
 use ntex::util::Bytes;
 
-use nanocl_error::http::HttpResult;
+use nanocl_error::http::{HttpError, HttpResult};
 use nanocl_error::http_client::HttpClientResult;
 
 use nanocl_stubs::{
   generic::GenericFilter,
-  vm_image::{VmImage, VmImageCloneStream, VmImageResizePayload},
+  vm_image::{
+    VmImage, VmImageCloneStream, VmImageExportQuery, VmImagePullPayload,
+    VmImageResizePayload,
+  },
 };
 
 use crate::NanocldClient;
@@ -100,6 +103,65 @@ impl NanocldClient {
     Ok(Self::res_stream(res).await)
   }
 
+  /// Pull a vm image from an http(s) url and return it once registered.
+  /// The progress is emitted as `Downloading` events of the image
+  ///
+  /// ## Example
+  ///
+  /// ```no_run,ignore
+  /// use nanocld_client::NanocldClient;
+  /// use nanocld_client::stubs::vm_image::VmImagePullPayload;
+  ///
+  /// let client = NanocldClient::connect_to("http://localhost:8585", None);
+  /// let payload = VmImagePullPayload {
+  ///   url: "https://example.com/image.qcow2".to_owned(),
+  ///   checksum: None,
+  /// };
+  /// let res = client.pull_vm_image("my-image", &payload).await;
+  /// ```
+  pub async fn pull_vm_image(
+    &self,
+    name: &str,
+    payload: &VmImagePullPayload,
+  ) -> HttpClientResult<VmImage> {
+    let res = self
+      .send_post(
+        &format!("{}/{name}/pull", Self::VM_IMAGE_PATH),
+        Some(payload),
+        None::<String>,
+      )
+      .await?;
+    Self::res_json(res).await
+  }
+
+  /// Export a vm image by it's name and return a stream of the qcow2 file
+  ///
+  /// ## Example
+  ///
+  /// ```no_run,ignore
+  /// use nanocld_client::NanocldClient;
+  ///
+  /// let client = NanocldClient::connect_to("http://localhost:8585", None);
+  /// let res = client.export_vm_image("my-image", None).await;
+  /// ```
+  pub async fn export_vm_image(
+    &self,
+    name: &str,
+    query: Option<&VmImageExportQuery>,
+  ) -> HttpClientResult<impl Stream<Item = HttpResult<Bytes>>> {
+    let res = self
+      .send_get(&format!("{}/{name}/export", Self::VM_IMAGE_PATH), query)
+      .await?;
+    let stream = res.map(|item| {
+      item.map_err(|err| {
+        HttpError::internal_server_error(format!(
+          "Unable to read stream: {err}"
+        ))
+      })
+    });
+    Ok(stream)
+  }
+
   /// Resize a vm image by it's name
   ///
   /// ## Example
@@ -127,4 +189,75 @@ impl NanocldClient {
       .await?;
     Self::res_json(res).await
   }
+
+  /// Mark a vm image as a read-only template
+  ///
+  /// ## Example
+  ///
+  /// ```no_run,ignore
+  /// use nanocld_client::NanocldClient;
+  ///
+  /// let client = NanocldClient::connect_to("http://localhost:8585", None);
+  /// let res = client.template_vm_image("my-image").await;
+  /// ```
+  pub async fn template_vm_image(
+    &self,
+    name: &str,
+  ) -> HttpClientResult<VmImage> {
+    let res = self
+      .send_post(
+        &format!("{}/{name}/template", Self::VM_IMAGE_PATH),
+        None::<String>,
+        None::<String>,
+      )
+      .await?;
+    Self::res_json(res).await
+  }
+
+  /// Turn a vm image template back into a base image
+  ///
+  /// ## Example
+  ///
+  /// ```no_run,ignore
+  /// use nanocld_client::NanocldClient;
+  ///
+  /// let client = NanocldClient::connect_to("http://localhost:8585", None);
+  /// let res = client.untemplate_vm_image("my-image").await;
+  /// ```
+  pub async fn untemplate_vm_image(
+    &self,
+    name: &str,
+  ) -> HttpClientResult<VmImage> {
+    let res = self
+      .send_delete(
+        &format!("{}/{name}/template", Self::VM_IMAGE_PATH),
+        None::<String>,
+      )
+      .await?;
+    Self::res_json(res).await
+  }
+
+  /// Detach a linked clone from the image it's based on
+  ///
+  /// ## Example
+  ///
+  /// ```no_run,ignore
+  /// use nanocld_client::NanocldClient;
+  ///
+  /// let client = NanocldClient::connect_to("http://localhost:8585", None);
+  /// let res = client.flatten_vm_image("my-clone").await;
+  /// ```
+  pub async fn flatten_vm_image(
+    &self,
+    name: &str,
+  ) -> HttpClientResult<VmImage> {
+    let res = self
+      .send_post(
+        &format!("{}/{name}/flatten", Self::VM_IMAGE_PATH),
+        None::<String>,
+        None::<String>,
+      )
+      .await?;
+    Self::res_json(res).await
+  }
 }